@@ -0,0 +1,129 @@
+//! Micro-benchmarks of the per-call hot paths; budgets and how to read the
+//! output are in `docs/performance.md`.
+//!
+//! Run with `cargo bench --bench hot_paths`. Set `BENCH_ENFORCE_BUDGET=1` to
+//! exit with an error when a benchmark is over its budget.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use mini_langchain::{
+    llm::{
+        capabilities::{adapt_messages, Capabilities},
+        extract_tool_calls,
+        tokens::count_tokens,
+        CallInfo,
+    },
+    message::Message,
+    template,
+};
+use ollama_rs::generation::chat::ChatMessage;
+use serde_json::json;
+
+/// Samples per benchmark; the median is reported.
+const SAMPLES: usize = 30;
+/// Target wall time of one sample.
+const SAMPLE_TIME: Duration = Duration::from_millis(20);
+
+struct Bench {
+    name: &'static str,
+    /// Median time per iteration must stay below this.
+    budget: Duration,
+    median: Duration,
+}
+
+fn bench<T>(name: &'static str, budget: Duration, mut f: impl FnMut() -> T) -> Bench {
+    // warm up and size the batch so one sample takes about SAMPLE_TIME
+    let mut iters: u32 = 1;
+    loop {
+        let start = Instant::now();
+        for _ in 0..iters {
+            black_box(f());
+        }
+        if start.elapsed() >= SAMPLE_TIME / 4 || iters >= 1 << 24 {
+            break;
+        }
+        iters *= 2;
+    }
+    let iters = iters * 4;
+
+    let mut samples: Vec<Duration> = (0..SAMPLES)
+        .map(|_| {
+            let start = Instant::now();
+            for _ in 0..iters {
+                black_box(f());
+            }
+            start.elapsed() / iters
+        })
+        .collect();
+    samples.sort();
+    Bench { name, budget, median: samples[SAMPLES / 2] }
+}
+
+/// A 40-turn agent conversation with tool calls and results.
+fn history() -> Vec<Message> {
+    let mut messages = vec![
+        Message::system("You are a helpful assistant. Answer concisely and use tools when needed."),
+        Message::tool(
+            "get_weather",
+            r#"{"name":"get_weather","description":"Get weather for a given city","args":[{"name":"city","type":"string"}]}"#,
+        ),
+    ];
+    for turn in 0..10 {
+        messages.push(Message::user(format!("What is the weather in city number {} today?", turn)));
+        let call = CallInfo { id: Some(format!("call_{}", turn)), name: "get_weather".to_string(), args: json!({ "city": "Paris" }) };
+        messages.push(Message::assistant(json!({ "tool_calls": [call.clone()] }).to_string()).with_tool_calls(vec![call]));
+        messages.push(Message::tool_result("get_weather", format!("call_{}", turn), "Tool get_weather returned: It's always sunny in Paris!"));
+        messages.push(Message::assistant("It's sunny in Paris. Anything else I can help with?"));
+    }
+    messages
+}
+
+fn main() {
+    let messages = history();
+    let generation = format!(
+        "Let me look that up for you.\n{}\nI'll report back shortly.",
+        json!({ "tool_calls": [
+            { "name": "get_weather", "args": { "city": "Paris" } },
+            { "name": "get_weather", "args": { "city": "Tokyo" } },
+            { "name": "search", "args": { "query": "events in Paris this weekend", "limit": 5 } },
+        ]}),
+    );
+    let prose = "The weather in Paris is sunny with a light breeze. ".repeat(40);
+    let prompt = template!("You are {role}. Answer the question about {topic} for {user}. Question: {question}");
+    let no_system_role = Capabilities { system_role: false, ..Capabilities::default() };
+
+    let results = [
+        bench("extract_tool_calls/three_calls", Duration::from_micros(20), || extract_tool_calls(&generation)),
+        bench("extract_tool_calls/plain_text", Duration::from_micros(10), || extract_tool_calls(&prose)),
+        bench("convert/adapt_messages", Duration::from_micros(50), || {
+            adapt_messages(&no_system_role, &messages).len()
+        }),
+        bench("convert/to_json", Duration::from_micros(100), || serde_json::to_string(&messages).map(|s| s.len())),
+        bench("convert/to_ollama", Duration::from_micros(30), || {
+            messages.iter().map(ChatMessage::from).count()
+        }),
+        bench("template/render", Duration::from_micros(2), || {
+            prompt.render("a travel agent", "weather", "Alice", "Will it rain in Paris tomorrow?")
+        }),
+        bench("count_tokens/history", Duration::from_micros(100), || count_tokens("gpt-4o", &messages)),
+    ];
+
+    let mut over_budget = 0;
+    println!("{:<34} {:>12} {:>12}", "benchmark", "median", "budget");
+    for result in results.iter() {
+        let over = result.median > result.budget;
+        over_budget += usize::from(over);
+        println!(
+            "{:<34} {:>12} {:>12}{}",
+            result.name,
+            format!("{:.2?}", result.median),
+            format!("{:.0?}", result.budget),
+            if over { "  OVER BUDGET" } else { "" },
+        );
+    }
+    if over_budget > 0 && std::env::var("BENCH_ENFORCE_BUDGET").is_ok_and(|v| v == "1") {
+        eprintln!("{} benchmark(s) over budget", over_budget);
+        std::process::exit(1);
+    }
+}
@@ -27,7 +27,7 @@ async fn main() {
     let mut agent = Agent::new("Ollama_qwen3:8b", llm, Some(5));
 
     // register echo tool
-    agent.register_tool(None, Arc::new(GetWeatherTool));
+    agent.register_tool(None, Arc::new(GetWeatherTool)).expect("register tool");
 
 
     agent.set_system_prompt(
@@ -24,7 +24,11 @@ pub fn tool(attr: TokenStream, item: TokenStream) -> TokenStream {
 
     let mut name_override = None;
     let mut description = None;
-    let mut params_meta = Vec::<(String, String)>::new();
+    let mut struct_name = None;
+    let mut vis: syn::Visibility = syn::parse_quote!(pub);
+    let mut params_meta = Vec::<(String, ParamMeta)>::new();
+    let mut translations = Vec::<LocaleMeta>::new();
+    let mut strict = false;
 
     for nested in args {
         match nested {
@@ -34,19 +38,53 @@ pub fn tool(attr: TokenStream, item: TokenStream) -> TokenStream {
                         match ident.to_string().as_str() {
                             "name" => name_override = Some(s.value()),
                             "description" => description = Some(s.value()),
+                            "struct_name" => match s.parse::<syn::Ident>() {
+                                Ok(ident) => struct_name = Some(ident),
+                                Err(e) => return e.to_compile_error().into(),
+                            },
+                            "vis" => match s.parse::<syn::Visibility>() {
+                                Ok(v) => vis = v,
+                                Err(e) => return e.to_compile_error().into(),
+                            },
                             _ => {}
                         }
                     }
                 }
             }
+            NestedMeta::Meta(Meta::Path(path)) if path.is_ident("strict") => strict = true,
+            // locale("fr", description = "...", params(city = "..."))
+            NestedMeta::Meta(Meta::List(list)) if list.path.is_ident("locale") => {
+                match LocaleMeta::parse(&list) {
+                    Ok(meta) => translations.push(meta),
+                    Err(e) => return e.to_compile_error().into(),
+                }
+            }
             NestedMeta::Meta(Meta::List(list)) if list.path.is_ident("params") => {
                 for nm in list.nested {
-                    if let NestedMeta::Meta(Meta::NameValue(nv)) = nm {
-                        if let (Some(ident), Lit::Str(s)) =
-                            (nv.path.get_ident(), &nv.lit)
-                        {
-                            params_meta.push((ident.to_string(), s.value()));
+                    match nm {
+                        // city = "description"
+                        NestedMeta::Meta(Meta::NameValue(nv)) => {
+                            if let (Some(ident), Lit::Str(s)) =
+                                (nv.path.get_ident(), &nv.lit)
+                            {
+                                let meta = ParamMeta {
+                                    description: Some(s.value()),
+                                    ..Default::default()
+                                };
+                                params_meta.push((ident.to_string(), meta));
+                            }
+                        }
+                        // units(description = "...", rename = "...", skip_in_schema)
+                        NestedMeta::Meta(Meta::List(param)) => {
+                            let Some(ident) = param.path.get_ident() else {
+                                continue;
+                            };
+                            match ParamMeta::parse(&param) {
+                                Ok(meta) => params_meta.push((ident.to_string(), meta)),
+                                Err(e) => return e.to_compile_error().into(),
+                            }
                         }
+                        _ => {}
                     }
                 }
             }
@@ -109,24 +147,47 @@ pub fn tool(attr: TokenStream, item: TokenStream) -> TokenStream {
             .into();
         }
     }
+    for translation in &translations {
+        for (k, _) in &translation.params {
+            if !param_names.contains(k) {
+                return syn::Error::new(
+                    translation.span,
+                    format!("param '{}' not found in function signature", k),
+                )
+                .to_compile_error()
+                .into();
+            }
+        }
+    }
 
+    let tool_struct_ident = struct_name.unwrap_or_else(|| {
+        syn::Ident::new(&format!("{}Tool", pascal_case(&fn_name)), fn_ident.span())
+    });
     let params_struct_ident =
-        syn::Ident::new(&format!("{}Params", pascal_case(&fn_name)), fn_ident.span());
-    let tool_struct_ident =
-        syn::Ident::new(&format!("{}Tool", pascal_case(&fn_name)), fn_ident.span());
+        syn::Ident::new(&format!("{}Params", tool_struct_ident), fn_ident.span());
+    let mod_ident = syn::Ident::new(&format!("__{}_tool", fn_name), fn_ident.span());
 
     let host = host_crate();
 
-    let field_defs = fields.iter().map(|(id, ty)| {
-        quote!(pub #id: #ty)
-    });
-
-    let args_entries = fields.iter().map(|(ident, ty)| {
-        let desc = params_meta
+    let meta_for = |ident: &syn::Ident| {
+        params_meta
             .iter()
             .find(|(k, _)| k == &ident.to_string())
             .map(|(_, v)| v.clone())
-            .unwrap_or_default();
+            .unwrap_or_default()
+    };
+
+    let field_defs = fields.iter().map(|(id, ty)| {
+        let meta = meta_for(id);
+        let rename = meta.rename.map(|name| quote!(#[serde(rename = #name)]));
+        // hidden params are never sent by the model
+        let default = meta.skip_in_schema.then(|| quote!(#[serde(default)]));
+        quote!(#rename #default pub #id: #ty)
+    });
+
+    let args_entries = fields.iter().filter(|(ident, _)| !meta_for(ident).skip_in_schema).map(|(ident, ty)| {
+        let meta = meta_for(ident);
+        let desc = meta.description.unwrap_or_default();
 
         if desc.is_empty() {
             return syn::Error::new_spanned(
@@ -137,8 +198,13 @@ pub fn tool(attr: TokenStream, item: TokenStream) -> TokenStream {
         }
 
         let arg_type = infer_json_type(ty);
-        let name_lit = syn::LitStr::new(&ident.to_string(), ident.span());
+        let exposed_name = meta.rename.unwrap_or_else(|| ident.to_string());
+        let name_lit = syn::LitStr::new(&exposed_name, ident.span());
         let desc_lit = syn::LitStr::new(&desc, ident.span());
+        let schema = match container_schema(ty, &host) {
+            Some(extra) => quote!(Some(#extra)),
+            None => quote!(None),
+        };
 
         quote! {
             #host::tools::traits::ArgSchema {
@@ -146,10 +212,35 @@ pub fn tool(attr: TokenStream, item: TokenStream) -> TokenStream {
                 arg_type: #arg_type.into(),
                 description: #desc_lit.into(),
                 required: true,
+                schema: #schema,
+            }
+        }
+    });
+
+    // translated descriptions are keyed by the name exposed in the schema
+    let translations_fn = (!translations.is_empty()).then(|| {
+        let entries = translations.iter().map(|translation| {
+            let locale = &translation.locale;
+            let description = &translation.description;
+            let args = translation.params.iter().map(|(param, desc)| {
+                let exposed = params_meta
+                    .iter()
+                    .find(|(k, _)| k == param)
+                    .and_then(|(_, meta)| meta.rename.clone())
+                    .unwrap_or_else(|| param.clone());
+                quote!(.with_arg(#exposed, #desc))
+            });
+            quote!(#host::tools::traits::ToolTranslation::new(#locale, #description) #(#args)*)
+        });
+        quote! {
+            fn translations(&self) -> Vec<#host::tools::traits::ToolTranslation> {
+                vec![#(#entries),*]
             }
         }
     });
 
+    let strict_fn = strict.then(|| quote!(fn strict(&self) -> bool { true }));
+
     let call_args = fields.iter().map(|(id, _)| quote!(params.#id));
     let is_async = input_fn.sig.asyncness.is_some();
 
@@ -169,35 +260,406 @@ pub fn tool(attr: TokenStream, item: TokenStream) -> TokenStream {
         }
     };
 
+    // Generated types live in their own module so the params struct doesn't
+    // pollute the caller's namespace; only the tool struct is re-exported.
     let expanded = quote! {
         #input_fn
 
-        #[derive(#host::serde::Deserialize)]
-        pub struct #params_struct_ident {
-            #(#field_defs,)*
+        #[doc(hidden)]
+        #[allow(unused_imports)]
+        mod #mod_ident {
+            use super::*;
+
+            #[derive(#host::serde::Deserialize)]
+            pub struct #params_struct_ident {
+                #(#field_defs,)*
+            }
+
+            pub struct #tool_struct_ident;
+
+            #[#host::async_trait::async_trait]
+            impl #host::tools::traits::Tool for #tool_struct_ident {
+                fn name(&self) -> &str { #tool_name }
+                fn description(&self) -> &str { #description }
+                fn args(&self) -> Vec<#host::tools::traits::ArgSchema> {
+                    vec![#(#args_entries),*]
+                }
+                #translations_fn
+                #strict_fn
+                async fn run(
+                    &self,
+                    input: #host::serde_json::Value,
+                ) -> Result<String, #host::tools::error::ToolError> {
+                    #run_body
+                }
+            }
         }
 
-        pub struct #tool_struct_ident;
+        #vis use #mod_ident::#tool_struct_ident;
+    };
+
+    TokenStream::from(expanded)
+}
+
+/// Declarative agent definition.
+///
+/// ```ignore
+/// /// You are a weather assistant. Query tools or answer directly.
+/// #[agent(llm = "Ollama", model = "qwen3:8b", tools(GetWeatherTool), max_iterations = 5)]
+/// struct WeatherAgent;
+///
+/// let agent = WeatherAgent::build();
+/// ```
+///
+/// The struct's doc comment becomes the system prompt. `tools(...)` lists
+/// unit-struct tools such as the ones generated by `#[tool]`. `llm` names a
+/// backend type implementing `Default`; with `model` its `with_model` is
+/// called. Generates `build_with_llm(llm)`, and `build()` when `llm` is given.
+#[proc_macro_attribute]
+pub fn agent(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(attr as AttributeArgs);
+    let input = parse_macro_input!(item as syn::ItemStruct);
 
-        #[#host::async_trait::async_trait]
-        impl #host::tools::traits::Tool for #tool_struct_ident {
-            fn name(&self) -> &str { #tool_name }
-            fn description(&self) -> &str { #description }
-            fn args(&self) -> Vec<#host::tools::traits::ArgSchema> {
-                vec![#(#args_entries),*]
+    let mut name = None;
+    let mut llm = None;
+    let mut model = None;
+    let mut max_iterations = None;
+    let mut tools = Vec::new();
+
+    for nested in args {
+        match nested {
+            NestedMeta::Meta(Meta::NameValue(nv)) => {
+                let key = nv.path.get_ident().map(|i| i.to_string()).unwrap_or_default();
+                match (key.as_str(), &nv.lit) {
+                    ("name", Lit::Str(s)) => name = Some(s.value()),
+                    ("model", Lit::Str(s)) => model = Some(s.value()),
+                    ("llm", Lit::Str(s)) => match s.parse::<syn::Path>() {
+                        Ok(path) => llm = Some(path),
+                        Err(e) => return e.to_compile_error().into(),
+                    },
+                    ("max_iterations", Lit::Int(i)) => match i.base10_parse::<usize>() {
+                        Ok(n) => max_iterations = Some(n),
+                        Err(e) => return e.to_compile_error().into(),
+                    },
+                    _ => {
+                        return syn::Error::new_spanned(&nv, "unknown agent argument")
+                            .to_compile_error()
+                            .into();
+                    }
+                }
+            }
+            NestedMeta::Meta(Meta::List(list)) if list.path.is_ident("tools") => {
+                for nm in list.nested {
+                    match nm {
+                        NestedMeta::Meta(Meta::Path(path)) => tools.push(path),
+                        other => {
+                            return syn::Error::new_spanned(other, "expected a tool type")
+                                .to_compile_error()
+                                .into();
+                        }
+                    }
+                }
             }
-            async fn run(
-                &self,
-                input: #host::serde_json::Value,
-            ) -> Result<String, #host::tools::error::ToolError> {
-                #run_body
+            other => {
+                return syn::Error::new_spanned(other, "unknown agent argument")
+                    .to_compile_error()
+                    .into();
             }
         }
+    }
+
+    if model.is_some() && llm.is_none() {
+        return syn::Error::new_spanned(&input.ident, "`model` requires `llm = \"...\"`")
+            .to_compile_error()
+            .into();
+    }
+
+    let doc_lines: Vec<String> = input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("doc"))
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(Meta::NameValue(nv)) => match nv.lit {
+                Lit::Str(s) => Some(s.value()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .map(|line| line.strip_prefix(' ').unwrap_or(&line).to_string())
+        .collect();
+    let system_prompt = doc_lines.join("\n").trim().to_string();
+
+    let host = host_crate();
+    let ident = &input.ident;
+    let agent_name = name.unwrap_or_else(|| ident.to_string());
+    let max_iterations = match max_iterations {
+        Some(n) => quote!(Some(#n)),
+        None => quote!(None),
+    };
+    let set_prompt = if system_prompt.is_empty() {
+        quote!()
+    } else {
+        quote!(agent.set_system_prompt(Self::SYSTEM_PROMPT);)
+    };
+    let prompt_const = if system_prompt.is_empty() {
+        quote!()
+    } else {
+        quote! {
+            /// System prompt taken from the doc comment.
+            pub const SYSTEM_PROMPT: &'static str = #system_prompt;
+        }
+    };
+    let build = llm.map(|llm| {
+        let with_model = model.map(|m| quote!(.with_model(#m)));
+        quote! {
+            /// Build the agent with its declared LLM.
+            pub fn build() -> #host::agent::types::Agent {
+                let llm = <#llm as ::core::default::Default>::default() #with_model;
+                Self::build_with_llm(::std::sync::Arc::new(llm))
+            }
+        }
+    });
+
+    let expanded = quote! {
+        #input
+
+        impl #ident {
+            #prompt_const
+
+            /// Build the agent on top of `llm`.
+            pub fn build_with_llm(
+                llm: ::std::sync::Arc<dyn #host::llm::traits::LLM>,
+            ) -> #host::agent::types::Agent {
+                let mut agent = #host::agent::types::Agent::new(#agent_name, llm, #max_iterations);
+                #set_prompt
+                #(agent
+                    .register_tool(None, ::std::sync::Arc::new(#tools))
+                    .expect("the default tool policy allows every tool");)*
+                agent
+            }
+
+            #build
+        }
     };
 
     TokenStream::from(expanded)
 }
 
+/// Compile-time checked prompt template.
+///
+/// `template!("Hello {name}, you are {age}")` validates the placeholders and
+/// expands to a value with `render(&self, name: &str, age: &str) -> String`
+/// taking one argument per distinct placeholder, in order of first use. Use
+/// `{{` and `}}` for literal braces.
+#[proc_macro]
+pub fn template(input: TokenStream) -> TokenStream {
+    let lit = parse_macro_input!(input as syn::LitStr);
+    let source = lit.value();
+
+    let segments = match parse_template(&source) {
+        Ok(segments) => segments,
+        Err(message) => return syn::Error::new_spanned(&lit, message).to_compile_error().into(),
+    };
+
+    let mut params: Vec<String> = Vec::new();
+    for segment in &segments {
+        if let TemplateSegment::Var(name) = segment
+            && !params.contains(name)
+        {
+            params.push(name.clone());
+        }
+    }
+    let param_idents: Vec<syn::Ident> = params
+        .iter()
+        .map(|p| syn::Ident::new(p, lit.span()))
+        .collect();
+
+    let literal_len: usize = segments
+        .iter()
+        .map(|segment| match segment {
+            TemplateSegment::Text(text) => text.len(),
+            TemplateSegment::Var(_) => 0,
+        })
+        .sum();
+    // mixed-site hygiene keeps the buffer apart from a `{out}` placeholder
+    let out = syn::Ident::new("__out", proc_macro2::Span::mixed_site());
+    let pushes = segments.iter().map(|segment| match segment {
+        TemplateSegment::Text(text) => quote! { #out.push_str(#text); },
+        TemplateSegment::Var(name) => {
+            let ident = syn::Ident::new(name, lit.span());
+            quote! { #out.push_str(#ident); }
+        }
+    });
+
+    let expanded = quote! {
+        {
+            #[derive(Debug, Clone, Copy)]
+            struct __PromptTemplate;
+
+            impl __PromptTemplate {
+                /// The template source.
+                #[allow(dead_code)]
+                pub const SOURCE: &'static str = #source;
+
+                #[allow(dead_code)]
+                pub fn render(&self, #(#param_idents: &str),*) -> String {
+                    let mut #out = String::with_capacity(#literal_len #(+ #param_idents.len())*);
+                    #(#pushes)*
+                    #out
+                }
+            }
+
+            __PromptTemplate
+        }
+    };
+
+    TokenStream::from(expanded)
+}
+
+enum TemplateSegment {
+    Text(String),
+    Var(String),
+}
+
+/// Split a template into literal text and `{placeholder}`s.
+fn parse_template(source: &str) -> Result<Vec<TemplateSegment>, String> {
+    let mut segments = Vec::new();
+    let mut text = String::new();
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => return Err(format!("unclosed placeholder `{{{}`", name)),
+                    }
+                }
+                let name = name.trim().to_string();
+                if name.is_empty() {
+                    return Err("empty placeholder `{}`; name it, e.g. `{input}`".to_string());
+                }
+                if syn::parse_str::<syn::Ident>(&name).is_err() {
+                    return Err(format!("placeholder `{{{}}}` is not a valid identifier", name));
+                }
+                if !text.is_empty() {
+                    segments.push(TemplateSegment::Text(std::mem::take(&mut text)));
+                }
+                segments.push(TemplateSegment::Var(name));
+            }
+            '}' => return Err("unmatched `}`; use `}}` for a literal brace".to_string()),
+            c => text.push(c),
+        }
+    }
+    if !text.is_empty() {
+        segments.push(TemplateSegment::Text(text));
+    }
+    Ok(segments)
+}
+
+/// Per-parameter options from `#[tool(params(...))]`.
+#[derive(Clone, Default)]
+struct ParamMeta {
+    description: Option<String>,
+    /// Name exposed in the schema and expected in the arguments JSON.
+    rename: Option<String>,
+    /// Leave the parameter out of the schema; it is filled with
+    /// `Default::default()` (e.g. an injected context value).
+    skip_in_schema: bool,
+}
+
+impl ParamMeta {
+    fn parse(list: &syn::MetaList) -> syn::Result<Self> {
+        let mut meta = ParamMeta::default();
+        for nested in &list.nested {
+            match nested {
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("description") => {
+                    meta.description = Some(lit_str(&nv.lit)?);
+                }
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("rename") => {
+                    meta.rename = Some(lit_str(&nv.lit)?);
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("skip_in_schema") => {
+                    meta.skip_in_schema = true;
+                }
+                other => {
+                    return Err(syn::Error::new_spanned(
+                        other,
+                        "expected `description = \"...\"`, `rename = \"...\"` or `skip_in_schema`",
+                    ));
+                }
+            }
+        }
+        Ok(meta)
+    }
+}
+
+/// A `locale("fr", description = "...", params(city = "..."))` entry of
+/// `#[tool(...)]`.
+struct LocaleMeta {
+    locale: String,
+    description: String,
+    params: Vec<(String, String)>,
+    span: proc_macro2::Span,
+}
+
+impl LocaleMeta {
+    fn parse(list: &syn::MetaList) -> syn::Result<Self> {
+        let mut locale = None;
+        let mut description = None;
+        let mut params = Vec::new();
+        for nested in &list.nested {
+            match nested {
+                NestedMeta::Lit(Lit::Str(s)) if locale.is_none() => locale = Some(s.value()),
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("description") => {
+                    description = Some(lit_str(&nv.lit)?);
+                }
+                NestedMeta::Meta(Meta::List(inner)) if inner.path.is_ident("params") => {
+                    for param in &inner.nested {
+                        match param {
+                            NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.get_ident().is_some() => {
+                                let ident = nv.path.get_ident().expect("checked above");
+                                params.push((ident.to_string(), lit_str(&nv.lit)?));
+                            }
+                            other => return Err(syn::Error::new_spanned(other, "expected `param = \"...\"`")),
+                        }
+                    }
+                }
+                other => {
+                    return Err(syn::Error::new_spanned(
+                        other,
+                        "expected `\"<locale>\"`, `description = \"...\"` or `params(...)`",
+                    ));
+                }
+            }
+        }
+        let Some(locale) = locale else {
+            return Err(syn::Error::new_spanned(list, "locale requires a language tag, e.g. `locale(\"fr\", ...)`"));
+        };
+        let Some(description) = description else {
+            return Err(syn::Error::new_spanned(list, "locale requires `description = \"...\"`"));
+        };
+        Ok(Self { locale, description, params, span: syn::spanned::Spanned::span(list) })
+    }
+}
+
+fn lit_str(lit: &Lit) -> syn::Result<String> {
+    match lit {
+        Lit::Str(s) => Ok(s.value()),
+        other => Err(syn::Error::new_spanned(other, "expected a string literal")),
+    }
+}
+
 fn pascal_case(s: &str) -> String {
     s.split('_')
         .map(|p| {
@@ -222,10 +684,65 @@ fn infer_json_type(ty: &Type) -> &'static str {
                 "u8" | "u16" | "u32" | "u64" |
                 "usize" | "isize" => "integer",
                 "f32" | "f64" => "number",
-                "Vec" => "array",
+                "Vec" | "VecDeque" | "HashSet" | "BTreeSet" => "array",
                 _ => "object",
             }
         }
+        Type::Array(_) => "array",
         _ => "object",
     }
+}
+
+/// Type arguments of the last path segment, e.g. `K, V` of `HashMap<K, V>`.
+fn generic_args(ty: &Type) -> Vec<&Type> {
+    let Type::Path(p) = ty else {
+        return Vec::new();
+    };
+    match &p.path.segments.last().unwrap().arguments {
+        syn::PathArguments::AngleBracketed(args) => args
+            .args
+            .iter()
+            .filter_map(|arg| match arg {
+                syn::GenericArgument::Type(ty) => Some(ty),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// `items` / `additionalProperties` keywords for container types, so
+/// providers can validate the element types.
+fn container_schema(ty: &Type, host: &proc_macro2::TokenStream) -> Option<proc_macro2::TokenStream> {
+    let item = match ty {
+        Type::Array(array) => Some(&*array.elem),
+        Type::Path(p) => {
+            let ident = p.path.segments.last().unwrap().ident.to_string();
+            let args = generic_args(ty);
+            match ident.as_str() {
+                "Vec" | "VecDeque" | "HashSet" | "BTreeSet" => args.first().copied(),
+                "HashMap" | "BTreeMap" => {
+                    let value = type_schema(args.get(1)?, host);
+                    return Some(quote!(#host::serde_json::json!({ "additionalProperties": (#value) })));
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }?;
+    let items = type_schema(item, host);
+    Some(quote!(#host::serde_json::json!({ "items": (#items) })))
+}
+
+/// Full JSON Schema of `ty`, including nested container typing.
+fn type_schema(ty: &Type, host: &proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    let json_type = infer_json_type(ty);
+    match container_schema(ty, host) {
+        Some(extra) => quote! {{
+            let mut schema = #extra;
+            schema["type"] = #host::serde_json::Value::from(#json_type);
+            schema
+        }},
+        None => quote!(#host::serde_json::json!({ "type": #json_type })),
+    }
 }
\ No newline at end of file
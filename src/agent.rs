@@ -1,8 +1,23 @@
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use crate::llm::traits::LLM;
+use crate::llm::{CallInfo, FinishReason};
+use crate::llm::warnings::RunWarning;
+use crate::llm::continuation::{generate_to_completion, DEFAULT_MAX_CONTINUATIONS};
+use crate::llm::capabilities::adapt_messages;
+use crate::llm::cost::{CostTracker, PricingTable};
+use crate::llm::options::GenerateOptions;
+use crate::llm::models::ModelInfo;
+use crate::safety::{SafetyAction, SafetyPolicy};
+use crate::prompt::datetime::TimeContext;
+use crate::prompt::presets::{PresetRegistry, PromptPreset};
 use crate::message::Message;
 use crate::tools::{
+    args::normalize_args,
+    error::ToolError,
+    output::ToolOutput,
+    requirements::ToolPolicy,
+    scope::{run_scoped, ScopeError},
     traits::Tool,
     schema::ToolSchema,
 };
@@ -12,10 +27,17 @@ use serde_json::json;
 pub mod types;
 pub mod error;
 pub mod traits;
+pub mod telemetry;
+pub mod memory;
+pub mod events;
+pub mod repro;
 
 use traits::AgentRunner;
 use types::{Agent,AgentResult,AgentExecuteResult};
 use error::AgentError;
+use telemetry::{ToolCallEvent, ToolCallObserver, ToolCallOutcome};
+use events::{AgentEvent, AgentEventObserver};
+use repro::{random_seed, ReproBundle, ToolRecord};
 
 
 impl Agent {
@@ -25,18 +47,115 @@ impl Agent {
             name: name.into(),
             llm,
             tools: HashMap::new(),
-            memory: Vec::new(),
+            memory: Mutex::new(Vec::new()),
+            memory_enabled: false,
             system_prompt: None,
             max_iterations: max_iterations.unwrap_or(100) ,
+            max_continuations: DEFAULT_MAX_CONTINUATIONS,
+            safety: None,
+            prompt_preset: None,
+            tool_call_observer: None,
+            event_observer: None,
+            parallel_tools: false,
+            tool_policy: ToolPolicy::default(),
+            pricing: None,
+            generate_options: GenerateOptions::default(),
+            time_context: None,
+            strict_tools: false,
+            locale: None,
+            model_info: None,
+            seed_per_run: false,
         }
     }
 
     /// Register a tool under the given name. Replaces any existing tool with the same name. Returns &mut Self for chaining.
-    pub fn register_tool(&mut self, name: Option<&str>, tool: Arc<dyn Tool>) -> &mut Self {
+    ///
+    /// Fails with `AgentError::ToolNotAllowed` if the tool's requirements are
+    /// not allowed by the agent's tool policy.
+    pub fn register_tool(&mut self, name: Option<&str>, tool: Arc<dyn Tool>) -> Result<&mut Self, AgentError> {
         // If no name is provided, use the tool's own name.
         let name = name.unwrap_or_else(|| tool.name());
+        check_tool_support(self.model_info.as_ref())?;
+        check_tool_policy(&self.tool_policy, name, tool.as_ref())?;
         self.tools.insert(name.into(), tool);
-        self
+        Ok(self)
+    }
+
+    /// Register every tool under its own name, e.g. `tools::catalog::standard()`.
+    /// Stops at the first tool the tool policy rejects.
+    pub fn register_tools<I>(&mut self, tools: I) -> Result<&mut Self, AgentError>
+    where
+        I: IntoIterator<Item = Arc<dyn Tool>>,
+    {
+        for tool in tools {
+            self.register_tool(None, tool)?;
+        }
+        Ok(self)
+    }
+
+    /// Report the cost of each run in `AgentResult::cost`.
+    pub fn set_pricing(&mut self, pricing: PricingTable) {
+        self.pricing = Some(pricing);
+    }
+
+    /// Sampling settings for every LLM call of a run, e.g. a low temperature
+    /// for an agent that mostly plans tool calls.
+    pub fn set_generate_options(&mut self, options: GenerateOptions) {
+        self.generate_options = options;
+    }
+
+    /// Tell the model the current date and time (refreshed every run) and
+    /// where it runs.
+    pub fn set_time_context(&mut self, context: TimeContext) {
+        self.time_context = Some(context);
+    }
+
+    /// Treat every tool as strict: schemas are sent in strict mode where the
+    /// provider supports it, and calls whose arguments don't match a tool's
+    /// schema fail with `ToolError::ParamsNotMatched` instead of running.
+    pub fn set_strict_tools(&mut self, strict: bool) {
+        self.strict_tools = strict;
+    }
+
+    /// Show tool descriptions in `locale` (a BCP 47 tag such as `"fr-FR"`)
+    /// for the tools that have a translation for it.
+    pub fn set_locale(&mut self, locale: impl Into<String>) {
+        self.locale = Some(locale.into());
+    }
+
+    /// The language the agent is prompted in: its locale, otherwise the one
+    /// of its time context.
+    pub fn prompt_locale(&self) -> Option<&str> {
+        self.locale
+            .as_deref()
+            .or_else(|| self.time_context.as_ref().and_then(|context| context.locale.as_deref()))
+    }
+
+    /// Restrict what registered tools may require. Fails, leaving the policy
+    /// unchanged, if an already registered tool is not allowed by `policy`.
+    pub fn set_tool_policy(&mut self, policy: ToolPolicy) -> Result<(), AgentError> {
+        for (name, tool) in self.tools.iter() {
+            check_tool_policy(&policy, name, tool.as_ref())?;
+        }
+        self.tool_policy = policy;
+        Ok(())
+    }
+
+    /// Describe the agent's model, e.g. from `ModelCatalog::model_info`.
+    /// Fails, leaving the info unchanged, if tools are registered and the
+    /// model can't call them.
+    pub fn set_model_info(&mut self, info: ModelInfo) -> Result<(), AgentError> {
+        if !self.tools.is_empty() {
+            check_tool_support(Some(&info))?;
+        }
+        self.model_info = Some(info);
+        Ok(())
+    }
+
+    /// Check that the agent's LLM is reachable and ready before starting a
+    /// run, see `LLM::health_check`.
+    pub async fn health_check(&self) -> Result<(), AgentError> {
+        Ok(self.llm.health_check().await?)
     }
 
     /// Change the maximum iterations for the agent's decision process.
@@ -44,6 +163,119 @@ impl Agent {
         self.max_iterations = max_iterations;
     }   
 
+    /// Change how many times a truncated generation is continued (0 disables it).
+    pub fn change_max_continuations(&mut self, max_continuations: usize) {
+        self.max_continuations = max_continuations;
+    }
+
+    /// Set the content-safety policy applied to the final output.
+    pub fn set_safety_policy(&mut self, policy: SafetyPolicy) {
+        self.safety = Some(policy);
+    }
+
+    /// Use `preset` instead of the one picked from the model name.
+    pub fn set_prompt_preset(&mut self, preset: PromptPreset) {
+        self.prompt_preset = Some(preset);
+    }
+
+    /// The preset in effect: the explicit one, or the built-in match for the
+    /// LLM's model name.
+    pub fn prompt_preset(&self) -> PromptPreset {
+        match self.prompt_preset.as_ref() {
+            Some(preset) => preset.clone(),
+            None => PresetRegistry::builtin()
+                .for_model(self.llm.model_name().unwrap_or_default())
+                .clone(),
+        }
+    }
+
+    /// Report every tool call attempt (and parse failures) to `observer`.
+    pub fn set_tool_call_observer(&mut self, observer: Arc<dyn ToolCallObserver>) {
+        self.tool_call_observer = Some(observer);
+    }
+
+    /// Run the tool calls of one LLM turn concurrently. A failing or
+    /// panicking call aborts the others and fails the run.
+    pub fn set_parallel_tools(&mut self, parallel: bool) {
+        self.parallel_tools = parallel;
+    }
+
+    /// Record the outcome of `call` and turn its output into the message fed
+    /// back to the LLM. An image for a vision model goes to `attachments`, to
+    /// be sent after the turn's tool results.
+    fn tool_message(
+        &self,
+        model: &str,
+        call: &PreparedCall,
+        output: Result<ToolOutput, ToolError>,
+        native_tools: bool,
+        attachments: &mut Vec<Message>,
+        warnings: &mut Vec<RunWarning>,
+    ) -> Result<Message, AgentError> {
+        let name = &call.info.name;
+        let tool_result = match output {
+            Ok(tool_result) => tool_result,
+            Err(e) => {
+                if call.malformed {
+                    self.record_tool_call(model, ToolCallOutcome::MalformedArgs, Some(name), Some(&call.args_sample));
+                } else if matches!(e, ToolError::ParamsNotMatched(_)) {
+                    self.record_tool_call(model, ToolCallOutcome::ArgsRejected, Some(name), Some(&call.args_sample));
+                }
+                return Err(e.into());
+            }
+        };
+        if call.malformed {
+            self.record_tool_call(model, ToolCallOutcome::MalformedArgs, Some(name), Some(&call.args_sample));
+        } else {
+            self.record_tool_call(model, ToolCallOutcome::Ok, Some(name), None);
+        }
+        let tool_result = match tool_result.to_image() {
+            Some(image) if self.sees_images() => {
+                attachments.push(Message::user(format!("Image returned by tool {}:", name)).with_images(vec![image]));
+                format!("an image, {}, attached below", tool_result.describe())
+            }
+            Some(_) => {
+                warnings.push(RunWarning::ImageNotShown { tool: name.clone() });
+                format!("an image, {}, that this model can't view", tool_result.describe())
+            }
+            None => tool_result.describe(),
+        };
+        self.emit(|| AgentEvent::ToolResult {
+            id: call.info.id.clone(),
+            name: name.clone(),
+            output: tool_result.clone(),
+        });
+        let content = format!("Tool {} returned: {}", name, tool_result);
+        // native results carry the call id so providers can pair them
+        Ok(match call.info.id.as_ref() {
+            Some(id) if native_tools => Message::tool_result(name, id, content),
+            _ => Message::tool_res(name, content),
+        })
+    }
+
+    /// Whether tool images are shown to the model: only when the backend
+    /// reports vision and the model info, if set, agrees.
+    fn sees_images(&self) -> bool {
+        self.llm.capabilities().vision && self.model_info.as_ref().is_none_or(|info| info.supports_vision)
+    }
+
+    /// Send the events of every run to `observer`.
+    pub fn set_event_observer(&mut self, observer: Arc<dyn AgentEventObserver>) {
+        self.event_observer = Some(observer);
+    }
+
+    fn emit(&self, event: impl FnOnce() -> AgentEvent) {
+        if let Some(observer) = self.event_observer.as_ref() {
+            observer.on_event(&event());
+        }
+    }
+
+    fn record_tool_call(&self, model: &str, outcome: ToolCallOutcome, tool: Option<&str>, sample: Option<&str>) {
+        if let Some(observer) = self.tool_call_observer.as_ref() {
+            observer.on_tool_call(&ToolCallEvent::new(model, outcome, tool, sample));
+        }
+    }
+
     /// Look up a tool by name.
     pub fn get_tool(&self, name: &str) -> Option<Arc<dyn Tool>> {
         self.tools.get(name).cloned()
@@ -61,81 +293,293 @@ impl Agent {
             msgs.push(Message::system(prompt.clone()));
         }
         if !self.tools.is_empty() {
-        msgs.push(Message::developer(
-            format!("I also provide some tools for you to choose from. If you want to call a tool, please include the following JSON format in your response: {}
-
-            IMPORTANT: After you have completed the task by calling all necessary tools, you MUST return a final response WITHOUT any tool_calls. Simply provide a summary or confirmation message to indicate completion. Do NOT continue calling tools after the task is done.", 
-                json!({
-                    "tool_calls": [
-                        {
-                            "name": "tool_name",
-                            "args": {
-                                "param1": "value1",
-                                "param2": "value2"
-                            }
+            let format = json!({
+                "tool_calls": [
+                    {
+                        "name": "tool_name",
+                        "args": {
+                            "param1": "value1",
+                            "param2": "value2"
                         }
-                    ]
-                }).to_string())
+                    }
+                ]
+            });
+            msgs.push(Message::developer(
+                self.prompt_preset().render_tool_instructions(&format.to_string())
             ));
         }
         msgs
     }
 
+    /// Schemas of the registered tools, sorted by name so prompts are stable
+    /// across runs, with descriptions in the agent's `prompt_locale`.
+    pub fn tool_schemas(&self) -> Vec<ToolSchema> {
+        let mut tools: Vec<_> = self.tools.iter().collect();
+        tools.sort_by(|a, b| a.0.cmp(b.0));
+        tools.into_iter().map(|(name, tool)| self.tool_schema(name, tool.as_ref())).collect()
+    }
+
+    fn tool_schema(&self, name: &str, tool: &dyn Tool) -> ToolSchema {
+        let mut schema = ToolSchema {
+            name: name.to_string(),
+            description: tool.description().to_string(),
+            args: tool.args(),
+            strict: self.strict_tools || tool.strict(),
+        };
+        if let Some(locale) = self.prompt_locale() {
+            schema.localize(&tool.translations(), locale);
+        }
+        schema
+    }
+
     // 生成工具提示
     pub fn generate_tools_prompt(&self) -> Vec<Message> {
-        self.tools.iter().map(|(name, tool)| {
-            let schema = ToolSchema {
-                name: name.clone(),
-                description: tool.description().to_string(),
-                args: tool.args(),
-            };
-            
-            Message::system(serde_json::to_string(&schema).unwrap())
-        }).collect()
+        self.tool_schemas()
+            .iter()
+            .map(|schema| Message::system(serde_json::to_string(schema).unwrap()))
+            .collect()
+    }
+}
+
+
+
+fn check_tool_policy(policy: &ToolPolicy, name: &str, tool: &dyn Tool) -> Result<(), AgentError> {
+    let missing = policy.violations(&tool.requirements());
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(AgentError::ToolNotAllowed { name: name.to_string(), missing })
     }
 }
 
+fn check_tool_support(info: Option<&ModelInfo>) -> Result<(), AgentError> {
+    match info {
+        Some(info) if !info.supports_tools => Err(AgentError::ToolsNotSupported { model: info.id.clone() }),
+        _ => Ok(()),
+    }
+}
 
+/// A tool call resolved to its tool, with normalized arguments.
+struct PreparedCall {
+    info: CallInfo,
+    tool: Arc<dyn Tool>,
+    args: serde_json::Value,
+    malformed: bool,
+    args_sample: String,
+}
+
+impl PreparedCall {
+    fn new(mut info: CallInfo, tool: Arc<dyn Tool>) -> Self {
+        let malformed = !info.args.is_object();
+        let args_sample = info.args.to_string();
+        let args = normalize_args(std::mem::take(&mut info.args));
+        Self { info, tool, args, malformed, args_sample }
+    }
+
+    /// The warning for a call that ran with repaired arguments.
+    fn warning(&self) -> Option<RunWarning> {
+        self.malformed.then(|| RunWarning::ToolArgsRepaired { tool: self.info.name.clone() })
+    }
+}
 
 #[async_trait::async_trait]
 impl AgentRunner for Agent {
     async fn call_llm(&self, prompt: &str) -> AgentExecuteResult {
+        // Providers with native function calling get the tool schemas as
+        // request parameters; everyone else gets them (and the JSON protocol)
+        // as prompt messages.
+        let native_tools = self.llm.capabilities().native_tools && !self.tools.is_empty();
         // Build a sequence of messages so LLM implementations that support
         // system/user roles can consume them properly.
-        let mut msgs: Vec<Message> = self.generate_system_prompt();
-        let tool_msgs = self.generate_tools_prompt();
-        msgs.extend(tool_msgs);
+        let mut msgs: Vec<Message> = if native_tools {
+            self.system_prompt.iter().map(|p| Message::system(p.clone())).collect()
+        } else {
+            let mut msgs = self.generate_system_prompt();
+            msgs.extend(self.generate_tools_prompt());
+            msgs
+        };
+        if let Some(context) = self.time_context.as_ref() {
+            msgs.push(Message::system(context.render()));
+        }
+        msgs.extend(self.memory_messages());
         msgs.push(Message::user(prompt.to_string()));
+        let mut options = self.generate_options.clone();
+        if options.seed.is_none() && self.seed_per_run {
+            options.seed = Some(random_seed());
+        }
+        let repro = self.repro_bundle(prompt, msgs, options);
+        self.run(repro).await
+    }
+}
+
+impl Agent {
+    /// Give every run without a seed in its generate options a fresh random
+    /// one, so any run can be repeated from its `ReproBundle`.
+    pub fn set_seed_per_run(&mut self, enabled: bool) {
+        self.seed_per_run = enabled;
+    }
+
+    /// Run again from a recorded bundle: same prompts and options (seed
+    /// included), with the agent's current LLM and tools. The prompts already
+    /// hold the original run's memory, so the agent's memory is not added.
+    pub async fn rerun(&self, repro: &ReproBundle) -> AgentExecuteResult {
+        self.run(repro.clone()).await
+    }
+
+    fn repro_bundle(&self, prompt: &str, prompts: Vec<Message>, options: GenerateOptions) -> ReproBundle {
+        let tools = self
+            .tool_schemas()
+            .into_iter()
+            .map(|schema| {
+                let version = self.tools.get(&schema.name).and_then(|tool| tool.version()).map(str::to_string);
+                ToolRecord { schema, version }
+            })
+            .collect();
+        ReproBundle {
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            provider: self.llm.provider().map(str::to_string),
+            model: self.llm.model_name().map(str::to_string),
+            options,
+            input: prompt.to_string(),
+            prompts,
+            tools,
+        }
+    }
+
+    async fn run(&self, repro: ReproBundle) -> AgentExecuteResult {
+        self.emit(|| AgentEvent::RunStarted { repro: Box::new(repro.clone()) });
+        let native_tools = self.llm.capabilities().native_tools && !self.tools.is_empty();
+        let schemas = if native_tools { self.tool_schemas() } else { Vec::new() };
+        let prompt = repro.input.as_str();
+        let options = &repro.options;
+        let mut msgs = repro.prompts.clone();
+        if !self.llm.capabilities().vision && msgs.iter().any(|m| !m.images.is_empty()) {
+            let model = self.llm.model_name().unwrap_or("unknown").to_string();
+            return Err(AgentError::VisionNotSupported { model });
+        }
         let mut result = AgentResult::default();
+        let mut costs = self.pricing.clone().map(CostTracker::new);
         let mut  counter:usize = 0;
         // Main loop: call LLM, check for tool calls, execute tools, repeat.
         while counter < self.max_iterations {
             // Call the LLM to get a response.
-            let res = self.llm.generate(&msgs).await?;
-            result.tokens.prompt_tokens += res.tokens.prompt_tokens;
-            result.tokens.completion_tokens += res.tokens.completion_tokens;
-            result.tokens.total_tokens += res.tokens.total_tokens;
+            let mut res = {
+                let mut capabilities = self.llm.capabilities();
+                capabilities.system_role &= self.prompt_preset().system_role;
+                let request = adapt_messages(&capabilities, &msgs);
+                if native_tools {
+                    self.llm.generate_with_options(&request, &schemas, options).await?
+                } else {
+                    generate_to_completion(self.llm.as_ref(), &request, options, self.max_continuations).await?
+                }
+            };
+            result.tokens.add(&res.tokens);
+            result.warnings.append(&mut res.warnings);
+            if res.finish_reason == Some(FinishReason::Length) {
+                result.warnings.push(RunWarning::Truncated);
+            }
             counter += 1;
+            let model = res.model.clone().unwrap_or_else(|| "unknown".to_string());
+            if let Some(costs) = costs.as_mut() {
+                // priced as the backend that answered, which a fallback
+                // may have switched
+                let priced_provider = res.provider.as_deref().or(self.llm.provider());
+                let priced_model = res.model.as_deref().or(self.llm.model_name()).unwrap_or("unknown");
+                if res.cached {
+                    costs.record_cached(priced_provider, priced_model, &res.tokens);
+                } else {
+                    costs.record(priced_provider, priced_model, &res.tokens);
+                }
+            }
             // check if there are tool calls
             if !res.tool_calls.is_empty() {
-                // add assistant message
-                msgs.push(Message::assistant(res.generation));
+                // add assistant message; native calls may come without any
+                // text, so record them in the prompt protocol's JSON form
+                let content = if res.generation.trim().is_empty() {
+                    json!({ "tool_calls": res.tool_calls }).to_string()
+                } else {
+                    res.generation
+                };
+                let assistant = Message::assistant(content);
+                msgs.push(if native_tools { assistant.with_tool_calls(res.tool_calls.clone()) } else { assistant });
                 // process tool calls
+                let mut prepared = Vec::with_capacity(res.tool_calls.len());
+                let mut attachments = Vec::new();
                 for call_info in res.tool_calls {
-                    let name = &call_info.name;
-                    if let Some(tool_impl) = self.tools.get(name){
-                        let tool_result = tool_impl.run(call_info.args).await?;
-                        let tool_res_msg = Message::tool_res(
-                            name,
-                            format!("Tool {} returned: {}", name, tool_result));
-                        msgs.push(tool_res_msg);
-                    }else{
+                    let Some(tool_impl) = self.tools.get(&call_info.name) else {
+                        self.record_tool_call(&model, ToolCallOutcome::UnknownTool, Some(&call_info.name), None);
                         return Err(AgentError::ToolNotFound(call_info.name));
+                    };
+                    let call = PreparedCall::new(call_info, tool_impl.clone());
+                    let schema = self.tool_schema(&call.info.name, call.tool.as_ref());
+                    if schema.strict
+                        && let Err(reason) = schema.validate_args(&call.args)
+                    {
+                        self.record_tool_call(&model, ToolCallOutcome::ArgsRejected, Some(&call.info.name), Some(&call.args_sample));
+                        return Err(ToolError::ParamsNotMatched(format!("{}: {}", call.info.name, reason)).into());
+                    }
+                    self.emit(|| AgentEvent::ToolCall {
+                        id: call.info.id.clone(),
+                        name: call.info.name.clone(),
+                        args: call.args.clone(),
+                    });
+                    if self.parallel_tools {
+                        prepared.push(call);
+                    } else {
+                        let output = call.tool.run_output(call.args.clone()).await;
+                        msgs.push(self.tool_message(&model, &call, output, native_tools, &mut attachments, &mut result.warnings)?);
+                        result.warnings.extend(call.warning());
+                    }
+                }
+                if !prepared.is_empty() {
+                    let jobs = prepared
+                        .iter()
+                        .map(|call| (call.info.name.clone(), call.tool.clone(), call.args.clone()))
+                        .collect();
+                    match run_scoped(jobs).await {
+                        Ok(outputs) => {
+                            for (call, output) in prepared.iter().zip(outputs) {
+                                msgs.push(self.tool_message(&model, call, Ok(output), native_tools, &mut attachments, &mut result.warnings)?);
+                                result.warnings.extend(call.warning());
+                            }
+                        }
+                        Err(ScopeError { index, error }) => {
+                            self.tool_message(&model, &prepared[index], Err(error), native_tools, &mut attachments, &mut result.warnings)?;
+                        }
                     }
                 }
+                // tool results must directly follow the calls they answer
+                msgs.append(&mut attachments);
             } else {
+                if res.generation.contains("tool_calls") {
+                    self.record_tool_call(&model, ToolCallOutcome::Unparseable, None, Some(&res.generation));
+                }
                 // update generation
                 result.generation = res.generation;
+                if let Some(policy) = self.safety.as_ref() {
+                    let outcome = policy.apply(&result.generation).await;
+                    if let Some(rule) = outcome.blocked_by {
+                        return Err(AgentError::SafetyBlocked(rule));
+                    }
+                    result.generation = outcome.text;
+                    result.warnings.extend(
+                        outcome
+                            .findings
+                            .iter()
+                            .filter(|finding| matches!(finding.action, SafetyAction::Rewrite { .. }))
+                            .map(|finding| RunWarning::ContentRewritten { rule: finding.rule.clone() }),
+                    );
+                    result.safety = outcome.findings;
+                }
+                msgs.push(Message::assistant(result.generation.clone()));
+                self.remember([Message::user(prompt), Message::assistant(result.generation.clone())]);
+                result.transcript = msgs;
+                result.cost = costs.map(CostTracker::into_report);
+                result.repro = Some(repro);
+                self.emit(|| AgentEvent::Final {
+                    generation: result.generation.clone(),
+                    tokens: result.tokens.clone(),
+                });
                 return Ok(result);
             }
         }
@@ -14,6 +14,21 @@ pub enum AgentError {
     LLMExecutionError(#[from] LLMError),
 
     #[error("Maximum iterations exceeded: {0}")]
-    MaxIterationsExceeded(usize)
+    MaxIterationsExceeded(usize),
+
+    #[error("Output blocked by safety rule: {0}")]
+    SafetyBlocked(String),
+
+    #[error("Memory index out of range: {0}")]
+    MemoryIndexOutOfRange(usize),
+
+    #[error("Tool '{name}' needs {} which the agent policy does not allow", .missing.join(", "))]
+    ToolNotAllowed { name: String, missing: Vec<String> },
+
+    #[error("Model '{model}' cannot call tools")]
+    ToolsNotSupported { model: String },
+
+    #[error("Model '{model}' cannot view images; remove them from the prompt or use a vision model")]
+    VisionNotSupported { model: String },
 
 }
@@ -0,0 +1,123 @@
+//! Serializable events for UIs and other processes following an agent run.
+//!
+//! On the wire every event is one JSON object tagged by `type` inside an
+//! [`EventEnvelope`] carrying the schema version:
+//!
+//! ```json
+//! {"version":1,"type":"tool_call","id":"call_1","name":"get_weather","args":{"city":"Paris"}}
+//! ```
+//!
+//! Fields are only ever added, with defaults, within a version. Consumers
+//! built against an older schema read event types they don't know as
+//! [`AgentEvent::Unknown`] instead of failing.
+
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+use crate::llm::{tokens::TokenUsage, FinishReason};
+use crate::tools::stream::{StreamData, ToolCallDelta};
+use super::repro::ReproBundle;
+
+/// Version of the event schema produced by this crate.
+pub const EVENT_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum AgentEvent {
+    /// A run started; see `agent::repro`.
+    RunStarted { repro: Box<ReproBundle> },
+    /// Streamed text from the LLM.
+    LlmChunk {
+        content: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tokens: Option<TokenUsage>,
+        /// Native tool calls as they stream in.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        tool_calls: Vec<ToolCallDelta>,
+    },
+    /// The LLM finished a response normally.
+    LlmDone {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tokens: Option<TokenUsage>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        finish_reason: Option<FinishReason>,
+    },
+    /// The agent is about to run a tool.
+    ToolCall {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+        name: String,
+        args: Value,
+    },
+    /// A tool returned.
+    ToolResult {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+        name: String,
+        output: String,
+    },
+    /// The run ended with a final answer.
+    Final {
+        generation: String,
+        #[serde(default)]
+        tokens: TokenUsage,
+    },
+    /// The run or the transport failed.
+    Error { message: String },
+    /// An event type from a newer schema.
+    #[serde(other)]
+    Unknown,
+}
+
+impl From<&StreamData> for AgentEvent {
+    fn from(data: &StreamData) -> Self {
+        if data.done {
+            AgentEvent::LlmDone {
+                tokens: data.tokens.clone(),
+                finish_reason: data.finish_reason.clone(),
+            }
+        } else {
+            AgentEvent::LlmChunk {
+                content: data.content.clone(),
+                tokens: data.tokens.clone(),
+                tool_calls: data.tool_calls.clone(),
+            }
+        }
+    }
+}
+
+/// An event as sent over the wire.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventEnvelope {
+    pub version: u32,
+    #[serde(flatten)]
+    pub event: AgentEvent,
+}
+
+impl EventEnvelope {
+    pub fn new(event: AgentEvent) -> Self {
+        Self {
+            version: EVENT_SCHEMA_VERSION,
+            event,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+/// Receives the events of every run.
+pub trait AgentEventObserver: Send + Sync {
+    fn on_event(&self, event: &AgentEvent);
+}
+
+impl<F> AgentEventObserver for F
+where
+    F: Fn(&AgentEvent) + Send + Sync,
+{
+    fn on_event(&self, event: &AgentEvent) {
+        self(event)
+    }
+}
@@ -0,0 +1,229 @@
+//! Conversation memory kept by an agent between `call_llm` runs, and the API
+//! to inspect and edit it.
+
+use serde::{Serialize, Deserialize};
+
+use crate::message::{Message, MessageRole};
+use super::types::Agent;
+use super::error::AgentError;
+
+/// One remembered message.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryEntry {
+    pub message: Message,
+    /// Pinned entries survive `MemoryOp::Clear`.
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+/// A remembered message as shown by [`Agent::memory_view`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryItem {
+    /// Position in memory; the index used by [`MemoryOp`]s.
+    pub index: usize,
+    /// Turn the message belongs to. A turn starts at each user message.
+    pub turn: usize,
+    pub message: Message,
+    pub pinned: bool,
+}
+
+/// An edit applied by [`Agent::memory_edit`]. Indices refer to the memory
+/// before the op is applied.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum MemoryOp {
+    /// Remove every message of a turn, pinned or not.
+    DeleteTurn { turn: usize },
+    DeleteMessage { index: usize },
+    Pin { index: usize },
+    Unpin { index: usize },
+    /// Insert a note (sent as a system message) before `index`; `index` may
+    /// equal the memory length to append.
+    InsertNote { index: usize, note: String },
+    /// Remove everything that is not pinned.
+    Clear,
+}
+
+/// Messages before the first user message, such as notes, join turn 0.
+fn turn_numbers(entries: &[MemoryEntry]) -> Vec<usize> {
+    let mut turn = 0;
+    let mut seen_user = false;
+    entries
+        .iter()
+        .map(|entry| {
+            if matches!(entry.message.role, MessageRole::User) {
+                turn += usize::from(seen_user);
+                seen_user = true;
+            }
+            turn
+        })
+        .collect()
+}
+
+impl Agent {
+    /// Keep the prompt and final answer of each run in memory and send the
+    /// remembered conversation with later runs.
+    pub fn enable_memory(&mut self, enabled: bool) {
+        self.memory_enabled = enabled;
+    }
+
+    /// Snapshot of the remembered conversation.
+    pub fn memory_view(&self) -> Vec<MemoryItem> {
+        let entries = self.memory.lock().unwrap_or_else(|e| e.into_inner());
+        let turns = turn_numbers(&entries);
+        entries
+            .iter()
+            .zip(turns)
+            .enumerate()
+            .map(|(index, (entry, turn))| MemoryItem {
+                index,
+                turn,
+                message: entry.message.clone(),
+                pinned: entry.pinned,
+            })
+            .collect()
+    }
+
+    /// Apply `ops` in order. Stops at the first invalid op; earlier ops stay applied.
+    pub fn memory_edit(&self, ops: impl IntoIterator<Item = MemoryOp>) -> Result<(), AgentError> {
+        let mut entries = self.memory.lock().unwrap_or_else(|e| e.into_inner());
+        for op in ops {
+            let len = entries.len();
+            let check = |index: usize| {
+                if index < len {
+                    Ok(index)
+                } else {
+                    Err(AgentError::MemoryIndexOutOfRange(index))
+                }
+            };
+            match op {
+                MemoryOp::DeleteTurn { turn } => {
+                    let turns = turn_numbers(&entries);
+                    if !turns.contains(&turn) {
+                        return Err(AgentError::MemoryIndexOutOfRange(turn));
+                    }
+                    let mut turns = turns.into_iter();
+                    entries.retain(|_| turns.next() != Some(turn));
+                }
+                MemoryOp::DeleteMessage { index } => {
+                    entries.remove(check(index)?);
+                }
+                MemoryOp::Pin { index } => entries[check(index)?].pinned = true,
+                MemoryOp::Unpin { index } => entries[check(index)?].pinned = false,
+                MemoryOp::InsertNote { index, note } => {
+                    if index > len {
+                        return Err(AgentError::MemoryIndexOutOfRange(index));
+                    }
+                    entries.insert(index, MemoryEntry { message: Message::system(note), pinned: false });
+                }
+                MemoryOp::Clear => entries.retain(|entry| entry.pinned),
+            }
+        }
+        Ok(())
+    }
+
+    /// Remembered messages, in the order they are sent to the LLM.
+    pub(crate) fn memory_messages(&self) -> Vec<Message> {
+        if !self.memory_enabled {
+            return Vec::new();
+        }
+        let entries = self.memory.lock().unwrap_or_else(|e| e.into_inner());
+        entries.iter().map(|entry| entry.message.clone()).collect()
+    }
+
+    pub(crate) fn remember(&self, messages: impl IntoIterator<Item = Message>) {
+        if !self.memory_enabled {
+            return;
+        }
+        let mut entries = self.memory.lock().unwrap_or_else(|e| e.into_inner());
+        entries.extend(messages.into_iter().map(|message| MemoryEntry { message, pinned: false }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use super::*;
+    use crate::llm::mock::MockLLM;
+
+    /// An agent remembering two runs: "q1" -> "a1" and "q2" -> "a2".
+    fn agent() -> Agent {
+        let mut agent = Agent::new("memory", Arc::new(MockLLM::new()), None);
+        agent.enable_memory(true);
+        agent.remember([
+            Message::user("q1"),
+            Message::assistant("a1"),
+            Message::user("q2"),
+            Message::assistant("a2"),
+        ]);
+        agent
+    }
+
+    fn contents(agent: &Agent) -> Vec<String> {
+        agent.memory_view().into_iter().map(|item| item.message.content).collect()
+    }
+
+    #[test]
+    fn numbers_turns_from_each_user_message() {
+        let agent = agent();
+        agent
+            .memory_edit([MemoryOp::InsertNote { index: 0, note: "context".to_string() }])
+            .expect("insert");
+        let view = agent.memory_view();
+        let turns: Vec<usize> = view.iter().map(|item| item.turn).collect();
+        let indices: Vec<usize> = view.iter().map(|item| item.index).collect();
+        // a note before the first user message belongs to turn 0
+        assert_eq!(turns, [0, 0, 0, 1, 1]);
+        assert_eq!(indices, [0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn delete_turn_removes_pinned_messages_but_clear_keeps_them() {
+        let agent = agent();
+        agent
+            .memory_edit([MemoryOp::Pin { index: 1 }, MemoryOp::DeleteTurn { turn: 0 }])
+            .expect("delete turn");
+        assert_eq!(contents(&agent), ["q2", "a2"]);
+
+        let agent = self::agent();
+        agent.memory_edit([MemoryOp::Pin { index: 1 }, MemoryOp::Clear]).expect("clear");
+        let view = agent.memory_view();
+        assert_eq!(contents(&agent), ["a1"]);
+        assert!(view[0].pinned);
+
+        agent.memory_edit([MemoryOp::Unpin { index: 0 }, MemoryOp::Clear]).expect("unpin");
+        assert!(agent.memory_view().is_empty());
+    }
+
+    #[test]
+    fn insert_note_accepts_the_memory_length() {
+        let agent = agent();
+        agent
+            .memory_edit([MemoryOp::InsertNote { index: 4, note: "end".to_string() }])
+            .expect("append");
+        let view = agent.memory_view();
+        assert_eq!(view[4].message.content, "end");
+        assert!(matches!(view[4].message.role, MessageRole::System));
+        assert_eq!(view[4].turn, 1);
+
+        let error = agent
+            .memory_edit([MemoryOp::InsertNote { index: 6, note: "past".to_string() }])
+            .expect_err("past the end");
+        assert!(matches!(error, AgentError::MemoryIndexOutOfRange(6)));
+    }
+
+    #[test]
+    fn stops_at_the_first_invalid_op() {
+        let agent = agent();
+        let error = agent
+            .memory_edit([
+                MemoryOp::DeleteMessage { index: 0 },
+                MemoryOp::DeleteTurn { turn: 5 },
+                MemoryOp::Clear,
+            ])
+            .expect_err("no turn 5");
+        assert!(matches!(error, AgentError::MemoryIndexOutOfRange(5)));
+        assert_eq!(contents(&agent), ["a1", "q2", "a2"]);
+        assert!(agent.memory_edit([MemoryOp::Pin { index: 3 }]).is_err());
+    }
+}
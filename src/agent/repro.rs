@@ -0,0 +1,66 @@
+//! What a run was started with, so a failing run can be repeated.
+//!
+//! Every run records a [`ReproBundle`] in `AgentResult::repro` and sends it
+//! in `AgentEvent::RunStarted`, which also covers runs that end in an error.
+//! `Agent::rerun` replays the recorded prompts and options, seed included,
+//! against the agent's LLM and tools. The output is only identical when the
+//! provider samples deterministically for a given seed, and the tools behave
+//! the same.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use serde::{Serialize, Deserialize};
+
+use crate::llm::options::GenerateOptions;
+use crate::message::Message;
+use crate::tools::schema::ToolSchema;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReproBundle {
+    /// Version of this crate that ran the agent.
+    pub crate_version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Options of every LLM call of the run, including its seed.
+    #[serde(default)]
+    pub options: GenerateOptions,
+    /// The prompt the run was called with.
+    pub input: String,
+    /// Messages of the first LLM call: instructions, tool descriptions,
+    /// time context, memory and the input, as rendered for this run.
+    pub prompts: Vec<Message>,
+    /// The tools the agent had registered.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<ToolRecord>,
+}
+
+impl ReproBundle {
+    pub fn seed(&self) -> Option<i64> {
+        self.options.seed
+    }
+}
+
+/// A registered tool as the model saw it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolRecord {
+    #[serde(flatten)]
+    pub schema: ToolSchema,
+    /// `Tool::version`, when the tool reports one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+/// A fresh seed for a run. Kept to 31 bits so every backend accepts it
+/// (Ollama seeds are `i32`).
+pub(crate) fn random_seed() -> i64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_nanos())
+            .unwrap_or_default(),
+    );
+    (hasher.finish() >> 33) as i64
+}
@@ -0,0 +1,129 @@
+//! Telemetry for how reliably a model produces usable tool calls.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use serde::{Serialize, Deserialize};
+
+/// Keep at most this many characters of model output per sample.
+const MAX_SAMPLE_CHARS: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolCallOutcome {
+    /// The call parsed and its arguments were accepted by the tool.
+    Ok,
+    /// The output mentions `tool_calls` but no call could be extracted.
+    Unparseable,
+    /// A call was extracted but its `args` are not a JSON object.
+    MalformedArgs,
+    /// The call names a tool that is not registered.
+    UnknownTool,
+    /// The tool rejected the arguments (`ToolError::ParamsNotMatched`).
+    ArgsRejected,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolCallEvent {
+    /// Model that produced the call, as reported by the backend.
+    pub model: String,
+    pub outcome: ToolCallOutcome,
+    pub tool: Option<String>,
+    /// The offending output or arguments, truncated; `None` for successful calls.
+    pub sample: Option<String>,
+}
+
+impl ToolCallEvent {
+    pub(crate) fn new(model: &str, outcome: ToolCallOutcome, tool: Option<&str>, sample: Option<&str>) -> Self {
+        Self {
+            model: model.to_string(),
+            outcome,
+            tool: tool.map(str::to_string),
+            sample: sample.map(|s| s.chars().take(MAX_SAMPLE_CHARS).collect()),
+        }
+    }
+}
+
+/// Receives one event per attempted tool call.
+pub trait ToolCallObserver: Send + Sync {
+    fn on_tool_call(&self, event: &ToolCallEvent);
+}
+
+impl<F> ToolCallObserver for F
+where
+    F: Fn(&ToolCallEvent) + Send + Sync,
+{
+    fn on_tool_call(&self, event: &ToolCallEvent) {
+        self(event)
+    }
+}
+
+/// Per-model counters collected by [`ToolCallStats`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModelToolCallStats {
+    pub counts: HashMap<ToolCallOutcome, u64>,
+    /// The most recent failures, oldest first.
+    pub samples: Vec<ToolCallEvent>,
+}
+
+impl ModelToolCallStats {
+    pub fn total(&self) -> u64 {
+        self.counts.values().sum()
+    }
+
+    pub fn failures(&self) -> u64 {
+        self.total() - self.counts.get(&ToolCallOutcome::Ok).copied().unwrap_or(0)
+    }
+
+    pub fn failure_rate(&self) -> f64 {
+        match self.total() {
+            0 => 0.0,
+            total => self.failures() as f64 / total as f64,
+        }
+    }
+}
+
+/// In-memory observer that aggregates events by model.
+#[derive(Debug)]
+pub struct ToolCallStats {
+    models: Mutex<HashMap<String, ModelToolCallStats>>,
+    max_samples: usize,
+}
+
+impl ToolCallStats {
+    /// Keep up to `max_samples` failure samples per model.
+    pub fn new(max_samples: usize) -> Self {
+        Self {
+            models: Mutex::new(HashMap::new()),
+            max_samples,
+        }
+    }
+
+    /// Copy of the current counters, keyed by model.
+    pub fn snapshot(&self) -> HashMap<String, ModelToolCallStats> {
+        self.models.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    pub fn reset(&self) {
+        self.models.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+}
+
+impl Default for ToolCallStats {
+    fn default() -> Self {
+        ToolCallStats::new(20)
+    }
+}
+
+impl ToolCallObserver for ToolCallStats {
+    fn on_tool_call(&self, event: &ToolCallEvent) {
+        let mut models = self.models.lock().unwrap_or_else(|e| e.into_inner());
+        let stats = models.entry(event.model.clone()).or_default();
+        *stats.counts.entry(event.outcome).or_default() += 1;
+        if event.outcome != ToolCallOutcome::Ok && self.max_samples > 0 {
+            if stats.samples.len() >= self.max_samples {
+                stats.samples.remove(0);
+            }
+            stats.samples.push(event.clone());
+        }
+    }
+}
@@ -1,9 +1,20 @@
 use crate::llm::traits::LLM;
-use std::sync::Arc;
-use crate::tools::traits::Tool;
+use std::sync::{Arc, Mutex};
+use crate::tools::{requirements::ToolPolicy, traits::Tool};
 use std::collections::HashMap;
 use super::error::AgentError;
 use crate::llm::tokens::TokenUsage;
+use crate::llm::cost::{CostReport, PricingTable};
+use crate::llm::options::GenerateOptions;
+use crate::llm::models::ModelInfo;
+use crate::llm::warnings::RunWarning;
+use crate::safety::{SafetyFinding, SafetyPolicy};
+use crate::message::Message;
+use crate::prompt::{datetime::TimeContext, presets::PromptPreset};
+use super::telemetry::ToolCallObserver;
+use super::events::AgentEventObserver;
+use super::memory::MemoryEntry;
+use super::repro::ReproBundle;
 use serde::{Serialize, Deserialize};
 
 /// High-level agent that holds an LLM and a set of tools, plus simple agent state.
@@ -21,19 +32,86 @@ pub struct Agent {
     /// the agent's role and available behaviors.
     pub system_prompt: Option<String>,
 
-    /// Simple short-term memory / conversation context kept by the agent.
-    /// We store `Message` objects elsewhere in the crate; for a minimal
-    /// implementation we keep user-visible strings here.
-    pub memory: Vec<String>,
+    /// Conversation remembered across runs (prompts and final answers).
+    /// Inspect and edit it with `memory_view`/`memory_edit`.
+    pub memory: Mutex<Vec<MemoryEntry>>,
+
+    /// Whether runs read from and append to `memory`.
+    pub memory_enabled: bool,
 
     /// Maximum iterations when running a looped decision process.
     pub max_iterations: usize,
+
+    /// How many times a generation cut off by the token limit is continued
+    /// before being handled as-is.
+    pub max_continuations: usize,
+
+    /// Optional content-safety policy applied to the final generation.
+    pub safety: Option<SafetyPolicy>,
+
+    /// Tool-calling prompt preset. When `None` it is picked from the
+    /// built-in registry by the LLM's model name.
+    pub prompt_preset: Option<PromptPreset>,
+
+    /// Optional observer notified of every tool call attempt and its outcome.
+    pub tool_call_observer: Option<Arc<dyn ToolCallObserver>>,
+
+    /// Optional observer of tool calls, tool results and final answers.
+    pub event_observer: Option<Arc<dyn AgentEventObserver>>,
+
+    /// Whether the tool calls of one LLM turn run concurrently.
+    pub parallel_tools: bool,
+
+    /// What registered tools may require. Checked at registration.
+    pub tool_policy: ToolPolicy,
+
+    /// Prices used to report what each run cost. `None` disables cost tracking.
+    pub pricing: Option<PricingTable>,
+
+    /// Sampling settings for the agent's LLM calls, overriding the backend's.
+    pub generate_options: GenerateOptions,
+
+    /// When set, the current date, time and locale are added to the system
+    /// prompt of every run.
+    pub time_context: Option<TimeContext>,
+
+    /// Make every tool's schema strict, not only the tools that ask for it.
+    pub strict_tools: bool,
+
+    /// BCP 47 tag of the language the agent is prompted in, e.g. `"fr-FR"`.
+    /// Tool descriptions are shown in it when a tool has a translation.
+    /// `None` falls back to the locale of `time_context`.
+    pub locale: Option<String>,
+
+    /// What the agent's model can do. When it can't call tools, registering
+    /// one fails.
+    pub model_info: Option<ModelInfo>,
+
+    /// Give every run without a configured seed a fresh random one, recorded
+    /// in its `ReproBundle`.
+    pub seed_per_run: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct AgentResult {
     pub tokens: TokenUsage,
     pub generation: String,
+    /// Safety rules that fired on the final generation.
+    #[serde(default)]
+    pub safety: Vec<SafetyFinding>,
+    /// Every message of the run, ending with the final assistant reply.
+    #[serde(default)]
+    pub transcript: Vec<Message>,
+    /// What the run's LLM calls cost, when the agent has a pricing table.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost: Option<CostReport>,
+    /// Soft failures the run recovered from, e.g. a retried LLM call or a
+    /// fallback provider.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<RunWarning>,
+    /// What the run was started with, for `Agent::rerun`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repro: Option<ReproBundle>,
 }
 
 pub type AgentExecuteResult = Result<AgentResult, AgentError>;
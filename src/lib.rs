@@ -4,11 +4,191 @@ pub mod agent;
 pub mod message;
 pub mod config;
 pub mod error;
+pub mod safety;
+pub mod prompt;
+#[cfg(feature = "testing")]
+pub mod testing;
 pub mod prelude;
 
 // re-export the proc-macro attribute for convenient use: `use mini_langchain::tool;` or `#[mini_langchain::tool(...)]`
+/// Parameters can be renamed in the schema, or left out of it and filled
+/// with `Default::default()`:
+///
+/// ```
+/// #[mini_langchain::tool(
+///     description = "Search the notes",
+///     params(query(description = "What to look for", rename = "q"), session(skip_in_schema))
+/// )]
+/// fn search(query: String, session: Option<String>) -> String {
+///     query
+/// }
+/// # fn main() {}
+/// ```
+///
+/// Options other than `description`, `rename` and `skip_in_schema` are
+/// rejected, as are non-string values and parameters missing from the
+/// signature:
+///
+/// ```compile_fail
+/// #[mini_langchain::tool(description = "Search the notes", params(query(description = "What", required)))]
+/// fn search(query: String) -> String {
+///     query
+/// }
+/// # fn main() {}
+/// ```
+///
+/// ```compile_fail
+/// #[mini_langchain::tool(description = "Search the notes", params(query(description = "What", rename = 1)))]
+/// fn search(query: String) -> String {
+///     query
+/// }
+/// # fn main() {}
+/// ```
+///
+/// ```compile_fail
+/// #[mini_langchain::tool(description = "Search the notes", params(query = "What", topic(rename = "t")))]
+/// fn search(query: String) -> String {
+///     query
+/// }
+/// # fn main() {}
+/// ```
+///
+/// The tool struct is named `<Function>Tool` and is `pub` unless
+/// `struct_name` and `vis` say otherwise. The generated types live in a
+/// hidden module that reaches the function through `super`, so `#[tool]`
+/// goes on module-level functions:
+///
+/// ```
+/// mod weather {
+///     #[mini_langchain::tool(struct_name = "Forecast", vis = "pub(crate)", description = "Forecast the weather")]
+///     fn forecast() -> String {
+///         "sunny".to_string()
+///     }
+/// }
+///
+/// fn main() {
+///     let _tool = weather::Forecast;
+/// }
+/// ```
+///
+/// `struct_name` must be an identifier and `vis` a visibility:
+///
+/// ```compile_fail
+/// #[mini_langchain::tool(struct_name = "Weather Forecast", description = "Forecast the weather")]
+/// fn forecast() -> String {
+///     "sunny".to_string()
+/// }
+/// # fn main() {}
+/// ```
+///
+/// ```compile_fail
+/// #[mini_langchain::tool(vis = "public", description = "Forecast the weather")]
+/// fn forecast() -> String {
+///     "sunny".to_string()
+/// }
+/// # fn main() {}
+/// ```
+///
+/// The struct is only visible as far as `vis` allows, and the params struct
+/// stays out of the caller's namespace:
+///
+/// ```compile_fail,E0603
+/// mod weather {
+///     #[mini_langchain::tool(vis = "pub(self)", description = "Forecast the weather")]
+///     fn forecast() -> String {
+///         "sunny".to_string()
+///     }
+/// }
+///
+/// fn main() {
+///     let _tool = weather::ForecastTool;
+/// }
+/// ```
+///
+/// ```compile_fail,E0425
+/// #[mini_langchain::tool(description = "Forecast the weather", params(days = "Days ahead"))]
+/// fn forecast(days: u32) -> String {
+///     format!("sunny for {} days", days)
+/// }
+///
+/// fn main() {
+///     let _params = ForecastToolParams { days: 1 };
+/// }
+/// ```
 #[allow(unused_imports)]
 pub use mini_langchain_macros::tool;
+/// Templates are checked when the crate compiles:
+///
+/// ```
+/// let prompt = mini_langchain::template!("Hello {name}, {{braces}} are literal");
+/// assert_eq!(prompt.render("Ada"), "Hello Ada, {braces} are literal");
+/// ```
+///
+/// An unclosed placeholder, a stray `}`, an empty `{}` or a placeholder that
+/// is not an identifier is an error:
+///
+/// ```compile_fail
+/// let prompt = mini_langchain::template!("Hello {name");
+/// ```
+///
+/// ```compile_fail
+/// let prompt = mini_langchain::template!("Hello name}");
+/// ```
+///
+/// ```compile_fail
+/// let prompt = mini_langchain::template!("Hello {}");
+/// ```
+///
+/// ```compile_fail
+/// let prompt = mini_langchain::template!("Hello {first name}");
+/// ```
+///
+/// and so is rendering with the wrong number of values:
+///
+/// ```compile_fail
+/// let prompt = mini_langchain::template!("Hello {name}");
+/// prompt.render("Ada", "Lovelace");
+/// ```
+pub use mini_langchain_macros::template;
+/// Arguments are checked when the crate compiles:
+///
+/// ```
+/// /// You are a helpful assistant.
+/// #[mini_langchain::agent(name = "helper", llm = "mini_langchain::llm::mock::MockLLM", model = "m", max_iterations = 3)]
+/// struct Helper;
+///
+/// assert_eq!(Helper::build().max_iterations, 3);
+/// ```
+///
+/// `model` needs an `llm` to apply to:
+///
+/// ```compile_fail
+/// #[mini_langchain::agent(model = "m")]
+/// struct Helper;
+/// ```
+///
+/// Unknown arguments, and `tools` entries that are not type paths, are
+/// rejected:
+///
+/// ```compile_fail
+/// #[mini_langchain::agent(temperature = "0.2")]
+/// struct Helper;
+/// ```
+///
+/// ```compile_fail
+/// #[mini_langchain::agent(tools("get_weather"))]
+/// struct Helper;
+/// ```
+///
+/// Without `llm` there is no `build()`, only `build_with_llm`:
+///
+/// ```compile_fail
+/// #[mini_langchain::agent(name = "helper")]
+/// struct Helper;
+///
+/// let agent = Helper::build();
+/// ```
+pub use mini_langchain_macros::agent;
 
 pub use async_trait;
 pub use serde_json;
@@ -3,14 +3,48 @@ pub mod openai;
 pub mod anthropic;
 pub mod qwen;
 pub mod deepseek;
+pub mod mistral;
+pub mod groq;
+pub mod openrouter;
+pub mod cohere;
+pub mod together;
+pub mod grok;
+pub mod zhipu;
+pub mod moonshot;
+pub mod huggingface;
+pub mod llamacpp;
+pub mod compatible;
 pub mod ollama;
 pub mod tokens;
 pub mod error;
+pub mod partial_json;
+pub mod continuation;
+pub mod draft_refine;
+pub mod capabilities;
+pub mod throttle;
+pub mod compress;
+pub mod cost;
+pub mod quota;
+pub mod embeddings;
+pub mod pool;
+pub mod middleware;
+pub mod layered;
+pub mod mock;
+pub mod cache;
+pub mod cassette;
+pub mod options;
+pub mod batch;
+pub mod warnings;
+pub mod reasoning;
+pub mod models;
+pub(crate) mod http;
+pub(crate) mod compat;
 
 
 use serde::{Serialize, Deserialize};
 use serde_json::Value as JsonValue;
 use tokens::TokenUsage;
+use warnings::RunWarning;
 
 /// Result of a text generation from an LLM.
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
@@ -22,15 +56,187 @@ pub struct GenerateResult {
     /// the agent to pass when invoking that tool.
     #[serde(default)]
     pub tool_calls: Vec<CallInfo>,
+    /// Why the provider stopped generating, when it reports it.
+    #[serde(default)]
+    pub finish_reason: Option<FinishReason>,
+    /// Chain-of-thought returned separately by reasoning models. Kept out of
+    /// `generation` so it is never parsed for tool calls.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning: Option<String>,
+    /// The model that actually produced the response, as reported by the
+    /// provider (may differ from the requested one with routers/aliases).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// The provider that produced the response, set by wrappers routing
+    /// between backends such as `FallbackLLM`. `None` means the one the LLM
+    /// called reports as `provider()`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    /// Log probabilities of the generated tokens, when requested and
+    /// supported by the provider.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<Vec<TokenLogprob>>,
+    /// Non-fatal problems on the way to this result, e.g. retries.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<RunWarning>,
+    /// Every completion when several were sampled (see `LLM::generate_n`),
+    /// the first being the one in the fields above. Empty otherwise.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub candidates: Vec<Candidate>,
+    /// Served by a `CachedLLM` rather than the provider, so `tokens` were
+    /// not billed again.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cached: bool,
+}
+
+/// One of several completions sampled for the same request.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Candidate {
+    pub generation: String,
+    #[serde(default)]
+    pub tool_calls: Vec<CallInfo>,
+    #[serde(default)]
+    pub finish_reason: Option<FinishReason>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<Vec<TokenLogprob>>,
+}
+
+impl From<&GenerateResult> for Candidate {
+    fn from(result: &GenerateResult) -> Self {
+        Self {
+            generation: result.generation.clone(),
+            tool_calls: result.tool_calls.clone(),
+            finish_reason: result.finish_reason.clone(),
+            reasoning: result.reasoning.clone(),
+            logprobs: result.logprobs.clone(),
+        }
+    }
+}
+
+/// Log probability of one generated token, in the OpenAI response shape.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct TokenLogprob {
+    pub token: String,
+    pub logprob: f32,
+    /// The most likely alternatives at this position.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub top_logprobs: Vec<TopLogprob>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct TopLogprob {
+    pub token: String,
+    pub logprob: f32,
+}
+
+impl GenerateResult {
+    /// Mean token log probability, a simple confidence score. `None` without
+    /// logprobs.
+    pub fn mean_logprob(&self) -> Option<f32> {
+        let logprobs = self.logprobs.as_ref().filter(|l| !l.is_empty())?;
+        Some(logprobs.iter().map(|l| l.logprob).sum::<f32>() / logprobs.len() as f32)
+    }
+
+    /// The sampled completions: `candidates`, or this result's own
+    /// completion when only one was sampled.
+    pub fn all_candidates(&self) -> Vec<Candidate> {
+        if self.candidates.is_empty() {
+            vec![Candidate::from(self)]
+        } else {
+            self.candidates.clone()
+        }
+    }
+
+    /// A result whose fields are those of the first of `candidates`, keeping
+    /// all of them in `candidates`. `None` without candidates.
+    pub fn from_candidates(candidates: Vec<Candidate>, tokens: TokenUsage, model: Option<String>) -> Option<Self> {
+        let first = candidates.first()?.clone();
+        Some(Self {
+            tokens,
+            generation: first.generation,
+            tool_calls: first.tool_calls,
+            finish_reason: first.finish_reason,
+            reasoning: first.reasoning,
+            model,
+            logprobs: first.logprobs,
+            candidates: if candidates.len() > 1 { candidates } else { Vec::new() },
+            ..Default::default()
+        })
+    }
+}
+
+/// Normalized reason a generation ended.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FinishReason {
+    /// Natural end of turn or a stop sequence was hit.
+    Stop,
+    /// The output token limit was reached; the generation is truncated.
+    Length,
+    /// The model stopped to call tools.
+    ToolCalls,
+    /// Output was withheld by a provider-side filter.
+    ContentFilter,
+    /// Any provider specific reason we don't map.
+    Other(String),
+}
+
+impl FinishReason {
+    /// Map the provider reason strings (OpenAI, Anthropic, DashScope ...) to a `FinishReason`.
+    pub fn parse(reason: &str) -> Self {
+        match reason {
+            "stop" | "end_turn" | "stop_sequence" | "eos" | "complete" => FinishReason::Stop,
+            "length" | "max_tokens" | "model_length" => FinishReason::Length,
+            "tool_calls" | "tool_use" | "function_call" | "tool_call" => FinishReason::ToolCalls,
+            "content_filter" | "refusal" => FinishReason::ContentFilter,
+            other => FinishReason::Other(other.to_string()),
+        }
+    }
 }
 
 /// Structured information about a single tool call requested by the LLM.
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct CallInfo {
+    /// Provider id of a native tool call, echoed back with its result.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
     pub name: String,
     #[serde(default)]
     pub args: JsonValue,
 }
 
 /// Result type for LLM operations.
-pub type LLMResult<T> = std::result::Result<T, error::LLMError>;
\ No newline at end of file
+pub type LLMResult<T> = std::result::Result<T, error::LLMError>;
+
+/// Extract `{"tool_calls": [{"name": ..., "args": {...}}]}` from free-form
+/// generation text. Used by backends that rely on the JSON-in-prompt protocol
+/// set up by the agent's developer message.
+pub fn extract_tool_calls(generation: &str) -> Vec<CallInfo> {
+    let mut tool_calls: Vec<CallInfo> = Vec::new();
+    let parsed_json_res = match (generation.find('{'), generation.rfind('}')) {
+        (Some(start), Some(end)) if end > start => {
+            let sub = &generation[start..=end];
+            serde_json::from_str::<JsonValue>(sub)
+        }
+        _ => {
+            serde_json::from_str::<JsonValue>(generation)
+        }
+    };
+
+    if let Ok(parsed) = parsed_json_res
+        && let Some(arr) = parsed.get("tool_calls").and_then(|v| v.as_array())
+    {
+        for entry in arr.iter() {
+            if let Some(obj) = entry.as_object()
+                && let Some(name_val) = obj.get("name").and_then(|v| v.as_str())
+            {
+                let name = name_val.to_string();
+                let args = obj.get("args").cloned().unwrap_or_else(|| serde_json::json!({}));
+                tool_calls.push(CallInfo { id: None, name, args });
+            }
+        }
+    }
+    tool_calls
+}
\ No newline at end of file
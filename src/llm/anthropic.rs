@@ -0,0 +1,565 @@
+// see https://docs.anthropic.com/en/api/messages
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+use async_stream::stream as async_stream;
+use futures::{
+    FutureExt,
+    StreamExt,
+    future::BoxFuture,
+    stream::BoxStream
+};
+
+use crate::message::{Message, MessageRole as MsgRole};
+use crate::tools::{schema::ToolSchema, stream::{StreamData, ToolCallDelta}};
+use crate::llm::{
+    traits::LLM,
+    batch,
+    pool::shared_client,
+    capabilities::Capabilities,
+    tokens::TokenUsage,
+    error::LLMError,
+    options::GenerateOptions,
+    reasoning,
+    CallInfo,
+    FinishReason,
+    http::{check_status, sse_events},
+    GenerateResult,
+    LLMResult,
+};
+
+/// Default model name used when no model is specified.
+pub const DEFAULT_MODEL: &str = "claude-sonnet-4-5";
+
+/// Claude requires `max_tokens` on every request.
+pub const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+pub const DEFAULT_BASE_URL: &str = "https://api.anthropic.com/v1";
+
+pub const API_VERSION: &str = "2023-06-01";
+
+#[derive(Debug, Clone)]
+pub struct Anthropic {
+    pub(crate) client: reqwest::Client,
+    pub(crate) api_key: String,
+    pub(crate) base_url: String,
+    pub(crate) model: String,
+    pub(crate) max_tokens: u32,
+    pub(crate) temperature: Option<f32>,
+    pub(crate) prompt_caching: bool,
+}
+
+impl Anthropic {
+    /// Create an `Anthropic` wrapper with the given API key and the default model.
+    pub fn new(api_key: impl Into<String>) -> Self {
+        Self {
+            client: shared_client(),
+            api_key: api_key.into(),
+            base_url: DEFAULT_BASE_URL.to_string(),
+            model: DEFAULT_MODEL.to_string(),
+            max_tokens: DEFAULT_MAX_TOKENS,
+            temperature: None,
+            prompt_caching: false,
+        }
+    }
+
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = max_tokens;
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    /// Mark the tool definitions and the system prompt as `cache_control`
+    /// breakpoints, so an agent that resends them on every iteration is
+    /// billed the cached rate after the first call. Prompts shorter than the
+    /// model's minimum cacheable length are sent uncached. Cache reads and
+    /// writes show up in `TokenUsage`.
+    pub fn with_prompt_caching(mut self, enabled: bool) -> Self {
+        self.prompt_caching = enabled;
+        self
+    }
+
+    /// Override the API base url (e.g. for a proxy).
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    fn generate_request(&self, messages: &[Message], tools: &[ToolSchema], stream: bool) -> MessagesRequest {
+        let mut system_parts: Vec<&str> = Vec::new();
+        let mut mapped: Vec<AnthropicMessage> = Vec::new();
+        for message in messages {
+            // native tool results go back as `tool_result` blocks in a user
+            // turn; results of one assistant turn share a single user message
+            if let Some(id) = message.tool_call_id.as_ref() {
+                let block = json!({
+                    "type": "tool_result",
+                    "tool_use_id": id,
+                    "content": message.content,
+                });
+                match mapped.last_mut() {
+                    Some(AnthropicMessage { role: "user", content: Value::Array(blocks) }) => blocks.push(block),
+                    _ => mapped.push(AnthropicMessage {
+                        role: "user",
+                        content: Value::Array(vec![block]),
+                    }),
+                }
+                continue;
+            }
+            match message.role {
+                // Claude has no system role inside `messages`; everything
+                // instruction-like goes into the top-level `system` parameter.
+                MsgRole::System | MsgRole::Developer | MsgRole::Tool => {
+                    system_parts.push(&message.content);
+                }
+                // images go in the same user turn as the tool results
+                // before them, so the turns keep alternating
+                MsgRole::User if !message.images.is_empty() => {
+                    let mut blocks = vec![json!({ "type": "text", "text": message.content })];
+                    blocks.extend(message.images.iter().map(|image| {
+                        json!({
+                            "type": "image",
+                            "source": { "type": "base64", "media_type": image.mime_type, "data": image.data },
+                        })
+                    }));
+                    match mapped.last_mut() {
+                        Some(AnthropicMessage { role: "user", content: Value::Array(previous) }) => previous.append(&mut blocks),
+                        _ => mapped.push(AnthropicMessage {
+                            role: "user",
+                            content: Value::Array(blocks),
+                        }),
+                    }
+                }
+                MsgRole::User | MsgRole::ToolResponce => mapped.push(AnthropicMessage {
+                    role: "user",
+                    content: Value::String(message.content.clone()),
+                }),
+                MsgRole::Assistant if !message.tool_calls.is_empty() => {
+                    let mut blocks = Vec::new();
+                    if !message.content.is_empty() {
+                        blocks.push(json!({ "type": "text", "text": message.content }));
+                    }
+                    for call in message.tool_calls.iter() {
+                        blocks.push(json!({
+                            "type": "tool_use",
+                            "id": call.id,
+                            "name": call.name,
+                            "input": call.args,
+                        }));
+                    }
+                    mapped.push(AnthropicMessage {
+                        role: "assistant",
+                        content: Value::Array(blocks),
+                    });
+                }
+                MsgRole::Assistant => mapped.push(AnthropicMessage {
+                    role: "assistant",
+                    content: Value::String(message.content.clone()),
+                }),
+            }
+        }
+        let tools = (!tools.is_empty()).then(|| {
+            let mut tools: Vec<Value> = tools
+                .iter()
+                .map(|tool| {
+                    json!({
+                        "name": tool.name,
+                        "description": tool.description,
+                        "input_schema": tool.parameters(),
+                    })
+                })
+                .collect();
+            // a breakpoint on the last tool caches every definition before it
+            if let (true, Some(Value::Object(last))) = (self.prompt_caching, tools.last_mut()) {
+                last.insert("cache_control".to_string(), cache_control());
+            }
+            tools
+        });
+        let system = if system_parts.is_empty() {
+            None
+        } else if self.prompt_caching {
+            Some(json!([{
+                "type": "text",
+                "text": system_parts.join("\n\n"),
+                "cache_control": cache_control(),
+            }]))
+        } else {
+            Some(Value::String(system_parts.join("\n\n")))
+        };
+        MessagesRequest {
+            model: self.model.clone(),
+            max_tokens: self.max_tokens,
+            system,
+            messages: mapped,
+            temperature: self.temperature,
+            top_p: None,
+            stop_sequences: None,
+            tools,
+            stream,
+        }
+    }
+
+    async fn create(&self, request: &MessagesRequest) -> LLMResult<GenerateResult> {
+        let response: MessagesResponse = self.send(request).await?.json().await?;
+
+        let generation = response
+            .content
+            .iter()
+            .filter(|block| block.block_type == "text")
+            .filter_map(|block| block.text.as_deref())
+            .collect::<Vec<_>>()
+            .join("");
+        let thinking = response
+            .content
+            .iter()
+            .filter(|block| block.block_type == "thinking")
+            .filter_map(|block| block.thinking.as_deref())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let (generation, reasoning) = reasoning::separate(generation, Some(thinking));
+        let native_calls: Vec<CallInfo> = response
+            .content
+            .iter()
+            .filter(|block| block.block_type == "tool_use")
+            .map(|block| CallInfo {
+                id: block.id.clone(),
+                name: block.name.clone().unwrap_or_default(),
+                args: block.input.clone().unwrap_or_else(|| json!({})),
+            })
+            .collect();
+        let tool_calls = if native_calls.is_empty() {
+            crate::llm::extract_tool_calls(&generation)
+        } else {
+            native_calls
+        };
+        let tokens = TokenUsage::from(&response.usage);
+        let finish_reason = response.stop_reason.as_deref().map(FinishReason::parse);
+
+        Ok(GenerateResult { tokens, generation, tool_calls, finish_reason, reasoning, model: response.model, ..Default::default() })
+    }
+
+    async fn send(&self, request: &MessagesRequest) -> LLMResult<reqwest::Response> {
+        let response = self
+            .client
+            .post(format!("{}/messages", self.base_url))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", API_VERSION)
+            .json(request)
+            .send()
+            .await?;
+        check_status(response).await
+    }
+
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+}
+
+impl Default for Anthropic {
+    /// Read the API key from `ANTHROPIC_API_KEY`.
+    fn default() -> Self {
+        Anthropic::new(std::env::var("ANTHROPIC_API_KEY").unwrap_or_default())
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct AnthropicMessage {
+    role: &'static str,
+    /// A plain string or an array of content blocks.
+    content: Value,
+}
+
+#[derive(Debug, Serialize)]
+struct MessagesRequest {
+    model: String,
+    max_tokens: u32,
+    /// A plain string, or one text block when it carries `cache_control`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<Value>,
+    messages: Vec<AnthropicMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_sequences: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<Value>>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+}
+
+impl MessagesRequest {
+    fn apply_options(&mut self, options: &GenerateOptions) {
+        if let Some(max_tokens) = options.max_tokens {
+            self.max_tokens = max_tokens;
+        }
+        if options.temperature.is_some() {
+            self.temperature = options.temperature;
+        }
+        if options.top_p.is_some() {
+            self.top_p = options.top_p;
+        }
+        if let Some(stop) = options.stop_sequences() {
+            self.stop_sequences = Some(stop);
+        }
+    }
+}
+
+fn cache_control() -> Value {
+    json!({ "type": "ephemeral" })
+}
+
+#[derive(Debug, Deserialize, Default, Clone)]
+struct AnthropicUsage {
+    /// Uncached prompt tokens only.
+    #[serde(default)]
+    input_tokens: u32,
+    #[serde(default)]
+    output_tokens: u32,
+    #[serde(default)]
+    cache_creation_input_tokens: u32,
+    #[serde(default)]
+    cache_read_input_tokens: u32,
+}
+
+impl From<&AnthropicUsage> for TokenUsage {
+    fn from(usage: &AnthropicUsage) -> Self {
+        let prompt_tokens = usage.input_tokens + usage.cache_creation_input_tokens + usage.cache_read_input_tokens;
+        TokenUsage {
+            cache_read_tokens: usage.cache_read_input_tokens,
+            cache_write_tokens: usage.cache_creation_input_tokens,
+            ..TokenUsage::new(prompt_tokens, usage.output_tokens)
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ContentBlock {
+    #[serde(rename = "type")]
+    block_type: String,
+    #[serde(default)]
+    text: Option<String>,
+    /// Extended thinking, in `thinking` blocks.
+    #[serde(default)]
+    thinking: Option<String>,
+    /// `tool_use` blocks carry `id`, `name` and `input`.
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    input: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MessagesResponse {
+    #[serde(default)]
+    model: Option<String>,
+    content: Vec<ContentBlock>,
+    #[serde(default)]
+    stop_reason: Option<String>,
+    #[serde(default)]
+    usage: AnthropicUsage,
+}
+
+impl LLM for Anthropic {
+    fn generate<'a>(&'a self, messages: &'a [Message]) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        async move {
+            let request = self.generate_request(messages, &[], false);
+            self.create(&request).await
+        }
+        .boxed()
+    }
+
+    fn generate_with_tools<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        async move {
+            let request = self.generate_request(messages, tools, false);
+            self.create(&request).await
+        }
+        .boxed()
+    }
+
+    fn generate_batch<'a>(&'a self, batches: &'a [Vec<Message>]) -> BoxFuture<'a, Vec<LLMResult<GenerateResult>>> {
+        batch::generate_concurrently(self, batches, batch::DEFAULT_CONCURRENCY).boxed()
+    }
+
+    fn generate_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        async move {
+            let mut request = self.generate_request(messages, tools, false);
+            request.apply_options(options);
+            self.create(&request).await
+        }
+        .boxed()
+    }
+
+    fn stream<'a>(&'a self, messages: &'a [Message]) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.stream_with_tools(messages, &[])
+    }
+
+    fn stream_with_tools<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.stream_with_options(messages, tools, GenerateOptions::none())
+    }
+
+    fn stream_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        let this = self;
+        let msgs = messages;
+
+        let s = async_stream! {
+            let mut request = this.generate_request(msgs, tools, true);
+            request.apply_options(options);
+            let response = match this.send(&request).await {
+                Ok(response) => response,
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            };
+
+            // input tokens are reported in `message_start`, output tokens in `message_delta`
+            let mut usage = AnthropicUsage::default();
+            let mut stop_reason = None;
+            // content block index of each `tool_use` block, in call order
+            let mut tool_blocks: Vec<u64> = Vec::new();
+            let mut events = sse_events(response);
+            while let Some(event_res) = events.next().await {
+                let event = match event_res {
+                    Ok(event) => event,
+                    Err(e) => {
+                        yield Err(e);
+                        return;
+                    }
+                };
+                let value: Value = match serde_json::from_str(&event.data) {
+                    Ok(value) => value,
+                    Err(e) => {
+                        yield Err(LLMError::from(e));
+                        continue;
+                    }
+                };
+                let event_type = value
+                    .get("type")
+                    .and_then(|t| t.as_str())
+                    .unwrap_or_default()
+                    .to_string();
+                match event_type.as_str() {
+                    "message_start" => {
+                        if let Some(u) = value.pointer("/message/usage") {
+                            usage = serde_json::from_value(u.clone()).unwrap_or_default();
+                        }
+                    }
+                    "content_block_start" if value.pointer("/content_block/type").and_then(|t| t.as_str()) == Some("tool_use") => {
+                        tool_blocks.push(value.get("index").and_then(|i| i.as_u64()).unwrap_or_default());
+                        let delta = ToolCallDelta {
+                            index: tool_blocks.len() - 1,
+                            id: value.pointer("/content_block/id").and_then(|id| id.as_str()).map(str::to_string),
+                            name: value.pointer("/content_block/name").and_then(|n| n.as_str()).map(str::to_string),
+                            arguments: String::new(),
+                        };
+                        yield Ok(StreamData::tool_calls(value, vec![delta]));
+                    }
+                    "content_block_delta" if value.pointer("/delta/type").and_then(|t| t.as_str()) == Some("input_json_delta") => {
+                        let block = value.get("index").and_then(|i| i.as_u64()).unwrap_or_default();
+                        let index = tool_blocks.iter().position(|b| *b == block).unwrap_or_default();
+                        let delta = ToolCallDelta {
+                            index,
+                            arguments: value
+                                .pointer("/delta/partial_json")
+                                .and_then(|p| p.as_str())
+                                .unwrap_or_default()
+                                .to_string(),
+                            ..Default::default()
+                        };
+                        yield Ok(StreamData::tool_calls(value, vec![delta]));
+                    }
+                    "content_block_delta" if value.pointer("/delta/type").and_then(|t| t.as_str()) == Some("thinking_delta") => {
+                        let thinking = value
+                            .pointer("/delta/thinking")
+                            .and_then(|t| t.as_str())
+                            .unwrap_or_default()
+                            .to_string();
+                        let mut data = StreamData::new(value, None, "");
+                        data.reasoning = thinking;
+                        yield Ok(data);
+                    }
+                    "content_block_delta" => {
+                        let content = value
+                            .pointer("/delta/text")
+                            .and_then(|t| t.as_str())
+                            .unwrap_or_default()
+                            .to_string();
+                        yield Ok(StreamData::new(value, None, content));
+                    }
+                    "message_delta" => {
+                        if let Some(output) = value.pointer("/usage/output_tokens").and_then(|t| t.as_u64()) {
+                            usage.output_tokens = output as u32;
+                        }
+                        if let Some(reason) = value.pointer("/delta/stop_reason").and_then(|r| r.as_str()) {
+                            stop_reason = Some(FinishReason::parse(reason));
+                        }
+                        let tokens = TokenUsage::from(&usage);
+                        yield Ok(StreamData::new(value, Some(tokens), ""));
+                    }
+                    "error" => {
+                        let message = value
+                            .pointer("/error/message")
+                            .and_then(|m| m.as_str())
+                            .unwrap_or("unknown stream error")
+                            .to_string();
+                        yield Err(LLMError::InvalidResponse(message));
+                        return;
+                    }
+                    "message_stop" => {
+                        yield Ok(StreamData::done(Some(TokenUsage::from(&usage)), stop_reason.take()));
+                        return;
+                    }
+                    _ => {}
+                }
+            }
+        };
+
+        Box::pin(s)
+    }
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            native_tools: true,
+            vision: true,
+            ..Capabilities::default()
+        }
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.model)
+    }
+
+    fn provider(&self) -> Option<&str> {
+        Some("anthropic")
+    }
+}
@@ -0,0 +1,89 @@
+//! Run many independent generations, e.g. classifying hundreds of inputs.
+//!
+//! `LLM::generate_batch` is sequential by default; HTTP backends override it
+//! with `generate_concurrently`, which keeps at most `limit` requests in
+//! flight. Decorators keep the sequential default so their per-call logic
+//! (retries, cost tracking, caching) still runs for every item; call
+//! `generate_concurrently` on the decorated LLM to batch through them.
+//!
+//! `generate_n_sequentially` samples several completions of one request for
+//! backends that can't return them from a single call.
+
+use futures::future::join_all;
+use tokio::sync::Semaphore;
+
+use crate::message::Message;
+use crate::tools::schema::ToolSchema;
+use crate::llm::{
+    traits::LLM,
+    error::LLMError,
+    options::GenerateOptions,
+    tokens::TokenUsage,
+    Candidate,
+    GenerateResult,
+    LLMResult,
+};
+
+/// Requests in flight at once for the hosted providers' `generate_batch`.
+pub const DEFAULT_CONCURRENCY: usize = 8;
+
+/// Generate a result for every conversation in `batches`, running at most
+/// `limit` requests at a time. Results are in input order; one failure does
+/// not stop the others.
+pub async fn generate_concurrently<L: LLM + ?Sized>(
+    llm: &L,
+    batches: &[Vec<Message>],
+    limit: usize,
+) -> Vec<LLMResult<GenerateResult>> {
+    let permits = Semaphore::new(limit.max(1));
+    let permits = &permits;
+    join_all(batches.iter().map(|messages| async move {
+        // the semaphore is never closed
+        let _permit = permits.acquire().await.expect("semaphore closed");
+        llm.generate(messages).await
+    }))
+    .await
+}
+
+/// Generate the conversations in `batches` one after another.
+pub async fn generate_sequentially<L: LLM + ?Sized>(llm: &L, batches: &[Vec<Message>]) -> Vec<LLMResult<GenerateResult>> {
+    let mut results = Vec::with_capacity(batches.len());
+    for messages in batches {
+        results.push(llm.generate(messages).await);
+    }
+    results
+}
+
+/// Reject a request for no completions, which no backend can answer.
+pub fn check_n(n: usize) -> LLMResult<()> {
+    if n == 0 {
+        return Err(LLMError::InvalidRequest("generate_n needs at least one completion".to_string()));
+    }
+    Ok(())
+}
+
+/// Sample `n` completions of one request with `n` calls, summing their
+/// usage. Fails if any call fails.
+pub async fn generate_n_sequentially<L: LLM + ?Sized>(
+    llm: &L,
+    messages: &[Message],
+    tools: &[ToolSchema],
+    options: &GenerateOptions,
+    n: usize,
+) -> LLMResult<GenerateResult> {
+    check_n(n)?;
+    let mut candidates = Vec::with_capacity(n);
+    let mut tokens = TokenUsage::default();
+    let mut model = None;
+    let mut warnings = Vec::new();
+    for _ in 0..n {
+        let result = llm.generate_with_options(messages, tools, options).await?;
+        tokens.add(&result.tokens);
+        model = model.or(result.model.clone());
+        warnings.extend(result.warnings.iter().cloned());
+        candidates.push(Candidate::from(&result));
+    }
+    let mut result = GenerateResult::from_candidates(candidates, tokens, model).unwrap_or_default();
+    result.warnings = warnings;
+    Ok(result)
+}
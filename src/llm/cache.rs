@@ -0,0 +1,491 @@
+//! Response caching so repeated prompts during development don't cost money
+//! or time.
+//!
+//! `CachedLLM` keys each call on the provider, the model, the messages, the
+//! tool schemas and any per-call `GenerateOptions`, and stores the
+//! `GenerateResult` in a pluggable `ResponseCache`: `MemoryCache` (LRU) or
+//! `DiskCache` (one JSON file per entry).
+//!
+//! Only calls with a temperature of zero are cached; everything else,
+//! including `generate` and `stream` which can't pin one, is passed through
+//! unless `with_cache_sampled` opts in. Hits come back with
+//! `GenerateResult::cached` set, so an agent's `CostReport` counts them as
+//! savings instead of spend.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use async_stream::stream as async_stream;
+use futures::{
+    FutureExt,
+    StreamExt,
+    future::BoxFuture,
+    stream::BoxStream
+};
+use serde::{Serialize, Deserialize};
+use serde_json::json;
+
+use crate::message::Message;
+use crate::tools::{schema::ToolSchema, stream::{StreamData, ToolCallDelta, ToolCallDeltas}};
+use crate::llm::{
+    traits::LLM,
+    capabilities::Capabilities,
+    tokens::TokenUsage,
+    options::GenerateOptions,
+    GenerateResult,
+    LLMResult,
+};
+
+#[async_trait::async_trait]
+pub trait ResponseCache: Send + Sync {
+    async fn get(&self, key: &str) -> Option<GenerateResult>;
+    async fn put(&self, key: &str, result: &GenerateResult);
+}
+
+/// In-memory cache that evicts the least recently used entry when full.
+#[derive(Debug)]
+pub struct MemoryCache {
+    capacity: usize,
+    state: Mutex<MemoryState>,
+}
+
+#[derive(Debug, Default)]
+struct MemoryState {
+    tick: u64,
+    entries: HashMap<String, (u64, GenerateResult)>,
+}
+
+impl MemoryCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            state: Mutex::new(MemoryState::default()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.lock().map(|state| state.entries.len()).unwrap_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[async_trait::async_trait]
+impl ResponseCache for MemoryCache {
+    async fn get(&self, key: &str) -> Option<GenerateResult> {
+        let mut state = self.state.lock().ok()?;
+        state.tick += 1;
+        let tick = state.tick;
+        let (used, result) = state.entries.get_mut(key)?;
+        *used = tick;
+        Some(result.clone())
+    }
+
+    async fn put(&self, key: &str, result: &GenerateResult) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        state.tick += 1;
+        let tick = state.tick;
+        if !state.entries.contains_key(key)
+            && state.entries.len() >= self.capacity
+            && let Some(oldest) = state
+                .entries
+                .iter()
+                .min_by_key(|(_, (used, _))| *used)
+                .map(|(key, _)| key.clone())
+        {
+            state.entries.remove(&oldest);
+        }
+        state.entries.insert(key.to_string(), (tick, result.clone()));
+    }
+}
+
+/// Cache persisted as `<dir>/<key>.json`, shared across runs.
+#[derive(Debug, Clone)]
+pub struct DiskCache {
+    dir: PathBuf,
+}
+
+impl DiskCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+}
+
+#[async_trait::async_trait]
+impl ResponseCache for DiskCache {
+    async fn get(&self, key: &str) -> Option<GenerateResult> {
+        let raw = tokio::fs::read(self.path(key)).await.ok()?;
+        serde_json::from_slice(&raw).ok()
+    }
+
+    async fn put(&self, key: &str, result: &GenerateResult) {
+        // a failed write only costs a future cache miss
+        let Ok(raw) = serde_json::to_vec_pretty(result) else {
+            return;
+        };
+        if tokio::fs::create_dir_all(&self.dir).await.is_ok() {
+            let _ = tokio::fs::write(self.path(key), raw).await;
+        }
+    }
+}
+
+/// Hit/miss counters of a `CachedLLM`, or of the calls in a `CostReport`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// Tokens the cached responses originally cost.
+    pub saved_tokens: TokenUsage,
+}
+
+impl CacheStats {
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+/// FNV-1a, stable across runs and Rust versions (unlike `DefaultHasher`),
+/// which the disk cache relies on.
+fn fnv1a(seed: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(seed, |hash, b| (hash ^ *b as u64).wrapping_mul(0x100000001b3))
+}
+
+/// Serve repeated calls from `cache`. Streams replay a hit as a single chunk.
+pub struct CachedLLM<L> {
+    pub inner: L,
+    pub cache: Arc<dyn ResponseCache>,
+    stats: Mutex<CacheStats>,
+    cache_sampled: bool,
+}
+
+impl<L: LLM> CachedLLM<L> {
+    pub fn new(inner: L, cache: Arc<dyn ResponseCache>) -> Self {
+        Self {
+            inner,
+            cache,
+            stats: Mutex::new(CacheStats::default()),
+            cache_sampled: false,
+        }
+    }
+
+    /// Also cache calls without a temperature of zero, replaying one sample
+    /// for every later call. Use this when the backend itself is configured
+    /// to be deterministic, or for `generate` and `stream`.
+    pub fn with_cache_sampled(mut self, cache_sampled: bool) -> Self {
+        self.cache_sampled = cache_sampled;
+        self
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.stats.lock().map(|stats| stats.clone()).unwrap_or_default()
+    }
+
+    /// Cache key of a request.
+    pub fn cache_key(&self, messages: &[Message], tools: &[ToolSchema]) -> String {
+        self.cache_key_with(messages, tools, &GenerateOptions::default())
+    }
+
+    /// Cache key of a request with per-call `options`. Empty options give the
+    /// same key as `cache_key`.
+    pub fn cache_key_with(&self, messages: &[Message], tools: &[ToolSchema], options: &GenerateOptions) -> String {
+        let mut material = json!({
+            "provider": self.inner.provider(),
+            "model": self.inner.model_name(),
+            "messages": messages,
+            "tools": tools,
+        });
+        if !options.is_empty() {
+            material["options"] = json!(options);
+        }
+        let raw = serde_json::to_vec(&material).unwrap_or_default();
+        // two differently seeded hashes keep collisions negligible
+        format!("{:016x}{:016x}", fnv1a(0xcbf29ce484222325, &raw), fnv1a(0x84222325cbf29ce4, &raw))
+    }
+
+    /// Whether a call with `options` may be cached. Without a temperature
+    /// the backend's default applies, which is usually sampled.
+    fn cacheable(&self, options: &GenerateOptions) -> bool {
+        self.cache_sampled || options.temperature == Some(0.0)
+    }
+
+    /// Cache `result` without its warnings, which belong to the original call.
+    async fn store(&self, key: &str, result: &GenerateResult) {
+        if result.warnings.is_empty() {
+            self.cache.put(key, result).await;
+        } else {
+            let mut result = result.clone();
+            result.warnings.clear();
+            self.cache.put(key, &result).await;
+        }
+    }
+
+    async fn lookup(&self, key: &str) -> Option<GenerateResult> {
+        let mut hit = self.cache.get(key).await;
+        if let Some(result) = hit.as_mut() {
+            result.cached = true;
+        }
+        if let Ok(mut stats) = self.stats.lock() {
+            match hit.as_ref() {
+                Some(result) => {
+                    stats.hits += 1;
+                    stats.saved_tokens.add(&result.tokens);
+                }
+                None => stats.misses += 1,
+            }
+        }
+        hit
+    }
+}
+
+impl<L: LLM> LLM for CachedLLM<L> {
+    fn generate<'a>(&'a self, messages: &'a [Message]) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        async move {
+            if !self.cache_sampled {
+                return self.inner.generate(messages).await;
+            }
+            let key = self.cache_key(messages, &[]);
+            if let Some(result) = self.lookup(&key).await {
+                return Ok(result);
+            }
+            let result = self.inner.generate(messages).await?;
+            self.store(&key, &result).await;
+            Ok(result)
+        }
+        .boxed()
+    }
+
+    fn generate_with_tools<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        async move {
+            if !self.cache_sampled {
+                return self.inner.generate_with_tools(messages, tools).await;
+            }
+            let key = self.cache_key(messages, tools);
+            if let Some(result) = self.lookup(&key).await {
+                return Ok(result);
+            }
+            let result = self.inner.generate_with_tools(messages, tools).await?;
+            self.store(&key, &result).await;
+            Ok(result)
+        }
+        .boxed()
+    }
+
+    fn generate_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        async move {
+            if !self.cacheable(options) {
+                return self.inner.generate_with_options(messages, tools, options).await;
+            }
+            let key = self.cache_key_with(messages, tools, options);
+            if let Some(result) = self.lookup(&key).await {
+                return Ok(result);
+            }
+            let result = self.inner.generate_with_options(messages, tools, options).await?;
+            self.store(&key, &result).await;
+            Ok(result)
+        }
+        .boxed()
+    }
+
+    /// Cached as one entry per `n`, so a hit returns all the candidates
+    /// rather than one completion repeated.
+    fn generate_n<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+        n: usize,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        async move {
+            if !self.cacheable(options) {
+                return self.inner.generate_n(messages, tools, options, n).await;
+            }
+            let key = format!("{}-n{}", self.cache_key_with(messages, tools, options), n);
+            if let Some(result) = self.lookup(&key).await {
+                return Ok(result);
+            }
+            let result = self.inner.generate_n(messages, tools, options, n).await?;
+            self.store(&key, &result).await;
+            Ok(result)
+        }
+        .boxed()
+    }
+
+    fn stream<'a>(&'a self, messages: &'a [Message]) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.stream_with_tools(messages, &[])
+    }
+
+    fn stream_with_tools<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.stream_with_options(messages, tools, GenerateOptions::none())
+    }
+
+    fn stream_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        if !self.cacheable(options) {
+            return self.inner.stream_with_options(messages, tools, options);
+        }
+        let this = self;
+        let s = async_stream! {
+            let key = this.cache_key_with(messages, tools, options);
+            if let Some(result) = this.lookup(&key).await {
+                let mut data = StreamData::new(json!({ "cached": true }), Some(result.tokens.clone()), result.generation);
+                data.reasoning = result.reasoning.unwrap_or_default();
+                if !tools.is_empty() {
+                    data.tool_calls = result
+                        .tool_calls
+                        .iter()
+                        .enumerate()
+                        .map(|(index, call)| ToolCallDelta::whole(index, call))
+                        .collect();
+                }
+                yield Ok(data);
+                yield Ok(StreamData::done(Some(result.tokens), result.finish_reason));
+                return;
+            }
+            let mut upstream = this.inner.stream_with_options(messages, tools, options);
+            let mut tool_calls = ToolCallDeltas::new();
+            let mut generation = String::new();
+            let mut reasoning = String::new();
+            let mut tokens = None;
+            let mut finish_reason = None;
+            let mut failed = false;
+            let mut done = false;
+            while let Some(item) = upstream.next().await {
+                match item.as_ref() {
+                    Ok(data) => {
+                        generation.push_str(&data.content);
+                        reasoning.push_str(&data.reasoning);
+                        for delta in &data.tool_calls {
+                            tool_calls.push(delta);
+                        }
+                        if data.tokens.is_some() {
+                            tokens = data.tokens.clone();
+                        }
+                        if data.done {
+                            done = true;
+                            finish_reason = data.finish_reason.clone();
+                        }
+                    }
+                    Err(_) => failed = true,
+                }
+                yield item;
+            }
+            // a stream that ended without its done marker was cut off
+            if done && !failed {
+                let tool_calls = if tool_calls.is_empty() {
+                    crate::llm::extract_tool_calls(&generation)
+                } else {
+                    tool_calls.finish()
+                };
+                let result = GenerateResult {
+                    tokens: tokens.unwrap_or_default(),
+                    generation,
+                    tool_calls,
+                    finish_reason,
+                    reasoning: (!reasoning.is_empty()).then_some(reasoning),
+                    model: this.inner.model_name().map(str::to_string),
+                    ..Default::default()
+                };
+                this.cache.put(&key, &result).await;
+            }
+        };
+
+        Box::pin(s)
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        self.inner.model_name()
+    }
+
+    fn provider(&self) -> Option<&str> {
+        self.inner.provider()
+    }
+
+    fn health_check(&self) -> BoxFuture<'_, LLMResult<()>> {
+        self.inner.health_check()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::mock::MockLLM;
+
+    fn mock(provider: &str) -> MockLLM {
+        MockLLM::new()
+            .with_model("shared-model")
+            .with_provider(provider)
+            .with_response("first")
+            .with_response("second")
+    }
+
+    #[tokio::test]
+    async fn caches_only_at_zero_temperature_unless_opted_in() {
+        let messages = [Message::user("hi")];
+        let llm = CachedLLM::new(mock("openai"), Arc::new(MemoryCache::new(8)));
+        assert_eq!(llm.generate(&messages).await.expect("generate").generation, "first");
+        assert_eq!(llm.generate(&messages).await.expect("generate").generation, "second");
+        assert_eq!((llm.stats().hits, llm.stats().misses), (0, 0));
+
+        let llm = CachedLLM::new(mock("openai"), Arc::new(MemoryCache::new(8)));
+        let unset = GenerateOptions::new().with_max_tokens(10);
+        llm.generate_with_options(&messages, &[], &unset).await.expect("generate");
+        assert_eq!(llm.inner.remaining(), 1);
+        let zero = GenerateOptions::new().with_temperature(0.0);
+        llm.generate_with_options(&messages, &[], &zero).await.expect("generate");
+        assert!(llm.generate_with_options(&messages, &[], &zero).await.expect("cached").cached);
+
+        let llm = CachedLLM::new(mock("openai"), Arc::new(MemoryCache::new(8))).with_cache_sampled(true);
+        llm.generate(&messages).await.expect("generate");
+        assert!(llm.generate(&messages).await.expect("cached").cached);
+        assert_eq!(llm.inner.remaining(), 1);
+    }
+
+    #[tokio::test]
+    async fn keys_on_the_provider() {
+        let messages = [Message::user("hi")];
+        let options = GenerateOptions::new().with_temperature(0.0);
+        let cache: Arc<dyn ResponseCache> = Arc::new(MemoryCache::new(8));
+        let openai = CachedLLM::new(mock("openai"), cache.clone());
+        let groq = CachedLLM::new(mock("groq"), cache);
+        assert_ne!(openai.cache_key(&messages, &[]), groq.cache_key(&messages, &[]));
+
+        openai.generate_with_options(&messages, &[], &options).await.expect("generate");
+        let answer = groq.generate_with_options(&messages, &[], &options).await.expect("generate");
+        assert!(!answer.cached);
+        assert_eq!(groq.inner.remaining(), 1);
+    }
+}
@@ -0,0 +1,174 @@
+//! Describe what a backend supports so callers can adapt the request instead
+//! of failing at the provider.
+
+use std::borrow::Cow;
+use futures::{
+    future::BoxFuture,
+    stream::BoxStream
+};
+use serde::{Serialize, Deserialize};
+
+use crate::message::{Message, MessageRole};
+use crate::tools::{schema::ToolSchema, stream::StreamData};
+use crate::llm::{
+    traits::LLM,
+    options::GenerateOptions,
+    GenerateResult,
+    LLMResult,
+};
+
+/// Features a backend/model combination supports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Capabilities {
+    /// Accepts tool schemas natively and returns structured tool calls.
+    /// When `false` the agent uses the JSON-in-prompt protocol.
+    pub native_tools: bool,
+    /// Understands system (and developer) messages.
+    pub system_role: bool,
+    /// Accepts images in messages (`Message::images`).
+    #[serde(default)]
+    pub vision: bool,
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self {
+            native_tools: false,
+            system_role: true,
+            vision: false,
+        }
+    }
+}
+
+/// Rewrite `messages` so they only use features `capabilities` allows.
+///
+/// Without a system role, system/developer messages and tool definitions
+/// (tool messages without a name) are prepended to the first user message.
+/// Prompt-mode tool results stay where they are, as user messages, so the
+/// model reads each one after the call that produced it. Borrows when
+/// nothing needs to change.
+pub fn adapt_messages<'a>(capabilities: &Capabilities, messages: &'a [Message]) -> Cow<'a, [Message]> {
+    if capabilities.system_role {
+        return Cow::Borrowed(messages);
+    }
+    let is_instruction = |m: &Message| match m.role {
+        MessageRole::System | MessageRole::Developer => true,
+        MessageRole::Tool => m.name.is_none(),
+        _ => false,
+    };
+    let is_tool_result = |m: &Message| matches!(m.role, MessageRole::Tool) && m.name.is_some();
+    if !messages.iter().any(|m| is_instruction(m) || is_tool_result(m)) {
+        return Cow::Borrowed(messages);
+    }
+
+    let instructions = messages
+        .iter()
+        .filter(|m| is_instruction(m))
+        .map(|m| m.content.as_str())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let mut adapted: Vec<Message> = messages
+        .iter()
+        .filter(|m| !is_instruction(m))
+        .map(|m| {
+            let mut m = m.clone();
+            if is_tool_result(&m) {
+                m.role = MessageRole::User;
+            }
+            m
+        })
+        .collect();
+    if instructions.is_empty() {
+        return Cow::Owned(adapted);
+    }
+    match adapted.iter_mut().find(|m| matches!(m.role, MessageRole::User)) {
+        Some(first_user) => {
+            first_user.content = format!("{}\n\n{}", instructions, first_user.content);
+        }
+        None => adapted.insert(0, Message::user(instructions)),
+    }
+    Cow::Owned(adapted)
+}
+
+/// Override the capabilities reported by an `LLM`, e.g. for a local model
+/// without a system role.
+pub struct WithCapabilities<L> {
+    pub inner: L,
+    pub capabilities: Capabilities,
+}
+
+impl<L: LLM> WithCapabilities<L> {
+    pub fn new(inner: L, capabilities: Capabilities) -> Self {
+        Self { inner, capabilities }
+    }
+}
+
+impl<L: LLM> LLM for WithCapabilities<L> {
+    fn generate<'a>(&'a self, messages: &'a [Message]) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.inner.generate(messages)
+    }
+
+    fn generate_with_tools<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.inner.generate_with_tools(messages, tools)
+    }
+
+    fn generate_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.inner.generate_with_options(messages, tools, options)
+    }
+
+    fn generate_n<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+        n: usize,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.inner.generate_n(messages, tools, options, n)
+    }
+
+    fn stream<'a>(&'a self, messages: &'a [Message]) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.inner.stream(messages)
+    }
+
+    fn stream_with_tools<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.inner.stream_with_tools(messages, tools)
+    }
+
+    fn stream_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.inner.stream_with_options(messages, tools, options)
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.capabilities
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        self.inner.model_name()
+    }
+
+    fn provider(&self) -> Option<&str> {
+        self.inner.provider()
+    }
+
+    fn health_check(&self) -> BoxFuture<'_, LLMResult<()>> {
+        self.inner.health_check()
+    }
+}
@@ -0,0 +1,451 @@
+//! Record real LLM calls once and replay them in tests.
+//!
+//! `RecordingLLM` passes calls through to a real backend and saves every
+//! request with its result to a [`Cassette`] file. `ReplayLLM` serves a
+//! cassette back without a network: each request gets the result recorded
+//! for an identical request, so agent tests can run in CI against real model
+//! behaviour. `record_or_replay` picks one depending on whether the cassette
+//! exists; delete the file to record again.
+//!
+//! ```ignore
+//! let llm = record_or_replay("tests/cassettes/weather.json", || OpenAI::new())?;
+//! let mut agent = Agent::new("weather", llm, None);
+//! agent.register_tool(None, Arc::new(GetWeatherTool))?;
+//! let result = agent.call_llm("Weather in Paris?").await?;
+//! ```
+//!
+//! Replay only matches requests that are identical to the recorded ones, so
+//! prompts must not change between runs: leave out time contexts and per-run
+//! seeds. Failed calls are not recorded.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use async_stream::stream as async_stream;
+use futures::{
+    FutureExt,
+    StreamExt,
+    future::BoxFuture,
+    stream::{self, BoxStream}
+};
+use serde::{Serialize, Deserialize};
+use serde_json::json;
+
+use crate::message::Message;
+use crate::tools::{schema::ToolSchema, stream::{StreamData, ToolCallDelta, ToolCallDeltas}};
+use crate::llm::{
+    traits::LLM,
+    capabilities::Capabilities,
+    error::LLMError,
+    middleware::LLMRequest,
+    options::GenerateOptions,
+    GenerateResult,
+    LLMResult,
+};
+
+/// One recorded call.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    pub request: LLMRequest,
+    /// The completions asked for, when the call was `LLM::generate_n`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub n: Option<usize>,
+    pub response: GenerateResult,
+}
+
+/// The calls of a recording, with what the backend reported about itself so
+/// the replay looks the same to an agent.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Cassette {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub capabilities: Capabilities,
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    pub fn load(path: impl AsRef<Path>) -> LLMResult<Self> {
+        let raw = std::fs::read(path)?;
+        Ok(serde_json::from_slice(&raw)?)
+    }
+
+    /// Save as pretty JSON, so recordings diff well under version control.
+    pub fn save(&self, path: impl AsRef<Path>) -> LLMResult<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Replay the cassette at `path` if it exists, otherwise record the calls of
+/// the LLM `record_with` returns into it.
+pub fn record_or_replay<L, F>(path: impl Into<PathBuf>, record_with: F) -> LLMResult<Arc<dyn LLM>>
+where
+    L: LLM + 'static,
+    F: FnOnce() -> L,
+{
+    let path = path.into();
+    if path.exists() {
+        Ok(Arc::new(ReplayLLM::open(path)?))
+    } else {
+        Ok(Arc::new(RecordingLLM::new(record_with(), path)))
+    }
+}
+
+/// Save every successful call of the wrapped LLM to a cassette file. The
+/// file is rewritten after each call, replacing any earlier recording.
+pub struct RecordingLLM<L> {
+    pub inner: L,
+    path: PathBuf,
+    cassette: Mutex<Cassette>,
+}
+
+impl<L: LLM> RecordingLLM<L> {
+    pub fn new(inner: L, path: impl Into<PathBuf>) -> Self {
+        let cassette = Cassette {
+            provider: inner.provider().map(str::to_string),
+            model: inner.model_name().map(str::to_string),
+            capabilities: inner.capabilities(),
+            interactions: Vec::new(),
+        };
+        Self {
+            inner,
+            path: path.into(),
+            cassette: Mutex::new(cassette),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The calls recorded so far.
+    pub fn cassette(&self) -> Cassette {
+        self.cassette.lock().map(|cassette| cassette.clone()).unwrap_or_default()
+    }
+
+    /// Record `response` without its warnings, which belong to the original
+    /// call.
+    fn record(&self, request: LLMRequest, n: Option<usize>, response: &GenerateResult) -> LLMResult<()> {
+        let mut response = response.clone();
+        response.warnings.clear();
+        let mut cassette = self.cassette.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        cassette.interactions.push(Interaction { request, n, response });
+        cassette.save(&self.path)
+    }
+
+    async fn call(
+        &self,
+        request: LLMRequest,
+        n: Option<usize>,
+        call: BoxFuture<'_, LLMResult<GenerateResult>>,
+    ) -> LLMResult<GenerateResult> {
+        let result = call.await?;
+        self.record(request, n, &result)?;
+        Ok(result)
+    }
+}
+
+impl<L: LLM> LLM for RecordingLLM<L> {
+    fn generate<'a>(&'a self, messages: &'a [Message]) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        let request = LLMRequest {
+            messages: messages.to_vec(),
+            ..Default::default()
+        };
+        self.call(request, None, self.inner.generate(messages)).boxed()
+    }
+
+    fn generate_with_tools<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        let request = LLMRequest {
+            messages: messages.to_vec(),
+            tools: tools.to_vec(),
+            ..Default::default()
+        };
+        self.call(request, None, self.inner.generate_with_tools(messages, tools)).boxed()
+    }
+
+    fn generate_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        let request = LLMRequest {
+            messages: messages.to_vec(),
+            tools: tools.to_vec(),
+            options: options.clone(),
+        };
+        self.call(request, None, self.inner.generate_with_options(messages, tools, options)).boxed()
+    }
+
+    fn generate_n<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+        n: usize,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        let request = LLMRequest {
+            messages: messages.to_vec(),
+            tools: tools.to_vec(),
+            options: options.clone(),
+        };
+        self.call(request, Some(n), self.inner.generate_n(messages, tools, options, n)).boxed()
+    }
+
+    fn stream<'a>(&'a self, messages: &'a [Message]) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.stream_with_tools(messages, &[])
+    }
+
+    fn stream_with_tools<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.stream_with_options(messages, tools, GenerateOptions::none())
+    }
+
+    /// Recorded once the done item arrives; a stream that fails or is cut
+    /// off is not.
+    fn stream_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        let this = self;
+        let s = async_stream! {
+            let mut upstream = this.inner.stream_with_options(messages, tools, options);
+            let mut result = GenerateResult::default();
+            let mut tool_calls = ToolCallDeltas::new();
+            let mut reasoning = String::new();
+            while let Some(item) = upstream.next().await {
+                let data = match item {
+                    Ok(data) => data,
+                    Err(e) => {
+                        yield Err(e);
+                        return;
+                    }
+                };
+                result.generation.push_str(&data.content);
+                reasoning.push_str(&data.reasoning);
+                for delta in &data.tool_calls {
+                    tool_calls.push(delta);
+                }
+                if let Some(tokens) = data.tokens.as_ref() {
+                    result.tokens = tokens.clone();
+                }
+                // consumers may stop reading at the done item
+                if data.done {
+                    result.finish_reason = data.finish_reason.clone();
+                    result.reasoning = (!reasoning.is_empty()).then_some(std::mem::take(&mut reasoning));
+                    result.tool_calls = if tool_calls.is_empty() {
+                        crate::llm::extract_tool_calls(&result.generation)
+                    } else {
+                        tool_calls.finish()
+                    };
+                    result.model = this.inner.model_name().map(str::to_string);
+                    let request = LLMRequest {
+                        messages: messages.to_vec(),
+                        tools: tools.to_vec(),
+                        options: options.clone(),
+                    };
+                    if let Err(e) = this.record(request, None, &result) {
+                        yield Err(e);
+                        return;
+                    }
+                }
+                yield Ok(data);
+            }
+        };
+
+        Box::pin(s)
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        self.inner.model_name()
+    }
+
+    fn provider(&self) -> Option<&str> {
+        self.inner.provider()
+    }
+
+    fn health_check(&self) -> BoxFuture<'_, LLMResult<()>> {
+        self.inner.health_check()
+    }
+}
+
+/// Serve the results of a cassette. Each request gets the first unused
+/// interaction recorded for an identical request, so a request repeated
+/// during the recording replays its results in the same order. A request
+/// that was never recorded fails with `LLMError::InvalidResponse`.
+#[derive(Debug)]
+pub struct ReplayLLM {
+    cassette: Cassette,
+    used: Mutex<Vec<bool>>,
+}
+
+impl ReplayLLM {
+    pub fn new(cassette: Cassette) -> Self {
+        let used = vec![false; cassette.interactions.len()];
+        Self {
+            cassette,
+            used: Mutex::new(used),
+        }
+    }
+
+    pub fn open(path: impl AsRef<Path>) -> LLMResult<Self> {
+        Ok(Self::new(Cassette::load(path)?))
+    }
+
+    /// Recorded interactions not replayed yet.
+    pub fn remaining(&self) -> usize {
+        self.used
+            .lock()
+            .map(|used| used.iter().filter(|used| !**used).count())
+            .unwrap_or_default()
+    }
+
+    fn replay(&self, request: LLMRequest, n: Option<usize>) -> LLMResult<GenerateResult> {
+        let mut used = self.used.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let found = self
+            .cassette
+            .interactions
+            .iter()
+            .enumerate()
+            .find(|(index, interaction)| !used[*index] && interaction.n == n && interaction.request == request);
+        match found {
+            Some((index, interaction)) => {
+                used[index] = true;
+                Ok(interaction.response.clone())
+            }
+            None => {
+                let last = request.messages.last().map(|m| m.content.as_str()).unwrap_or_default();
+                Err(LLMError::InvalidResponse(format!(
+                    "no recorded response for this request ({} messages, last: {:?}); record the cassette again",
+                    request.messages.len(),
+                    last
+                )))
+            }
+        }
+    }
+}
+
+impl LLM for ReplayLLM {
+    fn generate<'a>(&'a self, messages: &'a [Message]) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.generate_with_tools(messages, &[])
+    }
+
+    fn generate_with_tools<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        let result = self.replay(LLMRequest {
+            messages: messages.to_vec(),
+            tools: tools.to_vec(),
+            ..Default::default()
+        }, None);
+        async move { result }.boxed()
+    }
+
+    fn generate_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        let result = self.replay(LLMRequest {
+            messages: messages.to_vec(),
+            tools: tools.to_vec(),
+            options: options.clone(),
+        }, None);
+        async move { result }.boxed()
+    }
+
+    fn generate_n<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+        n: usize,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        let result = self.replay(LLMRequest {
+            messages: messages.to_vec(),
+            tools: tools.to_vec(),
+            options: options.clone(),
+        }, Some(n));
+        async move { result }.boxed()
+    }
+
+    fn stream<'a>(&'a self, messages: &'a [Message]) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.stream_with_tools(messages, &[])
+    }
+
+    fn stream_with_tools<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.stream_with_options(messages, tools, GenerateOptions::none())
+    }
+
+    /// The recorded result as one chunk followed by the done item.
+    fn stream_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        let result = match self.replay(LLMRequest {
+            messages: messages.to_vec(),
+            tools: tools.to_vec(),
+            options: options.clone(),
+        }, None) {
+            Ok(result) => result,
+            Err(e) => return stream::iter([Err(e)]).boxed(),
+        };
+        let mut data = StreamData::new(json!({ "replayed": true }), Some(result.tokens.clone()), result.generation);
+        data.reasoning = result.reasoning.unwrap_or_default();
+        // without tools the calls were parsed from the text, as they will be again
+        if !tools.is_empty() {
+            data.tool_calls = result
+                .tool_calls
+                .iter()
+                .enumerate()
+                .map(|(index, call)| ToolCallDelta::whole(index, call))
+                .collect();
+        }
+        stream::iter([Ok(data), Ok(StreamData::done(Some(result.tokens), result.finish_reason))]).boxed()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.cassette.capabilities
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        self.cassette.model.as_deref()
+    }
+
+    fn provider(&self) -> Option<&str> {
+        self.cassette.provider.as_deref()
+    }
+
+    /// Always healthy: there is no backend to reach.
+    fn health_check(&self) -> BoxFuture<'_, LLMResult<()>> {
+        async { Ok(()) }.boxed()
+    }
+}
@@ -0,0 +1,460 @@
+// see https://docs.cohere.com/reference/chat
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use async_stream::stream as async_stream;
+use futures::{
+    FutureExt,
+    StreamExt,
+    future::BoxFuture,
+    stream::BoxStream
+};
+
+use crate::message::{Message, MessageRole as MsgRole};
+use crate::tools::{schema::ToolSchema, stream::{StreamData, ToolCallDelta}};
+use crate::llm::{
+    traits::LLM,
+    batch,
+    capabilities::Capabilities,
+    compat::{to_chat_tool_calls, to_chat_tools},
+    pool::shared_client,
+    tokens::TokenUsage,
+    error::LLMError,
+    http::{check_status, sse_events},
+    options::GenerateOptions,
+    CallInfo,
+    FinishReason,
+    GenerateResult,
+    LLMResult,
+};
+
+pub const DEFAULT_BASE_URL: &str = "https://api.cohere.com/v2";
+
+/// Default model name used when no model is specified.
+pub const DEFAULT_MODEL: &str = "command-r-plus";
+
+#[derive(Debug, Clone)]
+pub struct Cohere {
+    pub(crate) client: reqwest::Client,
+    pub(crate) api_key: String,
+    pub(crate) base_url: String,
+    pub(crate) model: String,
+    pub(crate) max_tokens: Option<u32>,
+    pub(crate) temperature: Option<f32>,
+}
+
+impl Cohere {
+    /// Create a `Cohere` wrapper with the given API key and the default model.
+    pub fn new(api_key: impl Into<String>) -> Self {
+        Self {
+            client: shared_client(),
+            api_key: api_key.into(),
+            base_url: DEFAULT_BASE_URL.to_string(),
+            model: DEFAULT_MODEL.to_string(),
+            max_tokens: None,
+            temperature: None,
+        }
+    }
+
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    fn generate_request(&self, messages: &[Message], stream: bool) -> ChatRequest {
+        let messages = messages
+            .iter()
+            .map(|message| {
+                let role = match message.role {
+                    _ if message.tool_call_id.is_some() => "tool",
+                    MsgRole::System | MsgRole::Developer | MsgRole::Tool => "system",
+                    MsgRole::User | MsgRole::ToolResponce => "user",
+                    MsgRole::Assistant => "assistant",
+                };
+                CohereMessage {
+                    role,
+                    content: message.content.clone(),
+                    tool_calls: Some(to_chat_tool_calls(&message.tool_calls)).filter(|calls| !calls.is_empty()),
+                    tool_call_id: message.tool_call_id.clone(),
+                }
+            })
+            .collect();
+        ChatRequest {
+            model: self.model.clone(),
+            messages,
+            stream,
+            max_tokens: self.max_tokens,
+            temperature: self.temperature,
+            p: None,
+            stop_sequences: None,
+            seed: None,
+            tools: None,
+        }
+    }
+
+    async fn create(&self, request: &ChatRequest) -> LLMResult<GenerateResult> {
+        let response: ChatResponse = self.send(request).await?.json().await?;
+
+        let generation = response
+            .message
+            .content
+            .iter()
+            .filter(|block| block.block_type == "text")
+            .filter_map(|block| block.text.as_deref())
+            .collect::<Vec<_>>()
+            .join("");
+        let tool_calls = if response.message.tool_calls.is_empty() {
+            crate::llm::extract_tool_calls(&generation)
+        } else {
+            response.message.tool_calls.iter().map(CallInfo::from).collect()
+        };
+        let tokens = TokenUsage::from(&response.usage);
+        let finish_reason = response
+            .finish_reason
+            .as_deref()
+            .map(|r| FinishReason::parse(&r.to_ascii_lowercase()));
+
+        Ok(GenerateResult {
+            tokens,
+            generation,
+            tool_calls,
+            finish_reason,
+            model: Some(self.model.clone()),
+            ..Default::default()
+        })
+    }
+
+    async fn send(&self, request: &ChatRequest) -> LLMResult<reqwest::Response> {
+        let response = self
+            .client
+            .post(format!("{}/chat", self.base_url))
+            .bearer_auth(&self.api_key)
+            .json(request)
+            .send()
+            .await?;
+        check_status(response).await
+    }
+
+    fn stream_request(&self, request: ChatRequest) -> BoxStream<'_, LLMResult<StreamData>> {
+        let this = self;
+
+        let s = async_stream! {
+            let response = match this.send(&request).await {
+                Ok(response) => response,
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            };
+            let mut events = sse_events(response);
+            while let Some(event_res) = events.next().await {
+                let event = match event_res {
+                    Ok(event) => event,
+                    Err(e) => {
+                        yield Err(e);
+                        return;
+                    }
+                };
+                let value: Value = match serde_json::from_str(&event.data) {
+                    Ok(value) => value,
+                    Err(e) => {
+                        yield Err(LLMError::from(e));
+                        continue;
+                    }
+                };
+                let event_type = value
+                    .get("type")
+                    .and_then(|t| t.as_str())
+                    .unwrap_or_default()
+                    .to_string();
+                match event_type.as_str() {
+                    "content-delta" => {
+                        let content = value
+                            .pointer("/delta/message/content/text")
+                            .and_then(|t| t.as_str())
+                            .unwrap_or_default()
+                            .to_string();
+                        yield Ok(StreamData::new(value, None, content));
+                    }
+                    "tool-call-start" => {
+                        let call = value.pointer("/delta/message/tool_calls");
+                        let delta = ToolCallDelta {
+                            index: value.get("index").and_then(|i| i.as_u64()).unwrap_or_default() as usize,
+                            id: call.and_then(|c| c.get("id")).and_then(|id| id.as_str()).map(str::to_string),
+                            name: call.and_then(|c| c.pointer("/function/name")).and_then(|n| n.as_str()).map(str::to_string),
+                            arguments: call
+                                .and_then(|c| c.pointer("/function/arguments"))
+                                .and_then(|a| a.as_str())
+                                .unwrap_or_default()
+                                .to_string(),
+                        };
+                        yield Ok(StreamData::tool_calls(value, vec![delta]));
+                    }
+                    "tool-call-delta" => {
+                        let delta = ToolCallDelta {
+                            index: value.get("index").and_then(|i| i.as_u64()).unwrap_or_default() as usize,
+                            arguments: value
+                                .pointer("/delta/message/tool_calls/function/arguments")
+                                .and_then(|a| a.as_str())
+                                .unwrap_or_default()
+                                .to_string(),
+                            ..Default::default()
+                        };
+                        yield Ok(StreamData::tool_calls(value, vec![delta]));
+                    }
+                    "message-end" => {
+                        let tokens = value
+                            .pointer("/delta/usage")
+                            .and_then(|u| serde_json::from_value::<CohereUsage>(u.clone()).ok())
+                            .map(|u| TokenUsage::from(&u));
+                        let finish_reason = value
+                            .pointer("/delta/finish_reason")
+                            .and_then(|r| r.as_str())
+                            .map(|r| FinishReason::parse(&r.to_ascii_lowercase()));
+                        yield Ok(StreamData::new(value, tokens.clone(), ""));
+                        yield Ok(StreamData::done(tokens, finish_reason));
+                        return;
+                    }
+                    _ => {}
+                }
+            }
+        };
+
+        Box::pin(s)
+    }
+
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+}
+
+impl Default for Cohere {
+    /// Read the API key from `COHERE_API_KEY`.
+    fn default() -> Self {
+        Cohere::new(std::env::var("COHERE_API_KEY").unwrap_or_default())
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct CohereMessage {
+    role: &'static str,
+    content: String,
+    /// Native calls of an assistant turn, in the same shape as OpenAI's.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_calls: Option<Vec<Value>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_call_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ChatRequest {
+    model: String,
+    messages: Vec<CohereMessage>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    /// Cohere's name for `top_p`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_sequences: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<i64>,
+    /// Function definitions; Cohere v2 takes the OpenAI format.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<Value>>,
+}
+
+impl ChatRequest {
+    fn apply_options(&mut self, options: &GenerateOptions) {
+        if options.max_tokens.is_some() {
+            self.max_tokens = options.max_tokens;
+        }
+        if options.temperature.is_some() {
+            self.temperature = options.temperature;
+        }
+        if options.top_p.is_some() {
+            self.p = options.top_p;
+        }
+        if let Some(stop) = options.stop_sequences() {
+            self.stop_sequences = Some(stop);
+        }
+        if options.seed.is_some() {
+            self.seed = options.seed;
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct BilledUnits {
+    #[serde(default)]
+    input_tokens: f64,
+    #[serde(default)]
+    output_tokens: f64,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct CohereUsage {
+    #[serde(default)]
+    billed_units: BilledUnits,
+}
+
+impl From<&CohereUsage> for TokenUsage {
+    /// Cohere bills by `billed_units`, which is what we report.
+    fn from(usage: &CohereUsage) -> Self {
+        TokenUsage::new(usage.billed_units.input_tokens as u32, usage.billed_units.output_tokens as u32)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CohereFunction {
+    name: String,
+    #[serde(default)]
+    arguments: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct CohereToolCall {
+    #[serde(default)]
+    id: Option<String>,
+    function: CohereFunction,
+}
+
+impl From<&CohereToolCall> for CallInfo {
+    fn from(tool_call: &CohereToolCall) -> Self {
+        let args = match &tool_call.function.arguments {
+            Value::String(raw) => serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.clone())),
+            other => other.clone(),
+        };
+        CallInfo {
+            id: tool_call.id.clone(),
+            name: tool_call.function.name.clone(),
+            args,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ContentBlock {
+    #[serde(rename = "type")]
+    block_type: String,
+    #[serde(default)]
+    text: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct ResponseMessage {
+    #[serde(default)]
+    content: Vec<ContentBlock>,
+    #[serde(default)]
+    tool_calls: Vec<CohereToolCall>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatResponse {
+    #[serde(default)]
+    finish_reason: Option<String>,
+    #[serde(default)]
+    message: ResponseMessage,
+    #[serde(default)]
+    usage: CohereUsage,
+}
+
+impl LLM for Cohere {
+    fn generate<'a>(&'a self, messages: &'a [Message]) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        async move {
+            let request = self.generate_request(messages, false);
+            self.create(&request).await
+        }
+        .boxed()
+    }
+
+    fn generate_with_tools<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        async move {
+            let mut request = self.generate_request(messages, false);
+            request.tools = (!tools.is_empty()).then(|| to_chat_tools(tools));
+            self.create(&request).await
+        }
+        .boxed()
+    }
+
+    fn generate_batch<'a>(&'a self, batches: &'a [Vec<Message>]) -> BoxFuture<'a, Vec<LLMResult<GenerateResult>>> {
+        batch::generate_concurrently(self, batches, batch::DEFAULT_CONCURRENCY).boxed()
+    }
+
+    fn generate_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        async move {
+            let mut request = self.generate_request(messages, false);
+            request.tools = (!tools.is_empty()).then(|| to_chat_tools(tools));
+            request.apply_options(options);
+            self.create(&request).await
+        }
+        .boxed()
+    }
+
+    fn stream<'a>(&'a self, messages: &'a [Message]) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.stream_request(self.generate_request(messages, true))
+    }
+
+    fn stream_with_tools<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.stream_with_options(messages, tools, GenerateOptions::none())
+    }
+
+    fn stream_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        let mut request = self.generate_request(messages, true);
+        request.tools = (!tools.is_empty()).then(|| to_chat_tools(tools));
+        request.apply_options(options);
+        self.stream_request(request)
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            native_tools: true,
+            ..Capabilities::default()
+        }
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.model)
+    }
+
+    fn provider(&self) -> Option<&str> {
+        Some("cohere")
+    }
+}
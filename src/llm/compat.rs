@@ -0,0 +1,592 @@
+//! Shared client for OpenAI-shaped `/chat/completions` endpoints.
+//!
+//! Providers that speak this dialect (DashScope compatible-mode, DeepSeek, ...)
+//! embed a `ChatCompletions` and only customise urls, headers and extra body
+//! fields.
+
+use async_stream::stream as async_stream;
+use futures::{StreamExt, stream::BoxStream};
+use reqwest::header::HeaderMap;
+use serde::{Serialize, Deserialize};
+use serde_json::{Map, Value};
+
+use crate::message::{Message, MessageRole as MsgRole};
+use crate::tools::{schema::ToolSchema, stream::{StreamData, ToolCallDelta}};
+use crate::llm::{
+    tokens::TokenUsage,
+    error::LLMError,
+    http::{check_status, sse_events},
+    pool::shared_client,
+    options::GenerateOptions,
+    reasoning::{self, ReasoningSplitter},
+    CallInfo,
+    Candidate,
+    FinishReason,
+    GenerateResult,
+    LLMResult,
+    TokenLogprob,
+};
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct ChatMessage {
+    pub role: &'static str,
+    /// A string, or text and `image_url` parts when the message has images.
+    pub content: Value,
+    /// Moonshot partial mode: the model continues this assistant message.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub partial: Option<bool>,
+    /// Native calls of an assistant turn, see [`to_chat_tool_calls`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<Value>>,
+    /// The call a `tool` message answers.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+}
+
+/// Map our roles onto the plain `system`/`user`/`assistant` roles every
+/// compatible server understands. Only native tool calls, which carry a
+/// provider id, use the `tool` role and assistant `tool_calls`.
+pub(crate) fn to_chat_messages(messages: &[Message]) -> Vec<ChatMessage> {
+    messages
+        .iter()
+        .map(|message| {
+            let role = match message.role {
+                _ if message.tool_call_id.is_some() => "tool",
+                MsgRole::System | MsgRole::Tool | MsgRole::Developer => "system",
+                MsgRole::User | MsgRole::ToolResponce => "user",
+                MsgRole::Assistant => "assistant",
+            };
+            let tool_calls = Some(to_chat_tool_calls(&message.tool_calls)).filter(|calls| !calls.is_empty());
+            ChatMessage {
+                role,
+                content: to_chat_content(message),
+                partial: None,
+                tool_calls,
+                tool_call_id: message.tool_call_id.clone(),
+            }
+        })
+        .collect()
+}
+
+fn to_chat_content(message: &Message) -> Value {
+    if message.images.is_empty() {
+        return Value::String(message.content.clone());
+    }
+    let mut parts = vec![serde_json::json!({ "type": "text", "text": message.content })];
+    parts.extend(message.images.iter().map(|image| {
+        serde_json::json!({ "type": "image_url", "image_url": { "url": image.data_url() } })
+    }));
+    Value::Array(parts)
+}
+
+/// `tool_calls` entries for the calls that have a provider id; calls parsed
+/// from the JSON-in-prompt protocol stay in the message text only.
+pub(crate) fn to_chat_tool_calls(calls: &[CallInfo]) -> Vec<Value> {
+    calls
+        .iter()
+        .filter_map(|call| {
+            let arguments = match &call.args {
+                Value::String(raw) => raw.clone(),
+                args => args.to_string(),
+            };
+            Some(serde_json::json!({
+                "id": call.id.as_ref()?,
+                "type": "function",
+                "function": { "name": call.name, "arguments": arguments },
+            }))
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct ChatRequest {
+    pub model: String,
+    pub messages: Vec<ChatMessage>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream_options: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+    /// Native function definitions, see [`to_chat_tools`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<Value>>,
+    /// Provider specific fields merged into the top-level body.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl ChatRequest {
+    /// Override the configured sampling settings with per-call `options`.
+    pub fn apply_options(&mut self, options: &GenerateOptions) {
+        if options.max_tokens.is_some() {
+            self.max_tokens = options.max_tokens;
+        }
+        if options.temperature.is_some() {
+            self.temperature = options.temperature;
+        }
+        if options.top_p.is_some() {
+            self.top_p = options.top_p;
+        }
+        if let Some(stop) = options.stop_sequences() {
+            self.stop = Some(stop);
+        }
+        if options.seed.is_some() {
+            self.seed = options.seed;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+pub(crate) struct ChatUsage {
+    #[serde(default)]
+    pub prompt_tokens: u32,
+    #[serde(default)]
+    pub completion_tokens: u32,
+    #[serde(default)]
+    pub total_tokens: u32,
+}
+
+impl From<&ChatUsage> for TokenUsage {
+    fn from(usage: &ChatUsage) -> Self {
+        TokenUsage {
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+            total_tokens: usage.total_tokens,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct ChatFunction {
+    pub name: String,
+    #[serde(default)]
+    pub arguments: Value,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct ChatToolCall {
+    #[serde(default)]
+    pub id: Option<String>,
+    pub function: ChatFunction,
+}
+
+/// A `choices[].delta.tool_calls` entry of a streamed chunk.
+#[derive(Debug, Clone, Deserialize)]
+struct ChatToolCallChunk {
+    #[serde(default)]
+    index: usize,
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default)]
+    function: Option<ChatFunctionChunk>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ChatFunctionChunk {
+    #[serde(default)]
+    name: Option<String>,
+    /// Usually a fragment of a JSON string; servers that send whole calls
+    /// may use an object.
+    #[serde(default)]
+    arguments: Value,
+}
+
+impl From<ChatToolCallChunk> for ToolCallDelta {
+    fn from(chunk: ChatToolCallChunk) -> Self {
+        let (name, arguments) = match chunk.function {
+            Some(function) => {
+                let arguments = match function.arguments {
+                    Value::String(fragment) => fragment,
+                    Value::Null => String::new(),
+                    other => other.to_string(),
+                };
+                (function.name, arguments)
+            }
+            None => (None, String::new()),
+        };
+        ToolCallDelta { index: chunk.index, id: chunk.id, name, arguments }
+    }
+}
+
+impl From<&ChatToolCall> for CallInfo {
+    /// `arguments` is usually a JSON string, but some servers send an object.
+    fn from(tool_call: &ChatToolCall) -> Self {
+        let args = match &tool_call.function.arguments {
+            Value::String(raw) => serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.clone())),
+            Value::Null => Value::Object(Map::new()),
+            other => other.clone(),
+        };
+        CallInfo {
+            id: tool_call.id.clone(),
+            name: tool_call.function.name.clone(),
+            args,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+pub(crate) struct ChatResponseMessage {
+    #[serde(default)]
+    pub content: Option<String>,
+    /// DeepSeek style `reasoning_content`.
+    #[serde(default)]
+    pub reasoning_content: Option<String>,
+    #[serde(default)]
+    pub tool_calls: Option<Vec<ChatToolCall>>,
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+pub(crate) struct ChatLogprobs {
+    #[serde(default)]
+    pub content: Option<Vec<TokenLogprob>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct ChatChoice {
+    #[serde(default)]
+    pub message: ChatResponseMessage,
+    #[serde(default)]
+    pub finish_reason: Option<String>,
+    #[serde(default)]
+    pub logprobs: Option<ChatLogprobs>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct ChatResponse {
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub choices: Vec<ChatChoice>,
+    #[serde(default)]
+    pub usage: Option<ChatUsage>,
+}
+
+impl ChatResponse {
+    pub fn into_generate_result(self) -> LLMResult<GenerateResult> {
+        let tokens = self.usage.as_ref().map(TokenUsage::from).unwrap_or_default();
+        let candidates = self.choices.into_iter().map(ChatChoice::into_candidate).collect();
+        GenerateResult::from_candidates(candidates, tokens, self.model)
+            .ok_or_else(|| LLMError::InvalidResponse("no choices in response".to_string()))
+    }
+}
+
+impl ChatChoice {
+    fn into_candidate(self) -> Candidate {
+        let finish_reason = self.finish_reason.as_deref().map(FinishReason::parse);
+        let logprobs = self.logprobs.and_then(|l| l.content);
+        let (generation, reasoning) =
+            reasoning::separate(self.message.content.unwrap_or_default(), self.message.reasoning_content);
+        let tool_calls = match self.message.tool_calls {
+            Some(calls) if !calls.is_empty() => calls.iter().map(CallInfo::from).collect(),
+            _ => crate::llm::extract_tool_calls(&generation),
+        };
+        Candidate { generation, tool_calls, finish_reason, reasoning, logprobs }
+    }
+}
+
+/// `tools` entries in the OpenAI function format.
+pub(crate) fn to_chat_tools(tools: &[ToolSchema]) -> Vec<Value> {
+    tools
+        .iter()
+        .map(|tool| {
+            serde_json::json!({
+                "type": "function",
+                "function": {
+                    "name": tool.name,
+                    "description": tool.description,
+                    "parameters": tool.parameters(),
+                }
+            })
+        })
+        .collect()
+}
+
+/// Build a `GenerateResult` from a loosely OpenAI-shaped response, with a
+/// candidate per choice.
+///
+/// Accepts content given as a string, as an array of `{text}` parts or as a
+/// legacy completions `text` field; missing usage and ids are tolerated.
+pub(crate) fn relaxed_generate_result(value: Value) -> LLMResult<GenerateResult> {
+    let candidates = value
+        .get("choices")
+        .and_then(|choices| choices.as_array())
+        .map(|choices| choices.iter().map(relaxed_candidate).collect())
+        .unwrap_or_default();
+    let tokens = value
+        .get("usage")
+        .and_then(|u| serde_json::from_value::<ChatUsage>(u.clone()).ok())
+        .map(|u| {
+            let mut tokens = TokenUsage::from(&u);
+            if tokens.total_tokens == 0 {
+                tokens.total_tokens = tokens.prompt_tokens + tokens.completion_tokens;
+            }
+            tokens
+        })
+        .unwrap_or_default();
+    let model = value.get("model").and_then(|m| m.as_str()).map(str::to_string);
+    GenerateResult::from_candidates(candidates, tokens, model)
+        .ok_or_else(|| LLMError::InvalidResponse(format!("no choices in response: {}", value)))
+}
+
+fn relaxed_candidate(choice: &Value) -> Candidate {
+    let message = choice.get("message").or_else(|| choice.get("delta"));
+
+    let generation = match message.and_then(|m| m.get("content")) {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(parts)) => parts
+            .iter()
+            .filter_map(|part| part.get("text").and_then(|t| t.as_str()).or_else(|| part.as_str()))
+            .collect::<Vec<_>>()
+            .join(""),
+        _ => choice.get("text").and_then(|t| t.as_str()).unwrap_or_default().to_string(),
+    };
+    let provided = message
+        .and_then(|m| m.get("reasoning_content").or_else(|| m.get("reasoning")))
+        .and_then(|r| r.as_str())
+        .map(str::to_string);
+    let (generation, reasoning) = reasoning::separate(generation, provided);
+    let native_calls: Vec<CallInfo> = message
+        .and_then(|m| m.get("tool_calls"))
+        .and_then(|calls| calls.as_array())
+        .map(|calls| {
+            calls
+                .iter()
+                .filter_map(|call| serde_json::from_value::<ChatToolCall>(call.clone()).ok())
+                .map(|call| CallInfo::from(&call))
+                .collect()
+        })
+        .unwrap_or_default();
+    let tool_calls = if native_calls.is_empty() {
+        crate::llm::extract_tool_calls(&generation)
+    } else {
+        native_calls
+    };
+    let finish_reason = choice
+        .get("finish_reason")
+        .and_then(|r| r.as_str())
+        .map(FinishReason::parse);
+    let logprobs = choice
+        .pointer("/logprobs/content")
+        .and_then(|l| serde_json::from_value::<Vec<TokenLogprob>>(l.clone()).ok());
+
+    Candidate { generation, tool_calls, finish_reason, reasoning, logprobs }
+}
+
+/// Connection settings and defaults for one compatible endpoint.
+#[derive(Debug, Clone)]
+pub(crate) struct ChatCompletions {
+    pub client: reqwest::Client,
+    pub base_url: String,
+    pub api_key: Option<String>,
+    pub headers: HeaderMap,
+    pub model: String,
+    pub max_tokens: Option<u32>,
+    pub temperature: Option<f32>,
+    pub extra: Map<String, Value>,
+    /// Send `stream_options.include_usage`; some servers reject the field and
+    /// report usage in the last chunk anyway.
+    pub stream_usage: bool,
+}
+
+impl ChatCompletions {
+    pub fn new(base_url: impl Into<String>, model: impl Into<String>) -> Self {
+        Self {
+            client: shared_client(),
+            base_url: base_url.into(),
+            api_key: None,
+            headers: HeaderMap::new(),
+            model: model.into(),
+            max_tokens: None,
+            temperature: None,
+            extra: Map::new(),
+            stream_usage: true,
+        }
+    }
+
+    pub fn request_body(&self, messages: &[Message], stream: bool) -> ChatRequest {
+        ChatRequest {
+            model: self.model.clone(),
+            messages: to_chat_messages(messages),
+            stream,
+            stream_options: (stream && self.stream_usage).then(|| serde_json::json!({ "include_usage": true })),
+            max_tokens: self.max_tokens,
+            temperature: self.temperature,
+            top_p: None,
+            stop: None,
+            seed: None,
+            tools: None,
+            extra: self.extra.clone(),
+        }
+    }
+
+    pub async fn send(&self, body: &ChatRequest) -> LLMResult<reqwest::Response> {
+        let url = format!("{}/chat/completions", self.base_url.trim_end_matches('/'));
+        let mut request = self
+            .client
+            .post(url)
+            .headers(self.headers.clone())
+            .json(body);
+        if let Some(api_key) = self.api_key.as_ref() {
+            request = request.bearer_auth(api_key);
+        }
+        check_status(request.send().await?).await
+    }
+
+    pub async fn generate(&self, messages: &[Message]) -> LLMResult<GenerateResult> {
+        let body = self.request_body(messages, false);
+        let response: ChatResponse = self.send(&body).await?.json().await?;
+        response.into_generate_result()
+    }
+
+    pub async fn generate_with(&self, messages: &[Message], options: &GenerateOptions) -> LLMResult<GenerateResult> {
+        let mut body = self.request_body(messages, false);
+        body.apply_options(options);
+        let response: ChatResponse = self.send(&body).await?.json().await?;
+        response.into_generate_result()
+    }
+
+    /// Like [`generate_with`](Self::generate_with), offering `tools` as
+    /// native tool definitions for providers that accept them.
+    pub async fn generate_with_tools(
+        &self,
+        messages: &[Message],
+        tools: &[ToolSchema],
+        options: &GenerateOptions,
+    ) -> LLMResult<GenerateResult> {
+        let mut body = self.request_body(messages, false);
+        body.tools = (!tools.is_empty()).then(|| to_chat_tools(tools));
+        body.apply_options(options);
+        let response: ChatResponse = self.send(&body).await?.json().await?;
+        response.into_generate_result()
+    }
+
+    pub fn stream<'a>(&'a self, messages: &'a [Message]) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.stream_body(self.request_body(messages, true))
+    }
+
+    pub fn stream_with_tools<'a>(&'a self, messages: &'a [Message], tools: &[ToolSchema]) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.stream_with_options(messages, tools, GenerateOptions::none())
+    }
+
+    /// Like [`stream_with_tools`](Self::stream_with_tools) with per-call
+    /// `options`.
+    pub fn stream_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &[ToolSchema],
+        options: &GenerateOptions,
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        let mut body = self.request_body(messages, true);
+        body.tools = (!tools.is_empty()).then(|| to_chat_tools(tools));
+        body.apply_options(options);
+        self.stream_body(body)
+    }
+
+    /// Stream a prepared request body, for providers that adjust it first.
+    pub fn stream_body(&self, body: ChatRequest) -> BoxStream<'_, LLMResult<StreamData>> {
+        let this = self;
+
+        let s = async_stream! {
+            let response = match this.send(&body).await {
+                Ok(response) => response,
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            };
+            let mut chunks = chunk_stream(response);
+            while let Some(chunk) = chunks.next().await {
+                yield chunk;
+            }
+        };
+
+        Box::pin(s)
+    }
+}
+
+/// Decode an SSE body of `chat.completion.chunk` objects into `StreamData`.
+pub(crate) fn chunk_stream(response: reqwest::Response) -> BoxStream<'static, LLMResult<StreamData>> {
+    let s = async_stream! {
+        let mut events = sse_events(response);
+        let mut usage = None;
+        let mut finish_reason = None;
+        let mut finished = false;
+        let mut splitter = ReasoningSplitter::new();
+        while let Some(event_res) = events.next().await {
+            let event = match event_res {
+                Ok(event) => event,
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            };
+            if event.data == "[DONE]" {
+                finished = true;
+                break;
+            }
+            let value: Value = match serde_json::from_str(&event.data) {
+                Ok(value) => value,
+                Err(e) => {
+                    yield Err(LLMError::from(e));
+                    continue;
+                }
+            };
+            let (content, mut thinking) = splitter.push(
+                value
+                    .pointer("/choices/0/delta/content")
+                    .and_then(|c| c.as_str())
+                    .unwrap_or_default(),
+            );
+            if let Some(delta) = value
+                .pointer("/choices/0/delta/reasoning_content")
+                .or_else(|| value.pointer("/choices/0/delta/reasoning"))
+                .and_then(|r| r.as_str())
+            {
+                thinking.insert_str(0, delta);
+            }
+            // Groq reports streaming usage under `x_groq.usage`
+            let tokens = value
+                .get("usage")
+                .or_else(|| value.pointer("/x_groq/usage"))
+                .filter(|u| !u.is_null())
+                .and_then(|u| serde_json::from_value::<ChatUsage>(u.clone()).ok())
+                .map(|u| TokenUsage::from(&u));
+            if let Some(reason) = value.pointer("/choices/0/finish_reason").and_then(|r| r.as_str()) {
+                finish_reason = Some(FinishReason::parse(reason));
+            }
+            if tokens.is_some() {
+                usage = tokens.clone();
+            }
+            let tool_calls = value
+                .pointer("/choices/0/delta/tool_calls")
+                .and_then(|calls| serde_json::from_value::<Vec<ChatToolCallChunk>>(calls.clone()).ok())
+                .unwrap_or_default();
+            let mut data = StreamData::new(value, tokens, content);
+            data.reasoning = thinking;
+            data.tool_calls = tool_calls.into_iter().map(ToolCallDelta::from).collect();
+            yield Ok(data);
+        }
+        // some servers close without `[DONE]` but did report a finish reason
+        if finished || finish_reason.is_some() {
+            let (content, thinking) = splitter.finish();
+            if !content.is_empty() || !thinking.is_empty() {
+                let mut data = StreamData::new(Value::Null, None, content);
+                data.reasoning = thinking;
+                yield Ok(data);
+            }
+            yield Ok(StreamData::done(usage, finish_reason));
+        }
+    };
+
+    Box::pin(s)
+}
@@ -0,0 +1,209 @@
+//! Generic client for any OpenAI-shaped endpoint (vLLM, LM Studio, LiteLLM, ...).
+use futures::{
+    FutureExt,
+    future::BoxFuture,
+    stream::BoxStream
+};
+use reqwest::header::{HeaderName, HeaderValue};
+use serde_json::Value;
+
+use crate::message::Message;
+use crate::tools::{schema::ToolSchema, stream::StreamData};
+use crate::llm::{
+    traits::LLM,
+    batch,
+    capabilities::Capabilities,
+    compat::{relaxed_generate_result, to_chat_tools, ChatCompletions},
+    options::GenerateOptions,
+    GenerateResult,
+    LLMResult,
+};
+
+/// OpenAI compatible `/chat/completions` client with a configurable endpoint.
+///
+/// Responses are parsed leniently, so servers that omit usage, send content
+/// as an array of parts or answer in the legacy `text` field still work.
+#[derive(Debug, Clone)]
+pub struct OpenAICompatible {
+    pub(crate) inner: ChatCompletions,
+    pub(crate) native_tools: bool,
+    pub(crate) vision: bool,
+}
+
+impl OpenAICompatible {
+    /// `base_url` is the prefix in front of `/chat/completions`, e.g. `http://localhost:8000/v1`.
+    pub fn new(base_url: impl Into<String>, model: impl Into<String>) -> Self {
+        Self {
+            inner: ChatCompletions::new(base_url, model),
+            native_tools: false,
+            vision: false,
+        }
+    }
+
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.inner.api_key = Some(api_key.into());
+        self
+    }
+
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.inner.model = model.into();
+        self
+    }
+
+    /// Send an extra header with every request. Invalid names or values are ignored.
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        if let (Ok(name), Ok(value)) = (HeaderName::from_bytes(name.as_bytes()), HeaderValue::from_str(value)) {
+            self.inner.headers.insert(name, value);
+        }
+        self
+    }
+
+    /// Merge an extra field into every request body (e.g. vLLM's `top_k`).
+    pub fn with_extra(mut self, key: impl Into<String>, value: Value) -> Self {
+        self.inner.extra.insert(key.into(), value);
+        self
+    }
+
+    /// Request token log probabilities with the `top_logprobs` most likely
+    /// alternatives per position (0-20), returned in `GenerateResult::logprobs`.
+    pub fn with_logprobs(mut self, top_logprobs: u8) -> Self {
+        self.inner.extra.insert("logprobs".to_string(), Value::Bool(true));
+        self.inner.extra.insert("top_logprobs".to_string(), Value::from(top_logprobs));
+        self
+    }
+
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.inner.max_tokens = Some(max_tokens);
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.inner.temperature = Some(temperature);
+        self
+    }
+
+    /// Whether to request `stream_options.include_usage` (on by default);
+    /// disable for servers that reject unknown fields.
+    pub fn with_stream_usage(mut self, stream_usage: bool) -> Self {
+        self.inner.stream_usage = stream_usage;
+        self
+    }
+
+    /// Declare that the server supports native `tools` (e.g. vLLM started with
+    /// `--enable-auto-tool-choice`), so the agent sends tool schemas natively.
+    pub fn with_native_tools(mut self, native_tools: bool) -> Self {
+        self.native_tools = native_tools;
+        self
+    }
+
+    /// Declare that the served model accepts images, sent as `image_url`
+    /// parts.
+    pub fn with_vision(mut self, vision: bool) -> Self {
+        self.vision = vision;
+        self
+    }
+
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.inner.client = client;
+        self
+    }
+}
+
+impl LLM for OpenAICompatible {
+    fn generate<'a>(&'a self, messages: &'a [Message]) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        async move {
+            let body = self.inner.request_body(messages, false);
+            let value: Value = self.inner.send(&body).await?.json().await?;
+            relaxed_generate_result(value)
+        }
+        .boxed()
+    }
+
+    fn generate_with_tools<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        async move {
+            let mut body = self.inner.request_body(messages, false);
+            body.tools = (!tools.is_empty()).then(|| to_chat_tools(tools));
+            let value: Value = self.inner.send(&body).await?.json().await?;
+            relaxed_generate_result(value)
+        }
+        .boxed()
+    }
+
+    fn generate_batch<'a>(&'a self, batches: &'a [Vec<Message>]) -> BoxFuture<'a, Vec<LLMResult<GenerateResult>>> {
+        batch::generate_concurrently(self, batches, batch::DEFAULT_CONCURRENCY).boxed()
+    }
+
+    fn generate_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        async move {
+            let mut body = self.inner.request_body(messages, false);
+            body.tools = (!tools.is_empty()).then(|| to_chat_tools(tools));
+            body.apply_options(options);
+            let value: Value = self.inner.send(&body).await?.json().await?;
+            relaxed_generate_result(value)
+        }
+        .boxed()
+    }
+
+    /// Sends `n` in one request. A server that ignores it returns a single
+    /// completion, so check `candidates` before relying on their number.
+    fn generate_n<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+        n: usize,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        async move {
+            batch::check_n(n)?;
+            let mut body = self.inner.request_body(messages, false);
+            body.tools = (!tools.is_empty()).then(|| to_chat_tools(tools));
+            body.apply_options(options);
+            body.extra.insert("n".to_string(), n.into());
+            let value: Value = self.inner.send(&body).await?.json().await?;
+            relaxed_generate_result(value)
+        }
+        .boxed()
+    }
+
+    fn stream<'a>(&'a self, messages: &'a [Message]) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.inner.stream(messages)
+    }
+
+    fn stream_with_tools<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.inner.stream_with_tools(messages, tools)
+    }
+
+    fn stream_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.inner.stream_with_options(messages, tools, options)
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            native_tools: self.native_tools,
+            vision: self.vision,
+            ..Capabilities::default()
+        }
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.inner.model)
+    }
+}
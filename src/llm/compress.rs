@@ -0,0 +1,291 @@
+//! Shrink long injected context (tool results, retrieved documents) before it
+//! reaches the main LLM.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use async_stream::stream as async_stream;
+use futures::{
+    FutureExt,
+    StreamExt,
+    future::BoxFuture,
+    stream::BoxStream
+};
+
+use crate::message::{Message, MessageRole};
+use crate::tools::{schema::ToolSchema, stream::StreamData};
+use crate::llm::{
+    traits::LLM,
+    capabilities::Capabilities,
+    options::GenerateOptions,
+    GenerateResult,
+    LLMResult,
+};
+
+#[async_trait::async_trait]
+pub trait PromptCompressor: Send + Sync {
+    /// Compress `text` to roughly `target_chars`, keeping what is relevant to `query`.
+    async fn compress(&self, text: &str, query: &str, target_chars: usize) -> LLMResult<String>;
+}
+
+/// Extractive compressor: scores sentences by overlap with the query and by
+/// how often their words occur in the text, then keeps the best ones in
+/// original order until the budget is used.
+#[derive(Debug, Clone, Default)]
+pub struct HeuristicCompressor;
+
+fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.chars().count() > 2)
+        .map(|w| w.to_lowercase())
+}
+
+fn sentences(text: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        if matches!(c, '.' | '!' | '?' | '\n' | '。' | '！' | '？') {
+            let end = i + c.len_utf8();
+            let sentence = text[start..end].trim();
+            if !sentence.is_empty() {
+                out.push(sentence);
+            }
+            start = end;
+        }
+    }
+    let rest = text[start..].trim();
+    if !rest.is_empty() {
+        out.push(rest);
+    }
+    out
+}
+
+#[async_trait::async_trait]
+impl PromptCompressor for HeuristicCompressor {
+    async fn compress(&self, text: &str, query: &str, target_chars: usize) -> LLMResult<String> {
+        if text.len() <= target_chars {
+            return Ok(text.to_string());
+        }
+        let query_words: HashSet<String> = words(query).collect();
+        let mut frequency: HashMap<String, usize> = HashMap::new();
+        for word in words(text) {
+            *frequency.entry(word).or_default() += 1;
+        }
+
+        let sentences = sentences(text);
+        let mut scored: Vec<(usize, f64)> = sentences
+            .iter()
+            .enumerate()
+            .map(|(i, sentence)| {
+                let ws: Vec<String> = words(sentence).collect();
+                if ws.is_empty() {
+                    return (i, 0.0);
+                }
+                let relevance = ws.iter().filter(|w| query_words.contains(*w)).count() as f64;
+                let salience = ws.iter().map(|w| frequency[w] as f64).sum::<f64>() / ws.len() as f64;
+                // earlier sentences tend to carry headers/summaries
+                let position = 1.0 / (1.0 + i as f64).sqrt();
+                (i, relevance * 2.0 + salience.ln_1p() + position)
+            })
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        let mut keep = Vec::new();
+        let mut used = 0;
+        for (i, _) in scored {
+            let len = sentences[i].len() + 1;
+            if used + len > target_chars && !keep.is_empty() {
+                continue;
+            }
+            keep.push(i);
+            used += len;
+        }
+        keep.sort_unstable();
+        Ok(keep.iter().map(|i| sentences[*i]).collect::<Vec<_>>().join(" "))
+    }
+}
+
+pub const DEFAULT_SUMMARY_PROMPT: &str = "Compress the following text to at most {chars} characters. \
+Keep every fact relevant to: \"{query}\". Reply with the compressed text only.";
+
+/// Abstractive compressor backed by a (small, usually local) LLM.
+pub struct LLMCompressor {
+    pub llm: Arc<dyn LLM>,
+    pub prompt: String,
+}
+
+impl LLMCompressor {
+    pub fn new(llm: Arc<dyn LLM>) -> Self {
+        Self {
+            llm,
+            prompt: DEFAULT_SUMMARY_PROMPT.to_string(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl PromptCompressor for LLMCompressor {
+    async fn compress(&self, text: &str, query: &str, target_chars: usize) -> LLMResult<String> {
+        if text.len() <= target_chars {
+            return Ok(text.to_string());
+        }
+        let instruction = self
+            .prompt
+            .replace("{chars}", &target_chars.to_string())
+            .replace("{query}", query);
+        let messages = vec![Message::system(instruction), Message::user(text.to_string())];
+        Ok(self.llm.generate(&messages).await?.generation)
+    }
+}
+
+/// Compress tool results longer than `min_chars` to `ratio` of their size
+/// before calling the wrapped LLM. The last user message is used as the query.
+pub struct CompressedLLM<L> {
+    pub inner: L,
+    pub compressor: Arc<dyn PromptCompressor>,
+    pub min_chars: usize,
+    pub ratio: f32,
+}
+
+impl<L: LLM> CompressedLLM<L> {
+    pub fn new(inner: L, compressor: Arc<dyn PromptCompressor>) -> Self {
+        Self {
+            inner,
+            compressor,
+            min_chars: 2000,
+            ratio: 0.3,
+        }
+    }
+
+    pub fn with_min_chars(mut self, min_chars: usize) -> Self {
+        self.min_chars = min_chars;
+        self
+    }
+
+    pub fn with_ratio(mut self, ratio: f32) -> Self {
+        self.ratio = ratio.clamp(0.05, 1.0);
+        self
+    }
+
+    async fn compress_messages(&self, messages: &[Message]) -> LLMResult<Vec<Message>> {
+        let query = messages
+            .iter()
+            .rev()
+            .find(|m| matches!(m.role, MessageRole::User))
+            .map(|m| m.content.clone())
+            .unwrap_or_default();
+        let mut out = Vec::with_capacity(messages.len());
+        for message in messages {
+            let is_context = matches!(message.role, MessageRole::Tool | MessageRole::ToolResponce);
+            if is_context && message.content.len() > self.min_chars {
+                let target = (message.content.len() as f32 * self.ratio) as usize;
+                let mut compressed = message.clone();
+                compressed.content = self.compressor.compress(&message.content, &query, target).await?;
+                out.push(compressed);
+            } else {
+                out.push(message.clone());
+            }
+        }
+        Ok(out)
+    }
+}
+
+impl<L: LLM> LLM for CompressedLLM<L> {
+    fn generate<'a>(&'a self, messages: &'a [Message]) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        async move {
+            let msgs = self.compress_messages(messages).await?;
+            self.inner.generate(&msgs).await
+        }
+        .boxed()
+    }
+
+    fn generate_with_tools<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        async move {
+            let msgs = self.compress_messages(messages).await?;
+            self.inner.generate_with_tools(&msgs, tools).await
+        }
+        .boxed()
+    }
+
+    fn generate_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        async move {
+            let msgs = self.compress_messages(messages).await?;
+            self.inner.generate_with_options(&msgs, tools, options).await
+        }
+        .boxed()
+    }
+
+    fn generate_n<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+        n: usize,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        async move {
+            let msgs = self.compress_messages(messages).await?;
+            self.inner.generate_n(&msgs, tools, options, n).await
+        }
+        .boxed()
+    }
+
+    fn stream<'a>(&'a self, messages: &'a [Message]) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.stream_with_tools(messages, &[])
+    }
+
+    fn stream_with_tools<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.stream_with_options(messages, tools, GenerateOptions::none())
+    }
+
+    fn stream_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        let this = self;
+        let s = async_stream! {
+            let msgs = match this.compress_messages(messages).await {
+                Ok(msgs) => msgs,
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            };
+            let mut upstream = this.inner.stream_with_options(&msgs, tools, options);
+            while let Some(item) = upstream.next().await {
+                yield item;
+            }
+        };
+
+        Box::pin(s)
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        self.inner.model_name()
+    }
+
+    fn provider(&self) -> Option<&str> {
+        self.inner.provider()
+    }
+
+    fn health_check(&self) -> BoxFuture<'_, LLMResult<()>> {
+        self.inner.health_check()
+    }
+}
@@ -0,0 +1,80 @@
+//! Continue generations that were cut off by the output token limit.
+
+use crate::message::Message;
+use crate::llm::{
+    traits::LLM,
+    options::GenerateOptions,
+    FinishReason,
+    GenerateResult,
+    LLMResult,
+};
+
+/// How many times `generate_to_completion` re-prompts by default.
+pub const DEFAULT_MAX_CONTINUATIONS: usize = 3;
+
+/// Re-prompt `llm` with `previous.generation` as an assistant prefix and
+/// stitch the continuation onto it.
+///
+/// Token usage is summed and tool calls are re-extracted from the stitched
+/// text, so a JSON tool call split across the two segments is still found.
+pub async fn continue_generation<L>(
+    llm: &L,
+    messages: &[Message],
+    previous: &GenerateResult,
+    options: &GenerateOptions,
+) -> LLMResult<GenerateResult>
+where
+    L: LLM + ?Sized,
+{
+    let mut msgs = messages.to_vec();
+    msgs.push(Message::assistant(previous.generation.clone()));
+    let next = llm.generate_with(&msgs, options).await?;
+
+    let generation = format!("{}{}", previous.generation, next.generation);
+    let tool_calls = if next.tool_calls.is_empty() {
+        crate::llm::extract_tool_calls(&generation)
+    } else {
+        next.tool_calls
+    };
+    Ok(GenerateResult {
+        tokens: previous.tokens.sum(&next.tokens),
+        generation,
+        tool_calls,
+        finish_reason: next.finish_reason,
+        reasoning: next.reasoning.or_else(|| previous.reasoning.clone()),
+        model: next.model,
+        provider: next.provider,
+        logprobs: match (previous.logprobs.clone(), next.logprobs) {
+            (Some(mut previous), Some(next)) => {
+                previous.extend(next);
+                Some(previous)
+            }
+            (previous, next) => next.or(previous),
+        },
+        warnings: previous.warnings.iter().cloned().chain(next.warnings).collect(),
+        // only the first candidate is continued
+        candidates: Vec::new(),
+        // billed unless both parts came from a cache
+        cached: previous.cached && next.cached,
+    })
+}
+
+/// Call `generate_with` and keep continuing while the provider reports
+/// `FinishReason::Length`, up to `max_continuations` extra calls.
+pub async fn generate_to_completion<L>(
+    llm: &L,
+    messages: &[Message],
+    options: &GenerateOptions,
+    max_continuations: usize,
+) -> LLMResult<GenerateResult>
+where
+    L: LLM + ?Sized,
+{
+    let mut result = llm.generate_with(messages, options).await?;
+    let mut continuations = 0;
+    while result.finish_reason == Some(FinishReason::Length) && continuations < max_continuations {
+        result = continue_generation(llm, messages, &result, options).await?;
+        continuations += 1;
+    }
+    Ok(result)
+}
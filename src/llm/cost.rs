@@ -0,0 +1,457 @@
+//! Spend tracking for LLM calls.
+
+use std::sync::Arc;
+use async_stream::stream as async_stream;
+use futures::{
+    FutureExt,
+    StreamExt,
+    future::BoxFuture,
+    stream::BoxStream
+};
+use serde::{Serialize, Deserialize};
+
+use crate::message::Message;
+use crate::tools::{schema::ToolSchema, stream::StreamData};
+use crate::llm::{
+    traits::LLM,
+    cache::CacheStats,
+    capabilities::Capabilities,
+    error::LLMError,
+    throttle::estimate_prompt_tokens,
+    tokens::TokenUsage,
+    options::GenerateOptions,
+    GenerateResult,
+    LLMResult,
+};
+
+/// Price of a model in dollars per million tokens.
+///
+/// Prompt tokens read from or written to the provider's prompt cache are
+/// billed at their own rates when set, and at the input price otherwise.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Pricing {
+    pub input_per_million: f64,
+    pub output_per_million: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_read_per_million: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_write_per_million: Option<f64>,
+}
+
+impl Pricing {
+    pub fn new(input_per_million: f64, output_per_million: f64) -> Self {
+        Self {
+            input_per_million,
+            output_per_million,
+            cache_read_per_million: None,
+            cache_write_per_million: None,
+        }
+    }
+
+    /// From prices in dollars per thousand tokens.
+    pub fn per_thousand(input_per_thousand: f64, output_per_thousand: f64) -> Self {
+        Self::new(input_per_thousand * 1000.0, output_per_thousand * 1000.0)
+    }
+
+    /// Bill cache hits at `per_million`, e.g. a tenth of the input price at
+    /// Anthropic.
+    pub fn with_cache_read(mut self, per_million: f64) -> Self {
+        self.cache_read_per_million = Some(per_million);
+        self
+    }
+
+    /// Bill prompt tokens written to the cache at `per_million`, e.g. 1.25
+    /// times the input price at Anthropic.
+    pub fn with_cache_write(mut self, per_million: f64) -> Self {
+        self.cache_write_per_million = Some(per_million);
+        self
+    }
+
+    /// Cost of `usage` in dollars.
+    pub fn cost(&self, usage: &TokenUsage) -> f64 {
+        let cached = usage.cache_read_tokens.saturating_add(usage.cache_write_tokens);
+        let uncached = usage.prompt_tokens.saturating_sub(cached);
+        (uncached as f64 * self.input_per_million
+            + usage.cache_read_tokens as f64 * self.cache_read_per_million.unwrap_or(self.input_per_million)
+            + usage.cache_write_tokens as f64 * self.cache_write_per_million.unwrap_or(self.input_per_million)
+            + usage.completion_tokens as f64 * self.output_per_million)
+            / 1_000_000.0
+    }
+}
+
+/// Prices by `(provider, model)`.
+///
+/// Models match by prefix and the longest match wins, so `gpt-4o-mini` is not
+/// priced as `gpt-4o`; a cheaper or dearer variant of a model needs its own
+/// entry. An entry with provider `"*"` matches any provider.
+#[derive(Debug, Clone, Default)]
+pub struct PricingTable {
+    entries: Vec<(String, String, Pricing)>,
+}
+
+impl PricingTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// List prices of common hosted models at the time of writing, plus free
+    /// local models. Register your own to correct or extend them.
+    pub fn builtin() -> Self {
+        Self::new()
+            .with_price("openai", "gpt-4o", Pricing::new(2.5, 10.0).with_cache_read(1.25))
+            .with_price("openai", "gpt-4o-mini", Pricing::new(0.15, 0.6).with_cache_read(0.075))
+            .with_price("openai", "gpt-4.1", Pricing::new(2.0, 8.0).with_cache_read(0.5))
+            .with_price("openai", "gpt-4.1-mini", Pricing::new(0.4, 1.6).with_cache_read(0.1))
+            .with_price("openai", "gpt-4.1-nano", Pricing::new(0.1, 0.4).with_cache_read(0.025))
+            .with_price("openai", "o1", Pricing::new(15.0, 60.0).with_cache_read(7.5))
+            .with_price("openai", "o1-mini", Pricing::new(1.1, 4.4).with_cache_read(0.55))
+            .with_price("openai", "o1-pro", Pricing::new(150.0, 600.0))
+            .with_price("openai", "o3", Pricing::new(2.0, 8.0).with_cache_read(0.5))
+            .with_price("openai", "o3-mini", Pricing::new(1.1, 4.4).with_cache_read(0.55))
+            .with_price("openai", "o3-pro", Pricing::new(20.0, 80.0))
+            .with_price("openai", "o4-mini", Pricing::new(1.1, 4.4).with_cache_read(0.275))
+            .with_price("anthropic", "claude-3-5-haiku", Pricing::new(0.8, 4.0).with_cache_read(0.08).with_cache_write(1.0))
+            .with_price("anthropic", "claude-3-5-sonnet", Pricing::new(3.0, 15.0).with_cache_read(0.3).with_cache_write(3.75))
+            .with_price("anthropic", "claude-sonnet-4", Pricing::new(3.0, 15.0).with_cache_read(0.3).with_cache_write(3.75))
+            .with_price("anthropic", "claude-opus-4", Pricing::new(15.0, 75.0).with_cache_read(1.5).with_cache_write(18.75))
+            .with_price("deepseek", "deepseek-chat", Pricing::new(0.27, 1.1).with_cache_read(0.07))
+            .with_price("deepseek", "deepseek-reasoner", Pricing::new(0.55, 2.19).with_cache_read(0.14))
+            .with_price("mistral", "mistral-large", Pricing::new(2.0, 6.0))
+            .with_price("ollama", "", Pricing::default())
+            .with_price("llamacpp", "", Pricing::default())
+    }
+
+    /// Set the price of models starting with `model` at `provider`.
+    pub fn register(&mut self, provider: impl Into<String>, model: impl Into<String>, pricing: Pricing) {
+        let provider = provider.into();
+        let model = model.into();
+        self.entries.retain(|(p, m, _)| !(*p == provider && *m == model));
+        self.entries.push((provider, model, pricing));
+    }
+
+    pub fn with_price(mut self, provider: impl Into<String>, model: impl Into<String>, pricing: Pricing) -> Self {
+        self.register(provider, model, pricing);
+        self
+    }
+
+    /// Price of `model`. Without a provider, any provider's entry may match.
+    pub fn lookup(&self, provider: Option<&str>, model: &str) -> Option<Pricing> {
+        self.entries
+            .iter()
+            .filter(|(p, m, _)| {
+                let provider_matches = match provider {
+                    Some(provider) => p == "*" || p == provider,
+                    None => true,
+                };
+                provider_matches && model.starts_with(m.as_str())
+            })
+            .max_by_key(|(_, m, _)| m.len())
+            .map(|(_, _, pricing)| *pricing)
+    }
+}
+
+/// Usage and cost of one model within a run.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModelCost {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    pub model: String,
+    pub calls: u32,
+    /// Calls answered from a cache; their tokens are in `CostReport::cache`,
+    /// not in `usage`.
+    #[serde(default)]
+    pub cached_calls: u32,
+    pub usage: TokenUsage,
+    /// `None` when the model has no price in the table.
+    pub cost: Option<f64>,
+}
+
+/// What a run cost, per model.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CostReport {
+    /// Dollars spent on priced models.
+    pub total: f64,
+    pub models: Vec<ModelCost>,
+    /// Cache hits (calls served by a `CachedLLM`) and misses (every other
+    /// call), with the tokens the hits would have cost.
+    #[serde(default)]
+    pub cache: CacheStats,
+    /// Dollars the cache hits would have cost on priced models.
+    #[serde(default)]
+    pub saved: f64,
+}
+
+impl CostReport {
+    /// Whether some usage could not be priced, making `total` a lower bound.
+    pub fn has_unpriced(&self) -> bool {
+        self.models.iter().any(|m| m.cost.is_none())
+    }
+}
+
+/// Accumulates usage and cost of LLM calls using a `PricingTable`.
+#[derive(Debug, Clone)]
+pub struct CostTracker {
+    table: PricingTable,
+    report: CostReport,
+}
+
+impl CostTracker {
+    pub fn new(table: PricingTable) -> Self {
+        Self { table, report: CostReport::default() }
+    }
+
+    /// Add one call's usage. Returns its cost, if the model is priced.
+    pub fn record(&mut self, provider: Option<&str>, model: &str, usage: &TokenUsage) -> Option<f64> {
+        let cost = self.table.lookup(provider, model).map(|pricing| pricing.cost(usage));
+        self.report.cache.misses += 1;
+        let entry = self.entry(provider, model, cost.is_some());
+        entry.calls += 1;
+        entry.usage.add(usage);
+        if let (Some(total), Some(cost)) = (entry.cost.as_mut(), cost) {
+            *total += cost;
+            self.report.total += cost;
+        }
+        cost
+    }
+
+    /// Count a call answered from a cache, whose original `usage` was not
+    /// billed again. Returns what it would have cost, if the model is priced.
+    pub fn record_cached(&mut self, provider: Option<&str>, model: &str, usage: &TokenUsage) -> Option<f64> {
+        let saved = self.table.lookup(provider, model).map(|pricing| pricing.cost(usage));
+        self.report.cache.hits += 1;
+        self.report.cache.saved_tokens.add(usage);
+        self.report.saved += saved.unwrap_or_default();
+        let entry = self.entry(provider, model, saved.is_some());
+        entry.calls += 1;
+        entry.cached_calls += 1;
+        saved
+    }
+
+    fn entry(&mut self, provider: Option<&str>, model: &str, priced: bool) -> &mut ModelCost {
+        match self
+            .report
+            .models
+            .iter()
+            .position(|m| m.provider.as_deref() == provider && m.model == model)
+        {
+            Some(index) => &mut self.report.models[index],
+            None => {
+                self.report.models.push(ModelCost {
+                    provider: provider.map(str::to_string),
+                    model: model.to_string(),
+                    cost: priced.then_some(0.0),
+                    ..Default::default()
+                });
+                self.report.models.last_mut().expect("just pushed")
+            }
+        }
+    }
+
+    pub fn report(&self) -> &CostReport {
+        &self.report
+    }
+
+    pub fn into_report(self) -> CostReport {
+        self.report
+    }
+}
+
+/// Passed to the cutoff callback when a request hits its spend cap.
+#[derive(Debug, Clone)]
+pub struct CostCutoff {
+    pub cap: f64,
+    /// Spend at the moment of the cutoff (estimated while streaming).
+    pub spent: f64,
+    pub usage: TokenUsage,
+}
+
+type CutoffCallback = Arc<dyn Fn(&CostCutoff) + Send + Sync>;
+
+/// Enforce a per-request spend cap on the wrapped LLM.
+///
+/// While streaming, usage is estimated from the prompt and the text received
+/// so far (about four characters per token), and replaced by the provider's
+/// numbers when a chunk reports them. Once the cap is crossed the callback
+/// runs, the upstream stream is dropped (closing the connection) and a final
+/// `LLMError::CostCapExceeded` is yielded. `generate` can only check the
+/// prompt up front, since the provider bills the whole response.
+pub struct CostMeteredLLM<L> {
+    pub inner: L,
+    pub pricing: Pricing,
+    /// Maximum spend per request, in dollars.
+    pub cap: f64,
+    pub on_cutoff: Option<CutoffCallback>,
+}
+
+impl<L: LLM> CostMeteredLLM<L> {
+    pub fn new(inner: L, pricing: Pricing, cap: f64) -> Self {
+        Self {
+            inner,
+            pricing,
+            cap,
+            on_cutoff: None,
+        }
+    }
+
+    pub fn with_cutoff_callback<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&CostCutoff) + Send + Sync + 'static,
+    {
+        self.on_cutoff = Some(Arc::new(callback));
+        self
+    }
+
+    fn cut_off(&self, usage: TokenUsage) -> LLMError {
+        let spent = self.pricing.cost(&usage);
+        if let Some(callback) = self.on_cutoff.as_ref() {
+            callback(&CostCutoff { cap: self.cap, spent, usage });
+        }
+        LLMError::CostCapExceeded { cap: self.cap, spent }
+    }
+
+    fn check_prompt(&self, messages: &[Message]) -> LLMResult<()> {
+        let usage = TokenUsage::new(estimate_prompt_tokens(messages), 0);
+        if self.pricing.cost(&usage) > self.cap {
+            return Err(self.cut_off(usage));
+        }
+        Ok(())
+    }
+}
+
+impl<L: LLM> LLM for CostMeteredLLM<L> {
+    fn generate<'a>(&'a self, messages: &'a [Message]) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        async move {
+            self.check_prompt(messages)?;
+            self.inner.generate(messages).await
+        }
+        .boxed()
+    }
+
+    fn generate_with_tools<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        async move {
+            self.check_prompt(messages)?;
+            self.inner.generate_with_tools(messages, tools).await
+        }
+        .boxed()
+    }
+
+    fn generate_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        async move {
+            self.check_prompt(messages)?;
+            self.inner.generate_with_options(messages, tools, options).await
+        }
+        .boxed()
+    }
+
+    fn generate_n<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+        n: usize,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        async move {
+            self.check_prompt(messages)?;
+            self.inner.generate_n(messages, tools, options, n).await
+        }
+        .boxed()
+    }
+
+    fn stream<'a>(&'a self, messages: &'a [Message]) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.stream_with_tools(messages, &[])
+    }
+
+    fn stream_with_tools<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.stream_with_options(messages, tools, GenerateOptions::none())
+    }
+
+    fn stream_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        let this = self;
+        let s = async_stream! {
+            if let Err(e) = this.check_prompt(messages) {
+                yield Err(e);
+                return;
+            }
+            let prompt_tokens = estimate_prompt_tokens(messages);
+            let mut received_chars = 0usize;
+            let mut reported: Option<TokenUsage> = None;
+            let mut upstream = this.inner.stream_with_options(messages, tools, options);
+            while let Some(item) = upstream.next().await {
+                if let Ok(data) = item.as_ref() {
+                    received_chars += data.content.len();
+                    if let Some(tokens) = data.tokens.as_ref() {
+                        reported = Some(tokens.clone());
+                    }
+                }
+                let usage = reported
+                    .clone()
+                    .unwrap_or_else(|| TokenUsage::new(prompt_tokens, (received_chars / 4) as u32));
+                yield item;
+                if this.pricing.cost(&usage) > this.cap {
+                    drop(upstream);
+                    yield Err(this.cut_off(usage));
+                    return;
+                }
+            }
+        };
+
+        Box::pin(s)
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        self.inner.model_name()
+    }
+
+    fn provider(&self) -> Option<&str> {
+        self.inner.provider()
+    }
+
+    fn health_check(&self) -> BoxFuture<'_, LLMResult<()>> {
+        self.inner.health_check()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_prices_variants_at_their_own_rates() {
+        let table = PricingTable::builtin();
+        let price = |model: &str| table.lookup(Some("openai"), model).expect("priced");
+        assert_eq!(price("o3"), Pricing::new(2.0, 8.0).with_cache_read(0.5));
+        assert_eq!(price("o3-2025-04-16"), price("o3"));
+        assert_eq!(price("o3-mini").input_per_million, 1.1);
+        assert_eq!(price("o3-mini-2025-01-31"), price("o3-mini"));
+        assert_eq!(price("o3-pro").input_per_million, 20.0);
+        assert_eq!(price("o1-pro").output_per_million, 600.0);
+        assert_eq!(price("o4-mini").input_per_million, 1.1);
+        assert_eq!(price("gpt-4.1").input_per_million, 2.0);
+        assert_eq!(price("gpt-4.1-mini-2025-04-14").input_per_million, 0.4);
+        assert_eq!(price("gpt-4.1-nano").input_per_million, 0.1);
+        assert_eq!(price("gpt-4o-mini").input_per_million, 0.15);
+        assert!(table.lookup(Some("anthropic"), "o3").is_none());
+    }
+}
@@ -0,0 +1,124 @@
+// see https://api-docs.deepseek.com/
+use futures::{
+    FutureExt,
+    future::BoxFuture,
+    stream::BoxStream
+};
+
+use crate::message::Message;
+use crate::tools::{schema::ToolSchema, stream::StreamData};
+use crate::llm::{
+    traits::LLM,
+    batch,
+    compat::ChatCompletions,
+    options::GenerateOptions,
+    GenerateResult,
+    LLMResult,
+};
+
+pub const DEFAULT_BASE_URL: &str = "https://api.deepseek.com";
+
+/// Default model name used when no model is specified.
+pub const DEFAULT_MODEL: &str = "deepseek-chat";
+
+/// Reasoning model; returns `reasoning_content` next to the answer.
+pub const REASONER_MODEL: &str = "deepseek-reasoner";
+
+/// DeepSeek chat API client.
+///
+/// With `deepseek-reasoner` the chain-of-thought is returned in
+/// `GenerateResult::reasoning` and only the final answer lands in
+/// `generation`, so the agent never parses reasoning for tool calls. When
+/// streaming, reasoning deltas arrive in `StreamData::reasoning` and `content`
+/// carries only the answer.
+#[derive(Debug, Clone)]
+pub struct DeepSeek {
+    pub(crate) inner: ChatCompletions,
+}
+
+impl DeepSeek {
+    /// Create a `DeepSeek` wrapper with the given API key and the default model.
+    pub fn new(api_key: impl Into<String>) -> Self {
+        let mut inner = ChatCompletions::new(DEFAULT_BASE_URL, DEFAULT_MODEL);
+        inner.api_key = Some(api_key.into());
+        Self { inner }
+    }
+
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.inner.model = model.into();
+        self
+    }
+
+    /// Shortcut for `with_model(REASONER_MODEL)`.
+    pub fn reasoner(self) -> Self {
+        self.with_model(REASONER_MODEL)
+    }
+
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.inner.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// Note: `deepseek-reasoner` ignores temperature.
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.inner.temperature = Some(temperature);
+        self
+    }
+
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.inner.base_url = base_url.into();
+        self
+    }
+
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.inner.client = client;
+        self
+    }
+}
+
+impl Default for DeepSeek {
+    /// Read the API key from `DEEPSEEK_API_KEY`.
+    fn default() -> Self {
+        DeepSeek::new(std::env::var("DEEPSEEK_API_KEY").unwrap_or_default())
+    }
+}
+
+impl LLM for DeepSeek {
+    fn generate<'a>(&'a self, messages: &'a [Message]) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.inner.generate(messages).boxed()
+    }
+
+    fn generate_batch<'a>(&'a self, batches: &'a [Vec<Message>]) -> BoxFuture<'a, Vec<LLMResult<GenerateResult>>> {
+        batch::generate_concurrently(self, batches, batch::DEFAULT_CONCURRENCY).boxed()
+    }
+
+    fn generate_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        _tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.inner.generate_with(messages, options).boxed()
+    }
+
+    fn stream<'a>(&'a self, messages: &'a [Message]) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.inner.stream(messages)
+    }
+
+    fn stream_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        _tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.inner.stream_with_options(messages, &[], options)
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.inner.model)
+    }
+
+    fn provider(&self) -> Option<&str> {
+        Some("deepseek")
+    }
+}
@@ -0,0 +1,145 @@
+use std::sync::Arc;
+use async_stream::stream as async_stream;
+use futures::{
+    FutureExt,
+    StreamExt,
+    future::BoxFuture,
+    stream::BoxStream
+};
+
+use crate::message::Message;
+use crate::tools::{schema::ToolSchema, stream::StreamData};
+use crate::llm::{
+    traits::LLM,
+    options::GenerateOptions,
+    GenerateResult,
+    LLMResult,
+};
+
+/// Instruction sent to the refining model; `{draft}` is replaced with the draft.
+pub const DEFAULT_REFINE_PROMPT: &str = "A draft reply to the conversation above is given below. \
+Verify it, correct any mistakes or omissions, and respond with the final reply only.\n\nDraft:\n{draft}";
+
+/// Generate a draft with a fast/cheap model and let a stronger model refine it.
+///
+/// Callers see a single `LLM`; the returned `tokens` are the sum of both calls.
+pub struct DraftRefineLLM {
+    pub drafter: Arc<dyn LLM>,
+    pub refiner: Arc<dyn LLM>,
+    pub refine_prompt: String,
+}
+
+impl DraftRefineLLM {
+    pub fn new(drafter: Arc<dyn LLM>, refiner: Arc<dyn LLM>) -> Self {
+        Self {
+            drafter,
+            refiner,
+            refine_prompt: DEFAULT_REFINE_PROMPT.to_string(),
+        }
+    }
+
+    /// Replace the refine instruction. It must contain a `{draft}` placeholder.
+    pub fn with_refine_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.refine_prompt = prompt.into();
+        self
+    }
+
+    fn refine_messages(&self, messages: &[Message], draft: &GenerateResult) -> Vec<Message> {
+        let mut msgs = messages.to_vec();
+        msgs.push(Message::developer(self.refine_prompt.replace("{draft}", &draft.generation)));
+        msgs
+    }
+}
+
+impl LLM for DraftRefineLLM {
+    fn generate<'a>(&'a self, messages: &'a [Message]) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        async move {
+            let draft = self.drafter.generate(messages).await?;
+            let msgs = self.refine_messages(messages, &draft);
+            let mut refined = self.refiner.generate(&msgs).await?;
+            refined.tokens = draft.tokens.sum(&refined.tokens);
+            Ok(refined)
+        }
+        .boxed()
+    }
+
+    /// Both calls use `options`; tools are ignored like in `generate`.
+    fn generate_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        _tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        async move {
+            let draft = self.drafter.generate_with(messages, options).await?;
+            let msgs = self.refine_messages(messages, &draft);
+            let mut refined = self.refiner.generate_with(&msgs, options).await?;
+            refined.tokens = draft.tokens.sum(&refined.tokens);
+            Ok(refined)
+        }
+        .boxed()
+    }
+
+    /// The draft is produced without streaming; only the refinement is streamed.
+    /// Draft usage is added to the first chunk that reports tokens and to the
+    /// done marker.
+    fn stream<'a>(&'a self, messages: &'a [Message]) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.stream_with_options(messages, &[], GenerateOptions::none())
+    }
+
+    /// Both calls use `options`; tools are ignored like in `stream`.
+    fn stream_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        _tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        let this = self;
+        let s = async_stream! {
+            let draft = match this.drafter.generate_with(messages, options).await {
+                Ok(draft) => draft,
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            };
+            let msgs = this.refine_messages(messages, &draft);
+            let mut added = false;
+            let mut upstream = this.refiner.stream_with_options(&msgs, &[], options);
+            while let Some(item) = upstream.next().await {
+                match item {
+                    Ok(mut data) => {
+                        if let Some(tokens) = data.tokens.as_mut()
+                            && (!added || data.done)
+                        {
+                            tokens.add(&draft.tokens);
+                            added = true;
+                        }
+                        yield Ok(data);
+                    }
+                    Err(e) => yield Err(e),
+                }
+            }
+        };
+
+        Box::pin(s)
+    }
+
+    /// The refiner produces the final output.
+    fn model_name(&self) -> Option<&str> {
+        self.refiner.model_name()
+    }
+
+    fn provider(&self) -> Option<&str> {
+        self.refiner.provider()
+    }
+
+    /// Both models must be healthy.
+    fn health_check(&self) -> BoxFuture<'_, LLMResult<()>> {
+        async move {
+            self.drafter.health_check().await?;
+            self.refiner.health_check().await
+        }
+        .boxed()
+    }
+}
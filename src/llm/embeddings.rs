@@ -0,0 +1,75 @@
+//! Text embedding models, the foundation for retrieval.
+
+use std::sync::Arc;
+use futures::{FutureExt, future::BoxFuture};
+
+use crate::llm::{LLMResult, error::LLMError};
+
+/// Convert a concrete embedder into an `Arc<dyn Embedder>`.
+pub fn embedder_to_arc_dyn<E>(embedder: E) -> Arc<dyn Embedder>
+where
+    E: 'static + Embedder,
+{
+    Arc::new(embedder)
+}
+
+/// Turns texts into fixed-size vectors.
+///
+/// Implementations only provide `embed` for a single request;
+/// `embed_batched` splits larger inputs into `max_batch_size` chunks.
+pub trait Embedder: Send + Sync {
+    /// Embed `texts` in one request, returning one vector per text in order.
+    fn embed<'a>(&'a self, texts: &'a [String]) -> BoxFuture<'a, LLMResult<Vec<Vec<f32>>>>;
+
+    /// Length of the returned vectors, when known before the first request.
+    fn dimensions(&self) -> Option<usize>;
+
+    /// Most texts the backend accepts per request.
+    fn max_batch_size(&self) -> usize {
+        64
+    }
+
+    /// Embed any number of texts, issuing one request per `max_batch_size` chunk.
+    fn embed_batched<'a>(&'a self, texts: &'a [String]) -> BoxFuture<'a, LLMResult<Vec<Vec<f32>>>> {
+        async move {
+            let mut out = Vec::with_capacity(texts.len());
+            for chunk in texts.chunks(self.max_batch_size().max(1)) {
+                let vectors = self.embed(chunk).await?;
+                if vectors.len() != chunk.len() {
+                    return Err(LLMError::InvalidResponse(format!(
+                        "expected {} embeddings, got {}",
+                        chunk.len(),
+                        vectors.len()
+                    )));
+                }
+                out.extend(vectors);
+            }
+            Ok(out)
+        }
+        .boxed()
+    }
+
+    /// Embed a single text.
+    fn embed_query<'a>(&'a self, text: &'a str) -> BoxFuture<'a, LLMResult<Vec<f32>>> {
+        async move {
+            let texts = [text.to_string()];
+            self.embed(&texts)
+                .await?
+                .pop()
+                .ok_or_else(|| LLMError::InvalidResponse("no embedding returned".to_string()))
+        }
+        .boxed()
+    }
+}
+
+/// Cosine similarity of two vectors; 0 when either is all zeros.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
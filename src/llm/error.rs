@@ -1,4 +1,6 @@
 use super::ollama::OllamaError;
+use async_openai::error::OpenAIError;
+use std::fmt;
 
 
 #[derive(Debug, thiserror::Error)]
@@ -6,9 +8,15 @@ pub enum LLMError {
     #[error("Ollama error: {0}")]
     OllamaError(#[from] OllamaError),
 
+    #[error("OpenAI error: {0}")]
+    OpenAIError(#[source] Box<OpenAIError>),
+
     #[error("Rate limit exceeded: {0}")]
     RateLimitExceeded(String),
 
+    #[error("Rate limited: {0}")]
+    RateLimited(Box<RateLimitInfo>),
+
     #[error("Streaming not supported")]
     StreamNotSupported,
 
@@ -17,4 +25,149 @@ pub enum LLMError {
 
     #[error("Invalid response: {0}")]
     InvalidResponse(String),
-}
\ No newline at end of file
+
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("API error ({status}): {message}")]
+    Api {
+        status: u16,
+        message: String,
+    },
+
+    #[error("Timed out after {0:?}")]
+    Timeout(std::time::Duration),
+
+    #[error("Model not found: {0}")]
+    ModelNotFound(String),
+
+    #[error("Spend cap of ${cap:.4} exceeded (${spent:.4})")]
+    CostCapExceeded {
+        cap: f64,
+        spent: f64,
+    },
+
+    #[error("Monthly budget of ${budget:.2} for '{account}' exhausted in {month} (${spent:.4} spent)")]
+    QuotaExhausted {
+        account: String,
+        month: String,
+        budget: f64,
+        spent: f64,
+    },
+
+    #[error("No price for model '{model}' to bill to the budget of '{account}'")]
+    Unpriced {
+        account: String,
+        model: String,
+    },
+
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl From<OpenAIError> for LLMError {
+    fn from(e: OpenAIError) -> Self {
+        LLMError::OpenAIError(Box::new(e))
+    }
+}
+
+impl LLMError {
+    /// Whether the failure is transient (timeouts, connection errors, 429 and
+    /// 5xx responses), so repeating the same request may succeed.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            LLMError::RateLimitExceeded(_) | LLMError::RateLimited(_) | LLMError::Timeout(_) => true,
+            LLMError::Api { status, .. } => is_retryable_status(*status),
+            LLMError::Http(e) => is_retryable_reqwest(e),
+            LLMError::OllamaError(OllamaError::ReqwestError(e)) => is_retryable_reqwest(e),
+            LLMError::OpenAIError(e) => match e.as_ref() {
+                OpenAIError::Reqwest(e) => is_retryable_reqwest(e),
+                OpenAIError::ApiError(api) => {
+                    let kind = api.r#type.as_deref().unwrap_or_default();
+                    let code = api.code.as_deref().unwrap_or_default();
+                    kind == "server_error" || code == "rate_limit_exceeded"
+                }
+                OpenAIError::StreamError(_) => true,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// The provider's requested wait before retrying, from `retry-after`.
+    pub fn retry_after(&self) -> Option<std::time::Duration> {
+        match self {
+            LLMError::RateLimited(info) => info
+                .retry_after
+                .as_deref()
+                .and_then(|v| v.trim().parse::<f64>().ok())
+                .filter(|secs| secs.is_finite() && *secs >= 0.0)
+                .map(std::time::Duration::from_secs_f64),
+            _ => None,
+        }
+    }
+}
+
+fn is_retryable_status(status: u16) -> bool {
+    matches!(status, 408 | 409 | 429) || (500..600).contains(&status)
+}
+
+fn is_retryable_reqwest(e: &reqwest::Error) -> bool {
+    e.is_timeout()
+        || e.is_connect()
+        || e.status().is_some_and(|s| is_retryable_status(s.as_u16()))
+}
+
+/// Details of an HTTP 429, taken from the `retry-after` and `x-ratelimit-*`
+/// response headers when the provider sends them.
+#[derive(Debug, Clone, Default)]
+pub struct RateLimitInfo {
+    pub message: String,
+    pub retry_after: Option<String>,
+    pub limit_requests: Option<String>,
+    pub remaining_requests: Option<String>,
+    pub reset_requests: Option<String>,
+    pub limit_tokens: Option<String>,
+    pub remaining_tokens: Option<String>,
+    pub reset_tokens: Option<String>,
+}
+
+impl RateLimitInfo {
+    pub fn from_headers(headers: &reqwest::header::HeaderMap, message: String) -> Self {
+        let get = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string())
+        };
+        Self {
+            message,
+            retry_after: get("retry-after"),
+            limit_requests: get("x-ratelimit-limit-requests"),
+            remaining_requests: get("x-ratelimit-remaining-requests"),
+            reset_requests: get("x-ratelimit-reset-requests"),
+            limit_tokens: get("x-ratelimit-limit-tokens"),
+            remaining_tokens: get("x-ratelimit-remaining-tokens"),
+            reset_tokens: get("x-ratelimit-reset-tokens"),
+        }
+    }
+}
+
+impl fmt::Display for RateLimitInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        if let Some(retry_after) = self.retry_after.as_ref() {
+            write!(f, " (retry after {})", retry_after)?;
+        }
+        if let (Some(remaining), Some(reset)) = (self.remaining_requests.as_ref(), self.reset_requests.as_ref()) {
+            write!(f, " [requests remaining {}, reset {}]", remaining, reset)?;
+        }
+        if let (Some(remaining), Some(reset)) = (self.remaining_tokens.as_ref(), self.reset_tokens.as_ref()) {
+            write!(f, " [tokens remaining {}, reset {}]", remaining, reset)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,143 @@
+// see https://docs.x.ai/docs/api-reference#chat-completions
+use futures::{
+    FutureExt,
+    future::BoxFuture,
+    stream::BoxStream
+};
+use serde_json::Value;
+
+use crate::message::Message;
+use crate::tools::{schema::ToolSchema, stream::StreamData};
+use crate::llm::{
+    traits::LLM,
+    batch,
+    capabilities::Capabilities,
+    compat::ChatCompletions,
+    options::GenerateOptions,
+    GenerateResult,
+    LLMResult,
+};
+
+pub const DEFAULT_BASE_URL: &str = "https://api.x.ai/v1";
+
+/// Default model name used when no model is specified.
+pub const DEFAULT_MODEL: &str = "grok-3";
+
+/// xAI Grok client (OpenAI compatible API).
+///
+/// Tools go out in the request's `tools` field; reasoning models return
+/// their thinking in `GenerateResult::reasoning`.
+#[derive(Debug, Clone)]
+pub struct Grok {
+    pub(crate) inner: ChatCompletions,
+}
+
+impl Grok {
+    /// Create a `Grok` wrapper with the given API key and the default model.
+    pub fn new(api_key: impl Into<String>) -> Self {
+        let mut inner = ChatCompletions::new(DEFAULT_BASE_URL, DEFAULT_MODEL);
+        inner.api_key = Some(api_key.into());
+        Self { inner }
+    }
+
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.inner.model = model.into();
+        self
+    }
+
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.inner.max_tokens = Some(max_tokens);
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.inner.temperature = Some(temperature);
+        self
+    }
+
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.inner.base_url = base_url.into();
+        self
+    }
+
+    /// `"low"` or `"high"`; only accepted by reasoning models such as `grok-3-mini`.
+    pub fn with_reasoning_effort(mut self, effort: impl Into<String>) -> Self {
+        self.inner.extra.insert("reasoning_effort".to_string(), Value::String(effort.into()));
+        self
+    }
+
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.inner.client = client;
+        self
+    }
+}
+
+impl Default for Grok {
+    /// Read the API key from `XAI_API_KEY`.
+    fn default() -> Self {
+        Grok::new(std::env::var("XAI_API_KEY").unwrap_or_default())
+    }
+}
+
+impl LLM for Grok {
+    fn generate<'a>(&'a self, messages: &'a [Message]) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.inner.generate(messages).boxed()
+    }
+
+    fn generate_batch<'a>(&'a self, batches: &'a [Vec<Message>]) -> BoxFuture<'a, Vec<LLMResult<GenerateResult>>> {
+        batch::generate_concurrently(self, batches, batch::DEFAULT_CONCURRENCY).boxed()
+    }
+
+    fn generate_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.inner.generate_with_tools(messages, tools, options).boxed()
+    }
+
+    fn generate_with_tools<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        async move { self.inner.generate_with_tools(messages, tools, &GenerateOptions::default()).await }.boxed()
+    }
+
+    fn stream<'a>(&'a self, messages: &'a [Message]) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.inner.stream(messages)
+    }
+
+    fn stream_with_tools<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.inner.stream_with_tools(messages, tools)
+    }
+
+    fn stream_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.inner.stream_with_options(messages, tools, options)
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            native_tools: true,
+            ..Capabilities::default()
+        }
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.inner.model)
+    }
+
+    fn provider(&self) -> Option<&str> {
+        Some("xai")
+    }
+}
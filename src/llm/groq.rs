@@ -0,0 +1,114 @@
+// see https://console.groq.com/docs/api-reference
+use futures::{
+    FutureExt,
+    future::BoxFuture,
+    stream::BoxStream
+};
+
+use crate::message::Message;
+use crate::tools::{schema::ToolSchema, stream::StreamData};
+use crate::llm::{
+    traits::LLM,
+    batch,
+    compat::ChatCompletions,
+    options::GenerateOptions,
+    GenerateResult,
+    LLMResult,
+};
+
+pub const DEFAULT_BASE_URL: &str = "https://api.groq.com/openai/v1";
+
+/// Default model name used when no model is specified.
+pub const DEFAULT_MODEL: &str = "llama-3.3-70b-versatile";
+
+/// Groq client (OpenAI compatible API).
+///
+/// Groq's limits are tight; a 429 surfaces as `LLMError::RateLimited` with the
+/// `retry-after` and `x-ratelimit-*` headers parsed into `RateLimitInfo`.
+#[derive(Debug, Clone)]
+pub struct Groq {
+    pub(crate) inner: ChatCompletions,
+}
+
+impl Groq {
+    /// Create a `Groq` wrapper with the given API key and the default model.
+    pub fn new(api_key: impl Into<String>) -> Self {
+        let mut inner = ChatCompletions::new(DEFAULT_BASE_URL, DEFAULT_MODEL);
+        inner.api_key = Some(api_key.into());
+        // streaming usage is reported under `x_groq.usage` in the last chunk
+        inner.stream_usage = false;
+        Self { inner }
+    }
+
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.inner.model = model.into();
+        self
+    }
+
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.inner.max_tokens = Some(max_tokens);
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.inner.temperature = Some(temperature);
+        self
+    }
+
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.inner.base_url = base_url.into();
+        self
+    }
+
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.inner.client = client;
+        self
+    }
+}
+
+impl Default for Groq {
+    /// Read the API key from `GROQ_API_KEY`.
+    fn default() -> Self {
+        Groq::new(std::env::var("GROQ_API_KEY").unwrap_or_default())
+    }
+}
+
+impl LLM for Groq {
+    fn generate<'a>(&'a self, messages: &'a [Message]) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.inner.generate(messages).boxed()
+    }
+
+    fn generate_batch<'a>(&'a self, batches: &'a [Vec<Message>]) -> BoxFuture<'a, Vec<LLMResult<GenerateResult>>> {
+        batch::generate_concurrently(self, batches, batch::DEFAULT_CONCURRENCY).boxed()
+    }
+
+    fn generate_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        _tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.inner.generate_with(messages, options).boxed()
+    }
+
+    fn stream<'a>(&'a self, messages: &'a [Message]) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.inner.stream(messages)
+    }
+
+    fn stream_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        _tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.inner.stream_with_options(messages, &[], options)
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.inner.model)
+    }
+
+    fn provider(&self) -> Option<&str> {
+        Some("groq")
+    }
+}
@@ -0,0 +1,92 @@
+//! Small HTTP helpers shared by the reqwest-based backends.
+
+use async_stream::stream as async_stream;
+use futures::{StreamExt, stream::BoxStream};
+use reqwest::Response;
+
+use crate::llm::{error::{LLMError, RateLimitInfo}, LLMResult};
+
+/// A single server-sent event.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SseEvent {
+    pub event: Option<String>,
+    pub data: String,
+}
+
+/// Turn non-2xx responses into `LLMError`s, keeping the body as the message.
+pub(crate) async fn check_status(response: Response) -> LLMResult<Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let headers = response.headers().clone();
+    let message = response.text().await.unwrap_or_default();
+    if status.as_u16() == 429 {
+        return Err(LLMError::RateLimited(Box::new(RateLimitInfo::from_headers(&headers, message))));
+    }
+    Err(LLMError::Api {
+        status: status.as_u16(),
+        message,
+    })
+}
+
+/// Decode a `text/event-stream` body into events.
+///
+/// Bytes are buffered until a full line is available so multi-byte characters
+/// split across chunks are decoded correctly.
+pub(crate) fn sse_events(response: Response) -> BoxStream<'static, LLMResult<SseEvent>> {
+    let s = async_stream! {
+        let mut bytes = response.bytes_stream();
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut current = SseEvent::default();
+
+        while let Some(chunk) = bytes.next().await {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    yield Err(LLMError::from(e));
+                    return;
+                }
+            };
+            buffer.extend_from_slice(&chunk);
+
+            while let Some(pos) = buffer.iter().position(|b| *b == b'\n') {
+                let raw: Vec<u8> = buffer.drain(..=pos).collect();
+                let line = String::from_utf8_lossy(&raw);
+                let line = line.trim_end_matches(['\r', '\n']);
+
+                if line.is_empty() {
+                    // blank line terminates an event
+                    if !current.data.is_empty() || current.event.is_some() {
+                        yield Ok(std::mem::take(&mut current));
+                    }
+                    continue;
+                }
+                if line.starts_with(':') {
+                    // comment / keep-alive
+                    continue;
+                }
+                let (field, value) = match line.split_once(':') {
+                    Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+                    None => (line, ""),
+                };
+                match field {
+                    "event" => current.event = Some(value.to_string()),
+                    "data" => {
+                        if !current.data.is_empty() {
+                            current.data.push('\n');
+                        }
+                        current.data.push_str(value);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        if !current.data.is_empty() {
+            yield Ok(current);
+        }
+    };
+
+    Box::pin(s)
+}
@@ -0,0 +1,128 @@
+// see https://huggingface.co/docs/text-generation-inference/messages_api
+use futures::{
+    FutureExt,
+    future::BoxFuture,
+    stream::BoxStream
+};
+
+use crate::message::Message;
+use crate::tools::{schema::ToolSchema, stream::StreamData};
+use crate::llm::{
+    traits::LLM,
+    batch,
+    compat::ChatCompletions,
+    options::GenerateOptions,
+    GenerateResult,
+    LLMResult,
+};
+
+pub const DEFAULT_BASE_URL: &str = "https://router.huggingface.co/v1";
+
+/// Default model name used when no model is specified.
+pub const DEFAULT_MODEL: &str = "meta-llama/Llama-3.1-8B-Instruct";
+
+/// Hugging Face Inference Providers or a self-hosted Text Generation Inference
+/// server, both through the OpenAI compatible Messages API.
+///
+/// The server renders the `Message` history with the model's own chat template
+/// (from its `tokenizer_config.json`), so no prompt formatting happens here.
+#[derive(Debug, Clone)]
+pub struct HuggingFace {
+    pub(crate) inner: ChatCompletions,
+}
+
+impl HuggingFace {
+    /// Create a `HuggingFace` wrapper with the given API key and the default model.
+    pub fn new(api_key: impl Into<String>) -> Self {
+        let mut inner = ChatCompletions::new(DEFAULT_BASE_URL, DEFAULT_MODEL);
+        inner.api_key = Some(api_key.into());
+        Self { inner }
+    }
+
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.inner.model = model.into();
+        self
+    }
+
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.inner.max_tokens = Some(max_tokens);
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.inner.temperature = Some(temperature);
+        self
+    }
+
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.inner.base_url = base_url.into();
+        self
+    }
+
+    /// Talk to a self-hosted TGI server, e.g. `HuggingFace::tgi("http://localhost:8080/v1")`.
+    ///
+    /// TGI serves a single model, addressed as `tgi`; no API key is sent.
+    pub fn tgi(base_url: impl Into<String>) -> Self {
+        let mut inner = ChatCompletions::new(base_url, "tgi");
+        inner.stream_usage = false;
+        Self { inner }
+    }
+
+    /// Add a bearer token (e.g. for a TGI behind an Inference Endpoint).
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.inner.api_key = Some(api_key.into());
+        self
+    }
+
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.inner.client = client;
+        self
+    }
+}
+
+impl Default for HuggingFace {
+    /// Read the API key from `HF_TOKEN`.
+    fn default() -> Self {
+        HuggingFace::new(std::env::var("HF_TOKEN").unwrap_or_default())
+    }
+}
+
+impl LLM for HuggingFace {
+    fn generate<'a>(&'a self, messages: &'a [Message]) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.inner.generate(messages).boxed()
+    }
+
+    fn generate_batch<'a>(&'a self, batches: &'a [Vec<Message>]) -> BoxFuture<'a, Vec<LLMResult<GenerateResult>>> {
+        batch::generate_concurrently(self, batches, batch::DEFAULT_CONCURRENCY).boxed()
+    }
+
+    fn generate_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        _tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.inner.generate_with(messages, options).boxed()
+    }
+
+    fn stream<'a>(&'a self, messages: &'a [Message]) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.inner.stream(messages)
+    }
+
+    fn stream_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        _tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.inner.stream_with_options(messages, &[], options)
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.inner.model)
+    }
+
+    fn provider(&self) -> Option<&str> {
+        Some("huggingface")
+    }
+}
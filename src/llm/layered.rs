@@ -0,0 +1,271 @@
+//! Compose request/response hooks around any `LLM` without writing a full
+//! wrapper for each.
+//!
+//! An [`LLMMiddleware`] sees every request before the backend does (and may
+//! rewrite it or answer it itself) and every result after. `LayeredLLM`
+//! runs a stack of them:
+//!
+//! ```ignore
+//! let llm = LayeredLLM::new(OpenAI::new())
+//!     .with_layer(RequestLogger)   // sees every request and result
+//!     .with_layer(RedactEmails)    // rewrites what reaches the backend
+//!     .with_layer(AnswerCache::default());
+//! ```
+//!
+//! `before` hooks run in the order the layers were added and `after` hooks in
+//! reverse, so the first layer wraps all the others. A layer that answers
+//! the request itself skips the layers after it and the backend; the layers
+//! before it still see the result.
+
+use async_stream::stream as async_stream;
+use futures::{
+    FutureExt,
+    StreamExt,
+    future::BoxFuture,
+    stream::BoxStream
+};
+use serde_json::json;
+
+use crate::message::Message;
+use crate::tools::{schema::ToolSchema, stream::{StreamData, ToolCallDelta, ToolCallDeltas}};
+use crate::llm::{
+    traits::LLM,
+    capabilities::Capabilities,
+    error::LLMError,
+    middleware::{LLMMiddleware, LLMRequest},
+    options::GenerateOptions,
+    GenerateResult,
+    LLMResult,
+};
+
+pub struct LayeredLLM<L> {
+    pub inner: L,
+    layers: Vec<Box<dyn LLMMiddleware>>,
+}
+
+impl<L: LLM> LayeredLLM<L> {
+    pub fn new(inner: L) -> Self {
+        Self {
+            inner,
+            layers: Vec::new(),
+        }
+    }
+
+    /// Add `layer` inside the layers added before it.
+    pub fn with_layer(mut self, layer: impl LLMMiddleware + 'static) -> Self {
+        self.layers.push(Box::new(layer));
+        self
+    }
+
+    /// Run the `before` hooks. Returns how many layers were entered and the
+    /// result of the layer that answered, if any.
+    async fn enter(&self, request: &mut LLMRequest) -> LLMResult<(usize, Option<GenerateResult>)> {
+        for (index, layer) in self.layers.iter().enumerate() {
+            if let Some(result) = layer.before(request).await? {
+                return Ok((index + 1, Some(result)));
+            }
+        }
+        Ok((self.layers.len(), None))
+    }
+
+    async fn leave(&self, entered: usize, request: &LLMRequest, result: &mut GenerateResult) -> LLMResult<()> {
+        for layer in self.layers[..entered].iter().rev() {
+            layer.after(request, result).await?;
+        }
+        Ok(())
+    }
+
+    fn fail(&self, entered: usize, request: &LLMRequest, error: &LLMError) {
+        for layer in self.layers[..entered].iter().rev() {
+            layer.on_error(request, error);
+        }
+    }
+
+    /// Run `request` through the layers, asking the backend for `n`
+    /// completions when given.
+    async fn call(&self, mut request: LLMRequest, n: Option<usize>) -> LLMResult<GenerateResult> {
+        let (entered, answered) = self.enter(&mut request).await?;
+        let outcome = match (answered, n) {
+            (Some(result), _) => Ok(result),
+            (None, Some(n)) => {
+                self.inner
+                    .generate_n(&request.messages, &request.tools, &request.options, n)
+                    .await
+            }
+            (None, None) => {
+                self.inner
+                    .generate_with_options(&request.messages, &request.tools, &request.options)
+                    .await
+            }
+        };
+        let mut result = match outcome {
+            Ok(result) => result,
+            Err(e) => {
+                self.fail(entered, &request, &e);
+                return Err(e);
+            }
+        };
+        self.leave(entered, &request, &mut result).await?;
+        Ok(result)
+    }
+}
+
+impl<L: LLM> LLM for LayeredLLM<L> {
+    fn generate<'a>(&'a self, messages: &'a [Message]) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.generate_with_tools(messages, &[])
+    }
+
+    fn generate_with_tools<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.call(LLMRequest {
+            messages: messages.to_vec(),
+            tools: tools.to_vec(),
+            options: GenerateOptions::default(),
+        }, None)
+        .boxed()
+    }
+
+    fn generate_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.call(LLMRequest {
+            messages: messages.to_vec(),
+            tools: tools.to_vec(),
+            options: options.clone(),
+        }, None)
+        .boxed()
+    }
+
+    /// A layer that answers the request itself answers with one completion.
+    fn generate_n<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+        n: usize,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.call(LLMRequest {
+            messages: messages.to_vec(),
+            tools: tools.to_vec(),
+            options: options.clone(),
+        }, Some(n))
+        .boxed()
+    }
+
+    fn stream<'a>(&'a self, messages: &'a [Message]) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.stream_with_tools(messages, &[])
+    }
+
+    fn stream_with_tools<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.stream_with_options(messages, tools, GenerateOptions::none())
+    }
+
+    fn stream_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        let this = self;
+        let s = async_stream! {
+            let mut request = LLMRequest {
+                messages: messages.to_vec(),
+                tools: tools.to_vec(),
+                options: options.clone(),
+            };
+            let (entered, answered) = match this.enter(&mut request).await {
+                Ok(entered) => entered,
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            };
+            // a layer answered: replay its result as a stream
+            if let Some(mut result) = answered {
+                if let Err(e) = this.leave(entered, &request, &mut result).await {
+                    yield Err(e);
+                    return;
+                }
+                let mut data = StreamData::new(json!({ "layered": true }), Some(result.tokens.clone()), result.generation);
+                data.reasoning = result.reasoning.unwrap_or_default();
+                data.tool_calls = result
+                    .tool_calls
+                    .iter()
+                    .enumerate()
+                    .map(|(index, call)| ToolCallDelta::whole(index, call))
+                    .collect();
+                yield Ok(data);
+                yield Ok(StreamData::done(Some(result.tokens), result.finish_reason));
+                return;
+            }
+
+            let mut upstream = this.inner.stream_with_options(&request.messages, &request.tools, &request.options);
+            let mut result = GenerateResult::default();
+            let mut tool_calls = ToolCallDeltas::new();
+            let mut reasoning = String::new();
+            while let Some(item) = upstream.next().await {
+                let data = match item {
+                    Ok(data) => data,
+                    Err(e) => {
+                        this.fail(entered, &request, &e);
+                        yield Err(e);
+                        return;
+                    }
+                };
+                result.generation.push_str(&data.content);
+                reasoning.push_str(&data.reasoning);
+                for delta in &data.tool_calls {
+                    tool_calls.push(delta);
+                }
+                if let Some(tokens) = data.tokens.as_ref() {
+                    result.tokens = tokens.clone();
+                }
+                // consumers may stop reading at the done item, so the
+                // `after` hooks run before it is sent
+                if data.done {
+                    result.finish_reason = data.finish_reason.clone();
+                    result.reasoning = (!reasoning.is_empty()).then_some(std::mem::take(&mut reasoning));
+                    result.tool_calls = if tool_calls.is_empty() {
+                        crate::llm::extract_tool_calls(&result.generation)
+                    } else {
+                        tool_calls.finish()
+                    };
+                    result.model = this.inner.model_name().map(str::to_string);
+                    if let Err(e) = this.leave(entered, &request, &mut result).await {
+                        yield Err(e);
+                        return;
+                    }
+                }
+                yield Ok(data);
+            }
+        };
+
+        Box::pin(s)
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        self.inner.model_name()
+    }
+
+    fn provider(&self) -> Option<&str> {
+        self.inner.provider()
+    }
+
+    fn health_check(&self) -> BoxFuture<'_, LLMResult<()>> {
+        self.inner.health_check()
+    }
+}
@@ -0,0 +1,296 @@
+// see https://github.com/ggml-org/llama.cpp/tree/master/tools/server
+use async_stream::stream as async_stream;
+use futures::{
+    FutureExt,
+    StreamExt,
+    future::BoxFuture,
+    stream::BoxStream
+};
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+use crate::message::Message;
+use crate::tools::{schema::ToolSchema, stream::StreamData};
+use crate::llm::{
+    traits::LLM,
+    tokens::TokenUsage,
+    error::LLMError,
+    compat::ChatCompletions,
+    options::GenerateOptions,
+    http::{check_status, sse_events},
+    reasoning::{self, ReasoningSplitter},
+    FinishReason,
+    GenerateResult,
+    LLMResult,
+};
+
+pub const DEFAULT_BASE_URL: &str = "http://localhost:8080";
+
+/// llama.cpp serves whatever model it was started with; the name is informational.
+pub const DEFAULT_MODEL: &str = "default";
+
+/// GBNF grammar that only admits either a plain answer (not starting with `{`)
+/// or a well-formed `{"tool_calls": [{"name": ..., "args": {...}}]}` object,
+/// the format the agent prompt asks for.
+pub const TOOL_CALLS_GRAMMAR: &str = r#"root ::= tool-calls | answer
+answer ::= [^{] [^\x00]*
+tool-calls ::= "{" ws "\"tool_calls\"" ws ":" ws "[" ws ( call ( ws "," ws call )* )? ws "]" ws "}"
+call ::= "{" ws "\"name\"" ws ":" ws string ws "," ws "\"args\"" ws ":" ws object ws "}"
+object ::= "{" ws ( string ws ":" ws value ( ws "," ws string ws ":" ws value )* )? ws "}"
+array ::= "[" ws ( value ( ws "," ws value )* )? ws "]"
+value ::= object | array | string | number | "true" | "false" | "null"
+string ::= "\"" ( [^"\\\x7F\x00-\x1F] | "\\" ( ["\\/bfnrt] | "u" [0-9a-fA-F]{4} ) )* "\""
+number ::= "-"? [0-9]+ ( "." [0-9]+ )? ( [eE] [-+]? [0-9]+ )?
+ws ::= [ \t\n]*
+"#;
+
+/// Client for a llama.cpp `llama-server`.
+///
+/// `generate`/`stream` use the OpenAI compatible `/v1/chat/completions`
+/// endpoint, which applies the model's chat template. `complete` calls the
+/// raw `/completion` endpoint with a pre-formatted prompt.
+#[derive(Debug, Clone)]
+pub struct LlamaCpp {
+    pub(crate) inner: ChatCompletions,
+    pub(crate) server_url: String,
+}
+
+impl LlamaCpp {
+    /// Create a `LlamaCpp` wrapper for the server at `base_url` (e.g. `http://localhost:8080`).
+    pub fn new(base_url: impl Into<String>) -> Self {
+        let server_url = base_url.into().trim_end_matches('/').to_string();
+        let inner = ChatCompletions::new(format!("{}/v1", server_url), DEFAULT_MODEL);
+        Self { inner, server_url }
+    }
+
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.inner.model = model.into();
+        self
+    }
+
+    /// API key, if the server was started with `--api-key`.
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.inner.api_key = Some(api_key.into());
+        self
+    }
+
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.inner.max_tokens = Some(max_tokens);
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.inner.temperature = Some(temperature);
+        self
+    }
+
+    /// Constrain sampling with a GBNF grammar. Replaces any JSON schema.
+    pub fn with_grammar(mut self, grammar: impl Into<String>) -> Self {
+        self.inner.extra.remove("json_schema");
+        self.inner.extra.insert("grammar".to_string(), Value::String(grammar.into()));
+        self
+    }
+
+    /// Constrain the output to JSON matching `schema`. Replaces any grammar.
+    pub fn with_json_schema(mut self, schema: Value) -> Self {
+        self.inner.extra.remove("grammar");
+        self.inner.extra.insert("json_schema".to_string(), schema);
+        self
+    }
+
+    /// Use [`TOOL_CALLS_GRAMMAR`] so tool calls always parse.
+    pub fn with_tool_call_grammar(self) -> Self {
+        self.with_grammar(TOOL_CALLS_GRAMMAR)
+    }
+
+    /// Run a raw prompt through `/completion`, bypassing the chat template.
+    pub async fn complete(&self, prompt: &str) -> LLMResult<GenerateResult> {
+        let body = self.completion_request(prompt, false);
+        let response: CompletionResponse = self.send_completion(&body).await?.json().await?;
+        Ok(response.into_generate_result())
+    }
+
+    /// Streaming variant of [`LlamaCpp::complete`].
+    pub fn complete_stream<'a>(&'a self, prompt: &'a str) -> BoxStream<'a, LLMResult<StreamData>> {
+        let this = self;
+
+        let s = async_stream! {
+            let body = this.completion_request(prompt, true);
+            let response = match this.send_completion(&body).await {
+                Ok(response) => response,
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            };
+            let mut events = sse_events(response);
+            let mut splitter = ReasoningSplitter::new();
+            while let Some(event_res) = events.next().await {
+                let event = match event_res {
+                    Ok(event) => event,
+                    Err(e) => {
+                        yield Err(e);
+                        return;
+                    }
+                };
+                let chunk: CompletionResponse = match serde_json::from_str(&event.data) {
+                    Ok(chunk) => chunk,
+                    Err(e) => {
+                        yield Err(LLMError::from(e));
+                        continue;
+                    }
+                };
+                let value: Value = serde_json::from_str(&event.data).unwrap_or_default();
+                let tokens = chunk.stop.then(|| chunk.usage());
+                let finish_reason = chunk.finish_reason();
+                let (mut content, mut thinking) = splitter.push(&chunk.content);
+                if chunk.stop {
+                    let (rest, rest_thinking) = splitter.finish();
+                    content.push_str(&rest);
+                    thinking.push_str(&rest_thinking);
+                }
+                let mut data = StreamData::new(value, tokens.clone(), content);
+                data.reasoning = thinking;
+                yield Ok(data);
+                if chunk.stop {
+                    yield Ok(StreamData::done(tokens, finish_reason));
+                    return;
+                }
+            }
+        };
+
+        Box::pin(s)
+    }
+
+    fn completion_request(&self, prompt: &str, stream: bool) -> CompletionRequest {
+        CompletionRequest {
+            prompt: prompt.to_string(),
+            stream,
+            n_predict: self.inner.max_tokens.map(|n| n as i32),
+            temperature: self.inner.temperature,
+            grammar: self.inner.extra.get("grammar").and_then(|g| g.as_str()).map(str::to_string),
+            json_schema: self.inner.extra.get("json_schema").cloned(),
+        }
+    }
+
+    async fn send_completion(&self, body: &CompletionRequest) -> LLMResult<reqwest::Response> {
+        let mut request = self
+            .inner
+            .client
+            .post(format!("{}/completion", self.server_url))
+            .json(body);
+        if let Some(api_key) = self.inner.api_key.as_ref() {
+            request = request.bearer_auth(api_key);
+        }
+        check_status(request.send().await?).await
+    }
+
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.inner.client = client;
+        self
+    }
+}
+
+impl Default for LlamaCpp {
+    /// Connect to `LLAMA_CPP_BASE_URL`, or the local default port.
+    fn default() -> Self {
+        LlamaCpp::new(std::env::var("LLAMA_CPP_BASE_URL").unwrap_or_else(|_| DEFAULT_BASE_URL.to_string()))
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct CompletionRequest {
+    prompt: String,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    n_predict: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    grammar: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    json_schema: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompletionResponse {
+    #[serde(default)]
+    content: String,
+    #[serde(default)]
+    stop: bool,
+    #[serde(default)]
+    model: Option<String>,
+    #[serde(default)]
+    tokens_evaluated: u32,
+    #[serde(default)]
+    tokens_predicted: u32,
+    /// `"eos"`, `"limit"` or `"word"`.
+    #[serde(default)]
+    stop_type: Option<String>,
+}
+
+impl CompletionResponse {
+    fn usage(&self) -> TokenUsage {
+        TokenUsage::new(self.tokens_evaluated, self.tokens_predicted)
+    }
+
+    fn finish_reason(&self) -> Option<FinishReason> {
+        self.stop_type.as_deref().map(|stop_type| match stop_type {
+            "eos" | "word" => FinishReason::Stop,
+            "limit" => FinishReason::Length,
+            other => FinishReason::parse(other),
+        })
+    }
+
+    fn into_generate_result(self) -> GenerateResult {
+        let finish_reason = self.finish_reason();
+        let tokens = self.usage();
+        let (generation, reasoning) = reasoning::separate(self.content, None);
+        GenerateResult {
+            tokens,
+            tool_calls: crate::llm::extract_tool_calls(&generation),
+            generation,
+            finish_reason,
+            reasoning,
+            model: self.model,
+            ..Default::default()
+        }
+    }
+}
+
+impl LLM for LlamaCpp {
+    fn generate<'a>(&'a self, messages: &'a [Message]) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.inner.generate(messages).boxed()
+    }
+
+    fn generate_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        _tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.inner.generate_with(messages, options).boxed()
+    }
+
+    fn stream<'a>(&'a self, messages: &'a [Message]) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.inner.stream(messages)
+    }
+
+    fn stream_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        _tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.inner.stream_with_options(messages, &[], options)
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.inner.model)
+    }
+
+    fn provider(&self) -> Option<&str> {
+        Some("llamacpp")
+    }
+}
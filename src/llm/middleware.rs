@@ -0,0 +1,1051 @@
+//! Decorators that add resilience to any `LLM`: retries, timeouts,
+//! provider fallback and load balancing. Also home of [`LLMMiddleware`],
+//! the hooks a `LayeredLLM` runs around each request.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use async_stream::stream as async_stream;
+use futures::{
+    FutureExt,
+    StreamExt,
+    future::BoxFuture,
+    stream::BoxStream
+};
+use serde::{Serialize, Deserialize};
+
+use crate::message::Message;
+use crate::tools::{schema::ToolSchema, stream::StreamData};
+use crate::llm::{
+    traits::LLM,
+    capabilities::Capabilities,
+    error::LLMError,
+    options::GenerateOptions,
+    warnings::RunWarning,
+    GenerateResult,
+    LLMResult,
+};
+
+/// Exponential backoff settings for `RetryLLM`.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt.
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub multiplier: f64,
+    /// Fraction of the delay randomized in either direction (0.0 - 1.0), so
+    /// many clients don't retry in lockstep.
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: 0.2,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    pub fn with_initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    pub fn with_max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier.max(1.0);
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Delay before retry number `attempt` (0-based). A provider `retry-after`
+    /// wins over the computed backoff but is still capped by `max_backoff`.
+    pub fn delay(&self, attempt: u32, error: &LLMError) -> Duration {
+        if let Some(retry_after) = error.retry_after() {
+            return retry_after.min(self.max_backoff);
+        }
+        let base = self.initial_backoff.as_secs_f64() * self.multiplier.powi(attempt as i32);
+        let base = base.min(self.max_backoff.as_secs_f64());
+        let spread = base * self.jitter * (random_unit() * 2.0 - 1.0);
+        Duration::from_secs_f64((base + spread).clamp(0.0, self.max_backoff.as_secs_f64()))
+    }
+}
+
+/// Uniform value in `[0, 1)`, good enough for jitter.
+fn random_unit() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(0);
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Retry transient failures (see `LLMError::is_retryable`) with exponential
+/// backoff. Streams are retried only until the first chunk arrives.
+pub struct RetryLLM<L> {
+    pub inner: L,
+    pub policy: RetryPolicy,
+    retryable: fn(&LLMError) -> bool,
+}
+
+impl<L: LLM> RetryLLM<L> {
+    pub fn new(inner: L) -> Self {
+        Self::with_policy(inner, RetryPolicy::default())
+    }
+
+    pub fn with_policy(inner: L, policy: RetryPolicy) -> Self {
+        Self {
+            inner,
+            policy,
+            retryable: LLMError::is_retryable,
+        }
+    }
+
+    /// Replace the default classification of retryable errors.
+    pub fn with_classifier(mut self, retryable: fn(&LLMError) -> bool) -> Self {
+        self.retryable = retryable;
+        self
+    }
+
+    async fn retry<'a, F>(&'a self, mut call: F) -> LLMResult<GenerateResult>
+    where
+        F: FnMut() -> BoxFuture<'a, LLMResult<GenerateResult>>,
+    {
+        let mut attempt = 0;
+        let mut last_error = None;
+        loop {
+            match call().await {
+                Err(e) if attempt < self.policy.max_retries && (self.retryable)(&e) => {
+                    let delay = self.policy.delay(attempt, &e);
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                    last_error = Some(e);
+                }
+                Ok(mut result) => {
+                    if let Some(e) = last_error {
+                        result.warnings.push(RunWarning::Retried { retries: attempt, error: e.to_string() });
+                    }
+                    return Ok(result);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl<L: LLM> LLM for RetryLLM<L> {
+    fn generate<'a>(&'a self, messages: &'a [Message]) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.retry(move || self.inner.generate(messages)).boxed()
+    }
+
+    fn generate_with_tools<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.retry(move || self.inner.generate_with_tools(messages, tools)).boxed()
+    }
+
+    fn generate_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.retry(move || self.inner.generate_with_options(messages, tools, options)).boxed()
+    }
+
+    fn generate_n<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+        n: usize,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.retry(move || self.inner.generate_n(messages, tools, options, n)).boxed()
+    }
+
+    fn stream<'a>(&'a self, messages: &'a [Message]) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.stream_with_tools(messages, &[])
+    }
+
+    fn stream_with_tools<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.stream_with_options(messages, tools, GenerateOptions::none())
+    }
+
+    fn stream_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        let this = self;
+        let s = async_stream! {
+            let mut attempt = 0;
+            'attempts: loop {
+                let mut upstream = this.inner.stream_with_options(messages, tools, options);
+                let mut started = false;
+                while let Some(item) = upstream.next().await {
+                    match item {
+                        Err(e) if !started && attempt < this.policy.max_retries && (this.retryable)(&e) => {
+                            // release the failed connection before backing off
+                            drop(upstream);
+                            let delay = this.policy.delay(attempt, &e);
+                            tokio::time::sleep(delay).await;
+                            attempt += 1;
+                            continue 'attempts;
+                        }
+                        item => {
+                            started = true;
+                            yield item;
+                        }
+                    }
+                }
+                break;
+            }
+        };
+
+        Box::pin(s)
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        self.inner.model_name()
+    }
+
+    fn provider(&self) -> Option<&str> {
+        self.inner.provider()
+    }
+
+    fn health_check(&self) -> BoxFuture<'_, LLMResult<()>> {
+        self.inner.health_check()
+    }
+}
+
+/// Bound how long a call may take: `request` covers a whole `generate` and the
+/// wait for the first stream chunk, `chunk` the gap between later chunks.
+/// Expiry yields `LLMError::Timeout` instead of hanging the agent loop.
+pub struct TimeoutLLM<L> {
+    pub inner: L,
+    pub request: Duration,
+    pub chunk: Duration,
+}
+
+impl<L: LLM> TimeoutLLM<L> {
+    /// Use `timeout` both for whole requests and between stream chunks.
+    pub fn new(inner: L, timeout: Duration) -> Self {
+        Self {
+            inner,
+            request: timeout,
+            chunk: timeout,
+        }
+    }
+
+    pub fn with_chunk_timeout(mut self, chunk: Duration) -> Self {
+        self.chunk = chunk;
+        self
+    }
+
+    async fn bounded(&self, call: BoxFuture<'_, LLMResult<GenerateResult>>) -> LLMResult<GenerateResult> {
+        tokio::time::timeout(self.request, call)
+            .await
+            .unwrap_or(Err(LLMError::Timeout(self.request)))
+    }
+}
+
+impl<L: LLM> LLM for TimeoutLLM<L> {
+    fn generate<'a>(&'a self, messages: &'a [Message]) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.bounded(self.inner.generate(messages)).boxed()
+    }
+
+    fn generate_with_tools<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.bounded(self.inner.generate_with_tools(messages, tools)).boxed()
+    }
+
+    fn generate_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.bounded(self.inner.generate_with_options(messages, tools, options)).boxed()
+    }
+
+    fn generate_n<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+        n: usize,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.bounded(self.inner.generate_n(messages, tools, options, n)).boxed()
+    }
+
+    fn stream<'a>(&'a self, messages: &'a [Message]) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.stream_with_tools(messages, &[])
+    }
+
+    fn stream_with_tools<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.stream_with_options(messages, tools, GenerateOptions::none())
+    }
+
+    fn stream_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        let this = self;
+        let s = async_stream! {
+            let mut upstream = this.inner.stream_with_options(messages, tools, options);
+            let mut limit = this.request;
+            loop {
+                match tokio::time::timeout(limit, upstream.next()).await {
+                    Ok(Some(item)) => yield item,
+                    Ok(None) => break,
+                    Err(_) => {
+                        yield Err(LLMError::Timeout(limit));
+                        break;
+                    }
+                }
+                limit = this.chunk;
+            }
+        };
+
+        Box::pin(s)
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        self.inner.model_name()
+    }
+
+    fn provider(&self) -> Option<&str> {
+        self.inner.provider()
+    }
+
+    fn health_check(&self) -> BoxFuture<'_, LLMResult<()>> {
+        async move {
+            tokio::time::timeout(self.request, self.inner.health_check())
+                .await
+                .unwrap_or(Err(LLMError::Timeout(self.request)))
+        }
+        .boxed()
+    }
+}
+
+/// Features supported by every one of `backends`.
+fn common_capabilities<'a>(backends: impl Iterator<Item = &'a Arc<dyn LLM>>) -> Capabilities {
+    let mut capabilities = Capabilities {
+        native_tools: true,
+        system_role: true,
+        vision: true,
+    };
+    let mut any = false;
+    for backend in backends {
+        let other = backend.capabilities();
+        capabilities.native_tools &= other.native_tools;
+        capabilities.system_role &= other.system_role;
+        capabilities.vision &= other.vision;
+        any = true;
+    }
+    capabilities.native_tools &= any;
+    capabilities.vision &= any;
+    capabilities
+}
+
+/// Try `backends` in order, moving to the next one when a call fails with an
+/// error the classifier accepts (any error by default). Streams fall back only
+/// until the first chunk arrives.
+///
+/// `GenerateResult::model` names the model that answered, and `last_used`
+/// the index of its backend.
+pub struct FallbackLLM {
+    pub backends: Vec<Arc<dyn LLM>>,
+    should_fallback: fn(&LLMError) -> bool,
+    last_used: AtomicUsize,
+}
+
+impl FallbackLLM {
+    pub fn new(backends: Vec<Arc<dyn LLM>>) -> Self {
+        Self {
+            backends,
+            should_fallback: |_| true,
+            last_used: AtomicUsize::new(usize::MAX),
+        }
+    }
+
+    /// Only fall back on errors for which `should_fallback` returns true,
+    /// e.g. `LLMError::is_retryable`.
+    pub fn with_classifier(mut self, should_fallback: fn(&LLMError) -> bool) -> Self {
+        self.should_fallback = should_fallback;
+        self
+    }
+
+    /// Index of the backend that served the last successful call.
+    pub fn last_used(&self) -> Option<usize> {
+        match self.last_used.load(Ordering::Relaxed) {
+            usize::MAX => None,
+            index => Some(index),
+        }
+    }
+
+    async fn first_success<'a, F>(&'a self, mut call: F) -> LLMResult<GenerateResult>
+    where
+        F: FnMut(&'a dyn LLM) -> BoxFuture<'a, LLMResult<GenerateResult>>,
+    {
+        let mut last_error: Option<LLMError> = None;
+        for (index, backend) in self.backends.iter().enumerate() {
+            match call(backend.as_ref()).await {
+                Ok(mut result) => {
+                    self.last_used.store(index, Ordering::Relaxed);
+                    if result.model.is_none() {
+                        result.model = backend.model_name().map(str::to_string);
+                    }
+                    if result.provider.is_none() {
+                        result.provider = backend.provider().map(str::to_string);
+                    }
+                    if let Some(e) = last_error {
+                        result.warnings.push(RunWarning::FallbackUsed {
+                            index,
+                            model: result.model.clone(),
+                            error: e.to_string(),
+                        });
+                    }
+                    return Ok(result);
+                }
+                Err(e) if (self.should_fallback)(&e) => last_error = Some(e),
+                Err(e) => return Err(e),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| LLMError::InvalidResponse("FallbackLLM has no backends".to_string())))
+    }
+}
+
+impl LLM for FallbackLLM {
+    fn generate<'a>(&'a self, messages: &'a [Message]) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.first_success(move |backend| backend.generate(messages)).boxed()
+    }
+
+    fn generate_with_tools<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.first_success(move |backend| backend.generate_with_tools(messages, tools)).boxed()
+    }
+
+    fn generate_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.first_success(move |backend| backend.generate_with_options(messages, tools, options)).boxed()
+    }
+
+    fn generate_n<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+        n: usize,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.first_success(move |backend| backend.generate_n(messages, tools, options, n)).boxed()
+    }
+
+    fn stream<'a>(&'a self, messages: &'a [Message]) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.stream_with_tools(messages, &[])
+    }
+
+    fn stream_with_tools<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.stream_with_options(messages, tools, GenerateOptions::none())
+    }
+
+    fn stream_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        let this = self;
+        let s = async_stream! {
+            let mut last_error = None;
+            'backends: for (index, backend) in this.backends.iter().enumerate() {
+                let mut upstream = backend.stream_with_options(messages, tools, options);
+                let mut started = false;
+                while let Some(item) = upstream.next().await {
+                    match item {
+                        Err(e) if !started && (this.should_fallback)(&e) => {
+                            last_error = Some(e);
+                            continue 'backends;
+                        }
+                        item => {
+                            if !started {
+                                started = true;
+                                this.last_used.store(index, Ordering::Relaxed);
+                            }
+                            yield item;
+                        }
+                    }
+                }
+                return;
+            }
+            yield Err(last_error.unwrap_or_else(|| LLMError::InvalidResponse("FallbackLLM has no backends".to_string())));
+        };
+
+        Box::pin(s)
+    }
+
+    /// Only what every backend supports, so any of them can take over.
+    fn capabilities(&self) -> Capabilities {
+        common_capabilities(self.backends.iter())
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        self.backends.first().and_then(|backend| backend.model_name())
+    }
+
+    fn provider(&self) -> Option<&str> {
+        self.backends.first().and_then(|backend| backend.provider())
+    }
+
+    /// Healthy when a backend is, checked in order like a call would be.
+    fn health_check(&self) -> BoxFuture<'_, LLMResult<()>> {
+        async move {
+            let mut last_error = None;
+            for backend in self.backends.iter() {
+                match backend.health_check().await {
+                    Ok(()) => return Ok(()),
+                    Err(e) if (self.should_fallback)(&e) => last_error = Some(e),
+                    Err(e) => return Err(e),
+                }
+            }
+            Err(last_error.unwrap_or_else(|| LLMError::InvalidResponse("FallbackLLM has no backends".to_string())))
+        }
+        .boxed()
+    }
+}
+
+/// Health counters of one `BalancedLLM` backend.
+#[derive(Debug, Clone, Default)]
+pub struct BackendHealth {
+    pub successes: u64,
+    pub failures: u64,
+    pub consecutive_failures: u32,
+    /// Set while the backend is skipped after too many consecutive failures.
+    pub unhealthy_until: Option<Instant>,
+}
+
+impl BackendHealth {
+    pub fn is_healthy(&self) -> bool {
+        self.unhealthy_until.is_none_or(|until| Instant::now() >= until)
+    }
+}
+
+#[derive(Debug)]
+struct BalanceState {
+    /// Smooth weighted round-robin counters.
+    current: Vec<i64>,
+    health: Vec<BackendHealth>,
+}
+
+/// Spread calls over several LLMs (API keys, Ollama hosts ...) with smooth
+/// weighted round-robin. A backend failing `failure_threshold` times in a row
+/// is skipped for `cooldown`; if every backend is unhealthy all are used.
+pub struct BalancedLLM {
+    pub backends: Vec<(Arc<dyn LLM>, u32)>,
+    pub failure_threshold: u32,
+    pub cooldown: Duration,
+    state: Mutex<BalanceState>,
+}
+
+impl BalancedLLM {
+    /// Plain round-robin over `backends`.
+    pub fn new(backends: Vec<Arc<dyn LLM>>) -> Self {
+        Self::weighted(backends.into_iter().map(|backend| (backend, 1)).collect())
+    }
+
+    /// Each backend gets a share of calls proportional to its weight.
+    pub fn weighted(backends: Vec<(Arc<dyn LLM>, u32)>) -> Self {
+        let len = backends.len();
+        Self {
+            backends,
+            failure_threshold: 3,
+            cooldown: Duration::from_secs(30),
+            state: Mutex::new(BalanceState {
+                current: vec![0; len],
+                health: vec![BackendHealth::default(); len],
+            }),
+        }
+    }
+
+    pub fn with_failure_threshold(mut self, threshold: u32) -> Self {
+        self.failure_threshold = threshold.max(1);
+        self
+    }
+
+    pub fn with_cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    /// Health of every backend, in registration order.
+    pub fn health(&self) -> Vec<BackendHealth> {
+        self.state.lock().map(|state| state.health.clone()).unwrap_or_default()
+    }
+
+    fn pick(&self) -> LLMResult<usize> {
+        let mut state = self
+            .state
+            .lock()
+            .map_err(|_| LLMError::InvalidResponse("BalancedLLM state poisoned".to_string()))?;
+        let healthy: Vec<bool> = state.health.iter().map(BackendHealth::is_healthy).collect();
+        let any_healthy = healthy.iter().any(|h| *h);
+        let candidates: Vec<usize> = (0..self.backends.len())
+            .filter(|i| (healthy[*i] || !any_healthy) && self.backends[*i].1 > 0)
+            .collect();
+        if candidates.is_empty() {
+            return Err(LLMError::InvalidResponse("BalancedLLM has no backends".to_string()));
+        }
+        let total: i64 = candidates.iter().map(|i| self.backends[*i].1 as i64).sum();
+        for i in candidates.iter() {
+            state.current[*i] += self.backends[*i].1 as i64;
+        }
+        let chosen = *candidates
+            .iter()
+            .max_by_key(|i| (state.current[**i], std::cmp::Reverse(**i)))
+            .expect("candidates is not empty");
+        state.current[chosen] -= total;
+        Ok(chosen)
+    }
+
+    fn record(&self, index: usize, ok: bool) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        let health = &mut state.health[index];
+        if ok {
+            health.successes += 1;
+            health.consecutive_failures = 0;
+            health.unhealthy_until = None;
+        } else {
+            health.failures += 1;
+            health.consecutive_failures += 1;
+            if health.consecutive_failures >= self.failure_threshold {
+                health.unhealthy_until = Some(Instant::now() + self.cooldown);
+            }
+        }
+    }
+
+    async fn call<'a, F>(&'a self, call: F) -> LLMResult<GenerateResult>
+    where
+        F: FnOnce(&'a dyn LLM) -> BoxFuture<'a, LLMResult<GenerateResult>>,
+    {
+        let index = self.pick()?;
+        let backend = self.backends[index].0.as_ref();
+        let mut result = call(backend).await;
+        self.record(index, result.is_ok());
+        if let Ok(result) = result.as_mut()
+            && result.provider.is_none()
+        {
+            result.provider = backend.provider().map(str::to_string);
+        }
+        result
+    }
+}
+
+/// Records a `BalancedLLM` stream's outcome once it ends or is dropped.
+struct StreamOutcome<'a> {
+    balancer: &'a BalancedLLM,
+    index: usize,
+    ok: bool,
+    ended: bool,
+    done: bool,
+}
+
+impl Drop for StreamOutcome<'_> {
+    /// A connection dropped mid-stream counts against the backend; a caller
+    /// dropping the stream early doesn't, unless an error came through.
+    fn drop(&mut self) {
+        self.balancer.record(self.index, self.ok && (self.done || !self.ended));
+    }
+}
+
+impl LLM for BalancedLLM {
+    fn generate<'a>(&'a self, messages: &'a [Message]) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.call(move |backend| backend.generate(messages)).boxed()
+    }
+
+    fn generate_with_tools<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.call(move |backend| backend.generate_with_tools(messages, tools)).boxed()
+    }
+
+    fn generate_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.call(move |backend| backend.generate_with_options(messages, tools, options)).boxed()
+    }
+
+    fn generate_n<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+        n: usize,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.call(move |backend| backend.generate_n(messages, tools, options, n)).boxed()
+    }
+
+    fn stream<'a>(&'a self, messages: &'a [Message]) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.stream_with_tools(messages, &[])
+    }
+
+    fn stream_with_tools<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.stream_with_options(messages, tools, GenerateOptions::none())
+    }
+
+    fn stream_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        let this = self;
+        let s = async_stream! {
+            let index = match this.pick() {
+                Ok(index) => index,
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            };
+            let mut upstream = this.backends[index].0.stream_with_options(messages, tools, options);
+            let mut outcome = StreamOutcome { balancer: this, index, ok: true, ended: false, done: false };
+            while let Some(item) = upstream.next().await {
+                outcome.ok &= item.is_ok();
+                outcome.done |= item.as_ref().is_ok_and(|data| data.done);
+                yield item;
+            }
+            outcome.ended = true;
+        };
+
+        Box::pin(s)
+    }
+
+    /// Only what every backend supports, since any of them may serve a call.
+    fn capabilities(&self) -> Capabilities {
+        common_capabilities(self.backends.iter().map(|(backend, _)| backend))
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        self.backends.first().and_then(|(backend, _)| backend.model_name())
+    }
+
+    fn provider(&self) -> Option<&str> {
+        self.backends.first().and_then(|(backend, _)| backend.provider())
+    }
+
+    /// Checks every backend and records the outcomes in `health`. Healthy
+    /// when any backend is.
+    fn health_check(&self) -> BoxFuture<'_, LLMResult<()>> {
+        async move {
+            let mut first_error = None;
+            let mut any_healthy = false;
+            for (index, (backend, _)) in self.backends.iter().enumerate() {
+                let result = backend.health_check().await;
+                self.record(index, result.is_ok());
+                match result {
+                    Ok(()) => any_healthy = true,
+                    Err(e) => {
+                        first_error.get_or_insert(e);
+                    }
+                }
+            }
+            match first_error {
+                _ if any_healthy => Ok(()),
+                Some(e) => Err(e),
+                None => Err(LLMError::InvalidResponse("BalancedLLM has no backends".to_string())),
+            }
+        }
+        .boxed()
+    }
+}
+
+/// A request on its way to the backend.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LLMRequest {
+    pub messages: Vec<Message>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<ToolSchema>,
+    #[serde(default, skip_serializing_if = "GenerateOptions::is_empty")]
+    pub options: GenerateOptions,
+}
+
+/// Hooks around the requests of a `LayeredLLM`. Every hook defaults to
+/// doing nothing.
+pub trait LLMMiddleware: Send + Sync {
+    /// Inspect or rewrite `request`. Returning a result answers the request
+    /// without calling the backend or the layers after this one.
+    fn before<'a>(&'a self, request: &'a mut LLMRequest) -> BoxFuture<'a, LLMResult<Option<GenerateResult>>> {
+        let _ = request;
+        async { Ok(None) }.boxed()
+    }
+
+    /// Inspect or modify the result of `request`. For streamed requests this
+    /// runs on the assembled result once the stream has completed, and
+    /// changes to it are not sent.
+    fn after<'a>(&'a self, request: &'a LLMRequest, result: &'a mut GenerateResult) -> BoxFuture<'a, LLMResult<()>> {
+        let _ = (request, result);
+        async { Ok(()) }.boxed()
+    }
+
+    /// Observe a failed request.
+    fn on_error(&self, request: &LLMRequest, error: &LLMError) {
+        let _ = (request, error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::mock::MockLLM;
+
+    fn unavailable() -> LLMError {
+        LLMError::Api { status: 503, message: "overloaded".to_string() }
+    }
+
+    fn bad_request() -> LLMError {
+        LLMError::Api { status: 400, message: "bad request".to_string() }
+    }
+
+    /// Answers after `delay`; streams send a first chunk after `delay`, then
+    /// stall for `stall` before finishing.
+    struct Slow {
+        delay: Duration,
+        stall: Duration,
+    }
+
+    impl LLM for Slow {
+        fn generate<'a>(&'a self, _messages: &'a [Message]) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+            async move {
+                tokio::time::sleep(self.delay).await;
+                Ok(GenerateResult { generation: "slow".to_string(), ..Default::default() })
+            }
+            .boxed()
+        }
+
+        fn stream<'a>(&'a self, _messages: &'a [Message]) -> BoxStream<'a, LLMResult<StreamData>> {
+            Box::pin(async_stream! {
+                tokio::time::sleep(self.delay).await;
+                yield Ok(StreamData::new(serde_json::Value::Null, None, "slow"));
+                tokio::time::sleep(self.stall).await;
+                yield Ok(StreamData::done(None, None));
+            })
+        }
+    }
+
+    fn no_backoff() -> RetryPolicy {
+        RetryPolicy::default().with_initial_backoff(Duration::ZERO).with_max_retries(2)
+    }
+
+    #[tokio::test]
+    async fn retries_retryable_errors_up_to_the_limit() {
+        let mock = MockLLM::new()
+            .with_error(unavailable())
+            .with_error(unavailable())
+            .with_response("ok");
+        let llm = RetryLLM::with_policy(mock, no_backoff());
+        let result = llm.generate(&[Message::user("hi")]).await.expect("third attempt succeeds");
+        assert_eq!(result.generation, "ok");
+        assert!(matches!(result.warnings[..], [RunWarning::Retried { retries: 2, .. }]));
+
+        let mock = MockLLM::new()
+            .with_error(unavailable())
+            .with_error(unavailable())
+            .with_error(unavailable())
+            .with_response("too late");
+        let llm = RetryLLM::with_policy(mock, no_backoff());
+        let error = llm.generate(&[Message::user("hi")]).await.expect_err("out of retries");
+        assert!(matches!(error, LLMError::Api { status: 503, .. }));
+        assert_eq!(llm.inner.remaining(), 1);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_other_errors() {
+        let mock = MockLLM::new().with_error(bad_request()).with_response("unused");
+        let llm = RetryLLM::with_policy(mock, no_backoff());
+        let error = llm.generate(&[Message::user("hi")]).await.expect_err("not retried");
+        assert!(matches!(error, LLMError::Api { status: 400, .. }));
+        assert_eq!(llm.inner.remaining(), 1);
+
+        let mock = MockLLM::new().with_error(bad_request()).with_response("ok");
+        let llm = RetryLLM::with_policy(mock, no_backoff()).with_classifier(|_| true);
+        assert_eq!(llm.generate(&[Message::user("hi")]).await.expect("retried").generation, "ok");
+    }
+
+    #[tokio::test]
+    async fn retries_streams_that_fail_before_the_first_chunk() {
+        let mock = MockLLM::new().with_error(unavailable()).with_response("streamed");
+        let llm = RetryLLM::with_policy(mock, no_backoff());
+        let items: Vec<_> = llm.stream(&[Message::user("hi")]).collect().await;
+        assert!(items.iter().all(Result::is_ok));
+        assert_eq!(items[0].as_ref().expect("chunk").content, "streamed");
+        assert_eq!(llm.inner.remaining(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn times_out_slow_calls() {
+        let slow = Slow { delay: Duration::from_secs(10), stall: Duration::ZERO };
+        let llm = TimeoutLLM::new(slow, Duration::from_secs(5));
+        let error = llm.generate(&[Message::user("hi")]).await.expect_err("too slow");
+        assert!(matches!(error, LLMError::Timeout(limit) if limit == Duration::from_secs(5)));
+
+        let items: Vec<_> = llm.stream(&[Message::user("hi")]).collect().await;
+        assert!(matches!(items[..], [Err(LLMError::Timeout(_))]));
+
+        let llm = TimeoutLLM::new(Slow { delay: Duration::from_secs(1), stall: Duration::ZERO }, Duration::from_secs(5));
+        assert_eq!(llm.generate(&[Message::user("hi")]).await.expect("in time").generation, "slow");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn times_out_stalled_streams_between_chunks() {
+        let slow = Slow { delay: Duration::from_secs(4), stall: Duration::from_secs(10) };
+        let llm = TimeoutLLM::new(slow, Duration::from_secs(5)).with_chunk_timeout(Duration::from_secs(2));
+        let items: Vec<_> = llm.stream(&[Message::user("hi")]).collect().await;
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].as_ref().expect("first chunk").content, "slow");
+        assert!(matches!(items[1], Err(LLMError::Timeout(limit)) if limit == Duration::from_secs(2)));
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_the_next_backend() {
+        let first = Arc::new(MockLLM::new().with_model("first").with_error(unavailable()));
+        let second = Arc::new(MockLLM::new().with_model("second").with_response("from second"));
+        let llm = FallbackLLM::new(vec![first.clone(), second.clone()]);
+        let result = llm.generate(&[Message::user("hi")]).await.expect("second answers");
+        assert_eq!(result.generation, "from second");
+        assert_eq!(result.model.as_deref(), Some("second"));
+        assert_eq!(llm.last_used(), Some(1));
+        assert!(matches!(&result.warnings[..], [RunWarning::FallbackUsed { index: 1, .. }]));
+        assert_eq!((first.remaining(), second.remaining()), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn fails_with_the_last_error_when_every_backend_fails() {
+        let first = Arc::new(MockLLM::new().with_error(unavailable()));
+        let second = Arc::new(MockLLM::new().with_error(bad_request()));
+        let llm = FallbackLLM::new(vec![first, second]);
+        let error = llm.generate(&[Message::user("hi")]).await.expect_err("all failed");
+        assert!(matches!(error, LLMError::Api { status: 400, .. }));
+        assert_eq!(llm.last_used(), None);
+
+        let items: Vec<_> = FallbackLLM::new(vec![]).stream(&[Message::user("hi")]).collect().await;
+        assert!(matches!(items[..], [Err(LLMError::InvalidResponse(_))]));
+    }
+
+    #[tokio::test]
+    async fn stops_on_errors_the_classifier_rejects() {
+        let first = Arc::new(MockLLM::new().with_error(bad_request()));
+        let second = Arc::new(MockLLM::new().with_response("unused"));
+        let llm = FallbackLLM::new(vec![first, second.clone()]).with_classifier(LLMError::is_retryable);
+        let error = llm.generate(&[Message::user("hi")]).await.expect_err("no fallback");
+        assert!(matches!(error, LLMError::Api { status: 400, .. }));
+        assert_eq!(second.remaining(), 1);
+    }
+
+    #[tokio::test]
+    async fn balances_by_weight() {
+        let a = Arc::new(MockLLM::new().with_response("a").with_response("a").with_response("a"));
+        let b = Arc::new(MockLLM::new().with_response("b").with_response("b"));
+        let llm = BalancedLLM::weighted(vec![(a.clone(), 2), (b.clone(), 1)]);
+        let mut served = String::new();
+        for _ in 0..3 {
+            served += &llm.generate(&[Message::user("hi")]).await.expect("served").generation;
+        }
+        assert_eq!(served, "aba");
+        assert_eq!((a.remaining(), b.remaining()), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn skips_unhealthy_backends() {
+        let a = Arc::new(MockLLM::new().with_error(unavailable()).with_response("unused"));
+        let b = Arc::new(MockLLM::new().with_response("b").with_response("b"));
+        let llm = BalancedLLM::new(vec![a.clone(), b]).with_failure_threshold(1);
+        llm.generate(&[Message::user("hi")]).await.expect_err("a fails");
+        let health = llm.health();
+        assert_eq!((health[0].failures, health[0].consecutive_failures), (1, 1));
+        assert!(!health[0].is_healthy());
+        for _ in 0..2 {
+            assert_eq!(llm.generate(&[Message::user("hi")]).await.expect("b serves").generation, "b");
+        }
+        assert_eq!(a.remaining(), 1);
+    }
+
+    #[tokio::test]
+    async fn records_health_of_streams() {
+        let llm = BalancedLLM::new(vec![Arc::new(MockLLM::new().with_response("a").with_error(unavailable()))]);
+        let messages = [Message::user("hi")];
+        let mut stream = llm.stream(&messages);
+        assert_eq!(stream.next().await.expect("chunk").expect("ok").content, "a");
+        drop(stream);
+        let health = llm.health();
+        assert_eq!((health[0].successes, health[0].failures), (1, 0));
+
+        let items: Vec<_> = llm.stream(&[Message::user("hi")]).collect().await;
+        assert!(items[0].is_err());
+        assert_eq!(llm.health()[0].failures, 1);
+    }
+}
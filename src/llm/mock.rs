@@ -0,0 +1,233 @@
+//! An `LLM` that replays scripted results, for testing agent logic without a
+//! network or a model.
+//!
+//! ```ignore
+//! let llm = Arc::new(
+//!     MockLLM::new()
+//!         .with_tool_call("get_weather", json!({ "city": "Paris" }))
+//!         .with_response("It's sunny in Paris."),
+//! );
+//! let mut agent = Agent::new("test", llm.clone(), None);
+//! agent.register_tool(None, Arc::new(GetWeatherTool))?;
+//! let result = agent.call_llm("Weather in Paris?").await?;
+//! assert!(llm.received()[1].iter().any(|m| m.content.contains("sunny")));
+//! ```
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use futures::{
+    FutureExt,
+    future::BoxFuture,
+    stream::{self, BoxStream, StreamExt}
+};
+use serde_json::{json, Value};
+
+use crate::message::Message;
+use crate::tools::{schema::ToolSchema, stream::{StreamData, ToolCallDelta}};
+use crate::llm::{
+    traits::LLM,
+    capabilities::Capabilities,
+    error::LLMError,
+    middleware::LLMRequest,
+    options::GenerateOptions,
+    CallInfo,
+    FinishReason,
+    GenerateResult,
+    LLMResult,
+};
+
+/// Results are returned in the order they were scripted, one per call
+/// (streamed or not) once it is polled. A call after the script ran out
+/// fails.
+#[derive(Debug, Default)]
+pub struct MockLLM {
+    script: Mutex<VecDeque<LLMResult<GenerateResult>>>,
+    requests: Mutex<Vec<LLMRequest>>,
+    model: Option<String>,
+    provider: Option<String>,
+    native_tools: bool,
+    vision: bool,
+}
+
+impl MockLLM {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answer with `text`.
+    pub fn with_response(self, text: impl Into<String>) -> Self {
+        self.with_result(GenerateResult {
+            generation: text.into(),
+            finish_reason: Some(FinishReason::Stop),
+            ..Default::default()
+        })
+    }
+
+    /// Ask for a call of tool `name` with `args`.
+    pub fn with_tool_call(self, name: impl Into<String>, args: Value) -> Self {
+        let id = format!("call_{}", self.script.lock().map(|script| script.len()).unwrap_or_default());
+        self.with_result(GenerateResult {
+            tool_calls: vec![CallInfo {
+                id: Some(id),
+                name: name.into(),
+                args,
+            }],
+            finish_reason: Some(FinishReason::ToolCalls),
+            ..Default::default()
+        })
+    }
+
+    pub fn with_result(self, result: GenerateResult) -> Self {
+        self.push(Ok(result));
+        self
+    }
+
+    /// Fail the call, e.g. with `LLMError::Timeout` to test retries.
+    pub fn with_error(self, error: LLMError) -> Self {
+        self.push(Err(error));
+        self
+    }
+
+    /// Reported as `model_name` and in every result.
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// Reported as `provider`.
+    pub fn with_provider(mut self, provider: impl Into<String>) -> Self {
+        self.provider = Some(provider.into());
+        self
+    }
+
+    /// Report native tool support, so the agent sends tool schemas instead
+    /// of the JSON-in-prompt protocol.
+    pub fn with_native_tools(mut self, native_tools: bool) -> Self {
+        self.native_tools = native_tools;
+        self
+    }
+
+    /// Report vision support, so the agent shows the model images.
+    pub fn with_vision(mut self, vision: bool) -> Self {
+        self.vision = vision;
+        self
+    }
+
+    /// Script another result, e.g. while a test is running.
+    pub fn push(&self, result: LLMResult<GenerateResult>) {
+        if let Ok(mut script) = self.script.lock() {
+            script.push_back(result);
+        }
+    }
+
+    /// Scripted results not returned yet.
+    pub fn remaining(&self) -> usize {
+        self.script.lock().map(|script| script.len()).unwrap_or_default()
+    }
+
+    /// Every request received, in order.
+    pub fn requests(&self) -> Vec<LLMRequest> {
+        self.requests.lock().map(|requests| requests.clone()).unwrap_or_default()
+    }
+
+    /// The messages of every request received, in order.
+    pub fn received(&self) -> Vec<Vec<Message>> {
+        self.requests().into_iter().map(|request| request.messages).collect()
+    }
+
+    fn next(&self, messages: &[Message], tools: &[ToolSchema], options: &GenerateOptions) -> LLMResult<GenerateResult> {
+        if let Ok(mut requests) = self.requests.lock() {
+            requests.push(LLMRequest {
+                messages: messages.to_vec(),
+                tools: tools.to_vec(),
+                options: options.clone(),
+            });
+        }
+        let next = self.script.lock().ok().and_then(|mut script| script.pop_front());
+        let mut result = next.unwrap_or_else(|| {
+            Err(LLMError::InvalidResponse("MockLLM has no scripted result left".to_string()))
+        })?;
+        if result.model.is_none() {
+            result.model = self.model.clone();
+        }
+        Ok(result)
+    }
+}
+
+impl LLM for MockLLM {
+    fn generate<'a>(&'a self, messages: &'a [Message]) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.generate_with_tools(messages, &[])
+    }
+
+    fn generate_with_tools<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        async move { self.next(messages, tools, &GenerateOptions::default()) }.boxed()
+    }
+
+    fn generate_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        async move { self.next(messages, tools, options) }.boxed()
+    }
+
+    fn stream<'a>(&'a self, messages: &'a [Message]) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.stream_with_tools(messages, &[])
+    }
+
+    fn stream_with_tools<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.stream_with_options(messages, tools, GenerateOptions::none())
+    }
+
+    /// The scripted result as one chunk followed by the done item.
+    fn stream_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        let result = match self.next(messages, tools, options) {
+            Ok(result) => result,
+            Err(e) => return stream::iter([Err(e)]).boxed(),
+        };
+        let mut data = StreamData::new(json!({ "mock": true }), Some(result.tokens.clone()), result.generation);
+        data.reasoning = result.reasoning.unwrap_or_default();
+        data.tool_calls = result
+            .tool_calls
+            .iter()
+            .enumerate()
+            .map(|(index, call)| ToolCallDelta::whole(index, call))
+            .collect();
+        stream::iter([Ok(data), Ok(StreamData::done(Some(result.tokens), result.finish_reason))]).boxed()
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        self.model.as_deref()
+    }
+
+    fn provider(&self) -> Option<&str> {
+        self.provider.as_deref()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            native_tools: self.native_tools,
+            vision: self.vision,
+            ..Capabilities::default()
+        }
+    }
+
+    /// Always healthy; doesn't use up the script.
+    fn health_check(&self) -> BoxFuture<'_, LLMResult<()>> {
+        async { Ok(()) }.boxed()
+    }
+}
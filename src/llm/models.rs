@@ -0,0 +1,74 @@
+//! Which models a backend serves and what they can do.
+//!
+//! Backends that can list their models implement [`ModelCatalog`]. Pass the
+//! result to `Agent::set_model_info` so tools are refused up front for a
+//! model that can't call them, instead of the model ignoring or garbling the
+//! tool protocol at run time.
+
+use futures::{FutureExt, future::BoxFuture};
+use serde::{Serialize, Deserialize};
+
+use crate::llm::LLMResult;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelInfo {
+    /// The name to request the model by.
+    pub id: String,
+    /// Can call tools (native function calling).
+    pub supports_tools: bool,
+    /// Accepts image input.
+    pub supports_vision: bool,
+    /// Context window in tokens, when known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_window: Option<u32>,
+    /// Runs on the local machine or network rather than a hosted API.
+    pub is_local: bool,
+}
+
+impl ModelInfo {
+    /// A model with no known capabilities.
+    pub fn new(id: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            supports_tools: false,
+            supports_vision: false,
+            context_window: None,
+            is_local: false,
+        }
+    }
+
+    pub fn with_tools(mut self, supports_tools: bool) -> Self {
+        self.supports_tools = supports_tools;
+        self
+    }
+
+    pub fn with_vision(mut self, supports_vision: bool) -> Self {
+        self.supports_vision = supports_vision;
+        self
+    }
+
+    pub fn with_context_window(mut self, context_window: u32) -> Self {
+        self.context_window = Some(context_window);
+        self
+    }
+
+    pub fn with_local(mut self, is_local: bool) -> Self {
+        self.is_local = is_local;
+        self
+    }
+}
+
+/// A backend that can list the models it serves.
+pub trait ModelCatalog: Send + Sync {
+    fn list_models(&self) -> BoxFuture<'_, LLMResult<Vec<ModelInfo>>>;
+
+    /// Information about one model, `None` if the backend doesn't serve it.
+    /// The default searches `list_models`.
+    fn model_info<'a>(&'a self, model: &'a str) -> BoxFuture<'a, LLMResult<Option<ModelInfo>>> {
+        async move {
+            let models = self.list_models().await?;
+            Ok(models.into_iter().find(|info| info.id == model))
+        }
+        .boxed()
+    }
+}
@@ -0,0 +1,153 @@
+// see https://platform.moonshot.cn/docs/api/chat
+use futures::{
+    FutureExt,
+    future::BoxFuture,
+    stream::BoxStream
+};
+
+use crate::message::{Message, MessageRole};
+use crate::tools::{schema::ToolSchema, stream::StreamData};
+use crate::llm::{
+    traits::LLM,
+    batch,
+    compat::{ChatCompletions, ChatRequest, ChatResponse},
+    options::GenerateOptions,
+    GenerateResult,
+    LLMResult,
+};
+
+pub const DEFAULT_BASE_URL: &str = "https://api.moonshot.cn/v1";
+
+/// Default model name used when no model is specified.
+pub const DEFAULT_MODEL: &str = "moonshot-v1-128k";
+
+/// Moonshot Kimi client (OpenAI compatible API).
+///
+/// With partial mode enabled, a trailing assistant message is sent as a
+/// prefix (`"partial": true`) that the model continues. The generation then
+/// only contains the continuation, not the prefix.
+#[derive(Debug, Clone)]
+pub struct Moonshot {
+    pub(crate) inner: ChatCompletions,
+    pub(crate) partial: bool,
+}
+
+impl Moonshot {
+    /// Create a `Moonshot` wrapper with the given API key and the default model.
+    pub fn new(api_key: impl Into<String>) -> Self {
+        let mut inner = ChatCompletions::new(DEFAULT_BASE_URL, DEFAULT_MODEL);
+        inner.api_key = Some(api_key.into());
+        // usage is included in the final stream chunk without `stream_options`
+        inner.stream_usage = false;
+        Self { inner, partial: false }
+    }
+
+    /// Use the international endpoint (`api.moonshot.ai`).
+    pub fn global(api_key: impl Into<String>) -> Self {
+        Moonshot::new(api_key).with_base_url("https://api.moonshot.ai/v1")
+    }
+
+    /// e.g. `moonshot-v1-8k`, `moonshot-v1-32k`, `moonshot-v1-128k` or `kimi-latest`.
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.inner.model = model.into();
+        self
+    }
+
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.inner.max_tokens = Some(max_tokens);
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.inner.temperature = Some(temperature);
+        self
+    }
+
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.inner.base_url = base_url.into();
+        self
+    }
+
+    /// Continue a trailing assistant message instead of starting a new one.
+    pub fn with_partial_mode(mut self, partial: bool) -> Self {
+        self.partial = partial;
+        self
+    }
+
+    fn request_body(&self, messages: &[Message], stream: bool) -> ChatRequest {
+        let mut body = self.inner.request_body(messages, stream);
+        let ends_with_assistant = matches!(messages.last().map(|m| &m.role), Some(MessageRole::Assistant));
+        if self.partial
+            && ends_with_assistant
+            && let Some(last) = body.messages.last_mut()
+        {
+            last.partial = Some(true);
+        }
+        body
+    }
+
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.inner.client = client;
+        self
+    }
+}
+
+impl Default for Moonshot {
+    /// Read the API key from `MOONSHOT_API_KEY`.
+    fn default() -> Self {
+        Moonshot::new(std::env::var("MOONSHOT_API_KEY").unwrap_or_default())
+    }
+}
+
+impl LLM for Moonshot {
+    fn generate<'a>(&'a self, messages: &'a [Message]) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        async move {
+            let body = self.request_body(messages, false);
+            let response: ChatResponse = self.inner.send(&body).await?.json().await?;
+            response.into_generate_result()
+        }
+        .boxed()
+    }
+
+    fn generate_batch<'a>(&'a self, batches: &'a [Vec<Message>]) -> BoxFuture<'a, Vec<LLMResult<GenerateResult>>> {
+        batch::generate_concurrently(self, batches, batch::DEFAULT_CONCURRENCY).boxed()
+    }
+
+    fn generate_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        _tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        async move {
+            let mut body = self.request_body(messages, false);
+            body.apply_options(options);
+            let response: ChatResponse = self.inner.send(&body).await?.json().await?;
+            response.into_generate_result()
+        }
+        .boxed()
+    }
+
+    fn stream<'a>(&'a self, messages: &'a [Message]) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.inner.stream_body(self.request_body(messages, true))
+    }
+
+    fn stream_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        _tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        let mut body = self.request_body(messages, true);
+        body.apply_options(options);
+        self.inner.stream_body(body)
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.inner.model)
+    }
+
+    fn provider(&self) -> Option<&str> {
+        Some("moonshot")
+    }
+}
@@ -1,7 +1,5 @@
 
-use std::sync::Arc;
-use serde_json::error::Error as SerdeJsonError;
-use serde::de::Error as SerdeDeError;
+use std::sync::{Arc, OnceLock};
 use async_stream::stream as async_stream;
 use futures::{
     FutureExt,
@@ -11,13 +9,20 @@ use futures::{
 
 
 use crate::message::Message;
-use crate::tools::stream::StreamData;
+use crate::tools::{schema::ToolSchema, stream::{StreamData, ToolCallDelta}};
 use crate::message::MessageRole as MsgRole;
 
 use crate::llm::{
     traits::LLM,
+    capabilities::Capabilities,
+    embeddings::Embedder,
     tokens::TokenUsage,
     error::LLMError,
+    options::GenerateOptions,
+    models::{ModelCatalog, ModelInfo},
+    pool::shared_client,
+    reasoning,
+    CallInfo,
     GenerateResult,
     LLMResult,
 };
@@ -27,6 +32,9 @@ use crate::llm::{
 /// Common names: "llama3.2", "llama3", "llama2", or custom names from `ollama list`.
 pub const DEFAULT_MODEL: &str = "llama3.2";
 
+/// Default model used by `OllamaEmbeddings`.
+pub const DEFAULT_EMBEDDING_MODEL: &str = "nomic-embed-text";
+
 pub use ollama_rs::{
     error::OllamaError,
     Ollama as OllamaClient,
@@ -34,8 +42,13 @@ pub use ollama_rs::{
     generation::{
         chat::{request::ChatMessageRequest,ChatMessage, MessageRole},
         completion::request::GenerationRequest,
+        embeddings::request::{EmbeddingsInput, GenerateEmbeddingsRequest},
+        parameters::{KeepAlive, TimeUnit},
+        tools::{ToolCall, ToolFunctionInfo, ToolInfo, ToolType},
     }
 };
+use ollama_rs::generation::images::Image;
+use ollama_rs::models::ModelInfo as OllamaModelInfo;
 
 
 #[derive(Debug, Clone)]
@@ -43,6 +56,9 @@ pub struct Ollama {
     pub(crate) client: Arc<OllamaClient>,
     pub(crate) model: String,
     pub(crate) options: Option<ModelOptions>,
+    pub(crate) native_tools: bool,
+    pub(crate) vision: bool,
+    pub(crate) keep_alive: Option<KeepAlive>,
 }
 impl Ollama {
     /// Create an `Ollama` wrapper using the provided client and the default model.
@@ -54,6 +70,9 @@ impl Ollama {
             client,
             model: DEFAULT_MODEL.to_string(),
             options: None,
+            native_tools: false,
+            vision: false,
+            keep_alive: None,
         }
     }
 
@@ -63,27 +82,182 @@ impl Ollama {
         self
     }
 
-    /// Create an `Ollama` wrapper with additional generation options.
+    /// Create an `Ollama` wrapper with additional generation options, e.g.
+    /// `ModelOptions::default().stop(vec!["Observation:".into()])` to end every
+    /// generation, streamed or not, at a marker.
     pub fn with_options(mut self, options: ModelOptions) -> Self {
         self.options = Some(options);
         self
     }
 
-    fn generate_request(&self, messages: &[Message]) -> ChatMessageRequest {
+    /// Send tool schemas in the chat API `tools` field. Only enable this for
+    /// models that support tools; Ollama rejects the request otherwise.
+    pub fn with_native_tools(mut self, native_tools: bool) -> Self {
+        self.native_tools = native_tools;
+        self
+    }
+
+    /// Send message images in the `images` field. Only enable this for
+    /// vision models such as `llava` or `llama3.2-vision`.
+    pub fn with_vision(mut self, vision: bool) -> Self {
+        self.vision = vision;
+        self
+    }
+
+    /// How long Ollama keeps the model loaded after a request. Keeping it
+    /// loaded avoids reload latency between short, frequent calls.
+    ///
+    /// Connection pooling is configured on the client:
+    /// `OllamaClient::new_with_client(host, port, pool::shared_client())`
+    /// shares the pool of the other backends, `pool_config.build_client()?`
+    /// gives this one its own.
+    pub fn with_keep_alive(mut self, keep_alive: KeepAlive) -> Self {
+        self.keep_alive = Some(keep_alive);
+        self
+    }
+
+    fn generate_request(&self, messages: &[Message], overrides: &GenerateOptions) -> ChatMessageRequest {
         let mapped_messages = messages.iter().map(|message| message.into()).collect();
-        ChatMessageRequest::new(self.model.clone(), mapped_messages).think(true)
+        let mut request = ChatMessageRequest::new(self.model.clone(), mapped_messages).think(true);
+        if let Some(options) = self.model_options(overrides) {
+            request = request.options(options);
+        }
+        match self.keep_alive.clone() {
+            Some(keep_alive) => request.keep_alive(keep_alive),
+            None => request,
+        }
     }
 
+    /// The configured `ModelOptions` with per-call `overrides` applied.
+    fn model_options(&self, overrides: &GenerateOptions) -> Option<ModelOptions> {
+        if overrides.is_empty() {
+            return self.options.clone();
+        }
+        let mut options = self.options.clone().unwrap_or_default();
+        if let Some(temperature) = overrides.temperature {
+            options = options.temperature(temperature);
+        }
+        if let Some(top_p) = overrides.top_p {
+            options = options.top_p(top_p);
+        }
+        if let Some(max_tokens) = overrides.max_tokens {
+            options = options.num_predict(max_tokens as i32);
+        }
+        if let Some(stop) = overrides.stop_sequences() {
+            options = options.stop(stop);
+        }
+        if let Some(seed) = overrides.seed {
+            options = options.seed(seed as i32);
+        }
+        Some(options)
+    }
+
+    async fn chat(&self, request: ChatMessageRequest) -> LLMResult<GenerateResult> {
+        let response = self
+            .client
+            .send_chat_messages(request)
+            .await
+            .map_err(|e| LLMError::InvalidResponse(format!("{:?}", e)))?;
+        // `think(true)` puts reasoning in `thinking`; models without thinking
+        // support may still inline <think> blocks
+        let (generation, reasoning) =
+            reasoning::separate(response.message.content.clone(), response.message.thinking.clone());
+        let mut generation = generation.trim().to_string();
+        if generation.starts_with('{')
+            && generation.ends_with(']')
+            && let Some(last_brace) = generation.rfind('}')
+            && last_brace < generation.len() - 1
+        {
+            // 只保留到最后一个}
+            generation = generation[..=last_brace].to_string();
+        }
+
+        let tokens = if let Some(final_data) = response.final_data {
+            TokenUsage::new(final_data.prompt_eval_count as u32, final_data.eval_count as u32)
+        } else {
+            TokenUsage::default()
+        };
+
+        let tool_calls = if response.message.tool_calls.is_empty() {
+            crate::llm::extract_tool_calls(&generation)
+        } else {
+            response.message.tool_calls.iter().map(CallInfo::from).collect()
+        };
+        Ok(GenerateResult { tokens, generation, tool_calls, reasoning, model: Some(response.model), ..Default::default() })
+    }
 
 }
 
+/// Lists the models pulled on the server. Capabilities come from
+/// `/api/show`, one request per model.
+impl ModelCatalog for Ollama {
+    fn list_models(&self) -> BoxFuture<'_, LLMResult<Vec<ModelInfo>>> {
+        async move {
+            let local = self
+                .client
+                .list_local_models()
+                .await
+                .map_err(|e| LLMError::InvalidResponse(format!("{:?}", e)))?;
+            let mut models = Vec::with_capacity(local.len());
+            for model in local {
+                models.push(self.show_model(model.name).await?);
+            }
+            Ok(models)
+        }
+        .boxed()
+    }
+
+    fn model_info<'a>(&'a self, model: &'a str) -> BoxFuture<'a, LLMResult<Option<ModelInfo>>> {
+        async move {
+            match self.client.show_model_info(model.to_string()).await {
+                Ok(details) => Ok(Some(to_model_info(model.to_string(), details))),
+                // `/api/show` answers 404 with `model '..' not found`
+                Err(OllamaError::Other(message)) if message.contains("not found") => Ok(None),
+                Err(e) => Err(LLMError::InvalidResponse(format!("{:?}", e))),
+            }
+        }
+        .boxed()
+    }
+}
+
+impl Ollama {
+    async fn show_model(&self, name: String) -> LLMResult<ModelInfo> {
+        let details = self
+            .client
+            .show_model_info(name.clone())
+            .await
+            .map_err(|e| LLMError::InvalidResponse(format!("{:?}", e)))?;
+        Ok(to_model_info(name, details))
+    }
+}
+
+fn to_model_info(name: String, details: OllamaModelInfo) -> ModelInfo {
+    let has = |capability: &str| details.capabilities.iter().any(|c| c == capability);
+    let mut info = ModelInfo::new(name)
+        .with_tools(has("tools"))
+        .with_vision(has("vision"))
+        .with_local(true);
+    // keyed by architecture, e.g. `qwen3.context_length`
+    info.context_window = details
+        .model_info
+        .iter()
+        .find(|(key, _)| key.ends_with(".context_length"))
+        .and_then(|(_, value)| value.as_u64())
+        .map(|window| window as u32);
+    info
+}
+
 impl Default for Ollama {
+    /// `http://127.0.0.1:11434` with the shared HTTP client.
     fn default() -> Self {
-        let client = Arc::new(OllamaClient::default());
-        Ollama::new(client)
+        Ollama::new(Arc::new(default_client()))
     }
 }
 
+fn default_client() -> OllamaClient {
+    OllamaClient::new_with_client("http://127.0.0.1", 11434, shared_client())
+}
+
 
 
 
@@ -97,10 +271,51 @@ impl From<&Message> for ChatMessage {
             MsgRole::Tool | MsgRole::Developer => MessageRole::System,
 
         };
-        ChatMessage::new(role, message.content.clone())
+        let mapped = ChatMessage::new(role, message.content.clone());
+        if message.images.is_empty() {
+            return mapped;
+        }
+        mapped.with_images(message.images.iter().map(|image| Image::from_base64(image.data.clone())).collect())
+    }
+}
+
+
+/// `ToolInfo` holds a schemars `Schema`, which deserializes from any JSON Schema value.
+fn to_ollama_tool(schema: &ToolSchema) -> LLMResult<ToolInfo> {
+    Ok(ToolInfo {
+        tool_type: ToolType::Function,
+        function: ToolFunctionInfo {
+            name: schema.name.clone(),
+            description: schema.description.clone(),
+            parameters: serde_json::from_value(schema.parameters())?,
+        },
+    })
+}
+
+impl From<&ToolCall> for CallInfo {
+    fn from(tool_call: &ToolCall) -> Self {
+        let args = match &tool_call.function.arguments {
+            serde_json::Value::String(raw) => {
+                serde_json::from_str(raw).unwrap_or_else(|_| serde_json::Value::String(raw.clone()))
+            }
+            other => other.clone(),
+        };
+        CallInfo {
+            id: None,
+            name: tool_call.function.name.clone(),
+            args,
+        }
     }
 }
 
+/// Whole tool calls as deltas numbered from `first_index`.
+fn to_tool_call_deltas(tool_calls: &[ToolCall], first_index: usize) -> Vec<ToolCallDelta> {
+    tool_calls
+        .iter()
+        .enumerate()
+        .map(|(offset, tool_call)| ToolCallDelta::whole(first_index + offset, &CallInfo::from(tool_call)))
+        .collect()
+}
 
 impl LLM for Ollama {
     // fn generate<'a>(&'a self, messages: &'a [Message]) -> BoxFuture<'a, LLMResult<GenerateResult>> {
@@ -159,78 +374,91 @@ impl LLM for Ollama {
     // }
     fn generate<'a>(&'a self, messages: &'a [Message]) -> BoxFuture<'a, LLMResult<GenerateResult>> {
         async move {
-            let request = self.generate_request(messages);
-            let response = self
-                .client
-                .send_chat_messages(request)
-                .await
-                .map_err(|e| LLMError::InvalidResponse(format!("{:?}", e)))?;
-            let mut generation = response.message.content.clone();
-
-            generation = generation.trim().to_string();
-            if generation.starts_with('{') && generation.ends_with(']') {
-                if let Some(last_brace) = generation.rfind('}') {
-                    if last_brace < generation.len() - 1 {
-                        // 只保留到最后一个}
-                        generation = generation[..=last_brace].to_string();
-                    }
-                }
-            }
-
-            let tokens = if let Some(final_data) = response.final_data {
-                let prompt_tokens = final_data.prompt_eval_count as u32;
-                let completion_tokens = final_data.eval_count as u32;
-                TokenUsage {
-                    prompt_tokens,
-                    completion_tokens,
-                    total_tokens: prompt_tokens + completion_tokens,
-                }
-            } else {
-                TokenUsage::default()
-            };
+            let request = self.generate_request(messages, &GenerateOptions::default());
+            self.chat(request).await
+        }
+        .boxed()
+    }
 
-            // --- 关键健壮处理 ---
-            let mut tool_calls: Vec<crate::llm::CallInfo> = Vec::new();
-            let parsed_json_res = match (generation.find('{'), generation.rfind('}')) {
-                (Some(start), Some(end)) if end > start => {
-                    let sub = &generation[start..=end];
-                    serde_json::from_str::<serde_json::Value>(sub)
-                }
-                _ => {
-                    serde_json::from_str::<serde_json::Value>(&generation)
-                }
-            };
+    fn generate_with_tools<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        async move {
+            let tools = tools.iter().map(to_ollama_tool).collect::<LLMResult<Vec<_>>>()?;
+            let request = self.generate_request(messages, &GenerateOptions::default()).tools(tools);
+            self.chat(request).await
+        }
+        .boxed()
+    }
 
-            if let Ok(parsed) = parsed_json_res {
-                if let Some(arr) = parsed.get("tool_calls").and_then(|v| v.as_array()) {
-                    for entry in arr.iter() {
-                        if let Some(obj) = entry.as_object() {
-                            if let Some(name_val) = obj.get("name").and_then(|v| v.as_str()) {
-                                let name = name_val.to_string();
-                                let args = obj.get("args").cloned().unwrap_or_else(|| serde_json::json!({}));
-                                tool_calls.push(crate::llm::CallInfo { name, args });
-                            }
-                        }
-                    }
-                }
-            } else {
-                println!("DEBUG Ollama: JSON parse error: {:?}", parsed_json_res);
+    fn generate_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        async move {
+            let mut request = self.generate_request(messages, options);
+            if !tools.is_empty() {
+                let tools = tools.iter().map(to_ollama_tool).collect::<LLMResult<Vec<_>>>()?;
+                request = request.tools(tools);
             }
-            Ok(GenerateResult { tokens, generation, tool_calls })
+            self.chat(request).await
         }
         .boxed()
     }
 
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            native_tools: self.native_tools,
+            vision: self.vision,
+            ..Capabilities::default()
+        }
+    }
+
     fn stream<'a>(&'a self, messages: &'a [Message]) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.stream_with_tools(messages, &[])
+    }
+
+    /// Ollama sends each tool call whole, so every call arrives as a single
+    /// delta with complete arguments.
+    fn stream_with_tools<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.stream_with_options(messages, tools, GenerateOptions::none())
+    }
+
+    fn stream_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
         // Keep borrowed references `self` and `messages` in scope for the async generator.
         let this = self;
         let msgs = messages;
 
         let s = async_stream! {
+            let tools = match tools.iter().map(to_ollama_tool).collect::<LLMResult<Vec<_>>>() {
+                Ok(tools) => tools,
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            };
+            let mut request = this.generate_request(msgs, options);
+            if !tools.is_empty() {
+                request = request.tools(tools);
+            }
+
             // Prefer upstream streaming if feature enabled
             #[cfg(feature = "ollama_stream")]
             {
-                let request = this.generate_request(msgs);
+                use futures::StreamExt;
                 // get upstream stream (awaitable)
                 let upstream = match this.client.send_chat_messages_stream(request).await {
                     Ok(s) => s,
@@ -241,17 +469,34 @@ impl LLM for Ollama {
                 };
 
                 futures::pin_mut!(upstream);
+                let mut calls_seen = 0;
+                let mut splitter = reasoning::ReasoningSplitter::new();
                 while let Some(item_res) = upstream.next().await {
                     match item_res {
                         Ok(item) => {
                             let value = serde_json::to_value(&item).unwrap_or_default();
-                            let content = item.message.content.clone();
-                            let tokens = item.final_data.map(|final_data| crate::llm::tokens::TokenUsage {
-                                prompt_tokens: final_data.prompt_eval_count as u32,
-                                completion_tokens: final_data.eval_count as u32,
-                                total_tokens: final_data.prompt_eval_count as u32 + final_data.eval_count as u32,
-                            });
-                            yield Ok(StreamData::new(value, tokens, content));
+                            let (mut content, mut thinking) = splitter.push(&item.message.content);
+                            if let Some(delta) = item.message.thinking.as_deref() {
+                                thinking.insert_str(0, delta);
+                            }
+                            let done = item.done;
+                            if done {
+                                let (rest, rest_thinking) = splitter.finish();
+                                content.push_str(&rest);
+                                thinking.push_str(&rest_thinking);
+                            }
+                            let tokens = item
+                                .final_data
+                                .map(|final_data| TokenUsage::new(final_data.prompt_eval_count as u32, final_data.eval_count as u32));
+                            let mut data = StreamData::new(value, tokens.clone(), content);
+                            data.reasoning = thinking;
+                            data.tool_calls = to_tool_call_deltas(&item.message.tool_calls, calls_seen);
+                            calls_seen += data.tool_calls.len();
+                            yield Ok(data);
+                            if done {
+                                yield Ok(StreamData::done(tokens, Some(crate::llm::FinishReason::Stop)));
+                                return;
+                            }
                         }
                         Err(e) => {
                             // map upstream error to LLMError
@@ -264,24 +509,22 @@ impl LLM for Ollama {
             // Fallback: call non-streaming endpoint and yield single item
             #[cfg(not(feature = "ollama_stream"))]
             {
-                let request = this.generate_request(msgs);
                 match this.client.send_chat_messages(request).await {
                     Ok(response) => {
-                        let content = response.message.content.clone();
+                        let (content, thinking) =
+                            reasoning::separate(response.message.content.clone(), response.message.thinking.clone());
+                        let tool_calls = to_tool_call_deltas(&response.message.tool_calls, 0);
                         let value = serde_json::to_value(response.message).unwrap_or_default();
 
                         let tokens = response.final_data.map(|final_data| {
-                            let prompt_tokens = final_data.prompt_eval_count as u32;
-                            let completion_tokens = final_data.eval_count as u32;
-                            TokenUsage {
-                                prompt_tokens,
-                                completion_tokens,
-                                total_tokens: prompt_tokens + completion_tokens,
-                            }
+                            TokenUsage::new(final_data.prompt_eval_count as u32, final_data.eval_count as u32)
                         });
 
-                        let sd = StreamData::new(value, tokens, content);
+                        let mut sd = StreamData::new(value, tokens.clone(), content);
+                        sd.reasoning = thinking.unwrap_or_default();
+                        sd.tool_calls = tool_calls;
                         yield Ok(sd);
+                        yield Ok(StreamData::done(tokens, Some(crate::llm::FinishReason::Stop)));
                     }
                     Err(e) => {
                         yield Err(LLMError::InvalidResponse(format!("{:?}", e)));
@@ -292,4 +535,106 @@ impl LLM for Ollama {
 
         Box::pin(s)
     }
+
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.model)
+    }
+
+    fn provider(&self) -> Option<&str> {
+        Some("ollama")
+    }
+
+    /// Looks the model up instead of generating.
+    fn health_check(&self) -> BoxFuture<'_, LLMResult<()>> {
+        async move {
+            let model = self.model_name().unwrap_or_default();
+            match self.model_info(model).await? {
+                Some(_) => Ok(()),
+                None => Err(LLMError::ModelNotFound(model.to_string())),
+            }
+        }
+        .boxed()
+    }
+}
+
+/// Embeddings from Ollama's `/api/embed` endpoint, for fully local retrieval.
+#[derive(Debug, Clone)]
+pub struct OllamaEmbeddings {
+    pub(crate) client: Arc<OllamaClient>,
+    pub(crate) model: String,
+    pub(crate) keep_alive: Option<KeepAlive>,
+    pub(crate) truncate: Option<bool>,
+    /// Vector size, given explicitly or learned from the first response.
+    pub(crate) dimensions: OnceLock<usize>,
+}
+
+impl OllamaEmbeddings {
+    /// Create an `OllamaEmbeddings` using the provided client and the default model.
+    pub fn new(client: Arc<OllamaClient>) -> Self {
+        Self {
+            client,
+            model: DEFAULT_EMBEDDING_MODEL.to_string(),
+            keep_alive: None,
+            truncate: None,
+            dimensions: OnceLock::new(),
+        }
+    }
+
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
+    /// Vector size of the model, so `dimensions()` is known before the first request.
+    pub fn with_dimensions(self, dimensions: usize) -> Self {
+        let _ = self.dimensions.set(dimensions);
+        self
+    }
+
+    pub fn with_keep_alive(mut self, keep_alive: KeepAlive) -> Self {
+        self.keep_alive = Some(keep_alive);
+        self
+    }
+
+    /// Truncate inputs longer than the model context instead of failing.
+    pub fn with_truncate(mut self, truncate: bool) -> Self {
+        self.truncate = Some(truncate);
+        self
+    }
+}
+
+impl Default for OllamaEmbeddings {
+    fn default() -> Self {
+        Self::new(Arc::new(default_client()))
+    }
+}
+
+impl Embedder for OllamaEmbeddings {
+    fn embed<'a>(&'a self, texts: &'a [String]) -> BoxFuture<'a, LLMResult<Vec<Vec<f32>>>> {
+        async move {
+            if texts.is_empty() {
+                return Ok(Vec::new());
+            }
+            let mut request = GenerateEmbeddingsRequest::new(
+                self.model.clone(),
+                EmbeddingsInput::Multiple(texts.to_vec()),
+            );
+            if let Some(keep_alive) = self.keep_alive.clone() {
+                request = request.keep_alive(keep_alive);
+            }
+            if let Some(truncate) = self.truncate {
+                request = request.truncate(truncate);
+            }
+            let response = self.client.generate_embeddings(request).await?;
+            if let Some(first) = response.embeddings.first() {
+                let _ = self.dimensions.set(first.len());
+            }
+            Ok(response.embeddings)
+        }
+        .boxed()
+    }
+
+    fn dimensions(&self) -> Option<usize> {
+        self.dimensions.get().copied()
+    }
 }
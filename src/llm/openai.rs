@@ -2,28 +2,106 @@
 pub use async_openai::{
     Client, config::{Config, OpenAIConfig}
 };
-use serde_json::{json, Value};
-use crate::message::Message;
-use crate::tools::stream::StreamData;
+pub use async_openai::types::ReasoningEffort;
+use async_openai::error::OpenAIError;
+use async_openai::types::{
+    ChatChoice,
+    ChatCompletionRequestAssistantMessageArgs,
+    ChatCompletionRequestDeveloperMessageArgs,
+    ChatCompletionRequestMessage,
+    ChatCompletionRequestSystemMessageArgs,
+    ChatCompletionRequestToolMessageArgs,
+    ChatCompletionRequestUserMessageArgs,
+    ChatCompletionRequestUserMessageContent,
+    ChatCompletionRequestUserMessageContentPart,
+    ChatCompletionRequestMessageContentPartImage,
+    ChatCompletionRequestMessageContentPartText,
+    ImageUrl,
+    ChatCompletionMessageToolCall,
+    ChatCompletionMessageToolCallChunk,
+    ChatCompletionTokenLogprob,
+    ChatCompletionStreamOptions,
+    ChatCompletionTool,
+    ChatCompletionToolChoiceOption,
+    ChatCompletionToolType,
+    FunctionObject,
+    FunctionCall,
+    CompletionUsage,
+    FinishReason as OpenAIFinishReason,
+    CreateChatCompletionRequest,
+    CreateChatCompletionRequestArgs,
+    CreateEmbeddingRequest,
+    EmbeddingInput,
+    Stop,
+};
+use std::sync::Mutex;
+use serde_json::Value;
+use crate::message::{Message, MessageRole as MsgRole};
+use crate::tools::{schema::ToolSchema, stream::{StreamData, ToolCallDelta}};
 use serde::{Serialize, Deserialize};
 use crate::llm::{
     traits::LLM,
-    tokens::TokenUsage,
+    batch,
+    capabilities::Capabilities,
+    embeddings::Embedder,
+    tokens::{context_window, TokenUsage},
     error::LLMError,
+    options::GenerateOptions,
+    models::{ModelCatalog, ModelInfo},
+    pool::shared_client,
+    reasoning::{self, ReasoningSplitter},
+    CallInfo,
+    Candidate,
+    FinishReason,
     GenerateResult,
     LLMResult,
+    TokenLogprob,
+    TopLogprob,
 };
 
-use std::sync::Arc;
-use serde_json::error::Error as SerdeJsonError;
-use serde::de::Error as SerdeDeError;
 use async_stream::stream as async_stream;
 use futures::{
     FutureExt,
+    StreamExt,
     future::BoxFuture,
     stream::BoxStream
 };
 
+mod responses;
+
+pub use responses::BuiltinTool;
+
+/// Default model name used when no `CompletionOptions` are provided.
+pub const DEFAULT_MODEL: &str = "gpt-4o-mini";
+
+/// Whether `model` accepts `strict` function schemas (structured outputs).
+/// Models served under other names through `with_api_base` are assumed not
+/// to, leaving strict tools to the agent's own argument checks.
+pub fn supports_strict_tools(model: &str) -> bool {
+    let openai_family = model.starts_with("gpt-")
+        || (model.starts_with('o') && model[1..].starts_with(|c: char| c.is_ascii_digit()));
+    // the o1 previews can't call functions at all
+    openai_family && !model.starts_with("o1-preview") && !model.starts_with("o1-mini")
+}
+
+/// Whether `model` accepts images. Every current chat model does except
+/// GPT-3.5 and the mini reasoning models.
+pub fn supports_vision(model: &str) -> bool {
+    !(model.starts_with("gpt-3.5") || model.starts_with("o1-mini") || model.starts_with("o3-mini"))
+}
+
+/// Default model used by `OpenAIEmbeddings`.
+pub const DEFAULT_EMBEDDING_MODEL: &str = "text-embedding-3-small";
+
+/// Most inputs the embeddings endpoint accepts per request.
+pub const MAX_EMBEDDING_INPUTS: usize = 2048;
+
+/// Most input tokens the embeddings endpoint accepts per request.
+pub const MAX_EMBEDDING_REQUEST_TOKENS: u32 = 300_000;
+
+/// Most completions (`n`) one Chat Completions request samples.
+pub const MAX_N: usize = 128;
+
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OpenAIFunction{
@@ -57,35 +135,103 @@ pub struct CompletionOptions {
     /// A unique identifier representing your end-user, which will help OpenAI to monitor and detect abuse. [Learn more](https://platform.openai.com/docs/usage-policies/end-user-ids).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub user: Option<String>,
+
+    /// Return log probabilities of the output tokens in `GenerateResult::logprobs`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<bool>,
+
+    /// How many of the most likely alternatives to return per token (0-20).
+    /// Requires `logprobs`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_logprobs: Option<u8>,
+
+    /// Up to 4 sequences that end the generation, e.g. `"Observation:"` for
+    /// ReAct-style prompts. Also applied to streams.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<String>>,
+}
+
+/// Which OpenAI endpoint requests go to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OpenAIApi {
+    /// `/chat/completions`.
+    #[default]
+    ChatCompletions,
+    /// `/responses`, needed by some reasoning models and for built-in tools.
+    Responses,
 }
 
 pub struct OpenAI{
     pub client:Client<OpenAIConfig>,
-    pub options:Option<CompletionOptions>
-
+    /// The HTTP client inside `client`, kept to rebuild it with another
+    /// config.
+    pub http_client: reqwest::Client,
+    pub options:Option<CompletionOptions>,
+    pub api: OpenAIApi,
+    /// How much reasoning o-series and other reasoning models do.
+    pub reasoning_effort: Option<ReasoningEffort>,
+    /// Provider-side tools; only sent with `OpenAIApi::Responses`.
+    pub builtin_tools: Vec<BuiltinTool>,
 }
 
 impl OpenAI {
     pub fn new() -> Self {
-        let client = Client::new();
-        Self {
-            client,
-            options: None,
-        }
+        Self::with_client(Client::new())
     }
 
     pub fn with_api_key(api_key: impl Into<String>) -> Self {
         let config = OpenAIConfig::new().with_api_key(api_key);
+        Self::with_client(Client::with_config(config))
+    }
+
+    fn with_client(client: Client<OpenAIConfig>) -> Self {
+        let http_client = shared_client();
         Self {
-            client: Client::with_config(config),
+            client: client.with_http_client(http_client.clone()),
+            http_client,
             options: None,
+            api: OpenAIApi::default(),
+            reasoning_effort: None,
+            builtin_tools: Vec::new(),
         }
     }
 
+    pub fn with_api(mut self, api: OpenAIApi) -> Self {
+        self.api = api;
+        self
+    }
+
+    pub fn with_reasoning_effort(mut self, effort: ReasoningEffort) -> Self {
+        self.reasoning_effort = Some(effort);
+        self
+    }
+
+    /// Let the model use `tool`. Switches to the Responses API, the only one
+    /// that runs built-in tools.
+    pub fn with_builtin_tool(mut self, tool: BuiltinTool) -> Self {
+        self.api = OpenAIApi::Responses;
+        self.builtin_tools.push(tool);
+        self
+    }
+
+    /// Send requests to `api_base` instead of `https://api.openai.com/v1`,
+    /// e.g. a proxy or a test server.
+    pub fn with_api_base(mut self, api_base: impl Into<String>) -> Self {
+        let config = self.client.config().clone().with_api_base(api_base);
+        self.client = Client::with_config(config).with_http_client(self.http_client.clone());
+        self
+    }
+
     pub fn with_options(mut self, options: CompletionOptions) -> Self {
         self.options = Some(options);
         self
     }
+
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = self.client.with_http_client(client.clone());
+        self.http_client = client;
+        self
+    }
 }
 
 impl Default for OpenAI {
@@ -94,18 +240,519 @@ impl Default for OpenAI {
     }
 }
 
+impl OpenAI {
+    fn generate_request(
+        &self,
+        messages: &[Message],
+        tools: &[ToolSchema],
+        overrides: &GenerateOptions,
+        stream: bool,
+    ) -> LLMResult<CreateChatCompletionRequest> {
+        let mapped_messages = messages
+            .iter()
+            .map(to_openai_message)
+            .collect::<LLMResult<Vec<_>>>()?;
+
+        let mut builder = CreateChatCompletionRequestArgs::default();
+        builder.messages(mapped_messages);
+        match self.options.as_ref() {
+            Some(options) => {
+                builder.model(options.model.clone());
+                if let Some(max_tokens) = options.max_tokens {
+                    builder.max_completion_tokens(max_tokens);
+                }
+                if let Some(temperature) = options.temperature {
+                    builder.temperature(temperature);
+                }
+                if let Some(n) = options.n {
+                    builder.n(n);
+                }
+                if let Some(user) = options.user.as_ref() {
+                    builder.user(user.clone());
+                }
+                if let Some(logprobs) = options.logprobs {
+                    builder.logprobs(logprobs);
+                }
+                if let Some(top_logprobs) = options.top_logprobs {
+                    builder.top_logprobs(top_logprobs);
+                }
+                if let Some(stop) = options.stop.as_ref().filter(|stop| !stop.is_empty()) {
+                    builder.stop(Stop::StringArray(stop.clone()));
+                }
+            }
+            None => {
+                builder.model(DEFAULT_MODEL);
+            }
+        }
+        if let Some(max_tokens) = overrides.max_tokens {
+            builder.max_completion_tokens(max_tokens);
+        }
+        if let Some(temperature) = overrides.temperature {
+            builder.temperature(temperature);
+        }
+        if let Some(top_p) = overrides.top_p {
+            builder.top_p(top_p);
+        }
+        if let Some(stop) = overrides.stop_sequences() {
+            builder.stop(Stop::StringArray(stop));
+        }
+        if let Some(seed) = overrides.seed {
+            builder.seed(seed);
+        }
+        if let Some(effort) = self.reasoning_effort.clone() {
+            builder.reasoning_effort(effort);
+        }
+        if !tools.is_empty() {
+            let strict = supports_strict_tools(self.options.as_ref().map_or(DEFAULT_MODEL, |options| options.model.as_str()));
+            builder
+                .tools(tools.iter().map(|tool| to_openai_tool(tool, strict)).collect::<Vec<_>>())
+                .tool_choice(ChatCompletionToolChoiceOption::Auto);
+        }
+        if stream {
+            builder
+                .stream(true)
+                .stream_options(ChatCompletionStreamOptions { include_usage: true });
+        }
+        Ok(builder.build()?)
+    }
+
+    async fn complete(&self, messages: &[Message], tools: &[ToolSchema], options: &GenerateOptions) -> LLMResult<GenerateResult> {
+        match self.api {
+            OpenAIApi::ChatCompletions => self.create(self.generate_request(messages, tools, options, false)?).await,
+            OpenAIApi::Responses => {
+                let request = self.responses_request(messages, tools, options, false)?;
+                self.create_response(request, options).await
+            }
+        }
+    }
+
+    async fn create(&self, request: CreateChatCompletionRequest) -> LLMResult<GenerateResult> {
+        let response = self.client.chat().create(request).await?;
+
+        let tokens = response
+            .usage
+            .as_ref()
+            .map(to_token_usage)
+            .unwrap_or_default();
+
+        let model = Some(response.model.clone());
+        // one choice per requested completion (`n`)
+        let candidates = response.choices.into_iter().map(to_candidate).collect();
+        GenerateResult::from_candidates(candidates, tokens, model)
+            .ok_or_else(|| LLMError::InvalidResponse("no choices in response".to_string()))
+    }
+}
+
+fn to_candidate(choice: ChatChoice) -> Candidate {
+    let finish_reason = choice.finish_reason.map(map_finish_reason);
+    let logprobs = choice
+        .logprobs
+        .and_then(|l| l.content)
+        .map(|content| content.into_iter().map(to_token_logprob).collect());
+    // OpenAI-compatible servers behind `with_api_base` may inline <think> blocks
+    let (generation, reasoning) = reasoning::separate(choice.message.content.unwrap_or_default(), None);
+    let tool_calls = match choice.message.tool_calls {
+        Some(calls) if !calls.is_empty() => calls.iter().map(to_call_info).collect(),
+        // No native calls: fall back to the JSON-in-prompt protocol.
+        _ => crate::llm::extract_tool_calls(&generation),
+    };
+    Candidate { generation, tool_calls, finish_reason, reasoning, logprobs }
+}
+
+fn to_token_logprob(logprob: ChatCompletionTokenLogprob) -> TokenLogprob {
+    TokenLogprob {
+        token: logprob.token,
+        logprob: logprob.logprob,
+        top_logprobs: logprob
+            .top_logprobs
+            .into_iter()
+            .map(|top| TopLogprob { token: top.token, logprob: top.logprob })
+            .collect(),
+    }
+}
+
+/// A strict schema is sent in strict mode when the model supports it and
+/// the arguments can be expressed that way.
+fn to_openai_tool(schema: &ToolSchema, model_supports_strict: bool) -> ChatCompletionTool {
+    let strict_parameters = (schema.strict && model_supports_strict)
+        .then(|| schema.strict_parameters())
+        .flatten();
+    let strict = strict_parameters.is_some().then_some(true);
+    ChatCompletionTool {
+        r#type: ChatCompletionToolType::Function,
+        function: FunctionObject {
+            name: schema.name.clone(),
+            description: Some(schema.description.clone()),
+            parameters: Some(strict_parameters.unwrap_or_else(|| schema.parameters())),
+            strict,
+        },
+    }
+}
+
+/// Map our `Message` onto the chat completion message variants.
+///
+/// `Tool` messages carry tool definitions and are sent as system messages (as in
+/// the Ollama backend). Results of native calls go back as `tool` messages
+/// with the provider's `tool_call_id`; `ToolResponce` messages without one
+/// are sent as user messages named after the tool. Images of user messages
+/// are sent as `image_url` parts.
+fn to_openai_message(message: &Message) -> LLMResult<ChatCompletionRequestMessage> {
+    let content = message.content.clone();
+    if let Some(id) = message.tool_call_id.as_ref() {
+        return Ok(ChatCompletionRequestToolMessageArgs::default()
+            .content(content)
+            .tool_call_id(id.clone())
+            .build()?
+            .into());
+    }
+    let mapped = match message.role {
+        MsgRole::System | MsgRole::Tool => {
+            ChatCompletionRequestSystemMessageArgs::default()
+                .content(content)
+                .build()?
+                .into()
+        }
+        MsgRole::User => {
+            ChatCompletionRequestUserMessageArgs::default()
+                .content(to_openai_user_content(message))
+                .build()?
+                .into()
+        }
+        MsgRole::Assistant => {
+            let mut builder = ChatCompletionRequestAssistantMessageArgs::default();
+            builder.content(content);
+            let tool_calls = to_openai_tool_calls(&message.tool_calls);
+            if !tool_calls.is_empty() {
+                builder.tool_calls(tool_calls);
+            }
+            builder.build()?.into()
+        }
+        MsgRole::Developer => {
+            ChatCompletionRequestDeveloperMessageArgs::default()
+                .content(content)
+                .build()?
+                .into()
+        }
+        MsgRole::ToolResponce => {
+            let mut builder = ChatCompletionRequestUserMessageArgs::default();
+            builder.content(content);
+            if let Some(name) = message.name.as_ref() {
+                builder.name(name.clone());
+            }
+            builder.build()?.into()
+        }
+    };
+    Ok(mapped)
+}
+
+/// Text with `image_url` parts for the message's images.
+fn to_openai_user_content(message: &Message) -> ChatCompletionRequestUserMessageContent {
+    if message.images.is_empty() {
+        return message.content.clone().into();
+    }
+    let mut parts = vec![ChatCompletionRequestUserMessageContentPart::Text(
+        ChatCompletionRequestMessageContentPartText { text: message.content.clone() },
+    )];
+    parts.extend(message.images.iter().map(|image| {
+        ChatCompletionRequestUserMessageContentPart::ImageUrl(ChatCompletionRequestMessageContentPartImage {
+            image_url: ImageUrl { url: image.data_url(), detail: None },
+        })
+    }));
+    ChatCompletionRequestUserMessageContent::Array(parts)
+}
+
+/// The calls of an assistant turn, echoed back so the `tool` messages that
+/// follow can reference them. Calls without a provider id came from the
+/// JSON-in-prompt protocol and are left in the message text.
+fn to_openai_tool_calls(calls: &[CallInfo]) -> Vec<ChatCompletionMessageToolCall> {
+    calls
+        .iter()
+        .filter_map(|call| {
+            let id = call.id.clone()?;
+            let arguments = match &call.args {
+                Value::String(raw) => raw.clone(),
+                args => args.to_string(),
+            };
+            Some(ChatCompletionMessageToolCall {
+                id,
+                r#type: ChatCompletionToolType::Function,
+                function: FunctionCall { name: call.name.clone(), arguments },
+            })
+        })
+        .collect()
+}
+
+fn map_finish_reason(reason: OpenAIFinishReason) -> FinishReason {
+    match reason {
+        OpenAIFinishReason::Stop => FinishReason::Stop,
+        OpenAIFinishReason::Length => FinishReason::Length,
+        OpenAIFinishReason::ToolCalls | OpenAIFinishReason::FunctionCall => FinishReason::ToolCalls,
+        OpenAIFinishReason::ContentFilter => FinishReason::ContentFilter,
+    }
+}
+
+fn to_token_usage(usage: &CompletionUsage) -> TokenUsage {
+    TokenUsage {
+        prompt_tokens: usage.prompt_tokens,
+        completion_tokens: usage.completion_tokens,
+        total_tokens: usage.total_tokens,
+        // OpenAI caches long prompts automatically and only reports reads
+        cache_read_tokens: usage
+            .prompt_tokens_details
+            .as_ref()
+            .and_then(|details| details.cached_tokens)
+            .unwrap_or_default(),
+        cache_write_tokens: 0,
+    }
+}
+
+/// One streamed fragment of a native tool call.
+fn to_tool_call_delta(chunk: &ChatCompletionMessageToolCallChunk) -> ToolCallDelta {
+    let function = chunk.function.as_ref();
+    ToolCallDelta {
+        index: chunk.index as usize,
+        id: chunk.id.clone(),
+        name: function.and_then(|function| function.name.clone()),
+        arguments: function.and_then(|function| function.arguments.clone()).unwrap_or_default(),
+    }
+}
+
+/// Convert native function calls into `CallInfo`. Arguments arrive as a JSON
+/// string; if it does not parse we keep the raw string so the tool can report it.
+fn to_call_info(tool_call: &ChatCompletionMessageToolCall) -> CallInfo {
+    let args = serde_json::from_str::<Value>(&tool_call.function.arguments)
+        .unwrap_or_else(|_| Value::String(tool_call.function.arguments.clone()));
+    CallInfo {
+        id: Some(tool_call.id.clone()),
+        name: tool_call.function.name.clone(),
+        args,
+    }
+}
+
 impl LLM for OpenAI {
     fn generate<'a>(&'a self, messages: &'a [Message]) -> BoxFuture<'a, LLMResult<GenerateResult>> {
-        // Implementation for generating text using OpenAI API
-        unimplemented!()
+        async move { self.complete(messages, &[], &GenerateOptions::default()).await }.boxed()
+    }
+
+    fn generate_with_tools<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        async move { self.complete(messages, tools, &GenerateOptions::default()).await }.boxed()
+    }
+
+    fn generate_batch<'a>(&'a self, batches: &'a [Vec<Message>]) -> BoxFuture<'a, Vec<LLMResult<GenerateResult>>> {
+        batch::generate_concurrently(self, batches, batch::DEFAULT_CONCURRENCY).boxed()
+    }
+
+    fn generate_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        async move { self.complete(messages, tools, options).await }.boxed()
+    }
+
+    /// Chat Completions sample up to 128 completions per request, so larger
+    /// `n` are split over several; the Responses API has no `n`, so it makes
+    /// `n` calls.
+    fn generate_n<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+        n: usize,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        match self.api {
+            OpenAIApi::ChatCompletions => async move {
+                batch::check_n(n)?;
+                let mut candidates = Vec::with_capacity(n);
+                let mut tokens = TokenUsage::default();
+                let mut model = None;
+                let mut warnings = Vec::new();
+                let mut left = n;
+                while left > 0 {
+                    let count = left.min(MAX_N);
+                    let mut request = self.generate_request(messages, tools, options, false)?;
+                    request.n = Some(count as u8);
+                    let result = self.create(request).await?;
+                    tokens.add(&result.tokens);
+                    model = model.or(result.model.clone());
+                    warnings.extend(result.warnings.iter().cloned());
+                    candidates.extend(result.all_candidates());
+                    left -= count;
+                }
+                let mut result = GenerateResult::from_candidates(candidates, tokens, model).unwrap_or_default();
+                result.warnings = warnings;
+                Ok(result)
+            }
+            .boxed(),
+            OpenAIApi::Responses => batch::generate_n_sequentially(self, messages, tools, options, n).boxed(),
+        }
     }
 
     fn stream<'a>(&'a self, messages: &'a [Message]) -> BoxStream<'a, LLMResult<StreamData>> {
-        // Implementation for streaming text using OpenAI API
-        unimplemented!()
+        self.stream_with_tools(messages, &[])
+    }
+
+    fn stream_with_tools<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.stream_with_options(messages, tools, GenerateOptions::none())
+    }
+
+    fn stream_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        if self.api == OpenAIApi::Responses {
+            return options.stop_stream(self.stream_response(messages, tools, options));
+        }
+        let this = self;
+        let msgs = messages;
+
+        let s = async_stream! {
+            let request = match this.generate_request(msgs, tools, options, true) {
+                Ok(request) => request,
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            };
+            let mut upstream = match this.client.chat().create_stream(request).await {
+                Ok(s) => s,
+                Err(e) => {
+                    yield Err(LLMError::from(e));
+                    return;
+                }
+            };
+
+            // async-openai ends the stream at `[DONE]` and reports dropped
+            // connections as errors
+            let mut usage = None;
+            let mut finish_reason = None;
+            let mut failed = false;
+            let mut splitter = ReasoningSplitter::new();
+            while let Some(item_res) = upstream.next().await {
+                match item_res {
+                    Ok(item) => {
+                        let value = serde_json::to_value(&item).unwrap_or_default();
+                        let (content, thinking) = splitter.push(
+                            item.choices
+                                .first()
+                                .and_then(|choice| choice.delta.content.as_deref())
+                                .unwrap_or_default(),
+                        );
+                        if let Some(reason) = item.choices.first().and_then(|choice| choice.finish_reason) {
+                            finish_reason = Some(map_finish_reason(reason));
+                        }
+                        let tokens = item.usage.as_ref().map(to_token_usage);
+                        if tokens.is_some() {
+                            usage = tokens.clone();
+                        }
+                        let tool_calls = item
+                            .choices
+                            .first()
+                            .and_then(|choice| choice.delta.tool_calls.as_ref())
+                            .map(|chunks| chunks.iter().map(to_tool_call_delta).collect())
+                            .unwrap_or_default();
+                        let mut data = StreamData::new(value, tokens, content);
+                        data.reasoning = thinking;
+                        data.tool_calls = tool_calls;
+                        yield Ok(data);
+                    }
+                    Err(e) => {
+                        failed = true;
+                        yield Err(LLMError::from(e));
+                    }
+                }
+            }
+            if !failed {
+                let (content, thinking) = splitter.finish();
+                if !content.is_empty() || !thinking.is_empty() {
+                    let mut data = StreamData::new(Value::Null, None, content);
+                    data.reasoning = thinking;
+                    yield Ok(data);
+                }
+                yield Ok(StreamData::done(usage, finish_reason));
+            }
+        };
+
+        Box::pin(s)
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            native_tools: true,
+            vision: self.model_name().is_some_and(supports_vision),
+            ..Capabilities::default()
+        }
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        Some(self.options.as_ref().map_or(DEFAULT_MODEL, |options| options.model.as_str()))
+    }
+
+    fn provider(&self) -> Option<&str> {
+        Some("openai")
+    }
+
+    /// Looks the model up instead of generating.
+    fn health_check(&self) -> BoxFuture<'_, LLMResult<()>> {
+        async move {
+            let model = self.model_name().unwrap_or_default();
+            match self.model_info(model).await? {
+                Some(_) => Ok(()),
+                None => Err(LLMError::ModelNotFound(model.to_string())),
+            }
+        }
+        .boxed()
+    }
+}
+
+/// `/models` only returns ids, so capabilities come from the model family.
+impl ModelCatalog for OpenAI {
+    fn list_models(&self) -> BoxFuture<'_, LLMResult<Vec<ModelInfo>>> {
+        async move {
+            let response = self.client.models().list().await?;
+            Ok(response.data.iter().map(|model| openai_model_info(&model.id)).collect())
+        }
+        .boxed()
+    }
+
+    fn model_info<'a>(&'a self, model: &'a str) -> BoxFuture<'a, LLMResult<Option<ModelInfo>>> {
+        async move {
+            match self.client.models().retrieve(model).await {
+                Ok(model) => Ok(Some(openai_model_info(&model.id))),
+                Err(OpenAIError::ApiError(e)) if e.code.as_deref() == Some("model_not_found") => Ok(None),
+                Err(e) => Err(e.into()),
+            }
+        }
+        .boxed()
     }
 }
 
+fn openai_model_info(id: &str) -> ModelInfo {
+    const NOT_CHAT: &[&str] = &["instruct", "audio", "realtime", "transcribe", "tts", "search", "image"];
+    // the first o1 releases had neither function calling nor image input
+    const NO_TOOLS: &[&str] = &["o1-mini", "o1-preview"];
+    const NO_VISION: &[&str] = &["gpt-3.5", "gpt-4-0", "gpt-4-32k", "o1-mini", "o1-preview", "o3-mini"];
+    let name = id.to_lowercase();
+    let chat = ["gpt-", "chatgpt-", "o1", "o3", "o4"].iter().any(|prefix| name.starts_with(prefix))
+        && !NOT_CHAT.iter().any(|kind| name.contains(kind));
+    let tools = chat && !NO_TOOLS.iter().any(|prefix| name.starts_with(prefix));
+    let vision = chat && name != "gpt-4" && !NO_VISION.iter().any(|prefix| name.starts_with(prefix));
+    let mut info = ModelInfo::new(id).with_tools(tools).with_vision(vision);
+    info.context_window = context_window(&name);
+    info
+}
+
 pub struct OpenAIRequest {
     pub messages: Vec<Message>,
     pub model: String,
@@ -113,3 +760,146 @@ pub struct OpenAIRequest {
     pub tool_choice: Option<String>, // "auto" | "none"
 }
 
+
+/// OpenAI embeddings (`text-embedding-3-*`, `text-embedding-ada-002`).
+///
+/// `embed_batched` splits inputs so each request stays under both the input
+/// count and the per-request token limit. Token usage of every request is
+/// added to `usage()`.
+pub struct OpenAIEmbeddings {
+    pub client: Client<OpenAIConfig>,
+    pub model: String,
+    /// Shorten vectors to this size (`text-embedding-3-*` only).
+    pub dimensions: Option<u32>,
+    pub max_batch_size: usize,
+    usage: Mutex<TokenUsage>,
+}
+
+impl OpenAIEmbeddings {
+    pub fn new() -> Self {
+        Self::with_client(Client::new().with_http_client(shared_client()))
+    }
+
+    pub fn with_api_key(api_key: impl Into<String>) -> Self {
+        let config = OpenAIConfig::new().with_api_key(api_key);
+        Self::with_client(Client::with_config(config).with_http_client(shared_client()))
+    }
+
+    pub fn with_client(client: Client<OpenAIConfig>) -> Self {
+        Self {
+            client,
+            model: DEFAULT_EMBEDDING_MODEL.to_string(),
+            dimensions: None,
+            max_batch_size: MAX_EMBEDDING_INPUTS,
+            usage: Mutex::new(TokenUsage::default()),
+        }
+    }
+
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
+    pub fn with_dimensions(mut self, dimensions: u32) -> Self {
+        self.dimensions = Some(dimensions);
+        self
+    }
+
+    pub fn with_max_batch_size(mut self, max_batch_size: usize) -> Self {
+        self.max_batch_size = max_batch_size.clamp(1, MAX_EMBEDDING_INPUTS);
+        self
+    }
+
+    /// Tokens used by all requests so far.
+    pub fn usage(&self) -> TokenUsage {
+        self.usage.lock().map(|usage| usage.clone()).unwrap_or_default()
+    }
+
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = self.client.with_http_client(client);
+        self
+    }
+}
+
+impl Default for OpenAIEmbeddings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Rough token count of an embedding input: about four characters per token.
+fn estimate_input_tokens(text: &str) -> u32 {
+    (text.len() / 4 + 1) as u32
+}
+
+impl Embedder for OpenAIEmbeddings {
+    fn embed<'a>(&'a self, texts: &'a [String]) -> BoxFuture<'a, LLMResult<Vec<Vec<f32>>>> {
+        async move {
+            if texts.is_empty() {
+                return Ok(Vec::new());
+            }
+            let request = CreateEmbeddingRequest {
+                model: self.model.clone(),
+                input: EmbeddingInput::StringArray(texts.to_vec()),
+                dimensions: self.dimensions,
+                ..Default::default()
+            };
+            let response = self.client.embeddings().create(request).await?;
+            if let Ok(mut usage) = self.usage.lock() {
+                usage.add(&TokenUsage::new(response.usage.prompt_tokens, 0));
+            }
+
+            let mut data = response.data;
+            data.sort_by_key(|embedding| embedding.index);
+            Ok(data.into_iter().map(|embedding| embedding.embedding).collect())
+        }
+        .boxed()
+    }
+
+    fn dimensions(&self) -> Option<usize> {
+        if let Some(dimensions) = self.dimensions {
+            return Some(dimensions as usize);
+        }
+        match self.model.as_str() {
+            "text-embedding-3-small" | "text-embedding-ada-002" => Some(1536),
+            "text-embedding-3-large" => Some(3072),
+            _ => None,
+        }
+    }
+
+    fn max_batch_size(&self) -> usize {
+        self.max_batch_size
+    }
+
+    fn embed_batched<'a>(&'a self, texts: &'a [String]) -> BoxFuture<'a, LLMResult<Vec<Vec<f32>>>> {
+        async move {
+            let mut out = Vec::with_capacity(texts.len());
+            let mut start = 0;
+            while start < texts.len() {
+                // grow the batch until either limit is hit; always take at least one
+                let mut end = start;
+                let mut tokens = 0;
+                while end < texts.len() && end - start < self.max_batch_size {
+                    let next = estimate_input_tokens(&texts[end]);
+                    if end > start && tokens + next > MAX_EMBEDDING_REQUEST_TOKENS {
+                        break;
+                    }
+                    tokens += next;
+                    end += 1;
+                }
+                let vectors = self.embed(&texts[start..end]).await?;
+                if vectors.len() != end - start {
+                    return Err(LLMError::InvalidResponse(format!(
+                        "expected {} embeddings, got {}",
+                        end - start,
+                        vectors.len()
+                    )));
+                }
+                out.extend(vectors);
+                start = end;
+            }
+            Ok(out)
+        }
+        .boxed()
+    }
+}
@@ -0,0 +1,369 @@
+//! The Responses API (`/responses`), OpenAI's newer endpoint. Some reasoning
+//! models are only served here, and it runs built-in tools such as web search
+//! on the provider side. Selected with `OpenAI::with_api(OpenAIApi::Responses)`.
+//!
+//! Results map onto the same `GenerateResult`/`StreamData` as chat
+//! completions: function calls become `tool_calls` keyed by their `call_id`,
+//! and reasoning summaries land in `GenerateResult::reasoning`. The API has no
+//! stop sequences, so `GenerateOptions::stop` is enforced by truncation.
+
+use async_openai::types::responses::{
+    CodeInterpreter,
+    CodeInterpreterContainer,
+    CodeInterpreterContainerKind,
+    Content,
+    CreateResponse,
+    CreateResponseArgs,
+    FileSearch,
+    Function,
+    ImageGeneration,
+    Input,
+    InputContent,
+    InputItem,
+    InputMessage,
+    InputMessageType,
+    OutputContent,
+    OutputItem,
+    ReasoningConfig,
+    Response,
+    ResponseEvent,
+    Role,
+    Status,
+    ToolDefinition,
+    Usage,
+    WebSearchPreview,
+};
+use async_openai::error::OpenAIError;
+use async_stream::stream as async_stream;
+use futures::{StreamExt, stream::BoxStream};
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+
+use crate::message::{Message, MessageRole as MsgRole};
+use crate::tools::{schema::ToolSchema, stream::{StreamData, ToolCallDelta}};
+use crate::llm::{
+    tokens::TokenUsage,
+    error::LLMError,
+    options::GenerateOptions,
+    CallInfo,
+    FinishReason,
+    GenerateResult,
+    LLMResult,
+};
+
+use super::{supports_strict_tools, OpenAI, DEFAULT_MODEL};
+
+/// A tool OpenAI runs itself during a Responses API call. Its results are
+/// folded into the answer; only function tools come back as `tool_calls`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BuiltinTool {
+    WebSearch,
+    /// Search the given vector stores.
+    FileSearch { vector_store_ids: Vec<String> },
+    /// Run Python in a sandbox created for the request.
+    CodeInterpreter,
+    ImageGeneration,
+}
+
+impl BuiltinTool {
+    fn definition(&self) -> ToolDefinition {
+        match self {
+            BuiltinTool::WebSearch => ToolDefinition::WebSearchPreview(WebSearchPreview::default()),
+            BuiltinTool::FileSearch { vector_store_ids } => ToolDefinition::FileSearch(FileSearch {
+                vector_store_ids: vector_store_ids.clone(),
+                ..Default::default()
+            }),
+            BuiltinTool::CodeInterpreter => ToolDefinition::CodeInterpreter(CodeInterpreter {
+                container: CodeInterpreterContainer::Container(CodeInterpreterContainerKind::Auto { file_ids: None }),
+            }),
+            BuiltinTool::ImageGeneration => ToolDefinition::ImageGeneration(ImageGeneration::default()),
+        }
+    }
+}
+
+impl OpenAI {
+    pub(super) fn responses_request(
+        &self,
+        messages: &[Message],
+        tools: &[ToolSchema],
+        overrides: &GenerateOptions,
+        stream: bool,
+    ) -> LLMResult<CreateResponse> {
+        let mut builder = CreateResponseArgs::default();
+        builder.input(Input::Items(to_input_items(messages)));
+        match self.options.as_ref() {
+            Some(options) => {
+                builder.model(options.model.clone());
+                if let Some(max_tokens) = options.max_tokens {
+                    builder.max_output_tokens(max_tokens);
+                }
+                if let Some(temperature) = options.temperature {
+                    builder.temperature(temperature);
+                }
+                if let Some(user) = options.user.as_ref() {
+                    builder.user(user.clone());
+                }
+            }
+            None => {
+                builder.model(DEFAULT_MODEL);
+            }
+        }
+        if let Some(max_tokens) = overrides.max_tokens {
+            builder.max_output_tokens(max_tokens);
+        }
+        if let Some(temperature) = overrides.temperature {
+            builder.temperature(temperature);
+        }
+        if let Some(top_p) = overrides.top_p {
+            builder.top_p(top_p);
+        }
+        if let Some(effort) = self.reasoning_effort.clone() {
+            builder.reasoning(ReasoningConfig { effort: Some(effort), summary: None });
+        }
+        let model_supports_strict = supports_strict_tools(self.options.as_ref().map_or(DEFAULT_MODEL, |options| options.model.as_str()));
+        let definitions: Vec<ToolDefinition> = tools
+            .iter()
+            .map(|tool| {
+                let strict_parameters = (tool.strict && model_supports_strict)
+                    .then(|| tool.strict_parameters())
+                    .flatten();
+                ToolDefinition::Function(Function {
+                    name: tool.name.clone(),
+                    strict: strict_parameters.is_some(),
+                    parameters: strict_parameters.unwrap_or_else(|| tool.parameters()),
+                    description: Some(tool.description.clone()),
+                })
+            })
+            .chain(self.builtin_tools.iter().map(BuiltinTool::definition))
+            .collect();
+        if !definitions.is_empty() {
+            builder.tools(definitions);
+        }
+        if stream {
+            builder.stream(true);
+        }
+        Ok(builder.build()?)
+    }
+
+    pub(super) async fn create_response(&self, request: CreateResponse, options: &GenerateOptions) -> LLMResult<GenerateResult> {
+        let response = self.client.responses().create(request).await?;
+        let mut result = to_generate_result(response)?;
+        options.apply_stop(&mut result);
+        Ok(result)
+    }
+
+    /// Stop sequences are enforced by the caller, see
+    /// `GenerateOptions::stop_stream`.
+    pub(super) fn stream_response<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        let this = self;
+        let s = async_stream! {
+            let request = match this.responses_request(messages, tools, options, true) {
+                Ok(request) => request,
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            };
+            let mut upstream = match this.client.responses().create_stream(request).await {
+                Ok(s) => s,
+                Err(e) => {
+                    yield Err(LLMError::from(e));
+                    return;
+                }
+            };
+
+            let mut called_tools = false;
+            while let Some(event) = upstream.next().await {
+                let event = match event {
+                    Ok(event) => event,
+                    // event types newer than async-openai; nothing we map
+                    Err(OpenAIError::JSONDeserialize(..)) => continue,
+                    Err(e) => {
+                        yield Err(LLMError::from(e));
+                        return;
+                    }
+                };
+                let value = serde_json::to_value(&event).unwrap_or_default();
+                match event {
+                    ResponseEvent::ResponseOutputTextDelta(delta) => {
+                        yield Ok(StreamData::new(value, None, delta.delta));
+                    }
+                    ResponseEvent::ResponseOutputItemAdded(added) => {
+                        if let OutputItem::FunctionCall(call) = added.item {
+                            called_tools = true;
+                            let delta = ToolCallDelta {
+                                index: added.output_index as usize,
+                                id: Some(call.call_id),
+                                name: Some(call.name),
+                                arguments: call.arguments,
+                            };
+                            yield Ok(StreamData::tool_calls(value, vec![delta]));
+                        }
+                    }
+                    ResponseEvent::ResponseFunctionCallArgumentsDelta(delta) => {
+                        let delta = ToolCallDelta {
+                            index: delta.output_index as usize,
+                            arguments: delta.delta,
+                            ..Default::default()
+                        };
+                        yield Ok(StreamData::tool_calls(value, vec![delta]));
+                    }
+                    ResponseEvent::ResponseCompleted(done) => {
+                        let tokens = done.response.usage.as_ref().map(to_token_usage);
+                        let finish_reason = if called_tools { FinishReason::ToolCalls } else { FinishReason::Stop };
+                        yield Ok(StreamData::done(tokens, Some(finish_reason)));
+                        return;
+                    }
+                    ResponseEvent::ResponseIncomplete(done) => {
+                        let tokens = done.response.usage.as_ref().map(to_token_usage);
+                        let reason = done.response.incomplete_details.map(|details| incomplete_reason(&details.reason));
+                        yield Ok(StreamData::done(tokens, reason));
+                        return;
+                    }
+                    ResponseEvent::ResponseFailed(failed) => {
+                        let message = failed
+                            .response
+                            .error
+                            .map(|error| error.message)
+                            .unwrap_or_else(|| "response failed".to_string());
+                        yield Err(LLMError::InvalidResponse(message));
+                        return;
+                    }
+                    _ => {}
+                }
+            }
+        };
+
+        Box::pin(s)
+    }
+}
+
+/// Map our messages onto input items. Native tool calls and their results
+/// become `function_call`/`function_call_output` items paired by `call_id`;
+/// user images become `input_image` parts.
+fn to_input_items(messages: &[Message]) -> Vec<InputItem> {
+    let mut items = Vec::with_capacity(messages.len());
+    for message in messages {
+        if let Some(id) = message.tool_call_id.as_ref() {
+            items.push(InputItem::Custom(json!({
+                "type": "function_call_output",
+                "call_id": id,
+                "output": message.content,
+            })));
+            continue;
+        }
+        if matches!(message.role, MsgRole::User) && !message.images.is_empty() {
+            let mut content = vec![json!({ "type": "input_text", "text": message.content })];
+            content.extend(message.images.iter().map(|image| {
+                json!({ "type": "input_image", "image_url": image.data_url(), "detail": "auto" })
+            }));
+            items.push(InputItem::Custom(json!({
+                "type": "message",
+                "role": "user",
+                "content": content,
+            })));
+            continue;
+        }
+        let role = match message.role {
+            MsgRole::System | MsgRole::Tool => Role::System,
+            MsgRole::Developer => Role::Developer,
+            MsgRole::User | MsgRole::ToolResponce => Role::User,
+            MsgRole::Assistant => Role::Assistant,
+        };
+        items.push(InputItem::Message(InputMessage {
+            kind: InputMessageType::Message,
+            role,
+            content: InputContent::TextInput(message.content.clone()),
+        }));
+        for call in message.tool_calls.iter() {
+            let Some(id) = call.id.as_ref() else {
+                continue;
+            };
+            let arguments = match &call.args {
+                Value::String(raw) => raw.clone(),
+                args => args.to_string(),
+            };
+            items.push(InputItem::Custom(json!({
+                "type": "function_call",
+                "call_id": id,
+                "name": call.name,
+                "arguments": arguments,
+            })));
+        }
+    }
+    items
+}
+
+fn to_generate_result(response: Response) -> LLMResult<GenerateResult> {
+    if let Some(error) = response.error {
+        return Err(LLMError::InvalidResponse(error.message));
+    }
+    let mut generation = String::new();
+    let mut refused = false;
+    let mut reasoning: Vec<String> = Vec::new();
+    let mut native_calls = Vec::new();
+    for item in response.output {
+        match item {
+            OutputContent::Message(message) => {
+                for content in message.content {
+                    match content {
+                        Content::OutputText(text) => generation.push_str(&text.text),
+                        Content::Refusal(refusal) => {
+                            refused = true;
+                            generation.push_str(&refusal.refusal);
+                        }
+                    }
+                }
+            }
+            OutputContent::FunctionCall(call) => {
+                let args = serde_json::from_str::<Value>(&call.arguments).unwrap_or(Value::String(call.arguments));
+                native_calls.push(CallInfo { id: Some(call.call_id), name: call.name, args });
+            }
+            OutputContent::Reasoning(item) => {
+                reasoning.extend(item.summary.into_iter().map(|summary| summary.text));
+            }
+            _ => {}
+        }
+    }
+
+    let finish_reason = match (&response.status, response.incomplete_details.as_ref()) {
+        (Status::Incomplete, Some(details)) => incomplete_reason(&details.reason),
+        _ if refused => FinishReason::ContentFilter,
+        _ if !native_calls.is_empty() => FinishReason::ToolCalls,
+        _ => FinishReason::Stop,
+    };
+    let tool_calls = if native_calls.is_empty() {
+        crate::llm::extract_tool_calls(&generation)
+    } else {
+        native_calls
+    };
+    Ok(GenerateResult {
+        tokens: response.usage.as_ref().map(to_token_usage).unwrap_or_default(),
+        generation,
+        tool_calls,
+        finish_reason: Some(finish_reason),
+        reasoning: (!reasoning.is_empty()).then(|| reasoning.join("\n")),
+        model: Some(response.model),
+        ..Default::default()
+    })
+}
+
+fn incomplete_reason(reason: &str) -> FinishReason {
+    match reason {
+        "max_output_tokens" => FinishReason::Length,
+        other => FinishReason::parse(other),
+    }
+}
+
+fn to_token_usage(usage: &Usage) -> TokenUsage {
+    TokenUsage {
+        cache_read_tokens: usage.input_tokens_details.cached_tokens.unwrap_or_default(),
+        ..TokenUsage::new(usage.input_tokens, usage.output_tokens)
+    }
+}
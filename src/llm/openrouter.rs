@@ -0,0 +1,164 @@
+// see https://openrouter.ai/docs/api-reference/overview
+use futures::{
+    FutureExt,
+    future::BoxFuture,
+    stream::BoxStream
+};
+use reqwest::header::HeaderValue;
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+use crate::message::Message;
+use crate::tools::{schema::ToolSchema, stream::StreamData};
+use crate::llm::{
+    traits::LLM,
+    batch,
+    compat::ChatCompletions,
+    options::GenerateOptions,
+    GenerateResult,
+    LLMResult,
+};
+
+pub const DEFAULT_BASE_URL: &str = "https://openrouter.ai/api/v1";
+
+/// Default model name used when no model is specified.
+pub const DEFAULT_MODEL: &str = "openrouter/auto";
+
+/// Provider routing preferences (the `provider` request object).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProviderPreferences {
+    /// Providers to try, in order.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_fallbacks: Option<bool>,
+    /// Only route to providers that support every request parameter.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub require_parameters: Option<bool>,
+    /// `"allow"` or `"deny"`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_collection: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ignore: Option<Vec<String>>,
+    /// `"price"`, `"throughput"` or `"latency"`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort: Option<String>,
+}
+
+/// OpenRouter client.
+///
+/// `GenerateResult::model` holds the upstream model that actually served the
+/// request, which matters with `openrouter/auto` or fallback `models`.
+#[derive(Debug, Clone)]
+pub struct OpenRouter {
+    pub(crate) inner: ChatCompletions,
+}
+
+impl OpenRouter {
+    /// Create an `OpenRouter` wrapper with the given API key and the default model.
+    pub fn new(api_key: impl Into<String>) -> Self {
+        let mut inner = ChatCompletions::new(DEFAULT_BASE_URL, DEFAULT_MODEL);
+        inner.api_key = Some(api_key.into());
+        Self { inner }
+    }
+
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.inner.model = model.into();
+        self
+    }
+
+    /// Fallback models tried in order if the primary one is unavailable.
+    pub fn with_fallback_models<I, S>(mut self, models: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let models = models.into_iter().map(|m| Value::String(m.into())).collect();
+        self.inner.extra.insert("models".to_string(), Value::Array(models));
+        self
+    }
+
+    pub fn with_provider_preferences(mut self, preferences: ProviderPreferences) -> Self {
+        let value = serde_json::to_value(preferences).unwrap_or_default();
+        self.inner.extra.insert("provider".to_string(), value);
+        self
+    }
+
+    /// Your site url, sent as `HTTP-Referer` for OpenRouter rankings.
+    pub fn with_referer(mut self, referer: &str) -> Self {
+        if let Ok(value) = HeaderValue::from_str(referer) {
+            self.inner.headers.insert("http-referer", value);
+        }
+        self
+    }
+
+    /// Your app name, sent as `X-Title`.
+    pub fn with_title(mut self, title: &str) -> Self {
+        if let Ok(value) = HeaderValue::from_str(title) {
+            self.inner.headers.insert("x-title", value);
+        }
+        self
+    }
+
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.inner.max_tokens = Some(max_tokens);
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.inner.temperature = Some(temperature);
+        self
+    }
+
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.inner.client = client;
+        self
+    }
+}
+
+impl Default for OpenRouter {
+    /// Read the API key from `OPENROUTER_API_KEY`.
+    fn default() -> Self {
+        OpenRouter::new(std::env::var("OPENROUTER_API_KEY").unwrap_or_default())
+    }
+}
+
+impl LLM for OpenRouter {
+    fn generate<'a>(&'a self, messages: &'a [Message]) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.inner.generate(messages).boxed()
+    }
+
+    fn generate_batch<'a>(&'a self, batches: &'a [Vec<Message>]) -> BoxFuture<'a, Vec<LLMResult<GenerateResult>>> {
+        batch::generate_concurrently(self, batches, batch::DEFAULT_CONCURRENCY).boxed()
+    }
+
+    fn generate_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        _tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.inner.generate_with(messages, options).boxed()
+    }
+
+    fn stream<'a>(&'a self, messages: &'a [Message]) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.inner.stream(messages)
+    }
+
+    fn stream_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        _tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.inner.stream_with_options(messages, &[], options)
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.inner.model)
+    }
+
+    fn provider(&self) -> Option<&str> {
+        Some("openrouter")
+    }
+}
@@ -0,0 +1,158 @@
+//! Per-call generation options.
+
+use async_stream::stream as async_stream;
+use futures::{StreamExt, stream::BoxStream};
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+use crate::tools::stream::StreamData;
+use crate::llm::{FinishReason, GenerateResult, LLMResult};
+
+/// Sampling settings for one call, overriding what the backend was built
+/// with. `None` (or an empty `stop`) keeps the backend's setting.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GenerateOptions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    /// Sequences that end the generation when produced, e.g. `"Observation:"`
+    /// for ReAct-style prompts. The sequence itself is not part of the output.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
+    /// Sample deterministically, for reproducible tests and evaluations.
+    /// Best effort: providers don't guarantee identical outputs, Ollama uses
+    /// the low 32 bits and backends without seeding (Anthropic) ignore it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+}
+
+impl GenerateOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// No overrides, for `stream_with_tools` implementations handing on to
+    /// `stream_with_options`, which borrows its options for the stream's
+    /// lifetime.
+    pub fn none() -> &'static Self {
+        static NONE: GenerateOptions = GenerateOptions {
+            temperature: None,
+            top_p: None,
+            max_tokens: None,
+            stop: Vec::new(),
+            seed: None,
+        };
+        &NONE
+    }
+
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    pub fn with_top_p(mut self, top_p: f32) -> Self {
+        self.top_p = Some(top_p);
+        self
+    }
+
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    pub fn with_seed(mut self, seed: i64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn with_stop<I, S>(mut self, stop: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.stop = stop.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Whether nothing is overridden.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The stop sequences, or `None` when there are none (for request fields
+    /// that must be omitted rather than empty).
+    pub fn stop_sequences(&self) -> Option<Vec<String>> {
+        (!self.stop.is_empty()).then(|| self.stop.clone())
+    }
+
+    /// Byte offset of the earliest stop sequence in `text`.
+    pub fn find_stop(&self, text: &str) -> Option<usize> {
+        self.stop
+            .iter()
+            .filter(|stop| !stop.is_empty())
+            .filter_map(|stop| text.find(stop.as_str()))
+            .min()
+    }
+
+    /// Cut `result` at the earliest stop sequence, for backends that can't
+    /// stop server side. Returns whether it was cut.
+    pub fn apply_stop(&self, result: &mut GenerateResult) -> bool {
+        let Some(end) = self.find_stop(&result.generation) else {
+            return false;
+        };
+        result.generation.truncate(end);
+        result.finish_reason = Some(FinishReason::Stop);
+        true
+    }
+
+    /// `apply_stop` for a stream: end `upstream` at the earliest stop
+    /// sequence, dropping it so the provider stops generating. Content that
+    /// may be the start of a stop sequence is held back until the next
+    /// chunk shows it isn't. Cut streams end without usage.
+    pub fn stop_stream<'a>(&self, upstream: BoxStream<'a, LLMResult<StreamData>>) -> BoxStream<'a, LLMResult<StreamData>> {
+        let Some(longest) = self.stop.iter().map(String::len).max().filter(|len| *len > 0) else {
+            return upstream;
+        };
+        let stops = GenerateOptions::new().with_stop(self.stop.iter().cloned());
+        let s = async_stream! {
+            let mut upstream = upstream;
+            let mut pending = String::new();
+            while let Some(item) = upstream.next().await {
+                let mut data = match item {
+                    Ok(data) => data,
+                    Err(e) => {
+                        yield Err(e);
+                        continue;
+                    }
+                };
+                pending.push_str(&data.content);
+                if let Some(end) = stops.find_stop(&pending) {
+                    pending.truncate(end);
+                    data.content = pending;
+                    data.done = false;
+                    data.finish_reason = None;
+                    let tokens = data.tokens.take();
+                    yield Ok(data);
+                    yield Ok(StreamData::done(tokens, Some(FinishReason::Stop)));
+                    return;
+                }
+                let mut keep = if data.done { pending.len() } else { pending.len().saturating_sub(longest - 1) };
+                while !pending.is_char_boundary(keep) {
+                    keep -= 1;
+                }
+                let held = pending.split_off(keep);
+                data.content = std::mem::replace(&mut pending, held);
+                yield Ok(data);
+            }
+            // cut off without a done item
+            if !pending.is_empty() {
+                yield Ok(StreamData::new(Value::Null, None, pending));
+            }
+        };
+
+        Box::pin(s)
+    }
+}
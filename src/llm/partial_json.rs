@@ -0,0 +1,237 @@
+//! Best-effort parsing of incomplete JSON as it streams in from a model.
+//!
+//! The parser closes any open strings/objects/arrays and drops trailing
+//! incomplete tokens, so a consumer can render a structure (e.g. a table
+//! gaining rows) while the generation is still running.
+
+use async_stream::stream as async_stream;
+use futures::{StreamExt, stream::BoxStream};
+use serde_json::Value;
+
+use crate::llm::LLMResult;
+use crate::tools::stream::StreamData;
+
+/// Incrementally accumulates streamed text and yields partial JSON values.
+#[derive(Debug, Default, Clone)]
+pub struct PartialJsonParser {
+    buffer: String,
+    last: Option<Value>,
+}
+
+impl PartialJsonParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a chunk. Returns the new best-effort value if it changed.
+    pub fn push(&mut self, chunk: &str) -> Option<Value> {
+        self.buffer.push_str(chunk);
+        let value = parse_partial(&self.buffer)?;
+        if self.last.as_ref() == Some(&value) {
+            return None;
+        }
+        self.last = Some(value.clone());
+        Some(value)
+    }
+
+    /// The most recent best-effort value.
+    pub fn current(&self) -> Option<&Value> {
+        self.last.as_ref()
+    }
+
+    /// Raw text accumulated so far.
+    pub fn buffer(&self) -> &str {
+        &self.buffer
+    }
+
+    /// Strictly parse the complete buffer once the stream has finished.
+    pub fn finish(&self) -> Result<Value, serde_json::Error> {
+        serde_json::from_str(json_start(&self.buffer))
+    }
+}
+
+/// Parse `text` as JSON, repairing it if it was cut off mid-way.
+///
+/// Leading prose or a code fence before the first `{`/`[` is ignored.
+/// Returns `None` if nothing usable has been emitted yet.
+pub fn parse_partial(text: &str) -> Option<Value> {
+    let mut candidate = json_start(text).trim_end();
+    if candidate.is_empty() {
+        return None;
+    }
+    loop {
+        if let Some(value) = try_close(candidate) {
+            return Some(value);
+        }
+        let cut = last_cut(candidate)?;
+        candidate = candidate[..cut].trim_end();
+    }
+}
+
+/// Adapt a text stream into a stream of best-effort JSON values, yielding
+/// each time the parsed structure grows.
+pub fn partial_json_stream<'a>(
+    mut stream: BoxStream<'a, LLMResult<StreamData>>,
+) -> BoxStream<'a, LLMResult<Value>> {
+    let s = async_stream! {
+        let mut parser = PartialJsonParser::new();
+        while let Some(item) = stream.next().await {
+            match item {
+                Ok(data) => {
+                    if let Some(value) = parser.push(&data.content) {
+                        yield Ok(value);
+                    }
+                }
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            }
+        }
+    };
+    Box::pin(s)
+}
+
+fn json_start(text: &str) -> &str {
+    match text.find(['{', '[']) {
+        Some(start) => &text[start..],
+        None => "",
+    }
+}
+
+/// Append whatever is needed to close `text` and try to parse it.
+fn try_close(text: &str) -> Option<Value> {
+    let mut stack: Vec<char> = Vec::new();
+    let mut in_string = false;
+    let mut escaped = false;
+    // start of a `\uXXXX` escape and how many hex digits it still needs
+    let mut unicode: Option<(usize, usize)> = None;
+    for (i, c) in text.char_indices() {
+        if in_string {
+            unicode = match unicode {
+                Some((start, left)) if left > 1 => Some((start, left - 1)),
+                _ => None,
+            };
+            match (escaped, c) {
+                (true, 'u') => {
+                    escaped = false;
+                    unicode = Some((i - 1, 4));
+                }
+                (true, _) => escaped = false,
+                (false, '\\') => escaped = true,
+                (false, '"') => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' => stack.push('}'),
+            '[' => stack.push(']'),
+            '}' | ']' => {
+                stack.pop();
+            }
+            _ => {}
+        }
+    }
+
+    let mut fixed = text.to_string();
+    if in_string {
+        if escaped {
+            fixed.pop();
+        }
+        if let Some((start, _)) = unicode {
+            fixed.truncate(start);
+        }
+        fixed.push('"');
+    }
+    let trimmed_len = fixed.trim_end().trim_end_matches(',').len();
+    fixed.truncate(trimmed_len);
+    while let Some(close) = stack.pop() {
+        fixed.push(close);
+    }
+    serde_json::from_str(&fixed).ok()
+}
+
+/// Last position `text` can be truncated at to drop an incomplete trailing
+/// member: just before a `,` or just after an opening bracket.
+fn last_cut(text: &str) -> Option<usize> {
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut cut = None;
+    for (i, c) in text.char_indices() {
+        if in_string {
+            match (escaped, c) {
+                (true, _) => escaped = false,
+                (false, '\\') => escaped = true,
+                (false, '"') => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            ',' => cut = Some(i),
+            '{' | '[' if i + 1 < text.len() => cut = Some(i + 1),
+            _ => {}
+        }
+    }
+    cut
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn closes_truncated_strings() {
+        assert_eq!(parse_partial(r#"{"city": "Par"#), Some(json!({ "city": "Par" })));
+        assert_eq!(parse_partial(r#"["a", "b"#), Some(json!(["a", "b"])));
+        assert_eq!(parse_partial(r#"{"city": "Paris", "country": "Fr"#), Some(json!({ "city": "Paris", "country": "Fr" })));
+    }
+
+    #[test]
+    fn drops_incomplete_members() {
+        assert_eq!(parse_partial(r#"{"a": 1, "b"#), Some(json!({ "a": 1 })));
+        assert_eq!(parse_partial(r#"{"a": 1, "b": tr"#), Some(json!({ "a": 1 })));
+        assert_eq!(parse_partial(r#"{"a": 1,"#), Some(json!({ "a": 1 })));
+        assert_eq!(parse_partial(r#"{"a"#), Some(json!({})));
+        assert_eq!(parse_partial("Sure, here it is"), None);
+    }
+
+    #[test]
+    fn closes_nested_objects_and_arrays() {
+        assert_eq!(parse_partial(r#"{"a": {"b": [1, 2"#), Some(json!({ "a": { "b": [1, 2] } })));
+        assert_eq!(
+            parse_partial(r#"[{"x": 1}, {"x": 2, "tags": ["new", "ho"#),
+            Some(json!([{ "x": 1 }, { "x": 2, "tags": ["new", "ho"] }]))
+        );
+        assert_eq!(parse_partial(r#"{"rows": [{"id": 1}, {"#), Some(json!({ "rows": [{ "id": 1 }, {}] })));
+    }
+
+    #[test]
+    fn handles_escapes() {
+        assert_eq!(parse_partial(r#"{"quote": "say \"hi"#), Some(json!({ "quote": "say \"hi" })));
+        assert_eq!(parse_partial(r#"{"path": "C:\"#), Some(json!({ "path": "C:" })));
+        assert_eq!(parse_partial(r#"{"path": "C:\\dir"#), Some(json!({ "path": "C:\\dir" })));
+        assert_eq!(parse_partial(r#"{"text": "caf\u00"#), Some(json!({ "text": "caf" })));
+        assert_eq!(parse_partial(r#"{"text": "caf\u00e9"#), Some(json!({ "text": "café" })));
+        assert_eq!(parse_partial(r#"{"brace": "}]", "n": [1"#), Some(json!({ "brace": "}]", "n": [1] })));
+    }
+
+    #[test]
+    fn skips_leading_prose_and_fences() {
+        assert_eq!(parse_partial("Here you go:\n```json\n{\"a\": [1"), Some(json!({ "a": [1] })));
+    }
+
+    #[test]
+    fn parser_yields_only_changes() {
+        let mut parser = PartialJsonParser::new();
+        assert_eq!(parser.push(r#"{"a": "#), Some(json!({})));
+        assert_eq!(parser.push(" "), None);
+        assert_eq!(parser.push(r#""x"#), Some(json!({ "a": "x" })));
+        assert_eq!(parser.push(r#"y"}"#), Some(json!({ "a": "xy" })));
+        assert_eq!(parser.finish().expect("complete"), json!({ "a": "xy" }));
+    }
+}
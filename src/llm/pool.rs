@@ -0,0 +1,208 @@
+//! Connection pool and keep-alive settings for the HTTP clients behind the
+//! provider backends.
+//!
+//! Agents that issue many short LLM calls spend much of their tail latency on
+//! new TCP/TLS handshakes. Build a tuned client once and hand it to a backend:
+//!
+//! ```ignore
+//! use std::time::Duration;
+//! use mini_langchain::llm::{pool::PoolConfig, deepseek::DeepSeek};
+//!
+//! let client = PoolConfig::new()
+//!     .with_max_idle_per_host(32)
+//!     .with_idle_timeout(Duration::from_secs(90))
+//!     .with_http2_keep_alive(Duration::from_secs(30))
+//!     .build_client()?;
+//! let llm = DeepSeek::default().with_http_client(client);
+//! ```
+//!
+//! Every HTTP backend but Ollama, and `OpenAIEmbeddings`, has such a
+//! `with_http_client` builder. It only swaps the client: the backend's base
+//! URL, API key and other settings stay as they are. Ollama takes the client
+//! through `OllamaClient::new_with_client` instead.
+//!
+//! Backends created without `with_http_client` share one process-wide
+//! client, so they draw from the same connection pool instead of each
+//! opening their own. `PoolConfig::install` replaces that client for
+//! backends created afterwards:
+//!
+//! ```ignore
+//! PoolConfig::new()
+//!     .with_max_idle_per_host(64)
+//!     .with_timeout(Duration::from_secs(120))
+//!     .install()?;
+//! let chat = Anthropic::new(api_key);
+//! let fallback = OpenAI::new();
+//! ```
+
+use std::sync::RwLock;
+use std::time::Duration;
+use reqwest::{tls, Certificate};
+
+use crate::llm::LLMResult;
+
+/// Settings applied to a `reqwest::Client`. Unset fields keep reqwest's defaults.
+#[derive(Debug, Clone, Default)]
+pub struct PoolConfig {
+    /// Idle connections kept per host.
+    pub max_idle_per_host: Option<usize>,
+    /// How long an idle connection is kept before it is closed.
+    pub idle_timeout: Option<Duration>,
+    /// TCP keep-alive probe interval.
+    pub tcp_keepalive: Option<Duration>,
+    pub connect_timeout: Option<Duration>,
+    /// Limit on a whole request, including reading the body. A stream that
+    /// runs longer is cut off, so leave room for long generations.
+    pub timeout: Option<Duration>,
+    /// Refuse TLS versions older than this.
+    pub min_tls_version: Option<tls::Version>,
+    /// Extra trusted roots, e.g. a corporate proxy's CA.
+    pub root_certificates: Vec<Certificate>,
+    /// Skip HTTP/1.1 and ALPN and speak HTTP/2 directly (plain-text local servers).
+    pub http2_prior_knowledge: bool,
+    /// Interval of HTTP/2 PING frames that keep connections warm.
+    pub http2_keep_alive_interval: Option<Duration>,
+    /// How long to wait for a PING acknowledgement before closing.
+    pub http2_keep_alive_timeout: Option<Duration>,
+    /// Send PINGs even when no request is in flight.
+    pub http2_keep_alive_while_idle: bool,
+    pub http2_adaptive_window: bool,
+}
+
+impl PoolConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_idle_per_host(mut self, max_idle: usize) -> Self {
+        self.max_idle_per_host = Some(max_idle);
+        self
+    }
+
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_tcp_keepalive(mut self, interval: Duration) -> Self {
+        self.tcp_keepalive = Some(interval);
+        self
+    }
+
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_min_tls_version(mut self, version: tls::Version) -> Self {
+        self.min_tls_version = Some(version);
+        self
+    }
+
+    pub fn with_root_certificate(mut self, certificate: Certificate) -> Self {
+        self.root_certificates.push(certificate);
+        self
+    }
+
+    pub fn with_http2_prior_knowledge(mut self, enabled: bool) -> Self {
+        self.http2_prior_knowledge = enabled;
+        self
+    }
+
+    /// Ping idle HTTP/2 connections every `interval` so they are not dropped
+    /// between calls.
+    pub fn with_http2_keep_alive(mut self, interval: Duration) -> Self {
+        self.http2_keep_alive_interval = Some(interval);
+        self.http2_keep_alive_while_idle = true;
+        self
+    }
+
+    pub fn with_http2_keep_alive_timeout(mut self, timeout: Duration) -> Self {
+        self.http2_keep_alive_timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_http2_adaptive_window(mut self, enabled: bool) -> Self {
+        self.http2_adaptive_window = enabled;
+        self
+    }
+
+    /// Apply the settings to `builder`.
+    pub fn apply(&self, mut builder: reqwest::ClientBuilder) -> reqwest::ClientBuilder {
+        if let Some(max_idle) = self.max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(max_idle);
+        }
+        if let Some(timeout) = self.idle_timeout {
+            builder = builder.pool_idle_timeout(timeout);
+        }
+        if let Some(interval) = self.tcp_keepalive {
+            builder = builder.tcp_keepalive(interval);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(version) = self.min_tls_version {
+            builder = builder.min_tls_version(version);
+        }
+        for certificate in self.root_certificates.iter() {
+            builder = builder.add_root_certificate(certificate.clone());
+        }
+        if self.http2_prior_knowledge {
+            builder = builder.http2_prior_knowledge();
+        }
+        if let Some(interval) = self.http2_keep_alive_interval {
+            builder = builder.http2_keep_alive_interval(interval);
+        }
+        if let Some(timeout) = self.http2_keep_alive_timeout {
+            builder = builder.http2_keep_alive_timeout(timeout);
+        }
+        if self.http2_keep_alive_while_idle {
+            builder = builder.http2_keep_alive_while_idle(true);
+        }
+        if self.http2_adaptive_window {
+            builder = builder.http2_adaptive_window(true);
+        }
+        builder
+    }
+
+    /// Build a client with these settings.
+    pub fn build_client(&self) -> LLMResult<reqwest::Client> {
+        Ok(self.apply(reqwest::Client::builder()).build()?)
+    }
+
+    /// Build a client with these settings and make it the shared client.
+    pub fn install(&self) -> LLMResult<()> {
+        set_shared_client(self.build_client()?);
+        Ok(())
+    }
+}
+
+static SHARED_CLIENT: RwLock<Option<reqwest::Client>> = RwLock::new(None);
+
+/// The client backends use unless given one with `with_http_client`.
+/// Clones share its connection pool.
+pub fn shared_client() -> reqwest::Client {
+    if let Some(client) = SHARED_CLIENT.read().ok().and_then(|shared| shared.clone()) {
+        return client;
+    }
+    let Ok(mut shared) = SHARED_CLIENT.write() else {
+        return reqwest::Client::new();
+    };
+    shared.get_or_insert_with(reqwest::Client::new).clone()
+}
+
+/// Use `client` for the backends created from now on. Existing backends keep
+/// the client they were built with.
+pub fn set_shared_client(client: reqwest::Client) {
+    if let Ok(mut shared) = SHARED_CLIENT.write() {
+        *shared = Some(client);
+    }
+}
@@ -0,0 +1,404 @@
+//! Monthly spend budgets that survive restarts.
+//!
+//! A [`QuotaGuard`] keeps the dollars spent per account (a provider, or one
+//! API key of it) and calendar month (UTC) in a JSON file. `QuotaLLM` refuses
+//! a request with `LLMError::QuotaExhausted` once the month's budget of its
+//! account is used up, and adds the cost of every call it lets through:
+//!
+//! ```ignore
+//! let path = std::env::home_dir().unwrap_or_default().join(".config/my-agent/spend.json");
+//! let guard = Arc::new(QuotaGuard::open(path)?.with_budget("openai", 5.0));
+//! let llm = QuotaLLM::new(OpenAI::new(), guard.clone());
+//! println!("${:.2} left this month", guard.remaining("openai")?.unwrap_or_default());
+//! ```
+//!
+//! Costs come from a `PricingTable`. A budgeted account refuses calls of a
+//! model the table has no price for with `LLMError::Unpriced`, rather than
+//! letting them through unbilled; register a price for every model you
+//! budget. Several
+//! processes may share the file: every check and update re-reads it while
+//! holding an OS lock on `<file>.lock`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use async_stream::stream as async_stream;
+use futures::{
+    FutureExt,
+    StreamExt,
+    future::BoxFuture,
+    stream::BoxStream
+};
+
+use crate::message::Message;
+use crate::prompt::datetime::civil_from_days;
+use crate::tools::{schema::ToolSchema, stream::StreamData};
+use crate::llm::{
+    traits::LLM,
+    capabilities::Capabilities,
+    cost::{Pricing, PricingTable},
+    error::LLMError,
+    throttle::estimate_prompt_tokens,
+    tokens::TokenUsage,
+    options::GenerateOptions,
+    GenerateResult,
+    LLMResult,
+};
+
+/// Dollars spent, by account and then by month (`YYYY-MM`).
+type Ledger = BTreeMap<String, BTreeMap<String, f64>>;
+
+/// Persistent per-account monthly budgets. Share one guard between the
+/// `QuotaLLM`s that bill the same accounts.
+#[derive(Debug)]
+pub struct QuotaGuard {
+    path: PathBuf,
+    budgets: HashMap<String, f64>,
+    /// Serializes read-modify-write of the file within this process; the
+    /// lock file does across processes.
+    lock: Mutex<()>,
+}
+
+impl QuotaGuard {
+    /// Keep spend in the file at `path`, created on the first recorded call.
+    /// Fails if the file exists but can't be read or parsed, rather than
+    /// starting over from zero.
+    pub fn open(path: impl Into<PathBuf>) -> LLMResult<Self> {
+        let guard = Self {
+            path: path.into(),
+            budgets: HashMap::new(),
+            lock: Mutex::new(()),
+        };
+        guard.load()?;
+        Ok(guard)
+    }
+
+    /// Allow `account` at most `dollars` per calendar month. Accounts
+    /// without a budget are tracked but never refused.
+    pub fn with_budget(mut self, account: impl Into<String>, dollars: f64) -> Self {
+        self.budgets.insert(account.into(), dollars);
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn budget(&self, account: &str) -> Option<f64> {
+        self.budgets.get(account).copied()
+    }
+
+    /// Dollars `account` has spent this month.
+    pub fn spent(&self, account: &str) -> LLMResult<f64> {
+        let _lock = self.lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let lock_file = self.lock_file()?;
+        lock_file.lock_shared()?;
+        let ledger = self.load()?;
+        Ok(spent_in(&ledger, account, &current_month()))
+    }
+
+    /// Dollars left in this month's budget of `account`, `None` without a
+    /// budget.
+    pub fn remaining(&self, account: &str) -> LLMResult<Option<f64>> {
+        let Some(budget) = self.budget(account) else {
+            return Ok(None);
+        };
+        Ok(Some((budget - self.spent(account)?).max(0.0)))
+    }
+
+    /// Fail with `LLMError::QuotaExhausted` if spending `estimate` more would
+    /// exceed this month's budget of `account`.
+    pub fn check(&self, account: &str, estimate: f64) -> LLMResult<()> {
+        let Some(budget) = self.budget(account) else {
+            return Ok(());
+        };
+        let spent = self.spent(account)?;
+        if spent + estimate > budget || spent >= budget {
+            return Err(LLMError::QuotaExhausted {
+                account: account.to_string(),
+                month: current_month(),
+                budget,
+                spent,
+            });
+        }
+        Ok(())
+    }
+
+    /// Add `dollars` to this month's spend of `account` and save the file.
+    pub fn record(&self, account: &str, dollars: f64) -> LLMResult<()> {
+        if dollars <= 0.0 {
+            return Ok(());
+        }
+        let _lock = self.lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let lock_file = self.lock_file()?;
+        lock_file.lock()?;
+        let mut ledger = self.load()?;
+        *ledger
+            .entry(account.to_string())
+            .or_default()
+            .entry(current_month())
+            .or_default() += dollars;
+        self.save(&ledger)
+    }
+
+    /// The file other processes lock on; the ledger itself is replaced on
+    /// every save, so it can't hold the lock. Unlocked when dropped.
+    fn lock_file(&self) -> LLMResult<File> {
+        if let Some(parent) = self.path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut path = self.path.clone().into_os_string();
+        path.push(".lock");
+        Ok(OpenOptions::new().create(true).truncate(false).write(true).open(path)?)
+    }
+
+    fn load(&self) -> LLMResult<Ledger> {
+        match std::fs::read(&self.path) {
+            Ok(raw) => Ok(serde_json::from_slice(&raw)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Ledger::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Write to a temporary file and rename it, so a crash can't leave a
+    /// truncated ledger behind. The temporary name is unique to the process
+    /// and the save.
+    fn save(&self, ledger: &Ledger) -> LLMResult<()> {
+        static SAVES: AtomicU64 = AtomicU64::new(0);
+        let mut temp = self.path.clone().into_os_string();
+        temp.push(format!(".{}.{}.tmp", std::process::id(), SAVES.fetch_add(1, Ordering::Relaxed)));
+        std::fs::write(&temp, serde_json::to_vec_pretty(ledger)?)?;
+        if let Err(e) = std::fs::rename(&temp, &self.path) {
+            let _ = std::fs::remove_file(&temp);
+            return Err(e.into());
+        }
+        Ok(())
+    }
+}
+
+fn spent_in(ledger: &Ledger, account: &str, month: &str) -> f64 {
+    ledger
+        .get(account)
+        .and_then(|months| months.get(month))
+        .copied()
+        .unwrap_or_default()
+}
+
+/// The current UTC month as `YYYY-MM`.
+fn current_month() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or_default();
+    let (year, month, _) = civil_from_days(seconds.div_euclid(86_400));
+    format!("{:04}-{:02}", year, month)
+}
+
+/// Bill the calls of the wrapped LLM to an account of a `QuotaGuard`.
+///
+/// Each request is refused when the account's budget is used up, or would
+/// be by the estimated cost of the prompt alone, and when the account has a
+/// budget but the model has no price. A request already running
+/// is not cut off; its cost is added when it completes, so the last request
+/// of a month can overshoot the budget by its own cost.
+pub struct QuotaLLM<L> {
+    pub inner: L,
+    guard: Arc<QuotaGuard>,
+    account: String,
+    pricing: PricingTable,
+}
+
+impl<L: LLM> QuotaLLM<L> {
+    /// Bill to the account named after the wrapped LLM's provider, priced
+    /// with `PricingTable::builtin`.
+    pub fn new(inner: L, guard: Arc<QuotaGuard>) -> Self {
+        let account = inner.provider().unwrap_or("default").to_string();
+        Self {
+            inner,
+            guard,
+            account,
+            pricing: PricingTable::builtin(),
+        }
+    }
+
+    /// Bill to `account`, e.g. `"openai:personal"` to budget one API key.
+    pub fn with_account(mut self, account: impl Into<String>) -> Self {
+        self.account = account.into();
+        self
+    }
+
+    pub fn with_pricing(mut self, pricing: PricingTable) -> Self {
+        self.pricing = pricing;
+        self
+    }
+
+    pub fn account(&self) -> &str {
+        &self.account
+    }
+
+    fn price(&self) -> Option<Pricing> {
+        self.pricing
+            .lookup(self.inner.provider(), self.inner.model_name().unwrap_or_default())
+    }
+
+    async fn check(&self, messages: &[Message]) -> LLMResult<()> {
+        let price = self.price();
+        if price.is_none() && self.guard.budget(&self.account).is_some() {
+            return Err(LLMError::Unpriced {
+                account: self.account.clone(),
+                model: self.inner.model_name().unwrap_or_default().to_string(),
+            });
+        }
+        let estimate = price
+            .map(|pricing| pricing.cost(&TokenUsage::new(estimate_prompt_tokens(messages), 0)))
+            .unwrap_or_default();
+        self.with_guard(move |guard, account| guard.check(account, estimate)).await
+    }
+
+    async fn bill(&self, usage: &TokenUsage) -> LLMResult<()> {
+        match self.price() {
+            Some(pricing) => {
+                let dollars = pricing.cost(usage);
+                self.with_guard(move |guard, account| guard.record(account, dollars)).await
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Run `f` on a blocking thread: the guard reads and writes its file
+    /// synchronously, which would stall the async executor.
+    async fn with_guard<F>(&self, f: F) -> LLMResult<()>
+    where
+        F: FnOnce(&QuotaGuard, &str) -> LLMResult<()> + Send + 'static,
+    {
+        let guard = self.guard.clone();
+        let account = self.account.clone();
+        tokio::task::spawn_blocking(move || f(&guard, &account))
+            .await
+            .map_err(std::io::Error::other)?
+    }
+
+    async fn call(&self, messages: &[Message], call: BoxFuture<'_, LLMResult<GenerateResult>>) -> LLMResult<GenerateResult> {
+        self.check(messages).await?;
+        let result = call.await?;
+        if !result.cached {
+            self.bill(&result.tokens).await?;
+        }
+        Ok(result)
+    }
+}
+
+impl<L: LLM> LLM for QuotaLLM<L> {
+    fn generate<'a>(&'a self, messages: &'a [Message]) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.call(messages, self.inner.generate(messages)).boxed()
+    }
+
+    fn generate_with_tools<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.call(messages, self.inner.generate_with_tools(messages, tools)).boxed()
+    }
+
+    fn generate_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.call(messages, self.inner.generate_with_options(messages, tools, options)).boxed()
+    }
+
+    fn generate_n<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+        n: usize,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.call(messages, self.inner.generate_n(messages, tools, options, n)).boxed()
+    }
+
+    fn stream<'a>(&'a self, messages: &'a [Message]) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.stream_with_tools(messages, &[])
+    }
+
+    fn stream_with_tools<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.stream_with_options(messages, tools, GenerateOptions::none())
+    }
+
+    /// Usage is the provider's when a chunk reports it, otherwise estimated
+    /// from the prompt and the text received.
+    fn stream_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        let this = self;
+        let s = async_stream! {
+            if let Err(e) = this.check(messages).await {
+                yield Err(e);
+                return;
+            }
+            let prompt_tokens = estimate_prompt_tokens(messages);
+            let mut received_chars = 0usize;
+            let mut reported: Option<TokenUsage> = None;
+            let mut billed = false;
+            let mut upstream = this.inner.stream_with_options(messages, tools, options);
+            while let Some(item) = upstream.next().await {
+                if let Ok(data) = item.as_ref() {
+                    received_chars += data.content.len();
+                    if let Some(tokens) = data.tokens.as_ref() {
+                        reported = Some(tokens.clone());
+                    }
+                    // consumers may stop reading at the done item
+                    if data.done {
+                        billed = true;
+                        let usage = reported
+                            .clone()
+                            .unwrap_or_else(|| TokenUsage::new(prompt_tokens, (received_chars / 4) as u32));
+                        if let Err(e) = this.bill(&usage).await {
+                            yield Err(e);
+                            return;
+                        }
+                    }
+                }
+                yield item;
+            }
+            if !billed {
+                let usage = reported
+                    .unwrap_or_else(|| TokenUsage::new(prompt_tokens, (received_chars / 4) as u32));
+                if let Err(e) = this.bill(&usage).await {
+                    yield Err(e);
+                }
+            }
+        };
+
+        Box::pin(s)
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        self.inner.model_name()
+    }
+
+    fn provider(&self) -> Option<&str> {
+        self.inner.provider()
+    }
+
+    fn health_check(&self) -> BoxFuture<'_, LLMResult<()>> {
+        self.inner.health_check()
+    }
+}
@@ -0,0 +1,322 @@
+// see https://help.aliyun.com/zh/model-studio/
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use async_stream::stream as async_stream;
+use futures::{
+    FutureExt,
+    StreamExt,
+    future::BoxFuture,
+    stream::BoxStream
+};
+
+use crate::message::Message;
+use crate::tools::{schema::ToolSchema, stream::StreamData};
+use crate::llm::{
+    traits::LLM,
+    batch,
+    tokens::TokenUsage,
+    error::LLMError,
+    FinishReason,
+    compat::{to_chat_messages, ChatCompletions, ChatMessage},
+    http::{check_status, sse_events},
+    options::GenerateOptions,
+    reasoning,
+    GenerateResult,
+    LLMResult,
+};
+
+/// Default model name used when no model is specified.
+pub const DEFAULT_MODEL: &str = "qwen-plus";
+
+/// OpenAI compatible endpoint.
+pub const COMPATIBLE_BASE_URL: &str = "https://dashscope.aliyuncs.com/compatible-mode/v1";
+
+/// DashScope native text-generation endpoint.
+pub const NATIVE_URL: &str = "https://dashscope.aliyuncs.com/api/v1/services/aigc/text-generation/generation";
+
+/// Which DashScope API to talk to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QwenEndpoint {
+    /// `/compatible-mode/v1/chat/completions`
+    #[default]
+    Compatible,
+    /// `/api/v1/services/aigc/text-generation/generation`
+    Native,
+}
+
+#[derive(Debug, Clone)]
+pub struct Qwen {
+    pub(crate) inner: ChatCompletions,
+    pub(crate) endpoint: QwenEndpoint,
+    pub(crate) native_url: String,
+}
+
+impl Qwen {
+    /// Create a `Qwen` wrapper using the compatible-mode endpoint and the default model.
+    pub fn new(api_key: impl Into<String>) -> Self {
+        let mut inner = ChatCompletions::new(COMPATIBLE_BASE_URL, DEFAULT_MODEL);
+        inner.api_key = Some(api_key.into());
+        Self {
+            inner,
+            endpoint: QwenEndpoint::Compatible,
+            native_url: NATIVE_URL.to_string(),
+        }
+    }
+
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.inner.model = model.into();
+        self
+    }
+
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.inner.max_tokens = Some(max_tokens);
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.inner.temperature = Some(temperature);
+        self
+    }
+
+    /// Select the compatible-mode or native endpoint.
+    pub fn with_endpoint(mut self, endpoint: QwenEndpoint) -> Self {
+        self.endpoint = endpoint;
+        self
+    }
+
+    /// Override the compatible-mode base url (e.g. the international region).
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.inner.base_url = base_url.into();
+        self
+    }
+
+    /// Override the native endpoint url.
+    pub fn with_native_url(mut self, url: impl Into<String>) -> Self {
+        self.native_url = url.into();
+        self
+    }
+
+    fn native_request(&self, messages: &[Message], stream: bool, options: &GenerateOptions) -> NativeRequest {
+        NativeRequest {
+            model: self.inner.model.clone(),
+            input: NativeInput {
+                messages: to_chat_messages(messages),
+            },
+            parameters: NativeParameters {
+                result_format: "message",
+                incremental_output: stream,
+                max_tokens: options.max_tokens.or(self.inner.max_tokens),
+                temperature: options.temperature.or(self.inner.temperature),
+                top_p: options.top_p,
+                stop: options.stop_sequences(),
+                seed: options.seed,
+            },
+        }
+    }
+
+    async fn generate_native(&self, messages: &[Message], options: &GenerateOptions) -> LLMResult<GenerateResult> {
+        match self.endpoint {
+            QwenEndpoint::Compatible => self.inner.generate_with(messages, options).await,
+            QwenEndpoint::Native => {
+                let request = self.native_request(messages, false, options);
+                let value: Value = self.send_native(&request, false).await?.json().await?;
+                if value.get("output").is_none() {
+                    return Err(LLMError::InvalidResponse(value.to_string()));
+                }
+                let (generation, reasoning) = reasoning::separate(native_content(&value), native_reasoning(&value));
+                let tokens = native_usage(&value).unwrap_or_default();
+                let tool_calls = crate::llm::extract_tool_calls(&generation);
+                let finish_reason = value
+                    .pointer("/output/choices/0/finish_reason")
+                    .and_then(|r| r.as_str())
+                    .map(FinishReason::parse);
+                Ok(GenerateResult { tokens, generation, tool_calls, finish_reason, reasoning, ..Default::default() })
+            }
+        }
+    }
+
+    async fn send_native(&self, request: &NativeRequest, stream: bool) -> LLMResult<reqwest::Response> {
+        let mut builder = self.inner.client.post(&self.native_url).json(request);
+        if let Some(api_key) = self.inner.api_key.as_ref() {
+            builder = builder.bearer_auth(api_key);
+        }
+        if stream {
+            builder = builder.header("X-DashScope-SSE", "enable");
+        }
+        check_status(builder.send().await?).await
+    }
+
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.inner.client = client;
+        self
+    }
+}
+
+impl Default for Qwen {
+    /// Read the API key from `DASHSCOPE_API_KEY`.
+    fn default() -> Self {
+        Qwen::new(std::env::var("DASHSCOPE_API_KEY").unwrap_or_default())
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct NativeInput {
+    messages: Vec<ChatMessage>,
+}
+
+#[derive(Debug, Serialize)]
+struct NativeParameters {
+    result_format: &'static str,
+    incremental_output: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+struct NativeRequest {
+    model: String,
+    input: NativeInput,
+    parameters: NativeParameters,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct NativeUsage {
+    #[serde(default)]
+    input_tokens: u32,
+    #[serde(default)]
+    output_tokens: u32,
+}
+
+impl From<&NativeUsage> for TokenUsage {
+    fn from(usage: &NativeUsage) -> Self {
+        TokenUsage::new(usage.input_tokens, usage.output_tokens)
+    }
+}
+
+/// Pull `output.choices[0].message.content` out of a native response body.
+fn native_content(value: &Value) -> String {
+    value
+        .pointer("/output/choices/0/message/content")
+        .and_then(|c| c.as_str())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// qwen3 thinking output, sent when `enable_thinking` is on.
+fn native_reasoning(value: &Value) -> Option<String> {
+    value
+        .pointer("/output/choices/0/message/reasoning_content")
+        .and_then(|c| c.as_str())
+        .map(str::to_string)
+}
+
+fn native_usage(value: &Value) -> Option<TokenUsage> {
+    value
+        .get("usage")
+        .and_then(|u| serde_json::from_value::<NativeUsage>(u.clone()).ok())
+        .map(|u| TokenUsage::from(&u))
+}
+
+impl LLM for Qwen {
+    fn generate<'a>(&'a self, messages: &'a [Message]) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        async move { self.generate_native(messages, &GenerateOptions::default()).await }.boxed()
+    }
+
+    fn generate_batch<'a>(&'a self, batches: &'a [Vec<Message>]) -> BoxFuture<'a, Vec<LLMResult<GenerateResult>>> {
+        batch::generate_concurrently(self, batches, batch::DEFAULT_CONCURRENCY).boxed()
+    }
+
+    fn generate_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        _tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.generate_native(messages, options).boxed()
+    }
+
+    fn stream<'a>(&'a self, messages: &'a [Message]) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.stream_with_options(messages, &[], GenerateOptions::none())
+    }
+
+    fn stream_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        _tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        if self.endpoint == QwenEndpoint::Compatible {
+            return self.inner.stream_with_options(messages, &[], options);
+        }
+
+        let this = self;
+        let msgs = messages;
+        let s = async_stream! {
+            let request = this.native_request(msgs, true, options);
+            let response = match this.send_native(&request, true).await {
+                Ok(response) => response,
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            };
+            let mut events = sse_events(response);
+            let mut usage = None;
+            let mut finish_reason = None;
+            while let Some(event_res) = events.next().await {
+                let event = match event_res {
+                    Ok(event) => event,
+                    Err(e) => {
+                        yield Err(e);
+                        return;
+                    }
+                };
+                let value: Value = match serde_json::from_str(&event.data) {
+                    Ok(value) => value,
+                    Err(e) => {
+                        yield Err(LLMError::from(e));
+                        continue;
+                    }
+                };
+                let content = native_content(&value);
+                let thinking = native_reasoning(&value).unwrap_or_default();
+                let tokens = native_usage(&value);
+                // intermediate chunks carry the string "null"
+                if let Some(reason) = value
+                    .pointer("/output/choices/0/finish_reason")
+                    .and_then(|r| r.as_str())
+                    .filter(|r| *r != "null")
+                {
+                    finish_reason = Some(FinishReason::parse(reason));
+                }
+                if tokens.is_some() {
+                    usage = tokens.clone();
+                }
+                let mut data = StreamData::new(value, tokens, content);
+                data.reasoning = thinking;
+                yield Ok(data);
+            }
+            if finish_reason.is_some() {
+                yield Ok(StreamData::done(usage, finish_reason));
+            }
+        };
+
+        Box::pin(s)
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.inner.model)
+    }
+
+    fn provider(&self) -> Option<&str> {
+        Some("qwen")
+    }
+}
@@ -0,0 +1,115 @@
+//! Separate a model's thinking from its answer.
+//!
+//! Reasoning models served through plain chat APIs (qwen3 or deepseek-r1 on
+//! Ollama, vLLM or llama.cpp) put their chain-of-thought in the content,
+//! between `<think>` and `</think>`. Backends move it to
+//! `GenerateResult::reasoning` (and `StreamData::reasoning` when streaming)
+//! before looking for tool calls, so JSON the model drafted while thinking is
+//! never run and `generation` holds only the answer.
+
+const OPEN_TAG: &str = "<think>";
+const CLOSE_TAG: &str = "</think>";
+
+/// Split `text` into the answer and the content of its think blocks.
+///
+/// Text before a `</think>` without an opening tag is reasoning too: some
+/// chat templates (deepseek-r1) open the block in the prompt. An unclosed
+/// `<think>` means the generation stopped while thinking.
+pub fn split_reasoning(text: &str) -> (String, Option<String>) {
+    if !text.contains(OPEN_TAG) && !text.contains(CLOSE_TAG) {
+        return (text.to_string(), None);
+    }
+    let mut splitter = ReasoningSplitter::new();
+    if let Some(close) = text.find(CLOSE_TAG)
+        && !text[..close].contains(OPEN_TAG)
+    {
+        splitter.thinking = true;
+    }
+    let (mut answer, mut reasoning) = splitter.push(text);
+    let (rest, rest_reasoning) = splitter.finish();
+    answer.push_str(&rest);
+    reasoning.push_str(&rest_reasoning);
+    let reasoning = reasoning.trim();
+    (answer.trim().to_string(), (!reasoning.is_empty()).then(|| reasoning.to_string()))
+}
+
+/// Strip think blocks from `generation` and combine them with the reasoning
+/// the provider returned in a dedicated field.
+pub(crate) fn separate(generation: String, provided: Option<String>) -> (String, Option<String>) {
+    let provided = provided.filter(|r| !r.is_empty());
+    let (generation, inline) = split_reasoning(&generation);
+    let reasoning = match (provided, inline) {
+        (Some(provided), Some(inline)) => Some(format!("{}\n{}", provided, inline)),
+        (provided, inline) => provided.or(inline),
+    };
+    (generation, reasoning)
+}
+
+/// Incremental `split_reasoning` for streamed content.
+///
+/// Tags may be split across chunks, so text that could be the start of a tag
+/// is held back until the next chunk (or `finish`) decides it.
+#[derive(Debug, Clone, Default)]
+pub struct ReasoningSplitter {
+    thinking: bool,
+    /// Drop whitespace at the start of the answer after a think block.
+    trim_answer: bool,
+    pending: String,
+}
+
+impl ReasoningSplitter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the text so far ends inside a think block.
+    pub fn is_thinking(&self) -> bool {
+        self.thinking
+    }
+
+    /// Feed the next chunk; returns the (answer, reasoning) text it completes.
+    pub fn push(&mut self, chunk: &str) -> (String, String) {
+        self.pending.push_str(chunk);
+        let mut answer = String::new();
+        let mut reasoning = String::new();
+        loop {
+            let tag = if self.thinking { CLOSE_TAG } else { OPEN_TAG };
+            if let Some(pos) = self.pending.find(tag) {
+                let before = self.pending[..pos].to_string();
+                self.pending.drain(..pos + tag.len());
+                self.emit(&before, &mut answer, &mut reasoning);
+                self.thinking = !self.thinking;
+                self.trim_answer = !self.thinking;
+                continue;
+            }
+            let keep = (1..tag.len())
+                .rev()
+                .find(|len| self.pending.ends_with(&tag[..*len]))
+                .unwrap_or(0);
+            let ready: String = self.pending.drain(..self.pending.len() - keep).collect();
+            self.emit(&ready, &mut answer, &mut reasoning);
+            return (answer, reasoning);
+        }
+    }
+
+    /// Flush text held back at the end of the stream.
+    pub fn finish(&mut self) -> (String, String) {
+        let rest = std::mem::take(&mut self.pending);
+        let mut answer = String::new();
+        let mut reasoning = String::new();
+        self.emit(&rest, &mut answer, &mut reasoning);
+        (answer, reasoning)
+    }
+
+    fn emit(&mut self, text: &str, answer: &mut String, reasoning: &mut String) {
+        if self.thinking {
+            reasoning.push_str(text);
+            return;
+        }
+        let text = if self.trim_answer { text.trim_start() } else { text };
+        if !text.is_empty() {
+            self.trim_answer = false;
+            answer.push_str(text);
+        }
+    }
+}
@@ -0,0 +1,405 @@
+//! Rate-limit coordination for several agents sharing one provider.
+//!
+//! A single `ProviderThrottle` is shared (via `Arc`) by every `ThrottledLLM`
+//! that talks to the same provider/key. Requests queue on the throttle in FIFO
+//! order until both the request and the token budget allow them through.
+//! With `OverLimit::Error` calls that don't fit fail with
+//! `LLMError::RateLimitExceeded` instead of waiting.
+//!
+//! Dropping a call is safe at any point: a caller still waiting in the queue
+//! gives up its place, and a stream dropped mid-generation closes the upstream
+//! connection and still charges the tokens it used so far.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use async_stream::stream as async_stream;
+use futures::{
+    FutureExt,
+    StreamExt,
+    future::BoxFuture,
+    stream::BoxStream
+};
+use tokio::sync::Mutex;
+
+use crate::message::Message;
+use crate::tools::{schema::ToolSchema, stream::StreamData};
+use crate::llm::{
+    traits::LLM,
+    capabilities::Capabilities,
+    error::LLMError,
+    tokens::count_tokens,
+    options::GenerateOptions,
+    GenerateResult,
+    LLMResult,
+};
+
+/// Per-minute budgets. `None` means unlimited.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ThrottleConfig {
+    pub requests_per_minute: Option<u32>,
+    pub tokens_per_minute: Option<u32>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    capacity: f64,
+    available: f64,
+    refill_per_sec: f64,
+    last: Instant,
+}
+
+impl Bucket {
+    fn per_minute(limit: u32) -> Self {
+        let capacity = limit as f64;
+        Self {
+            capacity,
+            available: capacity,
+            refill_per_sec: capacity / 60.0,
+            last: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last).as_secs_f64();
+        self.available = (self.available + elapsed * self.refill_per_sec).min(self.capacity);
+        self.last = now;
+    }
+
+    /// Time until `amount` is available (capped at the bucket capacity).
+    fn wait_for(&self, amount: f64) -> Duration {
+        let needed = amount.min(self.capacity) - self.available;
+        if needed <= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(needed / self.refill_per_sec)
+        }
+    }
+}
+
+#[derive(Debug)]
+struct ThrottleState {
+    requests: Option<Bucket>,
+    tokens: Option<Bucket>,
+}
+
+impl ThrottleState {
+    /// Time until one request and `tokens` fit in both budgets.
+    fn wait_for(&mut self, tokens: u32) -> Duration {
+        let mut wait = Duration::ZERO;
+        if let Some(bucket) = self.requests.as_mut() {
+            bucket.refill();
+            wait = wait.max(bucket.wait_for(1.0));
+        }
+        if let Some(bucket) = self.tokens.as_mut() {
+            bucket.refill();
+            wait = wait.max(bucket.wait_for(tokens as f64));
+        }
+        wait
+    }
+
+    fn consume(&mut self, tokens: u32) {
+        if let Some(bucket) = self.requests.as_mut() {
+            bucket.available -= 1.0;
+        }
+        if let Some(bucket) = self.tokens.as_mut() {
+            bucket.available -= tokens as f64;
+        }
+    }
+}
+
+/// Shared request/token budget for one provider.
+#[derive(Debug)]
+pub struct ProviderThrottle {
+    config: ThrottleConfig,
+    state: Mutex<ThrottleState>,
+}
+
+impl ProviderThrottle {
+    pub fn new(config: ThrottleConfig) -> Arc<Self> {
+        Arc::new(Self {
+            config,
+            state: Mutex::new(ThrottleState {
+                requests: config.requests_per_minute.map(Bucket::per_minute),
+                tokens: config.tokens_per_minute.map(Bucket::per_minute),
+            }),
+        })
+    }
+
+    pub fn config(&self) -> ThrottleConfig {
+        self.config
+    }
+
+    /// Wait until one request and `estimated_tokens` fit in the budget, then
+    /// consume them. Callers are served in arrival order.
+    pub async fn acquire(&self, estimated_tokens: u32) {
+        // Holding the (fair) lock while sleeping is what makes the queue global.
+        let mut state = self.state.lock().await;
+        loop {
+            let wait = state.wait_for(estimated_tokens);
+            if wait.is_zero() {
+                break;
+            }
+            tokio::time::sleep(wait).await;
+        }
+        state.consume(estimated_tokens);
+    }
+
+    /// Consume one request and `estimated_tokens` if they fit right now;
+    /// otherwise return how long the caller would have to wait.
+    pub async fn try_acquire(&self, estimated_tokens: u32) -> Result<(), Duration> {
+        let mut state = self.state.lock().await;
+        let wait = state.wait_for(estimated_tokens);
+        if !wait.is_zero() {
+            return Err(wait);
+        }
+        state.consume(estimated_tokens);
+        Ok(())
+    }
+
+    /// Correct the token budget once the real usage is known. Overshoot is
+    /// carried as debt and delays subsequent requests.
+    pub async fn record_usage(&self, estimated_tokens: u32, actual_tokens: u32) {
+        let mut state = self.state.lock().await;
+        if let Some(bucket) = state.tokens.as_mut() {
+            bucket.refill();
+            bucket.available -= actual_tokens as f64 - estimated_tokens as f64;
+        }
+    }
+}
+
+/// Prompt size used for admission before the provider reports usage.
+pub(crate) fn estimate_prompt_tokens(messages: &[Message]) -> u32 {
+    count_tokens("", messages)
+}
+
+/// Settles the token budget of a stream admitted by `ThrottledLLM`.
+///
+/// A stream that runs to the end is charged what the provider reported. One
+/// dropped early never gets there, so the guard charges an estimate of the
+/// partial usage (prompt plus about four characters per received token) from
+/// `Drop`, on a task since the throttle lock is async.
+struct StreamCharge {
+    throttle: Arc<ProviderThrottle>,
+    estimated: u32,
+    reported: Option<u32>,
+    received_chars: usize,
+    settled: bool,
+}
+
+impl StreamCharge {
+    fn new(throttle: Arc<ProviderThrottle>, estimated: u32) -> Self {
+        Self {
+            throttle,
+            estimated,
+            reported: None,
+            received_chars: 0,
+            settled: false,
+        }
+    }
+
+    fn observe(&mut self, data: &StreamData) {
+        self.received_chars += data.content.len();
+        if let Some(tokens) = data.tokens.as_ref() {
+            self.reported = Some(tokens.total_tokens);
+        }
+    }
+
+    /// Tokens to charge, `None` while nothing was used beyond the estimate.
+    fn actual(&self) -> Option<u32> {
+        self.reported
+            .or_else(|| (self.received_chars > 0).then(|| self.estimated + (self.received_chars / 4) as u32))
+    }
+
+    async fn settle(mut self) {
+        self.settled = true;
+        if let Some(actual) = self.actual() {
+            self.throttle.record_usage(self.estimated, actual).await;
+        }
+    }
+}
+
+impl Drop for StreamCharge {
+    fn drop(&mut self) {
+        if self.settled {
+            return;
+        }
+        if let (Some(actual), Ok(runtime)) = (self.actual(), tokio::runtime::Handle::try_current()) {
+            let throttle = self.throttle.clone();
+            let estimated = self.estimated;
+            runtime.spawn(async move { throttle.record_usage(estimated, actual).await });
+        }
+    }
+}
+
+/// What a `ThrottledLLM` does with a call that exceeds the budget.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverLimit {
+    /// Wait in line until the budget allows the call.
+    #[default]
+    Queue,
+    /// Fail immediately with `LLMError::RateLimitExceeded`.
+    Error,
+}
+
+/// An `LLM` whose calls are admitted by a shared `ProviderThrottle`.
+pub struct ThrottledLLM<L> {
+    pub inner: L,
+    pub throttle: Arc<ProviderThrottle>,
+    pub over_limit: OverLimit,
+}
+
+/// A `ThrottledLLM` with its own budget, see `ThrottledLLM::with_limits`.
+pub type RateLimitedLLM<L> = ThrottledLLM<L>;
+
+impl<L: LLM> ThrottledLLM<L> {
+    pub fn new(inner: L, throttle: Arc<ProviderThrottle>) -> Self {
+        Self {
+            inner,
+            throttle,
+            over_limit: OverLimit::Queue,
+        }
+    }
+
+    /// Enforce `config` for this LLM alone rather than a shared provider budget.
+    pub fn with_limits(inner: L, config: ThrottleConfig) -> Self {
+        Self::new(inner, ProviderThrottle::new(config))
+    }
+
+    pub fn with_over_limit(mut self, over_limit: OverLimit) -> Self {
+        self.over_limit = over_limit;
+        self
+    }
+
+    async fn admit(&self, estimated_tokens: u32) -> LLMResult<()> {
+        match self.over_limit {
+            OverLimit::Queue => {
+                self.throttle.acquire(estimated_tokens).await;
+                Ok(())
+            }
+            OverLimit::Error => self.throttle.try_acquire(estimated_tokens).await.map_err(|wait| {
+                LLMError::RateLimitExceeded(format!(
+                    "request of ~{} tokens exceeds the budget; retry in {:.1}s",
+                    estimated_tokens,
+                    wait.as_secs_f64()
+                ))
+            }),
+        }
+    }
+}
+
+impl<L: LLM> LLM for ThrottledLLM<L> {
+    fn generate<'a>(&'a self, messages: &'a [Message]) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        async move {
+            let estimated = estimate_prompt_tokens(messages);
+            self.admit(estimated).await?;
+            let result = self.inner.generate(messages).await?;
+            self.throttle.record_usage(estimated, result.tokens.total_tokens).await;
+            Ok(result)
+        }
+        .boxed()
+    }
+
+    fn generate_with_tools<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        async move {
+            let estimated = estimate_prompt_tokens(messages);
+            self.admit(estimated).await?;
+            let result = self.inner.generate_with_tools(messages, tools).await?;
+            self.throttle.record_usage(estimated, result.tokens.total_tokens).await;
+            Ok(result)
+        }
+        .boxed()
+    }
+
+    fn generate_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        async move {
+            let estimated = estimate_prompt_tokens(messages);
+            self.admit(estimated).await?;
+            let result = self.inner.generate_with_options(messages, tools, options).await?;
+            self.throttle.record_usage(estimated, result.tokens.total_tokens).await;
+            Ok(result)
+        }
+        .boxed()
+    }
+
+    fn generate_n<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+        n: usize,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        async move {
+            let estimated = estimate_prompt_tokens(messages);
+            self.admit(estimated).await?;
+            let result = self.inner.generate_n(messages, tools, options, n).await?;
+            self.throttle.record_usage(estimated, result.tokens.total_tokens).await;
+            Ok(result)
+        }
+        .boxed()
+    }
+
+    fn stream<'a>(&'a self, messages: &'a [Message]) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.stream_with_tools(messages, &[])
+    }
+
+    fn stream_with_tools<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        self.stream_with_options(messages, tools, GenerateOptions::none())
+    }
+
+    fn stream_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        let this = self;
+        let s = async_stream! {
+            let estimated = estimate_prompt_tokens(messages);
+            if let Err(e) = this.admit(estimated).await {
+                yield Err(e);
+                return;
+            }
+            let mut charge = StreamCharge::new(this.throttle.clone(), estimated);
+            let mut upstream = this.inner.stream_with_options(messages, tools, options);
+            while let Some(item) = upstream.next().await {
+                if let Ok(data) = item.as_ref() {
+                    charge.observe(data);
+                }
+                yield item;
+            }
+            charge.settle().await;
+        };
+
+        Box::pin(s)
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    fn model_name(&self) -> Option<&str> {
+        self.inner.model_name()
+    }
+
+    fn provider(&self) -> Option<&str> {
+        self.inner.provider()
+    }
+
+    fn health_check(&self) -> BoxFuture<'_, LLMResult<()>> {
+        self.inner.health_check()
+    }
+}
@@ -1,26 +1,64 @@
+//! Token usage reported by providers, and local token counting for
+//! estimating prompt size before a call.
+//!
+//! Counting uses a [`TokenCounter`] picked by model name. No BPE tables ship
+//! with the crate, so the default is [`HeuristicCounter`]. For exact counts
+//! of OpenAI models, enable the `tiktoken` feature and call
+//! `tiktoken::register_openai_counters`; for other models, register a
+//! counter backed by their own tokenizer:
+//!
+//! ```ignore
+//! struct Llama(tokenizers::Tokenizer);
+//!
+//! impl TokenCounter for Llama {
+//!     fn count(&self, text: &str) -> u32 {
+//!         self.0.encode(text, false).map(|e| e.len() as u32).unwrap_or_default()
+//!     }
+//! }
+//!
+//! register_token_counter("llama", Arc::new(Llama(Tokenizer::from_file("tokenizer.json")?)));
+//! ```
 
+use std::sync::{Arc, LazyLock, RwLock};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+use crate::message::Message;
+
+#[cfg(feature = "tiktoken")]
+pub mod tiktoken;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
 pub struct TokenUsage {
     pub prompt_tokens: u32,
     pub completion_tokens: u32,
     pub total_tokens: u32,
+    /// Prompt tokens served from the provider's prompt cache. Included in
+    /// `prompt_tokens`.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub cache_read_tokens: u32,
+    /// Prompt tokens written to the provider's prompt cache. Included in
+    /// `prompt_tokens`.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub cache_write_tokens: u32,
+}
+
+fn is_zero(n: &u32) -> bool {
+    *n == 0
 }
 
 impl TokenUsage {
     pub fn sum(&self, other: &TokenUsage) -> TokenUsage {
-        TokenUsage {
-            prompt_tokens: self.prompt_tokens + other.prompt_tokens,
-            completion_tokens: self.completion_tokens + other.completion_tokens,
-            total_tokens: self.total_tokens + other.total_tokens,
-        }
+        let mut sum = self.clone();
+        sum.add(other);
+        sum
     }
 
     pub fn add(&mut self, other: &TokenUsage) {
         self.prompt_tokens += other.prompt_tokens;
         self.completion_tokens += other.completion_tokens;
         self.total_tokens += other.total_tokens;
+        self.cache_read_tokens += other.cache_read_tokens;
+        self.cache_write_tokens += other.cache_write_tokens;
     }
 }
 
@@ -30,6 +68,153 @@ impl TokenUsage {
             prompt_tokens,
             completion_tokens,
             total_tokens: prompt_tokens + completion_tokens,
+            ..Default::default()
         }
     }
 }
+
+/// Counts the tokens of a piece of text for one tokenizer.
+pub trait TokenCounter: Send + Sync {
+    fn count(&self, text: &str) -> u32;
+}
+
+/// Tokenizer-free estimate tuned on BPE vocabularies like `cl100k_base`:
+/// a latin word costs about one token per four characters, while CJK
+/// characters and punctuation cost about one token each. Typically within
+/// 10-15% of the real count for prose and code.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HeuristicCounter;
+
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x2E80..=0x9FFF | 0xAC00..=0xD7AF | 0xF900..=0xFAFF | 0xFF00..=0xFFEF | 0x20000..=0x2FA1F)
+}
+
+impl TokenCounter for HeuristicCounter {
+    fn count(&self, text: &str) -> u32 {
+        let mut tokens = 0u32;
+        let mut word = 0u32;
+        for c in text.chars() {
+            if c.is_alphanumeric() && !is_cjk(c) {
+                word += 1;
+                continue;
+            }
+            tokens += word.div_ceil(4);
+            word = 0;
+            if !c.is_whitespace() {
+                tokens += 1;
+            }
+        }
+        tokens + word.div_ceil(4)
+    }
+}
+
+/// Chat formatting cost per message (role and separators), following the
+/// OpenAI chat format.
+const TOKENS_PER_MESSAGE: u32 = 3;
+/// Tokens priming the assistant reply.
+const TOKENS_PER_REPLY: u32 = 3;
+
+type CounterEntry = (String, Arc<dyn TokenCounter>);
+
+static COUNTERS: LazyLock<RwLock<Vec<CounterEntry>>> = LazyLock::new(|| RwLock::new(Vec::new()));
+
+/// Use `counter` for models whose lowercased name contains `pattern`.
+/// Later registrations take precedence.
+pub fn register_token_counter(pattern: impl Into<String>, counter: Arc<dyn TokenCounter>) {
+    let mut counters = COUNTERS.write().unwrap_or_else(|e| e.into_inner());
+    counters.insert(0, (pattern.into().to_lowercase(), counter));
+}
+
+/// The counter registered for `model`, or the heuristic.
+pub fn token_counter(model: &str) -> Arc<dyn TokenCounter> {
+    let model = model.to_lowercase();
+    let counters = COUNTERS.read().unwrap_or_else(|e| e.into_inner());
+    counters
+        .iter()
+        .find(|(pattern, _)| model.contains(pattern.as_str()))
+        .map(|(_, counter)| counter.clone())
+        .unwrap_or_else(|| Arc::new(HeuristicCounter))
+}
+
+/// Estimated prompt tokens of `messages` for `model`, including the chat
+/// formatting overhead and native tool calls.
+pub fn count_tokens(model: &str, messages: &[Message]) -> u32 {
+    let counter = token_counter(model);
+    let body: u32 = messages
+        .iter()
+        .map(|m| {
+            let mut tokens = TOKENS_PER_MESSAGE + counter.count(&m.content);
+            if let Some(name) = m.name.as_ref() {
+                tokens += counter.count(name) + 1;
+            }
+            if !m.tool_calls.is_empty() {
+                let calls = serde_json::to_string(&m.tool_calls).unwrap_or_default();
+                tokens += counter.count(&calls);
+            }
+            tokens
+        })
+        .sum();
+    body + TOKENS_PER_REPLY
+}
+
+/// Context window of well-known models, matched by name.
+pub fn context_window(model: &str) -> Option<u32> {
+    let model = model.to_lowercase();
+    // specific names before their prefixes
+    const WINDOWS: &[(&str, u32)] = &[
+        ("gpt-4.1", 1_047_576),
+        ("gpt-4o", 128_000),
+        ("gpt-4-turbo", 128_000),
+        ("gpt-4-32k", 32_768),
+        ("gpt-4", 8_192),
+        ("gpt-3.5-turbo", 16_385),
+        ("o1", 200_000),
+        ("o3", 200_000),
+        ("o4", 200_000),
+        ("claude", 200_000),
+        ("deepseek", 128_000),
+        ("qwen", 131_072),
+        ("mistral-large", 128_000),
+        ("command-r", 128_000),
+        ("llama-3", 128_000),
+        ("llama3", 128_000),
+        ("grok", 131_072),
+        ("moonshot-v1-8k", 8_192),
+        ("moonshot-v1-32k", 32_768),
+        ("moonshot-v1-128k", 131_072),
+        ("glm-4", 128_000),
+    ];
+    WINDOWS
+        .iter()
+        .find(|(pattern, _)| model.contains(pattern))
+        .map(|(_, window)| *window)
+}
+
+/// Pre-flight estimate of a request against the model's context window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContextEstimate {
+    pub prompt_tokens: u32,
+    /// `None` for models we don't know.
+    pub context_window: Option<u32>,
+}
+
+impl ContextEstimate {
+    pub fn new(model: &str, messages: &[Message]) -> Self {
+        Self {
+            prompt_tokens: count_tokens(model, messages),
+            context_window: context_window(model),
+        }
+    }
+
+    /// Tokens left for the reply, if the window is known.
+    pub fn remaining(&self) -> Option<u32> {
+        self.context_window.map(|window| window.saturating_sub(self.prompt_tokens))
+    }
+
+    /// Whether the prompt plus `max_output` tokens fit. Unknown windows are
+    /// assumed to fit.
+    pub fn fits(&self, max_output: u32) -> bool {
+        self.remaining().is_none_or(|remaining| remaining >= max_output)
+    }
+}
@@ -0,0 +1,208 @@
+//! Exact token counts for OpenAI models from the `tiktoken` BPE tables.
+//!
+//! OpenAI publishes the byte-pair ranks of its encodings as `.tiktoken`
+//! files. [`Tiktoken::load`] downloads one on first use and caches it in
+//! `TIKTOKEN_CACHE_DIR` (or a directory under the system temp dir);
+//! [`Tiktoken::open`] reads a copy shipped with your application.
+//! [`register_openai_counters`] loads both and registers them for the
+//! OpenAI models:
+//!
+//! ```ignore
+//! tiktoken::register_openai_counters().await?;
+//! let tokens = count_tokens("gpt-4o", &messages); // exact, not estimated
+//! ```
+//!
+//! Special tokens such as `<|endoftext|>` are counted as plain text.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use base64::Engine;
+use regex::Regex;
+
+use crate::llm::{error::LLMError, pool::shared_client, LLMResult};
+use super::{register_token_counter, TokenCounter};
+
+/// An OpenAI BPE encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// GPT-4, GPT-3.5 and the embedding models.
+    Cl100kBase,
+    /// GPT-4o, GPT-4.1, GPT-5 and the o-series.
+    O200kBase,
+}
+
+impl Encoding {
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Cl100kBase => "cl100k_base",
+            Encoding::O200kBase => "o200k_base",
+        }
+    }
+
+    /// Where OpenAI publishes the ranks.
+    pub fn url(self) -> String {
+        format!("https://openaipublic.blob.core.windows.net/encodings/{}.tiktoken", self.name())
+    }
+
+    /// The pattern splitting text into pieces before merging. tiktoken ends
+    /// both with `\s+(?!\S)|\s+`; the `regex` crate has no lookahead, so
+    /// `pieces` trims those matches instead.
+    fn pattern(self) -> &'static str {
+        match self {
+            Encoding::Cl100kBase => concat!(
+                r"(?i:'s|'t|'re|'ve|'m|'ll|'d)|[^\r\n\p{L}\p{N}]?\p{L}+|\p{N}{1,3}",
+                r"| ?[^\s\p{L}\p{N}]+[\r\n]*|\s*[\r\n]+|\s+",
+            ),
+            Encoding::O200kBase => concat!(
+                r"[^\r\n\p{L}\p{N}]?[\p{Lu}\p{Lt}\p{Lm}\p{Lo}\p{M}]*[\p{Ll}\p{Lm}\p{Lo}\p{M}]+(?i:'s|'t|'re|'ve|'m|'ll|'d)?",
+                r"|[^\r\n\p{L}\p{N}]?[\p{Lu}\p{Lt}\p{Lm}\p{Lo}\p{M}]+[\p{Ll}\p{Lm}\p{Lo}\p{M}]*(?i:'s|'t|'re|'ve|'m|'ll|'d)?",
+                r"|\p{N}{1,3}| ?[^\s\p{L}\p{N}]+[\r\n/]*|\s*[\r\n]+|\s+",
+            ),
+        }
+    }
+}
+
+/// Counts tokens exactly as OpenAI's tokenizer splits them.
+#[derive(Debug, Clone)]
+pub struct Tiktoken {
+    encoding: Encoding,
+    ranks: HashMap<Vec<u8>, u32>,
+    pattern: Regex,
+}
+
+impl Tiktoken {
+    pub fn new(encoding: Encoding, ranks: HashMap<Vec<u8>, u32>) -> Self {
+        Self {
+            encoding,
+            ranks,
+            pattern: Regex::new(encoding.pattern()).expect("tiktoken pattern"),
+        }
+    }
+
+    /// From the contents of a `.tiktoken` file: one base64 token and its
+    /// rank per line.
+    pub fn parse(encoding: Encoding, data: &[u8]) -> LLMResult<Self> {
+        let malformed = |line: usize| LLMError::InvalidResponse(format!("malformed {} ranks at line {}", encoding.name(), line + 1));
+        let mut ranks = HashMap::new();
+        for (index, line) in data.split(|b| *b == b'\n').enumerate() {
+            if line.is_empty() {
+                continue;
+            }
+            let line = std::str::from_utf8(line).map_err(|_| malformed(index))?;
+            let (token, rank) = line.split_once(' ').ok_or_else(|| malformed(index))?;
+            let token = base64::engine::general_purpose::STANDARD
+                .decode(token)
+                .map_err(|_| malformed(index))?;
+            let rank = rank.trim().parse().map_err(|_| malformed(index))?;
+            ranks.insert(token, rank);
+        }
+        if ranks.is_empty() {
+            return Err(malformed(0));
+        }
+        Ok(Self::new(encoding, ranks))
+    }
+
+    /// Read the ranks from a local `.tiktoken` file.
+    pub fn open(encoding: Encoding, path: impl AsRef<Path>) -> LLMResult<Self> {
+        Self::parse(encoding, &std::fs::read(path)?)
+    }
+
+    /// Read the ranks from the cache, downloading them on first use.
+    pub async fn load(encoding: Encoding) -> LLMResult<Self> {
+        let path = cache_dir().join(format!("{}.tiktoken", encoding.name()));
+        match tokio::fs::read(&path).await {
+            Ok(data) => return Self::parse(encoding, &data),
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            Err(_) => {}
+        }
+        let data = shared_client()
+            .get(encoding.url())
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        let tiktoken = Self::parse(encoding, &data)?;
+        // written aside and renamed, so a concurrent load never reads half a file
+        tokio::fs::create_dir_all(cache_dir()).await?;
+        let temp = path.with_extension(format!("{}.tmp", std::process::id()));
+        tokio::fs::write(&temp, &data).await?;
+        tokio::fs::rename(&temp, &path).await?;
+        Ok(tiktoken)
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// Split `text` as tiktoken does before merging.
+    fn pieces<'t>(&self, text: &'t str) -> Vec<&'t str> {
+        let mut pieces = Vec::new();
+        let mut start = 0;
+        while let Some(found) = self.pattern.find_at(text, start) {
+            let mut end = found.end();
+            let piece = found.as_str();
+            // `\s+(?!\S)`: a run of spaces before a word leaves its last
+            // space to the word
+            if end < text.len()
+                && piece.chars().all(char::is_whitespace)
+                && !piece.ends_with(['\r', '\n'])
+                && let Some((last, _)) = piece.char_indices().last().filter(|(last, _)| *last > 0)
+            {
+                end = found.start() + last;
+            }
+            pieces.push(&text[found.start()..end]);
+            start = end;
+        }
+        pieces
+    }
+
+    /// Tokens of one piece: its bytes merged lowest rank first.
+    fn count_piece(&self, piece: &[u8]) -> usize {
+        if piece.len() < 2 || self.ranks.contains_key(piece) {
+            return 1;
+        }
+        // boundaries between the parts merged so far
+        let mut parts: Vec<usize> = (0..=piece.len()).collect();
+        while let Some((_, index)) = (0..parts.len() - 2)
+            .filter_map(|i| self.ranks.get(&piece[parts[i]..parts[i + 2]]).map(|rank| (*rank, i)))
+            .min()
+        {
+            parts.remove(index + 1);
+            if parts.len() < 3 {
+                break;
+            }
+        }
+        parts.len() - 1
+    }
+}
+
+impl TokenCounter for Tiktoken {
+    fn count(&self, text: &str) -> u32 {
+        self.pieces(text)
+            .into_iter()
+            .map(|piece| self.count_piece(piece.as_bytes()))
+            .sum::<usize>() as u32
+    }
+}
+
+fn cache_dir() -> PathBuf {
+    std::env::var_os("TIKTOKEN_CACHE_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| std::env::temp_dir().join("mini-langchain-tiktoken"))
+}
+
+/// Load both encodings and register them for the OpenAI models using them.
+pub async fn register_openai_counters() -> LLMResult<()> {
+    let cl100k: Arc<dyn TokenCounter> = Arc::new(Tiktoken::load(Encoding::Cl100kBase).await?);
+    let o200k: Arc<dyn TokenCounter> = Arc::new(Tiktoken::load(Encoding::O200kBase).await?);
+    for pattern in ["gpt-3.5", "gpt-4", "text-embedding"] {
+        register_token_counter(pattern, cl100k.clone());
+    }
+    // registered last so they take precedence, e.g. `gpt-4o` over `gpt-4`
+    for pattern in ["gpt-4o", "gpt-4.1", "gpt-4.5", "gpt-5", "o1", "o3", "o4"] {
+        register_token_counter(pattern, o200k.clone());
+    }
+    Ok(())
+}
@@ -1,9 +1,13 @@
 use std::sync::Arc;
 use crate::message::Message;
-use crate::llm::{LLMResult, GenerateResult};
+use crate::llm::{batch, LLMResult, GenerateResult};
+use futures::FutureExt;
 use futures::future::BoxFuture;
 use futures::stream::BoxStream;
 use crate::tools::stream::StreamData;
+use crate::llm::capabilities::Capabilities;
+use crate::llm::options::GenerateOptions;
+use crate::tools::schema::ToolSchema;
 
 /// Convert a concrete L into an `Arc<dyn LLM + Send + Sync>`.
 /// Convenience so callers can do `llm_to_arc_dyn(MyLlm::new(...))`.
@@ -29,5 +33,142 @@ pub trait LLM: Send + Sync {
 
     /// Return a stream that may borrow from `messages`. The stream lifetime is tied to `'a`.
     fn stream<'a>(&'a self, messages: &'a [Message]) -> BoxStream<'a, LLMResult<StreamData>>;
+
+    /// Generate with `tools` passed as native function definitions, returning
+    /// structured `tool_calls`. Callers only use this when
+    /// `capabilities().native_tools` is set; the default ignores `tools`.
+    fn generate_with_tools<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        let _ = tools;
+        self.generate(messages)
+    }
+
+    /// Stream with `tools` passed as native function definitions. Tool calls
+    /// arrive incrementally in `StreamData::tool_calls`; the default ignores
+    /// `tools`.
+    fn stream_with_tools<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        let _ = tools;
+        self.stream(messages)
+    }
+
+    /// Generate with per-call `options` overriding the backend's configured
+    /// sampling settings.
+    fn generate_with<'a>(
+        &'a self,
+        messages: &'a [Message],
+        options: &'a GenerateOptions,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        self.generate_with_options(messages, &[], options)
+    }
+
+    /// The general form behind `generate`, `generate_with_tools` and
+    /// `generate_with`: `tools` may be empty and `options` may be default.
+    /// Backends that support per-call options override this; the default
+    /// ignores the sampling settings and enforces `stop` by truncating the
+    /// generation.
+    fn generate_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        let generation = if tools.is_empty() {
+            self.generate(messages)
+        } else {
+            self.generate_with_tools(messages, tools)
+        };
+        if options.stop.is_empty() {
+            return generation;
+        }
+        generation
+            .map(move |result| {
+                result.map(|mut result| {
+                    options.apply_stop(&mut result);
+                    result
+                })
+            })
+            .boxed()
+    }
+
+    /// Stream with per-call `options`, the streaming form of
+    /// `generate_with_options`. Backends that support per-call options
+    /// override this; the default ignores the sampling settings and enforces
+    /// `stop` by ending the stream at the first stop sequence.
+    fn stream_with_options<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+    ) -> BoxStream<'a, LLMResult<StreamData>> {
+        let stream = if tools.is_empty() {
+            self.stream(messages)
+        } else {
+            self.stream_with_tools(messages, tools)
+        };
+        options.stop_stream(stream)
+    }
+
+    /// Generate a result for every conversation in `batches`, in input order.
+    /// One failure does not stop the others. The default runs them one after
+    /// another; hosted providers send them concurrently through
+    /// `batch::generate_concurrently`.
+    fn generate_batch<'a>(&'a self, batches: &'a [Vec<Message>]) -> BoxFuture<'a, Vec<LLMResult<GenerateResult>>> {
+        batch::generate_sequentially(self, batches).boxed()
+    }
+
+    /// Sample `n` completions of one request, for self-consistency or
+    /// best-of-N selection. They are in `GenerateResult::candidates` (when
+    /// `n > 1`), the first also filling the result's own fields, and `tokens`
+    /// covers all of them. Backends that can sample several completions in
+    /// one call (OpenAI's `n`) override this; the default makes `n` calls,
+    /// which return identical candidates if `options` fixes the seed.
+    fn generate_n<'a>(
+        &'a self,
+        messages: &'a [Message],
+        tools: &'a [ToolSchema],
+        options: &'a GenerateOptions,
+        n: usize,
+    ) -> BoxFuture<'a, LLMResult<GenerateResult>> {
+        batch::generate_n_sequentially(self, messages, tools, options, n).boxed()
+    }
+
+    /// The configured model, when the backend knows it. Used to pick
+    /// model-specific prompts; `GenerateResult::model` reports the one that
+    /// actually served a request.
+    fn model_name(&self) -> Option<&str> {
+        None
+    }
+
+    /// Short provider name such as `"openai"`, used with `model_name` to look
+    /// up prices. Generic OpenAI-compatible backends report `None`.
+    fn provider(&self) -> Option<&str> {
+        None
+    }
+
+    /// Features this backend supports. Callers such as the agent use this to
+    /// adapt requests; the default assumes a system role and no native tools.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::default()
+    }
+
+    /// Check that the backend is reachable and accepts the configured
+    /// credentials and model, e.g. before starting an agent run. The default
+    /// generates a single token; backends that can look up their model
+    /// without generating override it.
+    fn health_check(&self) -> BoxFuture<'_, LLMResult<()>> {
+        async move {
+            let messages = [Message::user("ping")];
+            let options = GenerateOptions::new().with_max_tokens(1);
+            self.generate_with(&messages, &options).await.map(|_| ())
+        }
+        .boxed()
+    }
 }
 
@@ -0,0 +1,53 @@
+//! Non-fatal problems that happened while producing a result.
+//!
+//! Decorators attach them to `GenerateResult::warnings`; the agent collects
+//! those plus its own into `AgentResult::warnings`, so callers can surface a
+//! run that succeeded in a degraded way.
+
+use std::fmt;
+use serde::{Serialize, Deserialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum RunWarning {
+    /// An LLM call failed and succeeded after `retries` more attempts.
+    Retried { retries: u32, error: String },
+    /// The first backends failed and the one at `index` answered instead.
+    FallbackUsed {
+        index: usize,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        model: Option<String>,
+        error: String,
+    },
+    /// The output hit the token limit and was used as it was.
+    Truncated,
+    /// The model sent malformed tool arguments that were repaired before the
+    /// tool ran.
+    ToolArgsRepaired { tool: String },
+    /// A safety rule rewrote the final answer.
+    ContentRewritten { rule: String },
+    /// A tool returned an image the model can't view; it only got a
+    /// description.
+    ImageNotShown { tool: String },
+}
+
+impl fmt::Display for RunWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunWarning::Retried { retries, error } => {
+                write!(f, "LLM call succeeded after {} retries (last error: {})", retries, error)
+            }
+            RunWarning::FallbackUsed { index, model: Some(model), error } => {
+                write!(f, "fallback backend {} ({}) answered (last error: {})", index, model, error)
+            }
+            RunWarning::FallbackUsed { index, model: None, error } => {
+                write!(f, "fallback backend {} answered (last error: {})", index, error)
+            }
+            RunWarning::Truncated => write!(f, "output was cut off by the token limit"),
+            RunWarning::ToolArgsRepaired { tool } => write!(f, "repaired malformed arguments for tool '{}'", tool),
+            RunWarning::ContentRewritten { rule } => write!(f, "safety rule '{}' rewrote the answer", rule),
+            RunWarning::ImageNotShown { tool } => write!(f, "model can't view the image returned by tool '{}'", tool),
+        }
+    }
+}
@@ -1,7 +1,16 @@
 
 use serde::{Serialize, Deserialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::llm::CallInfo;
+
+pub mod export;
+pub mod diff;
+pub mod image;
+
+pub use diff::{diff, HistoryPatch};
+pub use image::ImageContent;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum MessageRole {
     System,           // System message
@@ -13,12 +22,21 @@ pub enum MessageRole {
 }
 
 /// Message type (minimal)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Message {
     pub role: MessageRole,
     pub content: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,  // Name used for tool calls
+    /// Id of the native tool call this message answers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+    /// Native tool calls made by an assistant message.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<CallInfo>,
+    /// Images sent with the message to vision-capable models.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<ImageContent>,
 }
 
 
@@ -28,6 +46,9 @@ impl Message {
             role: MessageRole::System,
             content: content.into(),
             name: None,
+            tool_call_id: None,
+            tool_calls: Vec::new(),
+            images: Vec::new(),
         }
     }
     
@@ -36,6 +57,9 @@ impl Message {
             role: MessageRole::User,
             content: content.into(),
             name: None,
+            tool_call_id: None,
+            tool_calls: Vec::new(),
+            images: Vec::new(),
         }
     }
     
@@ -44,6 +68,9 @@ impl Message {
             role: MessageRole::Assistant,
             content: content.into(),
             name: None,
+            tool_call_id: None,
+            tool_calls: Vec::new(),
+            images: Vec::new(),
         }
     }
     
@@ -52,6 +79,9 @@ impl Message {
             role: MessageRole::Tool,
             content: content.into(),
             name: Some(name.into()),
+            tool_call_id: None,
+            tool_calls: Vec::new(),
+            images: Vec::new(),
         }
     }
     pub fn tool_res(name: impl Into<String>, content: impl Into<String>) -> Self {
@@ -59,14 +89,44 @@ impl Message {
             role: MessageRole::Tool,
             content: content.into(),
             name: Some(name.into()),
+            tool_call_id: None,
+            tool_calls: Vec::new(),
+            images: Vec::new(),
         }
     }
 
+    /// Result of a native tool call, paired with the call by its id.
+    pub fn tool_result(name: impl Into<String>, tool_call_id: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            role: MessageRole::ToolResponce,
+            content: content.into(),
+            name: Some(name.into()),
+            tool_call_id: Some(tool_call_id.into()),
+            tool_calls: Vec::new(),
+            images: Vec::new(),
+        }
+    }
+
+    /// Attach the native tool calls an assistant message made.
+    pub fn with_tool_calls(mut self, tool_calls: Vec<CallInfo>) -> Self {
+        self.tool_calls = tool_calls;
+        self
+    }
+
+    /// Attach images, e.g. a screenshot for a vision model to look at.
+    pub fn with_images(mut self, images: Vec<ImageContent>) -> Self {
+        self.images = images;
+        self
+    }
+
     pub fn developer(content: impl Into<String>) -> Self {
         Self {
             role: MessageRole::Developer,
             content: content.into(),
             name: None,
+            tool_call_id: None,
+            tool_calls: Vec::new(),
+            images: Vec::new(),
         }
     }   
 }
\ No newline at end of file
@@ -0,0 +1,242 @@
+//! Incremental updates of a message history.
+//!
+//! `diff` compares two versions of a conversation and returns a
+//! `HistoryPatch` holding only the turns that changed, so a store can persist
+//! a turn's worth of changes instead of rewriting the whole transcript.
+//! `HistoryPatch::apply` rebuilds the new history from the old one.
+
+use serde::{Serialize, Deserialize};
+use thiserror::Error;
+
+use super::Message;
+
+/// A message placed at `index`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TurnChange {
+    pub index: usize,
+    pub message: Message,
+}
+
+/// Changes turning one history into another.
+///
+/// `edited` and `removed` index the old history; `inserted` indexes the new
+/// one and only covers turns added before the end. Turns added at the end are
+/// in `appended`, which in the common case is the only non-empty field.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HistoryPatch {
+    /// Length of the history the patch applies to.
+    pub base_len: usize,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub appended: Vec<Message>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub edited: Vec<TurnChange>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub removed: Vec<usize>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inserted: Vec<TurnChange>,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum PatchError {
+    #[error("patch expects a history of {expected} messages, got {actual}")]
+    BaseMismatch { expected: usize, actual: usize },
+    #[error("patch index {0} is out of range")]
+    IndexOutOfRange(usize),
+}
+
+impl HistoryPatch {
+    /// Whether both histories were the same.
+    pub fn is_empty(&self) -> bool {
+        self.appended.is_empty() && self.edited.is_empty() && self.removed.is_empty() && self.inserted.is_empty()
+    }
+
+    /// Whether the new history only adds turns at the end.
+    pub fn is_append_only(&self) -> bool {
+        self.edited.is_empty() && self.removed.is_empty() && self.inserted.is_empty()
+    }
+
+    /// Rebuild the new history from `old`, which must be the history the
+    /// patch was computed against.
+    pub fn apply(&self, old: &[Message]) -> Result<Vec<Message>, PatchError> {
+        if old.len() != self.base_len {
+            return Err(PatchError::BaseMismatch { expected: self.base_len, actual: old.len() });
+        }
+        let mut kept: Vec<Option<Message>> = old.iter().cloned().map(Some).collect();
+        for change in &self.edited {
+            let slot = kept.get_mut(change.index).ok_or(PatchError::IndexOutOfRange(change.index))?;
+            *slot = Some(change.message.clone());
+        }
+        for index in &self.removed {
+            let slot = kept.get_mut(*index).ok_or(PatchError::IndexOutOfRange(*index))?;
+            *slot = None;
+        }
+        let mut history: Vec<Message> = kept.into_iter().flatten().collect();
+        for change in &self.inserted {
+            if change.index > history.len() {
+                return Err(PatchError::IndexOutOfRange(change.index));
+            }
+            history.insert(change.index, change.message.clone());
+        }
+        history.extend(self.appended.iter().cloned());
+        Ok(history)
+    }
+}
+
+/// Compute the changes from `old` to `new`.
+///
+/// Turns are aligned on their longest common subsequence after skipping the
+/// shared prefix, so appending to a long history stays cheap. Within each
+/// unaligned stretch, turns are paired up as edits and the rest counts as
+/// removed or added.
+pub fn diff(old: &[Message], new: &[Message]) -> HistoryPatch {
+    let mut patch = HistoryPatch {
+        base_len: old.len(),
+        ..Default::default()
+    };
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let (old_rest, new_rest) = (&old[prefix..], &new[prefix..]);
+
+    // anchors: pairs of (old index, new index) that stay unchanged, plus a
+    // sentinel past the end of both
+    let mut anchors = common_subsequence(old_rest, new_rest)
+        .into_iter()
+        .map(|(i, j)| (i + prefix, j + prefix))
+        .collect::<Vec<_>>();
+    anchors.push((old.len(), new.len()));
+
+    let (mut i, mut j) = (prefix, prefix);
+    for (index, (anchor_old, anchor_new)) in anchors.iter().copied().enumerate() {
+        let at_end = index + 1 == anchors.len();
+        let paired = (anchor_old - i).min(anchor_new - j);
+        for k in 0..paired {
+            patch.edited.push(TurnChange { index: i + k, message: new[j + k].clone() });
+        }
+        patch.removed.extend(i + paired..anchor_old);
+        for (k, message) in new.iter().enumerate().take(anchor_new).skip(j + paired) {
+            if at_end {
+                patch.appended.push(message.clone());
+            } else {
+                patch.inserted.push(TurnChange { index: k, message: message.clone() });
+            }
+        }
+        (i, j) = (anchor_old + 1, anchor_new + 1);
+    }
+    patch
+}
+
+/// Index pairs of a longest common subsequence of `a` and `b`.
+fn common_subsequence(a: &[Message], b: &[Message]) -> Vec<(usize, usize)> {
+    if a.is_empty() || b.is_empty() {
+        return Vec::new();
+    }
+    // lengths[i][j]: LCS length of a[i..] and b[j..]
+    let mut lengths = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i][j] = if a[i] == b[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+    let mut pairs = Vec::with_capacity(lengths[0][0]);
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(turns: &str) -> Vec<Message> {
+        turns.chars().map(|c| Message::user(c.to_string())).collect()
+    }
+
+    fn round_trip(old: &str, new: &str) -> HistoryPatch {
+        let (old, new) = (history(old), history(new));
+        let patch = diff(&old, &new);
+        assert_eq!(patch.apply(&old).as_ref(), Ok(&new), "{:?}", patch);
+        patch
+    }
+
+    #[test]
+    fn appends_at_the_end() {
+        let patch = round_trip("ab", "abcd");
+        assert!(patch.is_append_only());
+        assert_eq!(patch.appended, history("cd"));
+        assert!(round_trip("", "ab").is_append_only());
+        assert!(round_trip("ab", "ab").is_empty());
+    }
+
+    #[test]
+    fn inserts_before_the_end() {
+        let patch = round_trip("ac", "abc");
+        assert_eq!(patch.inserted, vec![TurnChange { index: 1, message: Message::user("b") }]);
+        assert!(patch.appended.is_empty());
+        round_trip("bc", "abc");
+        round_trip("ad", "abcd");
+    }
+
+    #[test]
+    fn deletes_anywhere() {
+        assert_eq!(round_trip("abc", "ac").removed, vec![1]);
+        assert_eq!(round_trip("abc", "bc").removed, vec![0]);
+        assert_eq!(round_trip("abc", "ab").removed, vec![2]);
+        assert_eq!(round_trip("abc", "").removed, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn edits_in_place() {
+        let patch = round_trip("abc", "axc");
+        assert_eq!(patch.edited, vec![TurnChange { index: 1, message: Message::user("x") }]);
+        assert!(patch.removed.is_empty() && patch.inserted.is_empty());
+        round_trip("abc", "abx");
+        round_trip("abc", "xy");
+        round_trip("abc", "xbcy");
+    }
+
+    #[test]
+    fn round_trips_every_small_history() {
+        let mut histories = vec![String::new()];
+        for len in 1..=3 {
+            let shorter: Vec<String> = histories.iter().filter(|h| h.len() == len - 1).cloned().collect();
+            for h in shorter {
+                for c in ['a', 'b', 'c'] {
+                    histories.push(format!("{}{}", h, c));
+                }
+            }
+        }
+        for old in &histories {
+            for new in &histories {
+                round_trip(old, new);
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_the_wrong_base() {
+        let patch = diff(&history("ab"), &history("abc"));
+        assert_eq!(patch.apply(&history("a")), Err(PatchError::BaseMismatch { expected: 2, actual: 1 }));
+
+        let bad = HistoryPatch { base_len: 1, removed: vec![3], ..Default::default() };
+        assert_eq!(bad.apply(&history("a")), Err(PatchError::IndexOutOfRange(3)));
+        let bad = HistoryPatch {
+            base_len: 1,
+            inserted: vec![TurnChange { index: 2, message: Message::user("x") }],
+            ..Default::default()
+        };
+        assert_eq!(bad.apply(&history("a")), Err(PatchError::IndexOutOfRange(2)));
+    }
+}
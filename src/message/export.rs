@@ -0,0 +1,126 @@
+//! Export recorded conversations as fine-tuning datasets.
+
+use serde::{Serialize, Deserialize};
+use serde_json::json;
+
+use crate::agent::types::AgentResult;
+use super::{Message, MessageRole};
+
+/// A recorded conversation plus optional quality signals used for filtering.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Transcript {
+    pub messages: Vec<Message>,
+    /// Whether the run achieved its goal, if known.
+    #[serde(default)]
+    pub success: Option<bool>,
+    /// A numeric quality score (e.g. from an evaluation), if known.
+    #[serde(default)]
+    pub score: Option<f64>,
+}
+
+impl Transcript {
+    pub fn new(messages: Vec<Message>) -> Self {
+        Self {
+            messages,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_success(mut self, success: bool) -> Self {
+        self.success = Some(success);
+        self
+    }
+
+    pub fn with_score(mut self, score: f64) -> Self {
+        self.score = Some(score);
+        self
+    }
+}
+
+impl From<&AgentResult> for Transcript {
+    /// A finished run counts as successful.
+    fn from(result: &AgentResult) -> Self {
+        Transcript::new(result.transcript.clone()).with_success(true)
+    }
+}
+
+/// Which transcripts make it into the dataset.
+#[derive(Debug, Clone, Default)]
+pub struct ExportFilter {
+    /// Drop transcripts not marked successful.
+    pub only_successful: bool,
+    /// Drop transcripts scored below this (unscored ones are kept).
+    pub min_score: Option<f64>,
+    /// Drop the tool schema/protocol messages the agent injects.
+    pub skip_tool_definitions: bool,
+}
+
+impl ExportFilter {
+    fn accepts(&self, transcript: &Transcript) -> bool {
+        if self.only_successful && transcript.success != Some(true) {
+            return false;
+        }
+        match (self.min_score, transcript.score) {
+            (Some(min), Some(score)) => score >= min,
+            _ => true,
+        }
+    }
+
+    fn keeps(&self, message: &Message) -> bool {
+        !(self.skip_tool_definitions && matches!(message.role, MessageRole::Developer))
+    }
+}
+
+/// OpenAI chat fine-tuning format: one `{"messages": [...]}` object per line.
+///
+/// Tool results are emitted as user turns since the transcript does not keep
+/// the `tool_call_id`s OpenAI requires for `tool` messages.
+pub fn to_openai_jsonl(transcripts: &[Transcript], filter: &ExportFilter) -> String {
+    transcripts
+        .iter()
+        .filter(|t| filter.accepts(t))
+        .map(|t| {
+            let messages: Vec<_> = t
+                .messages
+                .iter()
+                .filter(|m| filter.keeps(m))
+                .map(|m| {
+                    let role = match m.role {
+                        MessageRole::System | MessageRole::Developer => "system",
+                        MessageRole::User | MessageRole::Tool | MessageRole::ToolResponce => "user",
+                        MessageRole::Assistant => "assistant",
+                    };
+                    json!({ "role": role, "content": m.content })
+                })
+                .collect();
+            json!({ "messages": messages }).to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// ShareGPT format: one `{"conversations": [{"from", "value"}]}` object per line.
+pub fn to_sharegpt_jsonl(transcripts: &[Transcript], filter: &ExportFilter) -> String {
+    transcripts
+        .iter()
+        .filter(|t| filter.accepts(t))
+        .map(|t| {
+            let turns: Vec<_> = t
+                .messages
+                .iter()
+                .filter(|m| filter.keeps(m))
+                .map(|m| {
+                    let from = match m.role {
+                        MessageRole::System | MessageRole::Developer => "system",
+                        MessageRole::User => "human",
+                        MessageRole::Assistant => "gpt",
+                        MessageRole::Tool | MessageRole::ToolResponce => "observation",
+                    };
+                    json!({ "from": from, "value": m.content })
+                })
+                .collect();
+            json!({ "conversations": turns }).to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
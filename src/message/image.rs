@@ -0,0 +1,30 @@
+//! Images attached to a message, for vision-capable models.
+//!
+//! Backends that accept image input send them alongside the message text
+//! (OpenAI-style `image_url` parts, Anthropic `image` blocks, Ollama
+//! `images`); the others send the text alone.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Serialize, Deserialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImageContent {
+    /// e.g. `image/png`.
+    pub mime_type: String,
+    /// The image bytes, base64 encoded.
+    pub data: String,
+}
+
+impl ImageContent {
+    pub fn from_bytes(mime_type: impl Into<String>, bytes: &[u8]) -> Self {
+        Self {
+            mime_type: mime_type.into(),
+            data: STANDARD.encode(bytes),
+        }
+    }
+
+    /// The image as a `data:` url.
+    pub fn data_url(&self) -> String {
+        format!("data:{};base64,{}", self.mime_type, self.data)
+    }
+}
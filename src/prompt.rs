@@ -0,0 +1,4 @@
+//! Prompt building blocks shared by agents.
+
+pub mod presets;
+pub mod datetime;
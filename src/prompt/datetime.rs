@@ -0,0 +1,104 @@
+//! Current date, time and locale for the system prompt.
+//!
+//! Models don't know what day it is and will guess from their training data;
+//! an agent with a `TimeContext` tells them at the start of every run.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Serialize, Deserialize};
+
+const WEEKDAYS: [&str; 7] = ["Thursday", "Friday", "Saturday", "Sunday", "Monday", "Tuesday", "Wednesday"];
+
+/// Where and when the agent runs. Defaults to UTC with no locale.
+///
+/// The offset is fixed: the crate has no timezone database, so callers that
+/// care about daylight saving time set the offset that currently applies.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TimeContext {
+    /// Offset from UTC in minutes, e.g. `120` for UTC+02:00.
+    #[serde(default)]
+    pub utc_offset_minutes: i32,
+    /// Timezone name shown to the model, e.g. `"Europe/Paris"`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+    /// BCP 47 language tag, e.g. `"fr-FR"`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+}
+
+impl TimeContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_utc_offset(mut self, minutes: i32) -> Self {
+        self.utc_offset_minutes = minutes;
+        self
+    }
+
+    pub fn with_timezone(mut self, timezone: impl Into<String>) -> Self {
+        self.timezone = Some(timezone.into());
+        self
+    }
+
+    pub fn with_locale(mut self, locale: impl Into<String>) -> Self {
+        self.locale = Some(locale.into());
+        self
+    }
+
+    /// The prompt text for the current time.
+    pub fn render(&self) -> String {
+        self.render_at(SystemTime::now())
+    }
+
+    /// The prompt text for `time`, e.g.
+    /// `Current date and time: 2025-03-14 09:26 (Friday), UTC+01:00 (Europe/Paris). Locale: fr-FR.`
+    pub fn render_at(&self, time: SystemTime) -> String {
+        let seconds = match time.duration_since(UNIX_EPOCH) {
+            Ok(elapsed) => elapsed.as_secs() as i64,
+            Err(before) => -(before.duration().as_secs() as i64),
+        };
+        let local = seconds + self.utc_offset_minutes as i64 * 60;
+        let days = local.div_euclid(86_400);
+        let minute_of_day = local.rem_euclid(86_400) / 60;
+        let (year, month, day) = civil_from_days(days);
+        let weekday = WEEKDAYS[days.rem_euclid(7) as usize];
+
+        let sign = if self.utc_offset_minutes < 0 { '-' } else { '+' };
+        let offset = self.utc_offset_minutes.unsigned_abs();
+        let mut text = format!(
+            "Current date and time: {:04}-{:02}-{:02} {:02}:{:02} ({}), UTC{}{:02}:{:02}",
+            year,
+            month,
+            day,
+            minute_of_day / 60,
+            minute_of_day % 60,
+            weekday,
+            sign,
+            offset / 60,
+            offset % 60,
+        );
+        if let Some(timezone) = self.timezone.as_ref() {
+            text.push_str(&format!(" ({})", timezone));
+        }
+        text.push('.');
+        if let Some(locale) = self.locale.as_ref() {
+            text.push_str(&format!(" Locale: {}.", locale));
+        }
+        text
+    }
+}
+
+/// Gregorian (year, month, day) of a day count since 1970-01-01.
+/// See <http://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
@@ -0,0 +1,135 @@
+//! Tool-calling instructions tuned per model family.
+//!
+//! Small local models follow short, imperative instructions better than the
+//! generic developer message, and some families have formatting quirks (e.g.
+//! DeepSeek-R1 ignores system prompts). A [`PresetRegistry`] maps model names
+//! to a [`PromptPreset`]; the agent picks one from `LLM::model_name`.
+
+use std::sync::LazyLock;
+use serde::{Serialize, Deserialize};
+
+/// Placeholder replaced by the example tool-call JSON.
+pub const FORMAT_PLACEHOLDER: &str = "{format}";
+
+/// The original one-size-fits-all instructions, kept byte-identical so
+/// existing prompts don't change for unknown models.
+pub const DEFAULT_TOOL_INSTRUCTIONS: &str = "I also provide some tools for you to choose from. If you want to call a tool, please include the following JSON format in your response: {format}\n\n            IMPORTANT: After you have completed the task by calling all necessary tools, you MUST return a final response WITHOUT any tool_calls. Simply provide a summary or confirmation message to indicate completion. Do NOT continue calling tools after the task is done.";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PromptPreset {
+    /// Model family name, for display and debugging.
+    pub family: String,
+    /// Tool-calling protocol instructions; `{format}` is replaced by the
+    /// example call.
+    pub tool_instructions: String,
+    /// Whether the family follows system/developer messages. When `false` the
+    /// instructions are moved into the first user message.
+    pub system_role: bool,
+}
+
+impl PromptPreset {
+    pub fn new(family: impl Into<String>, tool_instructions: impl Into<String>) -> Self {
+        Self {
+            family: family.into(),
+            tool_instructions: tool_instructions.into(),
+            system_role: true,
+        }
+    }
+
+    pub fn with_system_role(mut self, system_role: bool) -> Self {
+        self.system_role = system_role;
+        self
+    }
+
+    /// The instructions with `format` substituted.
+    pub fn render_tool_instructions(&self, format: &str) -> String {
+        self.tool_instructions.replace(FORMAT_PLACEHOLDER, format)
+    }
+}
+
+impl Default for PromptPreset {
+    fn default() -> Self {
+        PromptPreset::new("default", DEFAULT_TOOL_INSTRUCTIONS)
+    }
+}
+
+/// Ordered list of `(pattern, preset)`. The first pattern contained in the
+/// lowercased model name wins, so register specific patterns first.
+#[derive(Debug, Clone, Default)]
+pub struct PresetRegistry {
+    entries: Vec<(String, PromptPreset)>,
+    fallback: PromptPreset,
+}
+
+impl PresetRegistry {
+    /// An empty registry that always returns the default preset.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The built-in presets for qwen, llama, deepseek-r1 and gpt-4o.
+    pub fn builtin() -> &'static PresetRegistry {
+        &BUILTIN
+    }
+
+    /// Add a preset; it is matched after the ones registered before it.
+    pub fn register(&mut self, pattern: impl Into<String>, preset: PromptPreset) -> &mut Self {
+        self.entries.push((pattern.into().to_lowercase(), preset));
+        self
+    }
+
+    /// Preset used when no pattern matches.
+    pub fn set_fallback(&mut self, preset: PromptPreset) -> &mut Self {
+        self.fallback = preset;
+        self
+    }
+
+    pub fn for_model(&self, model: &str) -> &PromptPreset {
+        let model = model.to_lowercase();
+        self.entries
+            .iter()
+            .find(|(pattern, _)| model.contains(pattern.as_str()))
+            .map(|(_, preset)| preset)
+            .unwrap_or(&self.fallback)
+    }
+}
+
+static BUILTIN: LazyLock<PresetRegistry> = LazyLock::new(|| {
+    let mut registry = PresetRegistry::new();
+    registry
+        .register(
+            "deepseek-r1",
+            PromptPreset::new(
+                "deepseek-r1",
+                "You can call tools. Think it through first; then, if you need a tool, end your answer with exactly this JSON (outside of your thinking): {format}\n\
+Once the tool results answer the question, reply in plain text without any tool_calls.",
+            )
+            .with_system_role(false),
+        )
+        .register(
+            "qwen",
+            PromptPreset::new(
+                "qwen",
+                "You can call tools. To call one, output a single JSON object in this format and nothing else: {format}\n\
+After you receive the tool results, answer the user directly in plain text without tool_calls.",
+            ),
+        )
+        .register(
+            "llama",
+            PromptPreset::new(
+                "llama",
+                "To use a tool, respond ONLY with JSON like this: {format}\n\
+Do not explain. Do not wrap the JSON in markdown.\n\
+When you have the answer, reply normally with no JSON.",
+            ),
+        )
+        .register(
+            "gpt-4o",
+            PromptPreset::new(
+                "gpt-4o",
+                "When a tool is needed, reply with only this JSON: {format}\n\
+When the task is complete, give the final answer as plain text without tool_calls.",
+            ),
+        );
+    registry
+});
@@ -0,0 +1,305 @@
+//! Content-safety checks applied to final agent output.
+//!
+//! A `SafetyPolicy` is a list of rules; each rule pairs a `SafetyChecker` with
+//! the `SafetyAction` to take when it fires. Keyword lists, custom predicates
+//! (e.g. a regex) and LLM-based moderation are provided as checkers.
+
+use std::ops::Range;
+use std::sync::Arc;
+use serde::{Serialize, Deserialize};
+
+use crate::llm::traits::LLM;
+use crate::message::Message;
+
+/// What to do with output that a rule flagged.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SafetyAction {
+    /// Fail the run with `AgentError::SafetyBlocked`.
+    Block,
+    /// Replace the flagged spans (or the whole output when the checker
+    /// reports no spans) with `replacement`.
+    Rewrite { replacement: String },
+    /// Keep the output unchanged and only report the finding.
+    Annotate,
+}
+
+/// A checker's verdict for one piece of text.
+#[derive(Debug, Clone, Default)]
+pub struct SafetyMatch {
+    pub detail: String,
+    /// Byte ranges of the offending text, if the checker can locate it.
+    pub spans: Vec<Range<usize>>,
+    /// The checker couldn't judge the text. The finding is reported, but
+    /// the rule's action is not taken.
+    pub unchecked: bool,
+}
+
+impl SafetyMatch {
+    /// Report that the text could not be checked, without acting on it.
+    pub fn unchecked(detail: impl Into<String>) -> Self {
+        Self {
+            detail: detail.into(),
+            spans: Vec::new(),
+            unchecked: true,
+        }
+    }
+}
+
+/// Which rule fired, reported in `AgentResult::safety`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SafetyFinding {
+    pub rule: String,
+    pub action: SafetyAction,
+    pub detail: String,
+}
+
+#[async_trait::async_trait]
+pub trait SafetyChecker: Send + Sync {
+    fn name(&self) -> &str;
+    async fn check(&self, text: &str) -> Option<SafetyMatch>;
+}
+
+/// Flags any of the given keywords (ASCII case-insensitive).
+pub struct KeywordChecker {
+    name: String,
+    keywords: Vec<String>,
+}
+
+impl KeywordChecker {
+    pub fn new<I, S>(name: impl Into<String>, keywords: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            name: name.into(),
+            keywords: keywords
+                .into_iter()
+                .map(|k| k.into().to_ascii_lowercase())
+                .filter(|k| !k.is_empty())
+                .collect(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl SafetyChecker for KeywordChecker {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn check(&self, text: &str) -> Option<SafetyMatch> {
+        // ASCII lowering keeps byte offsets identical to `text`.
+        let haystack = text.to_ascii_lowercase();
+        let mut spans = Vec::new();
+        let mut hits = Vec::new();
+        for keyword in &self.keywords {
+            let mut found = false;
+            for (start, _) in haystack.match_indices(keyword.as_str()) {
+                spans.push(start..start + keyword.len());
+                found = true;
+            }
+            if found {
+                hits.push(keyword.as_str());
+            }
+        }
+        if spans.is_empty() {
+            return None;
+        }
+        Some(SafetyMatch {
+            detail: format!("matched keywords: {}", hits.join(", ")),
+            spans,
+            ..Default::default()
+        })
+    }
+}
+
+/// Wrap any predicate as a checker, e.g. a compiled regex:
+/// `FnChecker::new("ssn", move |t| re.find(t).map(|m| SafetyMatch { detail: "ssn".into(), spans: vec![m.range()], ..Default::default() }))`.
+pub struct FnChecker<F> {
+    name: String,
+    f: F,
+}
+
+impl<F> FnChecker<F>
+where
+    F: Fn(&str) -> Option<SafetyMatch> + Send + Sync,
+{
+    pub fn new(name: impl Into<String>, f: F) -> Self {
+        Self { name: name.into(), f }
+    }
+}
+
+#[async_trait::async_trait]
+impl<F> SafetyChecker for FnChecker<F>
+where
+    F: Fn(&str) -> Option<SafetyMatch> + Send + Sync,
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn check(&self, text: &str) -> Option<SafetyMatch> {
+        (self.f)(text)
+    }
+}
+
+pub const DEFAULT_MODERATION_PROMPT: &str = "You are a content moderator. Reply with exactly `SAFE` if the text below \
+is acceptable, otherwise reply `UNSAFE: <short reason>`.";
+
+/// Ask an LLM to classify the text.
+///
+/// When the LLM call fails the text counts as flagged, so a `Block` rule
+/// blocks it. `with_fail_open(true)` lets it through instead, reporting the
+/// failure as a finding without taking the rule's action.
+pub struct ModerationChecker {
+    name: String,
+    llm: Arc<dyn LLM>,
+    prompt: String,
+    fail_open: bool,
+}
+
+impl ModerationChecker {
+    pub fn new(name: impl Into<String>, llm: Arc<dyn LLM>) -> Self {
+        Self {
+            name: name.into(),
+            llm,
+            prompt: DEFAULT_MODERATION_PROMPT.to_string(),
+            fail_open: false,
+        }
+    }
+
+    pub fn with_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.prompt = prompt.into();
+        self
+    }
+
+    pub fn with_fail_open(mut self, fail_open: bool) -> Self {
+        self.fail_open = fail_open;
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl SafetyChecker for ModerationChecker {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn check(&self, text: &str) -> Option<SafetyMatch> {
+        let messages = vec![Message::system(self.prompt.clone()), Message::user(text.to_string())];
+        let verdict = match self.llm.generate(&messages).await {
+            Ok(verdict) => verdict,
+            Err(e) if self.fail_open => {
+                return Some(SafetyMatch::unchecked(format!("moderation failed, output not checked: {}", e)));
+            }
+            Err(e) => {
+                return Some(SafetyMatch {
+                    detail: format!("moderation failed: {}", e),
+                    ..Default::default()
+                });
+            }
+        };
+        let verdict = verdict.generation.trim();
+        if verdict.to_ascii_uppercase().starts_with("UNSAFE") {
+            let detail = verdict
+                .split_once(':')
+                .map(|(_, reason)| reason.trim().to_string())
+                .unwrap_or_else(|| verdict.to_string());
+            return Some(SafetyMatch { detail, ..Default::default() });
+        }
+        None
+    }
+}
+
+pub struct SafetyRule {
+    pub checker: Arc<dyn SafetyChecker>,
+    pub action: SafetyAction,
+}
+
+/// Result of running a policy over some text.
+#[derive(Debug, Clone, Default)]
+pub struct SafetyOutcome {
+    pub text: String,
+    pub findings: Vec<SafetyFinding>,
+    /// Set to the rule name when a `Block` rule fired.
+    pub blocked_by: Option<String>,
+}
+
+#[derive(Default)]
+pub struct SafetyPolicy {
+    pub rules: Vec<SafetyRule>,
+}
+
+impl SafetyPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_rule(mut self, checker: Arc<dyn SafetyChecker>, action: SafetyAction) -> Self {
+        self.rules.push(SafetyRule { checker, action });
+        self
+    }
+
+    /// Run every rule in order. Rewrites are applied before later rules see the text;
+    /// the first `Block` stops evaluation. Text a checker couldn't judge is
+    /// reported as an `Annotate` finding.
+    pub async fn apply(&self, text: &str) -> SafetyOutcome {
+        let mut outcome = SafetyOutcome {
+            text: text.to_string(),
+            ..Default::default()
+        };
+        for rule in &self.rules {
+            let Some(found) = rule.checker.check(&outcome.text).await else {
+                continue;
+            };
+            if found.unchecked {
+                outcome.findings.push(SafetyFinding {
+                    rule: rule.checker.name().to_string(),
+                    action: SafetyAction::Annotate,
+                    detail: found.detail,
+                });
+                continue;
+            }
+            outcome.findings.push(SafetyFinding {
+                rule: rule.checker.name().to_string(),
+                action: rule.action.clone(),
+                detail: found.detail,
+            });
+            match &rule.action {
+                SafetyAction::Block => {
+                    outcome.blocked_by = Some(rule.checker.name().to_string());
+                    break;
+                }
+                SafetyAction::Rewrite { replacement } => {
+                    outcome.text = rewrite(&outcome.text, found.spans, replacement);
+                }
+                SafetyAction::Annotate => {}
+            }
+        }
+        outcome
+    }
+}
+
+fn rewrite(text: &str, mut spans: Vec<Range<usize>>, replacement: &str) -> String {
+    if spans.is_empty() {
+        return replacement.to_string();
+    }
+    spans.sort_by_key(|span| span.start);
+    let mut out = String::with_capacity(text.len());
+    let mut cursor = 0;
+    for span in spans {
+        // skip overlaps and anything not on a char boundary
+        if span.start < cursor || span.end > text.len()
+            || !text.is_char_boundary(span.start) || !text.is_char_boundary(span.end)
+        {
+            continue;
+        }
+        out.push_str(&text[cursor..span.start]);
+        out.push_str(replacement);
+        cursor = span.end;
+    }
+    out.push_str(&text[cursor..]);
+    out
+}
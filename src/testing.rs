@@ -0,0 +1,102 @@
+//! Test helpers, enabled with the `testing` feature.
+//!
+//! Snapshot assertions store the expected text under `snapshots/<name>.snap`
+//! next to the calling crate's `Cargo.toml`. A missing snapshot is written on
+//! first run; set `UPDATE_SNAPSHOTS=1` to accept intentional changes.
+//!
+//! ```ignore
+//! use mini_langchain::{assert_prompt_snapshot, testing::render_agent_prompt};
+//!
+//! assert_prompt_snapshot!("weather_agent", render_agent_prompt(&agent));
+//! ```
+//!
+//! [`FakeServer`] emulates provider HTTP endpoints for backend tests.
+
+use std::path::Path;
+
+use crate::agent::types::Agent;
+use crate::message::Message;
+
+pub mod fake_server;
+
+pub use fake_server::{FakeResponse, FakeServer, RecordedRequest};
+
+/// Compare `actual` with the stored snapshot `name` below `manifest_dir`.
+///
+/// Panics with a line diff when they differ.
+pub fn assert_snapshot_in(manifest_dir: &str, name: &str, actual: &str) {
+    let path = Path::new(manifest_dir).join("snapshots").join(format!("{}.snap", name));
+    let update = std::env::var("UPDATE_SNAPSHOTS").map(|v| v == "1").unwrap_or(false);
+
+    if update || !path.exists() {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).expect("create snapshots directory");
+        }
+        std::fs::write(&path, actual).expect("write snapshot");
+        return;
+    }
+
+    let expected = std::fs::read_to_string(&path).expect("read snapshot");
+    if expected != actual {
+        panic!(
+            "snapshot '{}' does not match ({}); rerun with UPDATE_SNAPSHOTS=1 if the change is intended\n{}",
+            name,
+            path.display(),
+            line_diff(&expected, actual)
+        );
+    }
+}
+
+/// Assert a prompt against a snapshot stored in the caller's crate.
+#[macro_export]
+macro_rules! assert_prompt_snapshot {
+    ($name:expr, $actual:expr) => {
+        $crate::testing::assert_snapshot_in(env!("CARGO_MANIFEST_DIR"), $name, &$actual)
+    };
+}
+
+/// Render messages as `[role]` headed blocks, a stable text form for snapshots.
+pub fn render_messages(messages: &[Message]) -> String {
+    messages
+        .iter()
+        .map(|m| {
+            let role = serde_json::to_value(&m.role)
+                .ok()
+                .and_then(|v| v.as_str().map(str::to_string))
+                .unwrap_or_default();
+            match m.name.as_ref() {
+                Some(name) => format!("[{} name={}]\n{}\n", role, name, m.content),
+                None => format!("[{}]\n{}\n", role, m.content),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Everything the agent sends before the user prompt: system prompt, tool
+/// protocol instructions and tool schemas.
+pub fn render_agent_prompt(agent: &Agent) -> String {
+    let mut msgs = agent.generate_system_prompt();
+    msgs.extend(agent.generate_tools_prompt());
+    render_messages(&msgs)
+}
+
+fn line_diff(expected: &str, actual: &str) -> String {
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+    let mut out = String::new();
+    for i in 0..expected.len().max(actual.len()) {
+        match (expected.get(i), actual.get(i)) {
+            (Some(e), Some(a)) if e == a => {}
+            (e, a) => {
+                if let Some(e) = e {
+                    out.push_str(&format!("{:>4} - {}\n", i + 1, e));
+                }
+                if let Some(a) = a {
+                    out.push_str(&format!("{:>4} + {}\n", i + 1, a));
+                }
+            }
+        }
+    }
+    out
+}
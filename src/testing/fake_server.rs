@@ -0,0 +1,467 @@
+//! A local HTTP server that plays an LLM provider in tests.
+//!
+//! Responses are queued per `(method, path)` and served in order; the last
+//! one is repeated once the queue is down to it. Every request is recorded so
+//! tests can assert on what a backend sent.
+//!
+//! ```ignore
+//! let server = FakeServer::start().await;
+//! server.mock("POST", "/v1/chat/completions", FakeResponse::openai_chat("Hello!"));
+//!
+//! let llm = OpenAICompatible::new(server.url("/v1"), "test-model");
+//! let result = llm.generate(&[Message::user("Hi")]).await?;
+//! assert_eq!(result.generation, "Hello!");
+//! assert_eq!(server.requests()[0].json()["model"], "test-model");
+//! ```
+//!
+//! Streams are sent with chunked encoding, so [`FakeResponse::disconnect_after`]
+//! looks like a dropped connection to the client rather than a short body.
+//!
+//! The server is written on tokio rather than wiremock or mockito because
+//! the tests exercise the connection itself: neither can drop a connection
+//! partway through a chunked body or report how many connections a client
+//! keeps open ([`FakeServer::open_connections`]). It also leaves the
+//! `testing` feature without dependencies of its own.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use serde_json::{json, Value};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+/// A request received by the server.
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    /// Header names are lowercased.
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+impl RecordedRequest {
+    /// The body parsed as JSON, `Value::Null` if it isn't.
+    pub fn json(&self) -> Value {
+        serde_json::from_str(&self.body).unwrap_or(Value::Null)
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Body {
+    Full(String),
+    /// Pieces written as separate chunks.
+    Chunks(Vec<String>),
+}
+
+/// A canned response, with optional fault injection.
+#[derive(Debug, Clone)]
+pub struct FakeResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Body,
+    delay: Option<Duration>,
+    chunk_delay: Option<Duration>,
+    disconnect_after: Option<usize>,
+}
+
+impl FakeResponse {
+    pub fn json(status: u16, body: Value) -> Self {
+        Self {
+            status,
+            headers: vec![("content-type".to_string(), "application/json".to_string())],
+            body: Body::Full(body.to_string()),
+            delay: None,
+            chunk_delay: None,
+            disconnect_after: None,
+        }
+    }
+
+    /// A server-sent events stream with one `data:` event per item.
+    pub fn sse<I, S>(events: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            status: 200,
+            headers: vec![("content-type".to_string(), "text/event-stream".to_string())],
+            body: Body::Chunks(events.into_iter().map(|e| format!("data: {}\n\n", e.into())).collect()),
+            delay: None,
+            chunk_delay: None,
+            disconnect_after: None,
+        }
+    }
+
+    /// A newline-delimited JSON stream, as Ollama sends.
+    pub fn ndjson<I>(lines: I) -> Self
+    where
+        I: IntoIterator<Item = Value>,
+    {
+        Self {
+            status: 200,
+            headers: vec![("content-type".to_string(), "application/x-ndjson".to_string())],
+            body: Body::Chunks(lines.into_iter().map(|line| format!("{}\n", line)).collect()),
+            delay: None,
+            chunk_delay: None,
+            disconnect_after: None,
+        }
+    }
+
+    /// An error response in the OpenAI error shape.
+    pub fn error(status: u16, message: &str) -> Self {
+        Self::json(status, json!({ "error": { "message": message, "type": "fake_error", "code": null } }))
+    }
+
+    /// `429 Too Many Requests` asking the client to retry after `seconds`.
+    pub fn rate_limited(seconds: u64) -> Self {
+        Self::error(429, "rate limit exceeded").with_header("retry-after", &seconds.to_string())
+    }
+
+    /// A non-streaming chat completion answering `content`.
+    pub fn openai_chat(content: &str) -> Self {
+        Self::json(200, json!({
+            "id": "chatcmpl-fake",
+            "object": "chat.completion",
+            "created": 0,
+            "model": "fake-model",
+            "choices": [{
+                "index": 0,
+                "message": { "role": "assistant", "content": content },
+                "finish_reason": "stop"
+            }],
+            "usage": { "prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15 }
+        }))
+    }
+
+    /// A chat completion calling tool `name` with `args`.
+    pub fn openai_tool_call(name: &str, args: Value) -> Self {
+        Self::json(200, json!({
+            "id": "chatcmpl-fake",
+            "object": "chat.completion",
+            "created": 0,
+            "model": "fake-model",
+            "choices": [{
+                "index": 0,
+                "message": {
+                    "role": "assistant",
+                    "content": null,
+                    "tool_calls": [{
+                        "id": "call_fake",
+                        "type": "function",
+                        "function": { "name": name, "arguments": args.to_string() }
+                    }]
+                },
+                "finish_reason": "tool_calls"
+            }],
+            "usage": { "prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15 }
+        }))
+    }
+
+    /// A streamed chat completion sending `pieces` as deltas, then usage and `[DONE]`.
+    pub fn openai_chat_stream(pieces: &[&str]) -> Self {
+        let chunk = |delta: Value, finish_reason: Value, usage: Value| {
+            json!({
+                "id": "chatcmpl-fake",
+                "object": "chat.completion.chunk",
+                "created": 0,
+                "model": "fake-model",
+                "choices": if usage.is_null() {
+                    json!([{ "index": 0, "delta": delta, "finish_reason": finish_reason }])
+                } else {
+                    json!([])
+                },
+                "usage": usage
+            })
+            .to_string()
+        };
+        let mut events: Vec<String> = pieces
+            .iter()
+            .map(|piece| chunk(json!({ "content": piece }), Value::Null, Value::Null))
+            .collect();
+        events.push(chunk(json!({}), json!("stop"), Value::Null));
+        events.push(chunk(
+            json!({}),
+            Value::Null,
+            json!({ "prompt_tokens": 10, "completion_tokens": pieces.len(), "total_tokens": 10 + pieces.len() }),
+        ));
+        events.push("[DONE]".to_string());
+        Self::sse(events)
+    }
+
+    /// A non-streaming Ollama `/api/chat` answer.
+    pub fn ollama_chat(content: &str) -> Self {
+        Self::json(200, json!({
+            "model": "fake-model",
+            "created_at": "1970-01-01T00:00:00Z",
+            "message": { "role": "assistant", "content": content },
+            "done": true,
+            "total_duration": 0,
+            "load_duration": 0,
+            "prompt_eval_count": 10,
+            "prompt_eval_duration": 0,
+            "eval_count": 5,
+            "eval_duration": 0
+        }))
+    }
+
+    /// A streamed Ollama `/api/chat` answer sending `pieces`.
+    pub fn ollama_chat_stream(pieces: &[&str]) -> Self {
+        let mut lines: Vec<Value> = pieces
+            .iter()
+            .map(|piece| json!({
+                "model": "fake-model",
+                "created_at": "1970-01-01T00:00:00Z",
+                "message": { "role": "assistant", "content": piece },
+                "done": false
+            }))
+            .collect();
+        let Value::Object(mut last) = Self::ollama_chat("").body_json() else {
+            unreachable!("ollama_chat builds an object");
+        };
+        last.insert("done".to_string(), Value::Bool(true));
+        lines.push(Value::Object(last));
+        Self::ndjson(lines)
+    }
+
+    pub fn with_status(mut self, status: u16) -> Self {
+        self.status = status;
+        self
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_lowercase(), value.to_string()));
+        self
+    }
+
+    /// Wait before sending the response head, e.g. to trigger client timeouts.
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    /// Wait before every body chunk, e.g. to drop a stream mid-generation.
+    pub fn with_chunk_delay(mut self, delay: Duration) -> Self {
+        self.chunk_delay = Some(delay);
+        self
+    }
+
+    /// Close the connection after `chunks` body chunks without finishing the
+    /// response. A full body counts as one chunk.
+    pub fn disconnect_after(mut self, chunks: usize) -> Self {
+        self.disconnect_after = Some(chunks);
+        self
+    }
+
+    fn body_json(&self) -> Value {
+        match &self.body {
+            Body::Full(body) => serde_json::from_str(body).unwrap_or(Value::Null),
+            Body::Chunks(_) => Value::Null,
+        }
+    }
+}
+
+type Routes = HashMap<(String, String), Vec<FakeResponse>>;
+
+/// Local provider emulation; see the module docs. The server stops when dropped.
+pub struct FakeServer {
+    addr: SocketAddr,
+    routes: Arc<Mutex<Routes>>,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+    open_connections: Arc<AtomicUsize>,
+    task: JoinHandle<()>,
+}
+
+impl FakeServer {
+    /// Listen on a free port on localhost.
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind fake server");
+        let addr = listener.local_addr().expect("fake server address");
+        let routes: Arc<Mutex<Routes>> = Arc::default();
+        let requests: Arc<Mutex<Vec<RecordedRequest>>> = Arc::default();
+        let open_connections: Arc<AtomicUsize> = Arc::default();
+
+        let task = {
+            let routes = routes.clone();
+            let requests = requests.clone();
+            let open_connections = open_connections.clone();
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    let routes = routes.clone();
+                    let requests = requests.clone();
+                    let open_connections = open_connections.clone();
+                    open_connections.fetch_add(1, Ordering::SeqCst);
+                    tokio::spawn(async move {
+                        // a client hanging up early is not a server failure
+                        let _ = serve(stream, routes, requests).await;
+                        open_connections.fetch_sub(1, Ordering::SeqCst);
+                    });
+                }
+            })
+        };
+        Self { addr, routes, requests, open_connections, task }
+    }
+
+    /// `http://127.0.0.1:<port>` followed by `path` (e.g. `"/v1"`).
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    pub fn port(&self) -> u16 {
+        self.addr.port()
+    }
+
+    /// Queue `response` for `method` requests to `path` (query strings are ignored).
+    pub fn mock(&self, method: &str, path: &str, response: FakeResponse) -> &Self {
+        let mut routes = self.routes.lock().unwrap_or_else(|e| e.into_inner());
+        routes
+            .entry((method.to_uppercase(), path.to_string()))
+            .or_default()
+            .push(response);
+        self
+    }
+
+    /// Connections still being served. A client that hangs up mid-response
+    /// is noticed at the next write, so poll with a chunk delay in place.
+    pub fn open_connections(&self) -> usize {
+        self.open_connections.load(Ordering::SeqCst)
+    }
+
+    /// Every request received so far, oldest first.
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+impl Drop for FakeServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn serve(
+    mut stream: TcpStream,
+    routes: Arc<Mutex<Routes>>,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+) -> std::io::Result<()> {
+    let Some(request) = read_request(&mut stream).await? else {
+        return Ok(());
+    };
+    let route = (request.method.clone(), request.path.clone());
+    requests.lock().unwrap_or_else(|e| e.into_inner()).push(request);
+
+    let response = {
+        let mut routes = routes.lock().unwrap_or_else(|e| e.into_inner());
+        match routes.get_mut(&route) {
+            Some(queue) if queue.len() > 1 => Some(queue.remove(0)),
+            Some(queue) => queue.first().cloned(),
+            None => None,
+        }
+    };
+    let response = response.unwrap_or_else(|| {
+        FakeResponse::error(404, &format!("no fake response for {} {}", route.0, route.1))
+    });
+    write_response(&mut stream, response).await
+}
+
+async fn read_request(stream: &mut TcpStream) -> std::io::Result<Option<RecordedRequest>> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let head_end = loop {
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(None);
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    };
+
+    let head = String::from_utf8_lossy(&buf[..head_end]).into_owned();
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default().to_string();
+    let target = request_line.next().unwrap_or_default();
+    let path = target.split('?').next().unwrap_or_default().to_string();
+    let headers: HashMap<String, String> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+        .collect();
+
+    let length: usize = headers
+        .get("content-length")
+        .and_then(|l| l.parse().ok())
+        .unwrap_or(0);
+    let mut body = buf[head_end..].to_vec();
+    while body.len() < length {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            break;
+        }
+        body.extend_from_slice(&chunk[..n]);
+    }
+
+    Ok(Some(RecordedRequest {
+        method,
+        path,
+        headers,
+        body: String::from_utf8_lossy(&body).into_owned(),
+    }))
+}
+
+async fn write_response(stream: &mut TcpStream, response: FakeResponse) -> std::io::Result<()> {
+    if let Some(delay) = response.delay {
+        tokio::time::sleep(delay).await;
+    }
+    let mut head = format!("HTTP/1.1 {} {}\r\nconnection: close\r\n", response.status, reason(response.status));
+    for (name, value) in response.headers.iter() {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+
+    match response.body {
+        Body::Full(body) => {
+            head.push_str(&format!("content-length: {}\r\n\r\n", body.len()));
+            stream.write_all(head.as_bytes()).await?;
+            if response.disconnect_after == Some(0) {
+                return Ok(());
+            }
+            stream.write_all(body.as_bytes()).await?;
+        }
+        Body::Chunks(chunks) => {
+            head.push_str("transfer-encoding: chunked\r\n\r\n");
+            stream.write_all(head.as_bytes()).await?;
+            for (i, chunk) in chunks.iter().enumerate() {
+                if let Some(delay) = response.chunk_delay {
+                    tokio::time::sleep(delay).await;
+                }
+                if response.disconnect_after == Some(i) {
+                    stream.flush().await?;
+                    return Ok(());
+                }
+                stream.write_all(format!("{:x}\r\n{}\r\n", chunk.len(), chunk).as_bytes()).await?;
+                stream.flush().await?;
+            }
+            stream.write_all(b"0\r\n\r\n").await?;
+        }
+    }
+    stream.flush().await
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        408 => "Request Timeout",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        _ => "Status",
+    }
+}
@@ -1,6 +1,11 @@
 pub mod builtin;
+pub mod catalog;
 pub mod schema;
 pub mod stream;
 pub mod traits;
 pub mod error;
+pub mod args;
+pub mod output;
 
+pub mod scope;
+pub mod requirements;
@@ -0,0 +1,152 @@
+//! Recover tool arguments that models send in slightly wrong shapes.
+//!
+//! Some models return `args` as a JSON string (`"{\"city\": \"Paris\"}"`)
+//! instead of an object, and hand-written JSON often uses single quotes or
+//! trailing commas. `normalize_args` turns those into the object the tool
+//! expects; anything it can't repair is passed through unchanged.
+
+use serde_json::Value;
+
+/// Re-parse stringified arguments into a JSON value.
+pub fn normalize_args(args: Value) -> Value {
+    match args {
+        Value::String(raw) => parse_lenient(&raw).unwrap_or(Value::String(raw)),
+        Value::Null => Value::Object(Default::default()),
+        other => other,
+    }
+}
+
+/// Parse JSON that may use single-quoted strings, trailing commas or the
+/// Python literals `True`, `False` and `None`. Only objects and arrays are
+/// accepted, since a bare string is not an argument list.
+pub fn parse_lenient(raw: &str) -> Option<Value> {
+    let trimmed = raw.trim();
+    if !(trimmed.starts_with('{') || trimmed.starts_with('[')) {
+        return None;
+    }
+    if let Ok(value) = serde_json::from_str::<Value>(trimmed) {
+        return Some(value);
+    }
+    serde_json::from_str(&to_strict_json(trimmed)).ok()
+}
+
+/// Rewrite the lenient syntax into strict JSON.
+fn to_strict_json(raw: &str) -> String {
+    let chars: Vec<char> = raw.chars().collect();
+    let mut out = String::with_capacity(raw.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '"' | '\'' => {
+                i = copy_string(&chars, i, &mut out);
+                continue;
+            }
+            ',' => {
+                // drop a comma that only precedes a closing bracket
+                let next = chars[i + 1..].iter().find(|c| !c.is_whitespace());
+                if !matches!(next, Some('}') | Some(']')) {
+                    out.push(',');
+                }
+            }
+            c if c.is_ascii_alphabetic() => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                out.push_str(match word.as_str() {
+                    "True" => "true",
+                    "False" => "false",
+                    "None" => "null",
+                    _ => &word,
+                });
+                continue;
+            }
+            c => out.push(c),
+        }
+        i += 1;
+    }
+    out
+}
+
+/// Copy the string starting at `chars[start]` as a double-quoted JSON string;
+/// returns the index after its closing quote.
+fn copy_string(chars: &[char], start: usize, out: &mut String) -> usize {
+    let quote = chars[start];
+    out.push('"');
+    let mut i = start + 1;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\\' && i + 1 < chars.len() {
+            let escaped = chars[i + 1];
+            // `\'` is not a JSON escape; the quote needs none inside "..."
+            if escaped == '\'' {
+                out.push('\'');
+            } else {
+                out.push('\\');
+                out.push(escaped);
+            }
+            i += 2;
+            continue;
+        }
+        if c == quote {
+            out.push('"');
+            return i + 1;
+        }
+        if c == '"' {
+            out.push_str("\\\"");
+        } else {
+            out.push(c);
+        }
+        i += 1;
+    }
+    i
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn reparses_stringified_object() {
+        let args = Value::String(r#"{"city": "Paris"}"#.to_string());
+        assert_eq!(normalize_args(args), json!({ "city": "Paris" }));
+    }
+
+    #[test]
+    fn accepts_single_quotes() {
+        let args = Value::String("{'city': 'Paris', 'note': 'say \"hi\"', 'owner': 'Bob\\'s'}".to_string());
+        assert_eq!(
+            normalize_args(args),
+            json!({ "city": "Paris", "note": "say \"hi\"", "owner": "Bob's" })
+        );
+    }
+
+    #[test]
+    fn drops_trailing_commas() {
+        let args = Value::String(r#"{"cities": ["Paris", "Rome",], "days": 3,}"#.to_string());
+        assert_eq!(normalize_args(args), json!({ "cities": ["Paris", "Rome"], "days": 3 }));
+    }
+
+    #[test]
+    fn maps_python_literals() {
+        let args = Value::String("{'metric': True, 'limit': None}".to_string());
+        assert_eq!(normalize_args(args), json!({ "metric": true, "limit": null }));
+    }
+
+    #[test]
+    fn keeps_commas_and_words_inside_strings() {
+        let args = Value::String("{'text': 'a, }True',}".to_string());
+        assert_eq!(normalize_args(args), json!({ "text": "a, }True" }));
+    }
+
+    #[test]
+    fn leaves_unrepairable_input_alone() {
+        let args = Value::String("Paris".to_string());
+        assert_eq!(normalize_args(args.clone()), args);
+        let object = json!({ "city": "Paris" });
+        assert_eq!(normalize_args(object.clone()), object);
+    }
+}
@@ -1,2 +1,11 @@
 pub mod calculator;
-pub mod search;
\ No newline at end of file
+pub mod search;
+pub mod code_edit;
+pub mod datetime;
+pub mod kv_store;
+#[cfg(any(feature = "catalog-http", feature = "browser"))]
+mod hosts;
+#[cfg(feature = "catalog-http")]
+pub mod http_get;
+#[cfg(feature = "browser")]
+pub mod browser;
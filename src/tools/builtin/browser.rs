@@ -0,0 +1,341 @@
+//! Drive a headless browser through a WebDriver server (chromedriver,
+//! geckodriver, ...).
+//!
+//! The model can navigate, click, read text and take screenshots, which are
+//! shown to vision models. Pages may only be on allowed hosts: when a
+//! navigation, redirect or click ends up anywhere else the page is replaced
+//! with `about:blank`. Every action counts against a step budget per browser
+//! session, so a looping model can't browse indefinitely.
+//!
+//! ```ignore
+//! // chromedriver --port=9515
+//! let browser = BrowserTool::new("http://localhost:9515", ["example.com"]);
+//! agent.register_tool(None, Arc::new(browser.clone()))?;
+//! let result = agent.call_llm("What does example.com say?").await?;
+//! browser.close().await?;
+//! ```
+
+use std::sync::Arc;
+use std::time::Duration;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use reqwest::{Method, Url};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::Mutex;
+
+use crate::tools::{
+    builtin::hosts::HostAllowlist,
+    error::ToolError,
+    output::ToolOutput,
+    requirements::{CostTier, ToolRequirements},
+    traits::{ArgSchema, Tool},
+};
+
+/// Actions per session before the tool refuses more.
+const DEFAULT_MAX_STEPS: usize = 20;
+/// Keep at most this many characters of extracted text.
+const DEFAULT_MAX_TEXT_CHARS: usize = 8000;
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+/// Key of an element reference in WebDriver responses.
+const ELEMENT_KEY: &str = "element-6066-11e4-a52e-4f735466cecf";
+
+/// Clones share the browser session and its step budget.
+#[derive(Debug, Clone)]
+pub struct BrowserTool {
+    client: reqwest::Client,
+    webdriver_url: String,
+    allowed_hosts: HostAllowlist,
+    capabilities: Value,
+    max_steps: usize,
+    max_text_chars: usize,
+    session: Arc<Mutex<Session>>,
+}
+
+#[derive(Debug, Default)]
+struct Session {
+    id: Option<String>,
+    steps: usize,
+}
+
+/// Why an action failed: reported to the model, or fatal for the run.
+enum Failure {
+    Browser(String),
+    Tool(ToolError),
+}
+
+impl From<ToolError> for Failure {
+    fn from(e: ToolError) -> Self {
+        Failure::Tool(e)
+    }
+}
+
+impl BrowserTool {
+    /// Use the WebDriver server at `webdriver_url`; pages may only be on
+    /// `allowed_hosts` and their subdomains.
+    pub fn new<I, S>(webdriver_url: impl Into<String>, allowed_hosts: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let client = reqwest::Client::builder()
+            .timeout(DEFAULT_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+        Self {
+            client,
+            webdriver_url: webdriver_url.into().trim_end_matches('/').to_string(),
+            allowed_hosts: HostAllowlist::new(allowed_hosts),
+            // each driver ignores the other's vendor options
+            capabilities: json!({
+                "alwaysMatch": {
+                    "goog:chromeOptions": { "args": ["--headless=new", "--disable-gpu"] },
+                    "moz:firefoxOptions": { "args": ["-headless"] },
+                }
+            }),
+            max_steps: DEFAULT_MAX_STEPS,
+            max_text_chars: DEFAULT_MAX_TEXT_CHARS,
+            session: Arc::new(Mutex::new(Session::default())),
+        }
+    }
+
+    pub fn with_max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    pub fn with_max_text_chars(mut self, max_text_chars: usize) -> Self {
+        self.max_text_chars = max_text_chars;
+        self
+    }
+
+    /// Capabilities the session is created with, replacing the headless
+    /// Chrome/Firefox defaults.
+    pub fn with_capabilities(mut self, capabilities: Value) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    /// End the browser session. The next action starts a new one with a
+    /// fresh step budget.
+    pub async fn close(&self) -> Result<(), ToolError> {
+        let mut session = self.session.lock().await;
+        session.steps = 0;
+        if let Some(id) = session.id.take() {
+            match self.command(Method::DELETE, &format!("session/{}", id), None).await {
+                Ok(_) | Err(Failure::Browser(_)) => {}
+                Err(Failure::Tool(e)) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    async fn act(&self, action: Action, url: Option<String>, selector: Option<String>) -> Result<ToolOutput, Failure> {
+        let mut session = self.session.lock().await;
+        if session.steps >= self.max_steps {
+            return Err(Failure::Browser(format!(
+                "the step budget of {} actions is used up",
+                self.max_steps
+            )));
+        }
+        session.steps += 1;
+        let id = match session.id.clone() {
+            Some(id) => id,
+            None => {
+                let created = self
+                    .command(Method::POST, "session", Some(json!({ "capabilities": self.capabilities })))
+                    .await?;
+                let id = created["sessionId"]
+                    .as_str()
+                    .ok_or_else(|| ToolError::execution_failed(self.name(), "WebDriver returned no session id"))?
+                    .to_string();
+                session.id = Some(id.clone());
+                id
+            }
+        };
+        let result = self.act_in(&id, action, url, selector).await;
+        // the driver was restarted or the browser closed
+        if let Err(Failure::Browser(message)) = &result
+            && message.starts_with("invalid session id")
+        {
+            session.id = None;
+        }
+        result
+    }
+
+    async fn act_in(&self, id: &str, action: Action, url: Option<String>, selector: Option<String>) -> Result<ToolOutput, Failure> {
+        match action {
+            Action::Navigate => {
+                let Some(url) = url else {
+                    return Err(ToolError::ParamsNotMatched("missing field `url`".to_string()).into());
+                };
+                let parsed = Url::parse(&url).map_err(|e| Failure::Browser(format!("invalid URL '{}': {}", url, e)))?;
+                if !self.allowed_hosts.allows(&parsed) {
+                    return Err(Failure::Browser(format!(
+                        "'{}' is not an allowed host (allowed: {})",
+                        parsed.host_str().unwrap_or_default(),
+                        self.allowed_hosts.list()
+                    )));
+                }
+                self.command(Method::POST, &format!("session/{}/url", id), Some(json!({ "url": url })))
+                    .await?;
+                let current = self.check_location(id).await?;
+                Ok(ToolOutput::Text(format!("OK: opened {}", current)))
+            }
+            Action::Click => {
+                let Some(selector) = selector else {
+                    return Err(ToolError::ParamsNotMatched("missing field `selector`".to_string()).into());
+                };
+                let element = self.find(id, &selector).await?;
+                self.command(Method::POST, &format!("session/{}/element/{}/click", id, element), Some(json!({})))
+                    .await?;
+                let current = self.check_location(id).await?;
+                Ok(ToolOutput::Text(format!("OK: clicked '{}', now on {}", selector, current)))
+            }
+            Action::ExtractText => {
+                let selector = selector.unwrap_or_else(|| "body".to_string());
+                let element = self.find(id, &selector).await?;
+                let text = self
+                    .command(Method::GET, &format!("session/{}/element/{}/text", id, element), None)
+                    .await?;
+                let text = text.as_str().unwrap_or_default();
+                Ok(ToolOutput::Text(match text.char_indices().nth(self.max_text_chars) {
+                    Some((end, _)) => format!("{}\n[truncated]", &text[..end]),
+                    None => text.to_string(),
+                }))
+            }
+            Action::Screenshot => {
+                let data = self.command(Method::GET, &format!("session/{}/screenshot", id), None).await?;
+                let png = STANDARD
+                    .decode(data.as_str().unwrap_or_default())
+                    .map_err(|e| ToolError::execution(self.name(), e))?;
+                Ok(ToolOutput::binary("image/png", png))
+            }
+        }
+    }
+
+    async fn find(&self, id: &str, selector: &str) -> Result<String, Failure> {
+        let found = self
+            .command(
+                Method::POST,
+                &format!("session/{}/element", id),
+                Some(json!({ "using": "css selector", "value": selector })),
+            )
+            .await?;
+        found[ELEMENT_KEY]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| Failure::Browser(format!("no element matches '{}'", selector)))
+    }
+
+    /// The current URL. A page outside the allowlist is replaced with
+    /// `about:blank` and reported as a failure.
+    async fn check_location(&self, id: &str) -> Result<String, Failure> {
+        let current = self.command(Method::GET, &format!("session/{}/url", id), None).await?;
+        let current = current.as_str().unwrap_or_default().to_string();
+        if Url::parse(&current).is_ok_and(|url| self.allowed_hosts.allows(&url)) {
+            return Ok(current);
+        }
+        self.command(Method::POST, &format!("session/{}/url", id), Some(json!({ "url": "about:blank" })))
+            .await?;
+        Err(Failure::Browser(format!(
+            "the page went to '{}', which is not an allowed host, and was closed",
+            current
+        )))
+    }
+
+    /// Send a WebDriver command and return its `value`. Errors the driver
+    /// reports (no such element, timeouts, ...) become `Failure::Browser`.
+    async fn command(&self, method: Method, path: &str, body: Option<Value>) -> Result<Value, Failure> {
+        let mut request = self.client.request(method, format!("{}/{}", self.webdriver_url, path));
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+        let response = request.send().await.map_err(|e| ToolError::execution(self.name(), e))?;
+        let status = response.status();
+        let mut body: Value = response.json().await.map_err(|e| ToolError::execution(self.name(), e))?;
+        let value = body["value"].take();
+        if !status.is_success() {
+            return Err(Failure::Browser(format!(
+                "{}: {}",
+                value["error"].as_str().unwrap_or("unknown error"),
+                value["message"].as_str().unwrap_or_default()
+            )));
+        }
+        Ok(value)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Action {
+    Navigate,
+    Click,
+    ExtractText,
+    Screenshot,
+}
+
+#[derive(Deserialize)]
+struct BrowserParams {
+    action: Action,
+    #[serde(default)]
+    url: Option<String>,
+    #[serde(default)]
+    selector: Option<String>,
+}
+
+#[async_trait::async_trait]
+impl Tool for BrowserTool {
+    fn name(&self) -> &str {
+        "browser"
+    }
+
+    fn description(&self) -> &str {
+        "Control a web browser. Actions: 'navigate' opens url, 'click' clicks the element matching selector, \
+'extract_text' returns the text of selector (default the whole page), 'screenshot' returns an image of the page. \
+Only some hosts are allowed."
+    }
+
+    fn args(&self) -> Vec<ArgSchema> {
+        vec![
+            ArgSchema {
+                name: "action".into(),
+                arg_type: "string".into(),
+                description: "One of navigate, click, extract_text, screenshot".into(),
+                required: true,
+                schema: Some(json!({ "enum": ["navigate", "click", "extract_text", "screenshot"] })),
+            },
+            ArgSchema {
+                name: "url".into(),
+                arg_type: "string".into(),
+                description: format!("Absolute http(s) URL on one of: {}; only for navigate", self.allowed_hosts.list()),
+                required: false,
+                schema: None,
+            },
+            ArgSchema {
+                name: "selector".into(),
+                arg_type: "string".into(),
+                description: "CSS selector; required for click, optional for extract_text".into(),
+                required: false,
+                schema: None,
+            },
+        ]
+    }
+
+    fn requirements(&self) -> ToolRequirements {
+        ToolRequirements::default().with_network().with_cost(CostTier::Medium)
+    }
+
+    async fn run(&self, input: Value) -> Result<String, ToolError> {
+        self.run_output(input).await.map(|output| output.describe())
+    }
+
+    async fn run_output(&self, input: Value) -> Result<ToolOutput, ToolError> {
+        let params: BrowserParams = serde_json::from_value(input)
+            .map_err(|e| ToolError::ParamsNotMatched(e.to_string()))?;
+        match self.act(params.action, params.url, params.selector).await {
+            Ok(output) => Ok(output),
+            Err(Failure::Browser(reason)) => Ok(ToolOutput::Text(format!("FAILED: {}", reason))),
+            Err(Failure::Tool(e)) => Err(e),
+        }
+    }
+}
@@ -0,0 +1,274 @@
+//! Arithmetic the model should not do in its head.
+//!
+//! Evaluates `+ - * / % ^`, parentheses, the constants `pi` and `e`, and the
+//! functions `sqrt abs ln log10 exp sin cos tan floor ceil round min max`.
+//! There are no variables and nothing is executed beyond the expression.
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::tools::{
+    error::ToolError,
+    traits::{ArgSchema, Tool},
+};
+
+/// Longest expression accepted, to keep evaluation bounded.
+const MAX_EXPRESSION_LEN: usize = 1000;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CalculatorTool;
+
+#[derive(Deserialize)]
+struct CalculatorParams {
+    expression: String,
+}
+
+#[async_trait::async_trait]
+impl Tool for CalculatorTool {
+    fn name(&self) -> &str {
+        "calculator"
+    }
+
+    fn description(&self) -> &str {
+        "Evaluate an arithmetic expression, e.g. '(3.5 + 4) * 2 ^ 3' or 'sqrt(2) / 3'. \
+Supports + - * / % ^, parentheses, pi, e and the functions sqrt, abs, ln, log10, exp, sin, cos, tan, floor, ceil, round, min and max."
+    }
+
+    fn args(&self) -> Vec<ArgSchema> {
+        vec![ArgSchema {
+            name: "expression".into(),
+            arg_type: "string".into(),
+            description: "The expression to evaluate".into(),
+            required: true,
+            schema: None,
+        }]
+    }
+
+    async fn run(&self, input: Value) -> Result<String, ToolError> {
+        let params: CalculatorParams = serde_json::from_value(input)
+            .map_err(|e| ToolError::ParamsNotMatched(e.to_string()))?;
+        match evaluate(&params.expression) {
+            Ok(value) => Ok(format_number(value)),
+            Err(reason) => Ok(format!("FAILED: {}", reason)),
+        }
+    }
+}
+
+/// Evaluate `expression`.
+pub fn evaluate(expression: &str) -> Result<f64, String> {
+    if expression.len() > MAX_EXPRESSION_LEN {
+        return Err(format!("expression is longer than {} characters", MAX_EXPRESSION_LEN));
+    }
+    let tokens = tokenize(expression)?;
+    let mut parser = Parser { tokens, pos: 0 };
+    let value = parser.expression(0)?;
+    if let Some(token) = parser.peek() {
+        return Err(format!("unexpected {:?}", token));
+    }
+    if !value.is_finite() {
+        return Err("result is not a finite number".to_string());
+    }
+    Ok(value)
+}
+
+/// Integers without a fraction, everything else as the shortest `f64` text.
+fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{}", value as i64)
+    } else {
+        format!("{}", value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Op(char),
+    Open,
+    Close,
+    Comma,
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = expression.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            c if c.is_whitespace() => {}
+            '0'..='9' | '.' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.' || chars[i] == '_') {
+                    i += 1;
+                }
+                // exponent, e.g. 1.5e-3
+                if i < chars.len() && matches!(chars[i], 'e' | 'E') {
+                    let mut end = i + 1;
+                    if end < chars.len() && matches!(chars[end], '+' | '-') {
+                        end += 1;
+                    }
+                    if end < chars.len() && chars[end].is_ascii_digit() {
+                        i = end;
+                        while i < chars.len() && chars[i].is_ascii_digit() {
+                            i += 1;
+                        }
+                    }
+                }
+                let text: String = chars[start..i].iter().filter(|c| **c != '_').collect();
+                let number = text.parse::<f64>().map_err(|_| format!("invalid number '{}'", text))?;
+                tokens.push(Token::Number(number));
+                continue;
+            }
+            c if c.is_alphabetic() => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                tokens.push(Token::Ident(chars[start..i].iter().collect::<String>().to_lowercase()));
+                continue;
+            }
+            '+' | '-' | '*' | '/' | '%' | '^' => tokens.push(Token::Op(c)),
+            '×' => tokens.push(Token::Op('*')),
+            '÷' => tokens.push(Token::Op('/')),
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            ',' => tokens.push(Token::Comma),
+            other => return Err(format!("unexpected character '{}'", other)),
+        }
+        i += 1;
+    }
+    if tokens.is_empty() {
+        return Err("empty expression".to_string());
+    }
+    Ok(tokens)
+}
+
+/// Precedence climbing over the token list.
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect_close(&mut self) -> Result<(), String> {
+        match self.next() {
+            Some(Token::Close) => Ok(()),
+            Some(token) => Err(format!("expected ')', found {:?}", token)),
+            None => Err("missing ')' at the end of the expression".to_string()),
+        }
+    }
+
+    fn expression(&mut self, min_precedence: u8) -> Result<f64, String> {
+        let mut lhs = self.unary()?;
+        while let Some(Token::Op(op)) = self.peek().cloned() {
+            let (precedence, right_assoc) = match op {
+                '+' | '-' => (1, false),
+                '*' | '/' | '%' => (2, false),
+                '^' => (4, true),
+                _ => unreachable!("tokenizer only emits known operators"),
+            };
+            if precedence < min_precedence {
+                break;
+            }
+            self.pos += 1;
+            let rhs = self.expression(if right_assoc { precedence } else { precedence + 1 })?;
+            lhs = match op {
+                '+' => lhs + rhs,
+                '-' => lhs - rhs,
+                '*' => lhs * rhs,
+                '/' if rhs == 0.0 => return Err("division by zero".to_string()),
+                '/' => lhs / rhs,
+                '%' if rhs == 0.0 => return Err("division by zero".to_string()),
+                '%' => lhs % rhs,
+                _ => lhs.powf(rhs),
+            };
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<f64, String> {
+        match self.peek() {
+            // binds looser than ^, so -2^2 is -4
+            Some(Token::Op('-')) => {
+                self.pos += 1;
+                Ok(-self.expression(3)?)
+            }
+            Some(Token::Op('+')) => {
+                self.pos += 1;
+                self.expression(3)
+            }
+            _ => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> Result<f64, String> {
+        match self.next() {
+            Some(Token::Number(number)) => Ok(number),
+            Some(Token::Open) => {
+                let value = self.expression(0)?;
+                self.expect_close()?;
+                Ok(value)
+            }
+            Some(Token::Ident(name)) => match name.as_str() {
+                "pi" => Ok(std::f64::consts::PI),
+                "e" => Ok(std::f64::consts::E),
+                _ => {
+                    let args = self.call_args(&name)?;
+                    call(&name, &args)
+                }
+            },
+            Some(token) => Err(format!("unexpected {:?}", token)),
+            None => Err("unexpected end of expression".to_string()),
+        }
+    }
+
+    fn call_args(&mut self, name: &str) -> Result<Vec<f64>, String> {
+        if self.peek() != Some(&Token::Open) {
+            return Err(format!("unknown name '{}'", name));
+        }
+        self.pos += 1;
+        let mut args = vec![self.expression(0)?];
+        while self.peek() == Some(&Token::Comma) {
+            self.pos += 1;
+            args.push(self.expression(0)?);
+        }
+        self.expect_close()?;
+        Ok(args)
+    }
+}
+
+fn call(name: &str, args: &[f64]) -> Result<f64, String> {
+    let unary = |f: fn(f64) -> f64| match args {
+        [x] => Ok(f(*x)),
+        _ => Err(format!("{}() takes 1 argument, got {}", name, args.len())),
+    };
+    match name {
+        "sqrt" if args.first().is_some_and(|x| *x < 0.0) => Err("sqrt() of a negative number".to_string()),
+        "sqrt" => unary(f64::sqrt),
+        "abs" => unary(f64::abs),
+        "ln" => unary(f64::ln),
+        "log10" | "log" => unary(f64::log10),
+        "exp" => unary(f64::exp),
+        "sin" => unary(f64::sin),
+        "cos" => unary(f64::cos),
+        "tan" => unary(f64::tan),
+        "floor" => unary(f64::floor),
+        "ceil" => unary(f64::ceil),
+        "round" => unary(f64::round),
+        "min" => Ok(args.iter().copied().fold(f64::INFINITY, f64::min)),
+        "max" => Ok(args.iter().copied().fold(f64::NEG_INFINITY, f64::max)),
+        other => Err(format!("unknown function '{}'", other)),
+    }
+}
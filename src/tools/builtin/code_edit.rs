@@ -0,0 +1,327 @@
+//! Diff-based code editing tool.
+//!
+//! The LLM sends a unified diff instead of a whole file. The tool applies it,
+//! optionally runs a check command (e.g. `cargo check`), and on failure rolls
+//! the file back and returns the error output so the model can correct itself.
+
+use std::path::{Component, Path, PathBuf};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::tools::{
+    error::{ToolError, ToolResultExt},
+    requirements::ToolRequirements,
+    traits::{ArgSchema, Tool},
+};
+
+/// Keep at most this many characters of check output in the tool response.
+const MAX_CHECK_OUTPUT: usize = 4000;
+
+pub struct CodeEditTool {
+    root: PathBuf,
+    check_command: Option<Vec<String>>,
+}
+
+impl CodeEditTool {
+    /// Edits are restricted to files below `root`.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            check_command: None,
+        }
+    }
+
+    /// Command (program + args) run in `root` after each edit; a non-zero exit
+    /// reverts the edit.
+    pub fn with_check_command<I, S>(mut self, command: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let command: Vec<String> = command.into_iter().map(Into::into).collect();
+        self.check_command = (!command.is_empty()).then_some(command);
+        self
+    }
+
+    async fn resolve(&self, path: &str) -> Result<PathBuf, String> {
+        let relative = Path::new(path);
+        let escapes = relative
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir));
+        if escapes {
+            return Err(format!("path '{}' must be relative and stay inside the workspace", path));
+        }
+        let resolved = self.root.join(relative);
+
+        // a symlink below the root may lead anywhere: canonicalize the part
+        // of the path that exists (failing on dangling links) and check
+        // that it is still inside the root
+        let root = tokio::fs::canonicalize(&self.root)
+            .await
+            .map_err(|e| format!("workspace root {}: {}", self.root.display(), e))?;
+        let mut existing = resolved.as_path();
+        while tokio::fs::symlink_metadata(existing).await.is_err() {
+            existing = match existing.parent() {
+                Some(parent) => parent,
+                None => break,
+            };
+        }
+        let real = tokio::fs::canonicalize(existing)
+            .await
+            .map_err(|e| format!("path '{}': {}", path, e))?;
+        if !real.starts_with(&root) {
+            return Err(format!("path '{}' leads outside the workspace", path));
+        }
+        Ok(resolved)
+    }
+
+    async fn check(&self) -> Result<(), String> {
+        let Some(command) = self.check_command.as_ref() else {
+            return Ok(());
+        };
+        let output = tokio::process::Command::new(&command[0])
+            .args(&command[1..])
+            .current_dir(&self.root)
+            .output()
+            .await
+            .map_err(|e| format!("failed to run check command: {}", e))?;
+        if output.status.success() {
+            return Ok(());
+        }
+        let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+        text.push_str(&String::from_utf8_lossy(&output.stderr));
+        if text.len() > MAX_CHECK_OUTPUT {
+            let mut start = text.len() - MAX_CHECK_OUTPUT;
+            while !text.is_char_boundary(start) {
+                start += 1;
+            }
+            text = text[start..].to_string();
+        }
+        Err(text)
+    }
+}
+
+#[derive(Deserialize)]
+struct CodeEditParams {
+    path: String,
+    diff: String,
+}
+
+#[async_trait::async_trait]
+impl Tool for CodeEditTool {
+    fn name(&self) -> &str {
+        "code_edit"
+    }
+
+    fn description(&self) -> &str {
+        "Edit a file by applying a unified diff (with @@ hunk headers and at least one line of context). \
+Returns whether the edit applied and passed validation; on failure the file is unchanged and the error is reported."
+    }
+
+    fn args(&self) -> Vec<ArgSchema> {
+        vec![
+            ArgSchema {
+                name: "path".into(),
+                arg_type: "string".into(),
+                description: "File path relative to the workspace root".into(),
+                required: true,
+                schema: None,
+            },
+            ArgSchema {
+                name: "diff".into(),
+                arg_type: "string".into(),
+                description: "Unified diff to apply to the file".into(),
+                required: true,
+                schema: None,
+            },
+        ]
+    }
+
+    fn requirements(&self) -> ToolRequirements {
+        ToolRequirements::default().with_filesystem()
+    }
+
+    async fn run(&self, input: Value) -> Result<String, ToolError> {
+        let params: CodeEditParams = serde_json::from_value(input)
+            .map_err(|e| ToolError::ParamsNotMatched(e.to_string()))?;
+        let path = match self.resolve(&params.path).await {
+            Ok(path) => path,
+            Err(reason) => return Ok(format!("FAILED: {}", reason)),
+        };
+
+        let existed = path.exists();
+        let original = if existed {
+            tokio::fs::read_to_string(&path)
+                .await
+                .with_context(self.name(), || format!("reading {}", params.path))?
+        } else {
+            String::new()
+        };
+
+        let updated = match apply_unified_diff(&original, &params.diff) {
+            Ok(updated) => updated,
+            Err(reason) => return Ok(format!("FAILED: diff did not apply: {}", reason)),
+        };
+
+        let write_err = |e: std::io::Error| ToolError::execution("code_edit", e);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await.map_err(write_err)?;
+        }
+        tokio::fs::write(&path, &updated).await.map_err(write_err)?;
+
+        if let Err(output) = self.check().await {
+            // roll back so the model always edits a known state
+            if existed {
+                tokio::fs::write(&path, &original).await.map_err(write_err)?;
+            } else {
+                tokio::fs::remove_file(&path).await.map_err(write_err)?;
+            }
+            return Ok(format!("FAILED: edit applied but validation failed; the file was reverted.\n{}", output));
+        }
+        Ok(format!("OK: applied diff to {}", params.path))
+    }
+}
+
+struct Hunk {
+    old_start: usize,
+    old: Vec<String>,
+    new: Vec<String>,
+}
+
+/// Parse one side of a hunk header, `-start[,len]` or `+start[,len]`. The
+/// length defaults to 1.
+fn parse_range(range: Option<&str>, sign: char) -> Option<(usize, usize)> {
+    let mut parts = range?.strip_prefix(sign)?.split(',');
+    let start = parts.next()?.parse().ok()?;
+    let len = match parts.next() {
+        Some(len) => len.parse().ok()?,
+        None => 1,
+    };
+    Some((start, len))
+}
+
+fn parse_hunks(diff: &str) -> Result<Vec<Hunk>, String> {
+    let mut hunks: Vec<Hunk> = Vec::new();
+    // lines of the current hunk still expected, per its header. `---` and
+    // `+++` file headers are only recognized once both are used up, so body
+    // lines such as `--- note` (removing `-- note`) are not mistaken for one
+    let mut old_left = 0usize;
+    let mut new_left = 0usize;
+    for line in diff.lines() {
+        let in_hunk = old_left > 0 || new_left > 0;
+        if line.starts_with("@@") {
+            if let Some(hunk) = hunks.last()
+                && in_hunk
+            {
+                return Err(format!("hunk at line {} has fewer lines than its header declares", hunk.old_start));
+            }
+            // @@ -old_start[,old_len] +new_start[,new_len] @@
+            let mut ranges = line.split_whitespace().skip(1);
+            let (Some((old_start, old_len)), Some((_, new_len))) =
+                (parse_range(ranges.next(), '-'), parse_range(ranges.next(), '+'))
+            else {
+                return Err(format!("malformed hunk header: {}", line));
+            };
+            hunks.push(Hunk { old_start, old: Vec::new(), new: Vec::new() });
+            old_left = old_len;
+            new_left = new_len;
+            continue;
+        }
+        let file_header = !in_hunk && (line.starts_with("--- ") || line.starts_with("+++ "));
+        if file_header || line.starts_with("diff ") || line.starts_with("index ") {
+            continue;
+        }
+        let Some(hunk) = hunks.last_mut() else {
+            continue;
+        };
+        // (old side, new side) of the line; an empty line is a context line
+        // whose leading space some models drop
+        let (old, new) = if let Some(rest) = line.strip_prefix('+') {
+            (None, Some(rest))
+        } else if let Some(rest) = line.strip_prefix('-') {
+            (Some(rest), None)
+        } else if let Some(rest) = line.strip_prefix(' ') {
+            (Some(rest), Some(rest))
+        } else if line.is_empty() && in_hunk {
+            (Some(""), Some(""))
+        } else {
+            // `\ No newline at end of file` and anything else is ignored
+            continue;
+        };
+        // a line past the header's counts would silently change what the
+        // hunk matches, so the diff is refused instead
+        if (old.is_some() && old_left == 0) || (new.is_some() && new_left == 0) {
+            return Err(format!(
+                "hunk at line {} has more lines than its header declares: {}",
+                hunk.old_start, line
+            ));
+        }
+        if let Some(old) = old {
+            hunk.old.push(old.to_string());
+            old_left -= 1;
+        }
+        if let Some(new) = new {
+            hunk.new.push(new.to_string());
+            new_left -= 1;
+        }
+    }
+    if let Some(hunk) = hunks.last()
+        && (old_left > 0 || new_left > 0)
+    {
+        return Err(format!("hunk at line {} has fewer lines than its header declares", hunk.old_start));
+    }
+    if hunks.is_empty() {
+        return Err("no @@ hunks found".to_string());
+    }
+    Ok(hunks)
+}
+
+/// Find `old` in `lines`, preferring the position closest to `expected`.
+fn find_hunk(lines: &[String], old: &[String], expected: usize) -> Option<usize> {
+    if old.is_empty() {
+        return Some(expected.min(lines.len()));
+    }
+    let matches_at = |pos: usize| {
+        pos + old.len() <= lines.len()
+            && lines[pos..pos + old.len()]
+                .iter()
+                .zip(old)
+                .all(|(a, b)| a.trim_end() == b.trim_end())
+    };
+    for delta in 0..=lines.len() {
+        if let Some(pos) = expected.checked_sub(delta)
+            && matches_at(pos)
+        {
+            return Some(pos);
+        }
+        if matches_at(expected + delta) {
+            return Some(expected + delta);
+        }
+    }
+    None
+}
+
+/// Apply a unified diff to `original`. Each hunk takes exactly as many lines
+/// as its header declares. The result keeps the line endings of `original`,
+/// `\r\n` if its first line ends that way.
+pub fn apply_unified_diff(original: &str, diff: &str) -> Result<String, String> {
+    let hunks = parse_hunks(diff)?;
+    let newline = match original.find('\n') {
+        Some(end) if original[..end].ends_with('\r') => "\r\n",
+        _ => "\n",
+    };
+    let mut lines: Vec<String> = original.lines().map(String::from).collect();
+    let mut offset: isize = 0;
+    for hunk in hunks {
+        let expected = (hunk.old_start as isize - 1 + offset).max(0) as usize;
+        let pos = find_hunk(&lines, &hunk.old, expected)
+            .ok_or_else(|| format!("hunk at line {} does not match the file content", hunk.old_start))?;
+        offset += hunk.new.len() as isize - hunk.old.len() as isize;
+        lines.splice(pos..pos + hunk.old.len(), hunk.new);
+    }
+    let mut out = lines.join(newline);
+    if original.ends_with('\n') || original.is_empty() {
+        out.push_str(newline);
+    }
+    Ok(out)
+}
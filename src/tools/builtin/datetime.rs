@@ -0,0 +1,72 @@
+//! The current date and time, for models that would otherwise guess it.
+
+use std::time::SystemTime;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::prompt::datetime::TimeContext;
+use crate::tools::{
+    error::ToolError,
+    traits::{ArgSchema, Tool},
+};
+
+/// Reports the current time in the configured `TimeContext`; the model may
+/// ask for another UTC offset.
+#[derive(Debug, Clone, Default)]
+pub struct DateTimeTool {
+    context: TimeContext,
+}
+
+impl DateTimeTool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Default offset, timezone name and locale of the answer.
+    pub fn with_context(mut self, context: TimeContext) -> Self {
+        self.context = context;
+        self
+    }
+}
+
+#[derive(Deserialize)]
+struct DateTimeParams {
+    #[serde(default)]
+    utc_offset_minutes: Option<i32>,
+}
+
+#[async_trait::async_trait]
+impl Tool for DateTimeTool {
+    fn name(&self) -> &str {
+        "current_datetime"
+    }
+
+    fn description(&self) -> &str {
+        "Get the current date, time and weekday."
+    }
+
+    fn args(&self) -> Vec<ArgSchema> {
+        vec![ArgSchema {
+            name: "utc_offset_minutes".into(),
+            arg_type: "integer".into(),
+            description: "Offset from UTC in minutes, e.g. 540 for Tokyo; defaults to the agent's local time".into(),
+            required: false,
+            schema: None,
+        }]
+    }
+
+    async fn run(&self, input: Value) -> Result<String, ToolError> {
+        let params: DateTimeParams = serde_json::from_value(input)
+            .map_err(|e| ToolError::ParamsNotMatched(e.to_string()))?;
+        let context = match params.utc_offset_minutes {
+            // a different offset makes the configured timezone name wrong
+            Some(offset) if offset != self.context.utc_offset_minutes => TimeContext {
+                utc_offset_minutes: offset,
+                timezone: None,
+                locale: self.context.locale.clone(),
+            },
+            _ => self.context.clone(),
+        };
+        Ok(context.render_at(SystemTime::now()))
+    }
+}
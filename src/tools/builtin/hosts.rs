@@ -0,0 +1,43 @@
+//! Host allowlists for the tools that reach the network.
+
+use reqwest::Url;
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct HostAllowlist {
+    hosts: Vec<String>,
+}
+
+impl HostAllowlist {
+    /// `example.com` also allows its subdomains, e.g. `www.example.com`.
+    pub(crate) fn new<I, S>(hosts: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            hosts: hosts
+                .into_iter()
+                .map(|host| host.into().trim_end_matches('.').to_ascii_lowercase())
+                .collect(),
+        }
+    }
+
+    /// Whether `url` is http(s) on an allowed host.
+    pub(crate) fn allows(&self, url: &Url) -> bool {
+        if !matches!(url.scheme(), "http" | "https") {
+            return false;
+        }
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        self.hosts.iter().any(|allowed| {
+            host == *allowed || host.strip_suffix(allowed.as_str()).is_some_and(|prefix| prefix.ends_with('.'))
+        })
+    }
+
+    /// The hosts, for tool descriptions and error messages.
+    pub(crate) fn list(&self) -> String {
+        self.hosts.join(", ")
+    }
+}
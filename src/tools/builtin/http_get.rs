@@ -0,0 +1,137 @@
+//! Fetch a web page or API response from an explicit list of hosts.
+//!
+//! Only `GET` over http(s) to allowed hosts (and their subdomains) is
+//! possible, so a model steered by a malicious page can't reach internal
+//! services or post data anywhere.
+
+use std::time::Duration;
+use reqwest::Url;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::tools::{
+    builtin::hosts::HostAllowlist,
+    error::ToolError,
+    requirements::{CostTier, ToolRequirements},
+    traits::{ArgSchema, Tool},
+};
+
+/// Keep at most this many characters of the body in the tool response.
+const DEFAULT_MAX_RESPONSE_CHARS: usize = 8000;
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone)]
+pub struct HttpGetTool {
+    client: reqwest::Client,
+    allowed_hosts: HostAllowlist,
+    max_response_chars: usize,
+}
+
+impl HttpGetTool {
+    /// Requests may only go to `allowed_hosts`, e.g. `["api.github.com",
+    /// "example.com"]`; `example.com` also allows `www.example.com`.
+    pub fn new<I, S>(allowed_hosts: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let client = reqwest::Client::builder()
+            .timeout(DEFAULT_TIMEOUT)
+            // a redirect could leave the allowlist
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            // only fails if the TLS backend can't be initialized
+            .expect("failed to build the HTTP client");
+        Self {
+            client,
+            allowed_hosts: HostAllowlist::new(allowed_hosts),
+            max_response_chars: DEFAULT_MAX_RESPONSE_CHARS,
+        }
+    }
+
+    pub fn with_max_response_chars(mut self, max_response_chars: usize) -> Self {
+        self.max_response_chars = max_response_chars;
+        self
+    }
+
+    /// Whether a request to `url` is allowed.
+    pub fn allows(&self, url: &Url) -> bool {
+        self.allowed_hosts.allows(url)
+    }
+}
+
+#[derive(Deserialize)]
+struct HttpGetParams {
+    url: String,
+}
+
+#[async_trait::async_trait]
+impl Tool for HttpGetTool {
+    fn name(&self) -> &str {
+        "http_get"
+    }
+
+    fn description(&self) -> &str {
+        "Fetch a URL with an HTTP GET request and return the status and body (truncated). Only some hosts are allowed."
+    }
+
+    fn args(&self) -> Vec<ArgSchema> {
+        vec![ArgSchema {
+            name: "url".into(),
+            arg_type: "string".into(),
+            description: format!("Absolute http(s) URL on one of: {}", self.allowed_hosts.list()),
+            required: true,
+            schema: None,
+        }]
+    }
+
+    fn requirements(&self) -> ToolRequirements {
+        ToolRequirements::default().with_network().with_cost(CostTier::Low)
+    }
+
+    async fn run(&self, input: Value) -> Result<String, ToolError> {
+        let params: HttpGetParams = serde_json::from_value(input)
+            .map_err(|e| ToolError::ParamsNotMatched(e.to_string()))?;
+        let url = match Url::parse(&params.url) {
+            Ok(url) => url,
+            Err(e) => return Ok(format!("FAILED: invalid URL '{}': {}", params.url, e)),
+        };
+        if !self.allows(&url) {
+            return Ok(format!(
+                "FAILED: '{}' is not an allowed host (allowed: {})",
+                url.host_str().unwrap_or_default(),
+                self.allowed_hosts.list()
+            ));
+        }
+
+        let mut response = match self.client.get(url).send().await {
+            Ok(response) => response,
+            Err(e) => return Ok(format!("FAILED: request failed: {}", e)),
+        };
+        let status = response.status();
+        // a character takes at most 4 bytes; stop reading past what is kept
+        let limit = self.max_response_chars.saturating_add(1).saturating_mul(4);
+        let mut raw = Vec::new();
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|e| ToolError::execution(self.name(), e))?
+        {
+            raw.extend_from_slice(&chunk);
+            if raw.len() >= limit {
+                raw.truncate(limit);
+                break;
+            }
+        }
+        let body = String::from_utf8_lossy(&raw);
+        let mut text = format!("HTTP {}\n", status.as_u16());
+        match body.char_indices().nth(self.max_response_chars) {
+            Some((end, _)) => {
+                text.push_str(&body[..end]);
+                text.push_str("\n[truncated]");
+            }
+            None => text.push_str(&body),
+        }
+        Ok(text)
+    }
+}
@@ -0,0 +1,151 @@
+//! A small in-memory key-value store the model can use as a scratchpad
+//! across tool calls and runs.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::tools::{
+    error::ToolError,
+    traits::{ArgSchema, Tool},
+};
+
+/// Keys kept before `set` refuses new ones.
+const DEFAULT_MAX_ENTRIES: usize = 1000;
+/// Longest value accepted, in bytes.
+const DEFAULT_MAX_VALUE_LEN: usize = 16 * 1024;
+
+/// Entries live as long as the tool (clones share them), not on disk.
+#[derive(Debug, Clone)]
+pub struct KeyValueStoreTool {
+    entries: Arc<Mutex<BTreeMap<String, String>>>,
+    max_entries: usize,
+    max_value_len: usize,
+}
+
+impl Default for KeyValueStoreTool {
+    fn default() -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(BTreeMap::new())),
+            max_entries: DEFAULT_MAX_ENTRIES,
+            max_value_len: DEFAULT_MAX_VALUE_LEN,
+        }
+    }
+}
+
+impl KeyValueStoreTool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    pub fn with_max_value_len(mut self, max_value_len: usize) -> Self {
+        self.max_value_len = max_value_len;
+        self
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.entries.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).get(key).cloned()
+    }
+
+    /// A copy of all entries, in key order.
+    pub fn entries(&self) -> BTreeMap<String, String> {
+        self.entries.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Operation {
+    Get,
+    Set,
+    Delete,
+    List,
+}
+
+#[derive(Deserialize)]
+struct KeyValueParams {
+    operation: Operation,
+    #[serde(default)]
+    key: Option<String>,
+    #[serde(default)]
+    value: Option<String>,
+}
+
+#[async_trait::async_trait]
+impl Tool for KeyValueStoreTool {
+    fn name(&self) -> &str {
+        "kv_store"
+    }
+
+    fn description(&self) -> &str {
+        "Remember short notes between steps. Operations: 'set' stores value under key, 'get' reads key, \
+'delete' removes key, 'list' returns all keys."
+    }
+
+    fn args(&self) -> Vec<ArgSchema> {
+        vec![
+            ArgSchema {
+                name: "operation".into(),
+                arg_type: "string".into(),
+                description: "One of get, set, delete, list".into(),
+                required: true,
+                schema: Some(json!({ "enum": ["get", "set", "delete", "list"] })),
+            },
+            ArgSchema {
+                name: "key".into(),
+                arg_type: "string".into(),
+                description: "The key; required except for list".into(),
+                required: false,
+                schema: None,
+            },
+            ArgSchema {
+                name: "value".into(),
+                arg_type: "string".into(),
+                description: "The value to store; only for set".into(),
+                required: false,
+                schema: None,
+            },
+        ]
+    }
+
+    async fn run(&self, input: Value) -> Result<String, ToolError> {
+        let params: KeyValueParams = serde_json::from_value(input)
+            .map_err(|e| ToolError::ParamsNotMatched(e.to_string()))?;
+        let mut entries = self.entries.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let output = match (params.operation, params.key) {
+            (Operation::List, _) => {
+                let keys: Vec<&String> = entries.keys().collect();
+                json!(keys).to_string()
+            }
+            (_, None) => return Err(ToolError::ParamsNotMatched("missing field `key`".to_string())),
+            (Operation::Get, Some(key)) => match entries.get(&key) {
+                Some(value) => value.clone(),
+                None => format!("NOT FOUND: no value for '{}'", key),
+            },
+            (Operation::Set, Some(key)) => {
+                let Some(value) = params.value else {
+                    return Err(ToolError::ParamsNotMatched("missing field `value`".to_string()));
+                };
+                if value.len() > self.max_value_len {
+                    format!("FAILED: value is longer than {} bytes", self.max_value_len)
+                } else if !entries.contains_key(&key) && entries.len() >= self.max_entries {
+                    format!("FAILED: the store is full ({} keys); delete one first", self.max_entries)
+                } else {
+                    entries.insert(key.clone(), value);
+                    format!("OK: stored '{}'", key)
+                }
+            }
+            (Operation::Delete, Some(key)) => match entries.remove(&key) {
+                Some(_) => format!("OK: deleted '{}'", key),
+                None => format!("NOT FOUND: no value for '{}'", key),
+            },
+        };
+        Ok(output)
+    }
+}
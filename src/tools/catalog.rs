@@ -0,0 +1,43 @@
+//! Ready-made tools for getting an agent going before writing your own.
+//!
+//! ```ignore
+//! let mut agent = Agent::new("assistant", llm, None);
+//! agent.register_tools(catalog::standard())?;
+//! let result = agent.call_llm("What is 17% of 2,340?").await?;
+//! ```
+//!
+//! `standard()` only contains tools that run locally without filesystem
+//! access, so it registers under `ToolPolicy::sandboxed()`. The HTTP tool
+//! needs the `catalog-http` feature and an explicit host allowlist.
+
+use std::sync::Arc;
+
+use crate::tools::builtin::{
+    calculator::CalculatorTool,
+    datetime::DateTimeTool,
+    kv_store::KeyValueStoreTool,
+};
+#[cfg(feature = "catalog-http")]
+use crate::tools::builtin::http_get::HttpGetTool;
+use crate::tools::traits::Tool;
+
+/// Calculator, current date/time and an in-memory key-value store.
+pub fn standard() -> Vec<Arc<dyn Tool>> {
+    vec![
+        Arc::new(CalculatorTool),
+        Arc::new(DateTimeTool::new()),
+        Arc::new(KeyValueStoreTool::new()),
+    ]
+}
+
+/// `standard()` plus `http_get` restricted to `allowed_hosts`.
+#[cfg(feature = "catalog-http")]
+pub fn standard_with_http<I, S>(allowed_hosts: I) -> Vec<Arc<dyn Tool>>
+where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+{
+    let mut tools = standard();
+    tools.push(Arc::new(HttpGetTool::new(allowed_hosts)));
+    tools
+}
@@ -1,5 +1,8 @@
+use std::error::Error as StdError;
 use crate::llm::error::LLMError;
 
+/// Boxed underlying cause of a tool failure.
+pub type BoxError = Box<dyn StdError + Send + Sync + 'static>;
 
 #[derive(Debug, thiserror::Error)]
 pub enum ToolError {
@@ -10,6 +13,8 @@ pub enum ToolError {
     ExecutionError {
         name: String,
         reason: String,
+        #[source]
+        source: Option<BoxError>,
     },
 
     #[error("LLM error: {0}")]
@@ -21,3 +26,58 @@ pub enum ToolError {
     #[error("Unknown tool error")]
     Unknown,
 }
+
+impl ToolError {
+    /// Execution failure of tool `name` caused by `err`, which stays
+    /// reachable through `Error::source`.
+    pub fn execution(name: impl Into<String>, err: impl Into<BoxError>) -> Self {
+        let source = err.into();
+        ToolError::ExecutionError {
+            name: name.into(),
+            reason: source.to_string(),
+            source: Some(source),
+        }
+    }
+
+    /// Execution failure of tool `name` without an underlying error.
+    pub fn execution_failed(name: impl Into<String>, reason: impl Into<String>) -> Self {
+        ToolError::ExecutionError {
+            name: name.into(),
+            reason: reason.into(),
+            source: None,
+        }
+    }
+}
+
+/// Turn any error into a `ToolError::ExecutionError` that keeps the cause.
+///
+/// ```ignore
+/// let text = std::fs::read_to_string(&path)
+///     .with_context("read_file", || format!("reading {}", path.display()))?;
+/// ```
+pub trait ToolResultExt<T> {
+    fn with_context<C, F>(self, name: &str, context: F) -> Result<T, ToolError>
+    where
+        C: std::fmt::Display,
+        F: FnOnce() -> C;
+}
+
+impl<T, E> ToolResultExt<T> for Result<T, E>
+where
+    E: Into<BoxError>,
+{
+    fn with_context<C, F>(self, name: &str, context: F) -> Result<T, ToolError>
+    where
+        C: std::fmt::Display,
+        F: FnOnce() -> C,
+    {
+        self.map_err(|err| {
+            let source = err.into();
+            ToolError::ExecutionError {
+                name: name.to_string(),
+                reason: format!("{}: {}", context(), source),
+                source: Some(source),
+            }
+        })
+    }
+}
@@ -0,0 +1,59 @@
+//! What a tool run produces: text, or bytes such as a screenshot.
+//!
+//! Image output is shown to vision-capable models: the tool result says an
+//! image was returned and the agent attaches it to a user message after the
+//! turn's tool results. Other binary output, and images for models without
+//! vision, are described in the tool result instead.
+
+use crate::message::ImageContent;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToolOutput {
+    Text(String),
+    Binary {
+        /// e.g. `image/png` or `application/pdf`.
+        mime_type: String,
+        data: Vec<u8>,
+    },
+}
+
+impl ToolOutput {
+    pub fn binary(mime_type: impl Into<String>, data: Vec<u8>) -> Self {
+        ToolOutput::Binary {
+            mime_type: mime_type.into(),
+            data,
+        }
+    }
+
+    pub fn is_image(&self) -> bool {
+        matches!(self, ToolOutput::Binary { mime_type, .. } if mime_type.starts_with("image/"))
+    }
+
+    /// The output as a message image, `None` unless it is an image.
+    pub fn to_image(&self) -> Option<ImageContent> {
+        match self {
+            ToolOutput::Binary { mime_type, data } if self.is_image() => Some(ImageContent::from_bytes(mime_type.clone(), data)),
+            _ => None,
+        }
+    }
+
+    /// Text standing in for the output in the tool result.
+    pub fn describe(&self) -> String {
+        match self {
+            ToolOutput::Text(text) => text.clone(),
+            ToolOutput::Binary { mime_type, data } => format!("{} ({} bytes)", mime_type, data.len()),
+        }
+    }
+}
+
+impl From<String> for ToolOutput {
+    fn from(text: String) -> Self {
+        ToolOutput::Text(text)
+    }
+}
+
+impl From<&str> for ToolOutput {
+    fn from(text: &str) -> Self {
+        ToolOutput::Text(text.to_string())
+    }
+}
@@ -0,0 +1,124 @@
+//! What a tool needs from its environment, and what an agent allows.
+//!
+//! The agent checks a tool's [`ToolRequirements`] against its [`ToolPolicy`]
+//! when the tool is registered, so a misconfigured agent fails at setup
+//! instead of on the first call.
+
+use serde::{Serialize, Deserialize};
+
+/// Rough price of running a tool once, ordered from cheapest.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CostTier {
+    #[default]
+    Free,
+    Low,
+    Medium,
+    High,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolRequirements {
+    /// Makes network requests.
+    pub network: bool,
+    /// Reads or writes local files (or runs processes that do).
+    pub filesystem: bool,
+    /// Should only run after a human approved the call.
+    pub approval: bool,
+    pub cost: CostTier,
+}
+
+impl ToolRequirements {
+    pub fn with_network(mut self) -> Self {
+        self.network = true;
+        self
+    }
+
+    pub fn with_filesystem(mut self) -> Self {
+        self.filesystem = true;
+        self
+    }
+
+    pub fn with_approval(mut self) -> Self {
+        self.approval = true;
+        self
+    }
+
+    pub fn with_cost(mut self, cost: CostTier) -> Self {
+        self.cost = cost;
+        self
+    }
+}
+
+/// What an agent lets its tools do. The default allows everything.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolPolicy {
+    pub allow_network: bool,
+    pub allow_filesystem: bool,
+    /// Whether the agent can get calls approved. Tools that need approval
+    /// are rejected when it can't.
+    pub approval_available: bool,
+    pub max_cost: CostTier,
+}
+
+impl Default for ToolPolicy {
+    fn default() -> Self {
+        Self {
+            allow_network: true,
+            allow_filesystem: true,
+            approval_available: true,
+            max_cost: CostTier::High,
+        }
+    }
+}
+
+impl ToolPolicy {
+    /// Nothing beyond pure computation: no network, no filesystem, no
+    /// approval, free tools only.
+    pub fn sandboxed() -> Self {
+        Self {
+            allow_network: false,
+            allow_filesystem: false,
+            approval_available: false,
+            max_cost: CostTier::Free,
+        }
+    }
+
+    pub fn with_network(mut self, allow: bool) -> Self {
+        self.allow_network = allow;
+        self
+    }
+
+    pub fn with_filesystem(mut self, allow: bool) -> Self {
+        self.allow_filesystem = allow;
+        self
+    }
+
+    pub fn with_approval(mut self, available: bool) -> Self {
+        self.approval_available = available;
+        self
+    }
+
+    pub fn with_max_cost(mut self, max_cost: CostTier) -> Self {
+        self.max_cost = max_cost;
+        self
+    }
+
+    /// Every requirement the policy does not satisfy, e.g. `["network"]`.
+    pub fn violations(&self, requirements: &ToolRequirements) -> Vec<String> {
+        let mut out = Vec::new();
+        if requirements.network && !self.allow_network {
+            out.push("network access".to_string());
+        }
+        if requirements.filesystem && !self.allow_filesystem {
+            out.push("filesystem access".to_string());
+        }
+        if requirements.approval && !self.approval_available {
+            out.push("human approval".to_string());
+        }
+        if requirements.cost > self.max_cost {
+            out.push(format!("cost tier {:?} (max {:?})", requirements.cost, self.max_cost));
+        }
+        out
+    }
+}
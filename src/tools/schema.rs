@@ -1,16 +1,214 @@
+use std::collections::BTreeMap;
 use serde::{Serialize, Deserialize};
+use serde_json::{json, Map, Value};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ArgSchema {
     pub name: String,
     pub arg_type: String,
     pub description: String,
     pub required: bool,
+    /// Extra JSON Schema keywords for the argument, e.g. `items` for arrays
+    /// or `additionalProperties` for maps.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<Value>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ToolSchema {
     pub name: String,
     pub description: String,
     pub args: Vec<ArgSchema>,
+    /// Guarantee well-formed arguments. Providers that enforce schemas (OpenAI
+    /// structured outputs) get `strict_parameters`; the agent also checks
+    /// every call with `validate_args` before running the tool.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub strict: bool,
+}
+
+impl ToolSchema {
+    /// JSON Schema of the arguments, as native function calling APIs expect
+    /// in `parameters`.
+    pub fn parameters(&self) -> Value {
+        let mut properties = Map::new();
+        for arg in self.args.iter() {
+            let mut property = json!({ "type": arg.arg_type, "description": arg.description });
+            if let (Some(Value::Object(extra)), Value::Object(property)) = (arg.schema.as_ref(), &mut property) {
+                for (key, value) in extra {
+                    property.entry(key.clone()).or_insert_with(|| value.clone());
+                }
+            }
+            properties.insert(arg.name.clone(), property);
+        }
+        let required: Vec<&str> = self
+            .args
+            .iter()
+            .filter(|arg| arg.required)
+            .map(|arg| arg.name.as_str())
+            .collect();
+        json!({
+            "type": "object",
+            "properties": properties,
+            "required": required,
+        })
+    }
+
+    /// The parameters in the form strict mode requires: every argument
+    /// listed in `required` (optional ones may be `null` instead) and no
+    /// additional properties in any object. `None` when an argument is a map,
+    /// which strict mode can't describe.
+    pub fn strict_parameters(&self) -> Option<Value> {
+        let mut parameters = self.parameters();
+        for arg in self.args.iter().filter(|arg| !arg.required) {
+            if let Some(property) = parameters["properties"].get_mut(&arg.name) {
+                property["type"] = json!([arg.arg_type, "null"]);
+            }
+        }
+        close_schema(&mut parameters).then_some(parameters)
+    }
+
+    /// Check `args` against the schema: an object with every required
+    /// argument, no unknown ones, values of the declared types (and `enum`
+    /// values, where given). Optional arguments may be `null`.
+    pub fn validate_args(&self, args: &Value) -> Result<(), String> {
+        let Some(object) = args.as_object() else {
+            return Err(format!("arguments must be a JSON object, got {}", args));
+        };
+        if let Some(unknown) = object.keys().find(|key| !self.args.iter().any(|arg| &arg.name == *key)) {
+            return Err(format!("unknown argument `{}`", unknown));
+        }
+        for arg in self.args.iter() {
+            let value = match object.get(&arg.name) {
+                None | Some(Value::Null) if arg.required => {
+                    return Err(format!("missing required argument `{}`", arg.name));
+                }
+                None | Some(Value::Null) => continue,
+                Some(value) => value,
+            };
+            if !type_matches(&arg.arg_type, value) {
+                return Err(format!("argument `{}` must be of type {}, got {}", arg.name, arg.arg_type, value));
+            }
+            let extra = arg.schema.as_ref();
+            if let Some(Value::Array(allowed)) = extra.and_then(|schema| schema.get("enum"))
+                && !allowed.contains(value)
+            {
+                return Err(format!("argument `{}` must be one of {}, got {}", arg.name, Value::from(allowed.clone()), value));
+            }
+            if let (Value::Array(items), Some(item_type)) = (value, extra.and_then(|schema| schema.pointer("/items/type")).and_then(Value::as_str))
+                && let Some(item) = items.iter().find(|item| !type_matches(item_type, item))
+            {
+                return Err(format!("items of `{}` must be of type {}, got {}", arg.name, item_type, item));
+            }
+        }
+        Ok(())
+    }
+
+    /// Use the descriptions of the translation matching `locale`, if any.
+    /// Arguments the translation doesn't cover keep their description.
+    pub fn localize(&mut self, translations: &[ToolTranslation], locale: &str) {
+        let Some(translation) = ToolTranslation::select(translations, locale) else {
+            return;
+        };
+        self.description = translation.description.clone();
+        for arg in self.args.iter_mut() {
+            if let Some(description) = translation.args.get(&arg.name) {
+                arg.description = description.clone();
+            }
+        }
+    }
+}
+
+/// Tool and argument descriptions in another language, so models prompted
+/// in that language pick and fill the tool more reliably.
+///
+/// ```ignore
+/// ToolTranslation::new("fr", "Donne la météo d'une ville")
+///     .with_arg("city", "Le nom de la ville")
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolTranslation {
+    /// BCP 47 language tag, e.g. `"fr"` or `"pt-BR"`.
+    pub locale: String,
+    pub description: String,
+    /// Descriptions by argument name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub args: BTreeMap<String, String>,
+}
+
+impl ToolTranslation {
+    pub fn new(locale: impl Into<String>, description: impl Into<String>) -> Self {
+        Self {
+            locale: locale.into(),
+            description: description.into(),
+            args: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_arg(mut self, name: impl Into<String>, description: impl Into<String>) -> Self {
+        self.args.insert(name.into(), description.into());
+        self
+    }
+
+    /// The translation for `locale`: an exact match (ignoring case and
+    /// `_`/`-`), otherwise one for the same language, so `"fr-CA"` falls
+    /// back to `"fr"` or `"fr-FR"`.
+    pub fn select<'a>(translations: &'a [ToolTranslation], locale: &str) -> Option<&'a ToolTranslation> {
+        let wanted = normalize_locale(locale);
+        let language = |tag: &str| tag.split('-').next().unwrap_or_default().to_string();
+        translations
+            .iter()
+            .find(|t| normalize_locale(&t.locale) == wanted)
+            .or_else(|| {
+                translations
+                    .iter()
+                    .find(|t| language(&normalize_locale(&t.locale)) == language(&wanted))
+            })
+    }
+}
+
+/// Require every property and forbid others in each object of `schema`.
+/// False when an object has no fixed `properties` (a map).
+fn close_schema(schema: &mut Value) -> bool {
+    let Some(object) = schema.as_object_mut() else {
+        return true;
+    };
+    let is_object = match object.get("type") {
+        Some(Value::String(kind)) => kind == "object",
+        Some(Value::Array(kinds)) => kinds.iter().any(|kind| kind == "object"),
+        _ => false,
+    };
+    if is_object {
+        let Some(Value::Object(properties)) = object.get_mut("properties") else {
+            return false;
+        };
+        if !properties.values_mut().all(close_schema) {
+            return false;
+        }
+        let names: Vec<Value> = properties.keys().cloned().map(Value::from).collect();
+        object.insert("required".to_string(), Value::from(names));
+        object.insert("additionalProperties".to_string(), Value::Bool(false));
+    }
+    match object.get_mut("items") {
+        Some(items) => close_schema(items),
+        None => true,
+    }
+}
+
+/// Whether `value` is of the JSON Schema `kind`. Unknown kinds accept
+/// anything.
+fn type_matches(kind: &str, value: &Value) -> bool {
+    match kind {
+        "string" => value.is_string(),
+        "integer" => value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|n| n.fract() == 0.0),
+        "number" => value.is_number(),
+        "boolean" => value.is_boolean(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+fn normalize_locale(locale: &str) -> String {
+    locale.trim().replace('_', "-").to_ascii_lowercase()
 }
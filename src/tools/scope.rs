@@ -0,0 +1,74 @@
+//! Run several tool calls concurrently as one unit.
+//!
+//! Every call is a task in a `JoinSet` owned by the scope. The first failure
+//! aborts the remaining calls, and dropping the scope (e.g. the agent run was
+//! cancelled) aborts everything still running. A panicking tool becomes a
+//! `ToolError` for that call instead of unwinding through the agent.
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::Arc;
+use serde_json::Value;
+use tokio::task::{Id, JoinError, JoinSet};
+
+use crate::tools::{error::ToolError, output::ToolOutput, traits::Tool};
+
+/// A failed call and its position in the input.
+#[derive(Debug)]
+pub struct ScopeError {
+    pub index: usize,
+    pub error: ToolError,
+}
+
+/// Run `calls` (tool name, tool, args) concurrently.
+///
+/// Returns the outputs in input order, or the first error. Sibling calls still
+/// running when an error arrives are aborted.
+pub async fn run_scoped(calls: Vec<(String, Arc<dyn Tool>, Value)>) -> Result<Vec<ToolOutput>, ScopeError> {
+    let mut set = JoinSet::new();
+    let mut tasks: HashMap<Id, (usize, String)> = HashMap::new();
+    for (index, (name, tool, args)) in calls.into_iter().enumerate() {
+        let handle = set.spawn(async move { tool.run_output(args).await });
+        tasks.insert(handle.id(), (index, name));
+    }
+
+    let mut outputs: Vec<Option<ToolOutput>> = vec![None; tasks.len()];
+    while let Some(joined) = set.join_next_with_id().await {
+        let (id, result) = match joined {
+            Ok((id, result)) => (id, result),
+            Err(e) => {
+                let id = e.id();
+                let name = tasks.get(&id).map(|(_, name)| name.clone()).unwrap_or_default();
+                (id, Err(join_error(&name, e)))
+            }
+        };
+        let index = tasks.get(&id).map(|(index, _)| *index).unwrap_or_default();
+        match result {
+            Ok(output) => outputs[index] = Some(output),
+            Err(error) => {
+                set.abort_all();
+                return Err(ScopeError { index, error });
+            }
+        }
+    }
+    Ok(outputs.into_iter().map(|output| output.unwrap_or(ToolOutput::Text(String::new()))).collect())
+}
+
+fn join_error(name: &str, error: JoinError) -> ToolError {
+    if error.is_panic() {
+        let message = panic_message(error.into_panic());
+        ToolError::execution_failed(name, format!("tool panicked: {}", message))
+    } else {
+        ToolError::execution_failed(name, "tool was cancelled")
+    }
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| "unknown panic".to_string()),
+    }
+}
@@ -1,4 +1,6 @@
-use crate::llm::tokens::TokenUsage;
+use crate::llm::{error::LLMError, partial_json::parse_partial, tokens::TokenUsage, CallInfo, FinishReason, GenerateResult, LLMResult};
+use futures::{StreamExt, stream::BoxStream};
+use serde::{Serialize, Deserialize};
 use serde_json::Value;
 use std::io::{self, Write};
 
@@ -7,6 +9,112 @@ pub struct StreamData {
     pub value: Value,
     pub tokens: Option<TokenUsage>,
     pub content: String,
+    /// Thinking text of this chunk, kept out of `content`; see
+    /// `llm::reasoning`.
+    pub reasoning: String,
+    /// Why generation stopped; set on the terminal item.
+    pub finish_reason: Option<FinishReason>,
+    /// Marks the terminal item. Providers emit exactly one at the end of a
+    /// complete response; a stream that ends without it was cut off.
+    pub done: bool,
+    /// Native tool calls as the provider streams them, see `ToolCallDeltas`.
+    pub tool_calls: Vec<ToolCallDelta>,
+}
+
+/// A piece of a native tool call, in the OpenAI streaming shape: the first
+/// delta of a call carries its id and name, later ones append argument text.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ToolCallDelta {
+    /// Which call of the response this belongs to.
+    pub index: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// The next fragment of the JSON arguments.
+    #[serde(default)]
+    pub arguments: String,
+}
+
+impl ToolCallDelta {
+    /// A complete call as a single delta, for providers that don't split
+    /// calls up.
+    pub fn whole(index: usize, call: &CallInfo) -> Self {
+        Self {
+            index,
+            id: call.id.clone(),
+            name: Some(call.name.clone()),
+            arguments: call.args.to_string(),
+        }
+    }
+}
+
+/// Tool calls assembled from streamed `ToolCallDelta`s.
+///
+/// `partial_args` parses the arguments received so far, so a consumer can
+/// validate a call (e.g. the tool name or a required field) before the
+/// generation finishes.
+#[derive(Debug, Clone, Default)]
+pub struct ToolCallDeltas {
+    calls: Vec<ToolCallDelta>,
+}
+
+impl ToolCallDeltas {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, delta: &ToolCallDelta) {
+        match self.calls.iter_mut().find(|call| call.index == delta.index) {
+            Some(call) => {
+                if delta.id.is_some() {
+                    call.id = delta.id.clone();
+                }
+                if delta.name.is_some() {
+                    call.name = delta.name.clone();
+                }
+                call.arguments.push_str(&delta.arguments);
+            }
+            None => self.calls.push(delta.clone()),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.calls.is_empty()
+    }
+
+    /// The calls so far, with the raw argument text received for each.
+    pub fn calls(&self) -> &[ToolCallDelta] {
+        &self.calls
+    }
+
+    /// Best-effort arguments of call `index` so far.
+    pub fn partial_args(&self, index: usize) -> Option<Value> {
+        let call = self.calls.iter().find(|call| call.index == index)?;
+        parse_partial(&call.arguments)
+    }
+
+    /// The completed calls. Arguments that are not valid JSON are kept as a
+    /// string for the agent to report.
+    pub fn finish(&self) -> Vec<CallInfo> {
+        let mut calls = self.calls.clone();
+        calls.sort_by_key(|call| call.index);
+        calls
+            .into_iter()
+            .map(|call| {
+                let args = if call.arguments.trim().is_empty() {
+                    Value::Object(Default::default())
+                } else {
+                    serde_json::from_str(&call.arguments).unwrap_or(Value::String(call.arguments))
+                };
+                CallInfo {
+                    id: call.id,
+                    name: call.name.unwrap_or_default(),
+                    args,
+                }
+            })
+            .collect()
+    }
 }
 
 
@@ -16,6 +124,31 @@ impl StreamData {
             value,
             tokens,
             content: content.into(),
+            reasoning: String::new(),
+            finish_reason: None,
+            done: false,
+            tool_calls: Vec::new(),
+        }
+    }
+
+    /// An item carrying only tool-call deltas.
+    pub fn tool_calls(value: Value, tool_calls: Vec<ToolCallDelta>) -> Self {
+        Self {
+            tool_calls,
+            ..Self::new(value, None, "")
+        }
+    }
+
+    /// The terminal item of a completed stream, carrying the final usage.
+    pub fn done(final_usage: Option<TokenUsage>, finish_reason: Option<FinishReason>) -> Self {
+        Self {
+            value: Value::Null,
+            tokens: final_usage,
+            content: String::new(),
+            reasoning: String::new(),
+            finish_reason,
+            done: true,
+            tool_calls: Vec::new(),
         }
     }
 
@@ -26,3 +159,38 @@ impl StreamData {
         handle.flush()
     }
 }
+
+/// Drain a stream into a `GenerateResult`.
+///
+/// Fails with `InvalidResponse` if the stream ends without its done marker,
+/// so a dropped connection is not mistaken for a short answer.
+pub async fn collect_stream(mut stream: BoxStream<'_, LLMResult<StreamData>>) -> LLMResult<GenerateResult> {
+    let mut result = GenerateResult::default();
+    let mut tool_calls = ToolCallDeltas::new();
+    let mut reasoning = String::new();
+    while let Some(item) = stream.next().await {
+        let data = item?;
+        result.generation.push_str(&data.content);
+        reasoning.push_str(&data.reasoning);
+        if let Some(tokens) = data.tokens {
+            result.tokens = tokens;
+        }
+        for delta in &data.tool_calls {
+            tool_calls.push(delta);
+        }
+        if data.done {
+            result.finish_reason = data.finish_reason;
+            result.reasoning = (!reasoning.is_empty()).then_some(reasoning);
+            result.tool_calls = if tool_calls.is_empty() {
+                crate::llm::extract_tool_calls(&result.generation)
+            } else {
+                tool_calls.finish()
+            };
+            return Ok(result);
+        }
+    }
+    Err(LLMError::InvalidResponse(format!(
+        "stream ended before completion after {} bytes",
+        result.generation.len()
+    )))
+}
@@ -1,7 +1,9 @@
 use super::error::ToolError;
+use super::output::ToolOutput;
+use super::requirements::ToolRequirements;
 
 // re-export ArgSchema for macros use
-pub use super::schema::ArgSchema;
+pub use super::schema::{ArgSchema, ToolTranslation};
 
 #[async_trait::async_trait]
 pub trait Tool: Send + Sync {
@@ -9,4 +11,34 @@ pub trait Tool: Send + Sync {
     fn description(&self) -> &str;
     fn args(&self) -> Vec<ArgSchema>;
     async fn run(&self, input: serde_json::Value) -> Result<String, ToolError>;
-}
\ No newline at end of file
+
+    /// Run the tool for the agent. Override to return binary output such as
+    /// a screenshot; `run` then only serves callers that want text. Defaults
+    /// to the text of `run`.
+    async fn run_output(&self, input: serde_json::Value) -> Result<ToolOutput, ToolError> {
+        self.run(input).await.map(ToolOutput::Text)
+    }
+
+    /// Version of the tool's behaviour, recorded in each run's `ReproBundle`.
+    fn version(&self) -> Option<&str> {
+        None
+    }
+
+    /// `description` and the argument descriptions in other languages. An
+    /// agent with a locale shows the model the matching one. Defaults to
+    /// none.
+    fn translations(&self) -> Vec<ToolTranslation> {
+        Vec::new()
+    }
+
+    /// Whether the tool's schema is strict (see `ToolSchema::strict`), so it
+    /// never runs with arguments that don't match `args`. Defaults to `false`.
+    fn strict(&self) -> bool {
+        false
+    }
+
+    /// What the tool needs from the agent's environment. Defaults to nothing.
+    fn requirements(&self) -> ToolRequirements {
+        ToolRequirements::default()
+    }
+}
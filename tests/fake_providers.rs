@@ -0,0 +1,1459 @@
+//! Provider backends against `testing::FakeServer`.
+//!
+//! Run with `cargo test --features testing`.
+#![cfg(feature = "testing")]
+
+use std::sync::Arc;
+use futures::{FutureExt, StreamExt, future::BoxFuture};
+use serde_json::json;
+use mini_langchain::{
+    *,
+    agent::{
+        error::AgentError,
+        repro::ReproBundle,
+        types::Agent,
+        traits::AgentRunner,
+    },
+    llm::{
+        anthropic::Anthropic,
+        compatible::OpenAICompatible,
+        cache::{CachedLLM, MemoryCache},
+        capabilities::{Capabilities, WithCapabilities},
+        cohere::Cohere,
+        cassette::{record_or_replay, ReplayLLM},
+        cost::{CostMeteredLLM, Pricing, PricingTable},
+        error::LLMError,
+        grok::Grok,
+        layered::LayeredLLM,
+        middleware::{FallbackLLM, LLMMiddleware, LLMRequest, RetryLLM, RetryPolicy},
+        mistral::Mistral,
+        mock::MockLLM,
+        models::{ModelCatalog, ModelInfo},
+        ollama::{Ollama, OllamaClient},
+        openai::{BuiltinTool, OpenAI, OpenAIApi, ReasoningEffort},
+        throttle::{ProviderThrottle, ThrottleConfig, ThrottledLLM},
+        options::GenerateOptions,
+        tokens::TokenUsage,
+        pool::PoolConfig,
+        quota::{QuotaGuard, QuotaLLM},
+        warnings::RunWarning,
+        traits::LLM,
+        FinishReason,
+        GenerateResult,
+        LLMResult,
+    },
+    message::{ImageContent, Message, MessageRole},
+    prompt::datetime::TimeContext,
+    testing::{FakeResponse, FakeServer},
+    tools::{
+        builtin::code_edit::{apply_unified_diff, CodeEditTool},
+        catalog,
+        error::ToolError,
+        output::ToolOutput,
+        requirements::ToolPolicy,
+        stream::{collect_stream, StreamData, ToolCallDeltas},
+        traits::{ArgSchema, Tool},
+    },
+};
+
+#[tool(
+    name = "get_weather",
+    description = "Get weather for a given city",
+    params(city = "City name, e.g. 'San Francisco'"),
+    locale("fr", description = "Donne la météo d'une ville", params(city = "Nom de la ville, p. ex. 'Lyon'")),
+    locale("pt-BR", description = "Obtém o clima de uma cidade")
+)]
+fn get_weather(city: String) -> String {
+    format!("It's always sunny in {}!", city)
+}
+
+static ALARMS_SET: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+#[tool(
+    strict,
+    name = "set_alarm",
+    description = "Set an alarm",
+    params(hour = "Hour of the day, 0-23", label = "What the alarm is for")
+)]
+fn set_alarm(hour: u32, label: String) -> String {
+    ALARMS_SET.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+    format!("Alarm '{}' set for {}:00", label, hour)
+}
+
+const CHAT_PATH: &str = "/v1/chat/completions";
+
+/// Returns a (truncated) PNG, like a browser screenshot tool would.
+struct ScreenshotTool;
+
+#[async_trait::async_trait]
+impl Tool for ScreenshotTool {
+    fn name(&self) -> &str {
+        "screenshot"
+    }
+
+    fn description(&self) -> &str {
+        "Take a screenshot of the current page"
+    }
+
+    fn args(&self) -> Vec<ArgSchema> {
+        Vec::new()
+    }
+
+    async fn run(&self, _input: serde_json::Value) -> Result<String, ToolError> {
+        Ok("screenshot taken".to_string())
+    }
+
+    async fn run_output(&self, _input: serde_json::Value) -> Result<ToolOutput, ToolError> {
+        Ok(ToolOutput::binary("image/png", b"\x89PNG\r\n".to_vec()))
+    }
+}
+
+#[tokio::test]
+async fn compatible_generate_sends_model_and_messages() {
+    let server = FakeServer::start().await;
+    server.mock("POST", CHAT_PATH, FakeResponse::openai_chat("Hello!"));
+
+    let llm = OpenAICompatible::new(server.url("/v1"), "test-model").with_api_key("sk-test");
+    let result = llm.generate(&[Message::user("Hi")]).await.expect("generate");
+
+    assert_eq!(result.generation, "Hello!");
+    assert_eq!(result.finish_reason, Some(FinishReason::Stop));
+    assert_eq!(result.tokens.total_tokens, 15);
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    let body = requests[0].json();
+    assert_eq!(body["model"], "test-model");
+    assert_eq!(body["messages"][0]["content"], "Hi");
+    assert_eq!(requests[0].headers["authorization"], "Bearer sk-test");
+}
+
+#[tokio::test]
+async fn per_call_options_override_configured_ones() {
+    let server = FakeServer::start().await;
+    server.mock("POST", CHAT_PATH, FakeResponse::openai_chat("Hello!"));
+
+    let llm = OpenAICompatible::new(server.url("/v1"), "test-model").with_temperature(0.9);
+    let options = GenerateOptions::new()
+        .with_temperature(0.0)
+        .with_max_tokens(64)
+        .with_stop(["\n\n"]);
+    llm.generate_with(&[Message::user("Hi")], &options).await.expect("generate");
+    llm.generate(&[Message::user("Hi")]).await.expect("generate");
+
+    let requests = server.requests();
+    let body = requests[0].json();
+    assert_eq!(body["temperature"], 0.0);
+    assert_eq!(body["max_tokens"], 64);
+    assert_eq!(body["stop"], json!(["\n\n"]));
+    assert!(body.get("top_p").is_none());
+    let body = requests[1].json();
+    assert!((body["temperature"].as_f64().unwrap() - 0.9).abs() < 1e-6);
+    assert!(body.get("stop").is_none());
+}
+
+#[tokio::test]
+async fn generate_batch_returns_one_result_per_conversation() {
+    let server = FakeServer::start().await;
+    server.mock("POST", CHAT_PATH, FakeResponse::openai_chat("positive"));
+
+    let llm = OpenAICompatible::new(server.url("/v1"), "test-model");
+    let batches: Vec<Vec<Message>> = ["great", "fine", "awful"]
+        .iter()
+        .map(|review| vec![Message::user(format!("Classify: {}", review))])
+        .collect();
+    let results = llm.generate_batch(&batches).await;
+    assert_eq!(results.len(), 3);
+    assert!(results.iter().all(|result| result.as_ref().expect("generate").generation == "positive"));
+
+    let mut sent: Vec<String> = server
+        .requests()
+        .iter()
+        .map(|request| request.json()["messages"][0]["content"].as_str().unwrap_or_default().to_string())
+        .collect();
+    sent.sort();
+    assert_eq!(sent, ["Classify: awful", "Classify: fine", "Classify: great"]);
+}
+
+#[tokio::test]
+async fn think_blocks_are_routed_to_reasoning() {
+    let server = FakeServer::start().await;
+    let thinking = r#"<think>Maybe {"tool_calls": [{"name": "get_weather", "args": {}}]}? No, just answer.</think>
+
+It's 4."#;
+    server
+        .mock("POST", CHAT_PATH, FakeResponse::openai_chat(thinking))
+        .mock("POST", CHAT_PATH, FakeResponse::openai_chat_stream(&["<thi", "nk>Add them.</th", "ink>\n\nIt's", " 4."]));
+
+    let llm = OpenAICompatible::new(server.url("/v1"), "qwen3");
+    let result = llm.generate(&[Message::user("2 + 2?")]).await.expect("generate");
+    assert_eq!(result.generation, "It's 4.");
+    assert!(result.tool_calls.is_empty(), "parsed a call from the reasoning: {:?}", result.tool_calls);
+    assert!(result.reasoning.expect("reasoning").starts_with("Maybe"));
+
+    let streamed = collect_stream(llm.stream(&[Message::user("2 + 2?")])).await.expect("stream");
+    assert_eq!(streamed.generation, "It's 4.");
+    assert_eq!(streamed.reasoning.as_deref(), Some("Add them."));
+}
+
+#[tokio::test]
+async fn compatible_stream_ends_with_done_marker() {
+    let server = FakeServer::start().await;
+    server.mock("POST", CHAT_PATH, FakeResponse::openai_chat_stream(&["Hel", "lo"]));
+
+    let llm = OpenAICompatible::new(server.url("/v1"), "test-model");
+    let messages = [Message::user("Hi")];
+    let items: Vec<_> = llm.stream(&messages).collect().await;
+    let last = items.last().expect("items").as_ref().expect("ok item");
+    assert!(last.done);
+    assert_eq!(last.finish_reason, Some(FinishReason::Stop));
+
+    let result = collect_stream(llm.stream(&messages)).await.expect("complete stream");
+    assert_eq!(result.generation, "Hello");
+    assert_eq!(result.tokens.completion_tokens, 2);
+}
+
+#[tokio::test]
+async fn streamed_tool_call_deltas_assemble() {
+    let server = FakeServer::start().await;
+    let chunk = |delta: serde_json::Value, finish_reason: serde_json::Value| {
+        json!({ "choices": [{ "index": 0, "delta": delta, "finish_reason": finish_reason }] }).to_string()
+    };
+    let call = |id: serde_json::Value, name: serde_json::Value, arguments: &str| {
+        json!({ "tool_calls": [{ "index": 0, "id": id, "function": { "name": name, "arguments": arguments } }] })
+    };
+    server.mock(
+        "POST",
+        CHAT_PATH,
+        FakeResponse::sse([
+            chunk(call(json!("call_1"), json!("get_weather"), ""), serde_json::Value::Null),
+            chunk(call(serde_json::Value::Null, serde_json::Value::Null, "{\"city\": \"Par"), serde_json::Value::Null),
+            chunk(call(serde_json::Value::Null, serde_json::Value::Null, "is\"}"), json!("tool_calls")),
+            "[DONE]".to_string(),
+        ]),
+    );
+
+    let llm = OpenAICompatible::new(server.url("/v1"), "test-model").with_native_tools(true);
+    let mut agent = Agent::new("fake", Arc::new(llm.clone()), Some(5));
+    agent.register_tool(None, Arc::new(GetWeatherTool)).expect("register tool");
+    let tools = agent.tool_schemas();
+    let messages = [Message::user("Weather in Paris?")];
+    let mut deltas = ToolCallDeltas::new();
+    let mut stream = llm.stream_with_tools(&messages, &tools);
+    let mut saw_partial = false;
+    while let Some(item) = stream.next().await {
+        for delta in &item.expect("ok item").tool_calls {
+            deltas.push(delta);
+        }
+        saw_partial |= deltas.partial_args(0) == Some(json!({ "city": "Par" }));
+    }
+    assert!(saw_partial);
+    let calls = deltas.finish();
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0].id.as_deref(), Some("call_1"));
+    assert_eq!(calls[0].name, "get_weather");
+    assert_eq!(calls[0].args, json!({ "city": "Paris" }));
+    assert_eq!(server.requests()[0].json()["tools"][0]["function"]["name"], "get_weather");
+
+    let result = collect_stream(llm.stream_with_tools(&messages, &tools)).await.expect("complete stream");
+    assert_eq!(result.tool_calls[0].args, json!({ "city": "Paris" }));
+}
+
+#[tokio::test]
+async fn dropped_stream_is_an_error() {
+    let server = FakeServer::start().await;
+    server.mock(
+        "POST",
+        CHAT_PATH,
+        FakeResponse::openai_chat_stream(&["Hel", "lo", " world"]).disconnect_after(2),
+    );
+
+    let llm = OpenAICompatible::new(server.url("/v1"), "test-model");
+    let messages = [Message::user("Hi")];
+    assert!(collect_stream(llm.stream(&messages)).await.is_err());
+}
+
+#[tokio::test]
+async fn dropping_a_wrapped_stream_closes_the_connection_and_charges_the_budget() {
+    let server = FakeServer::start().await;
+    let piece = "x".repeat(4000);
+    let pieces = vec![piece.as_str(); 100];
+    server.mock(
+        "POST",
+        CHAT_PATH,
+        FakeResponse::openai_chat_stream(&pieces).with_chunk_delay(std::time::Duration::from_millis(10)),
+    );
+
+    let throttle = ProviderThrottle::new(ThrottleConfig { requests_per_minute: None, tokens_per_minute: Some(1000) });
+    let llm = OpenAICompatible::new(server.url("/v1"), "test-model");
+    let llm = ThrottledLLM::new(CostMeteredLLM::new(RetryLLM::new(llm), Pricing::default(), 1.0), throttle.clone());
+    let messages = [Message::user("Hi")];
+    let mut stream = llm.stream(&messages);
+    let mut received = 0;
+    while received < 2 {
+        let data = stream.next().await.expect("item").expect("ok item");
+        received += usize::from(!data.content.is_empty());
+    }
+    assert_eq!(server.open_connections(), 1);
+    drop(stream);
+
+    for _ in 0..100 {
+        if server.open_connections() == 0 {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    assert_eq!(server.open_connections(), 0, "connection left open after drop");
+    // the two received chunks (~2000 tokens) were charged, exhausting the budget
+    assert!(throttle.try_acquire(1).await.is_err());
+}
+
+#[tokio::test]
+async fn rate_limit_carries_retry_after() {
+    let server = FakeServer::start().await;
+    server.mock("POST", CHAT_PATH, FakeResponse::rate_limited(7));
+
+    let llm = OpenAICompatible::new(server.url("/v1"), "test-model");
+    let err = llm.generate(&[Message::user("Hi")]).await.expect_err("rate limited");
+    assert!(err.is_retryable());
+    assert_eq!(err.retry_after(), Some(std::time::Duration::from_secs(7)));
+}
+
+#[tokio::test]
+async fn server_error_is_reported_with_status() {
+    let server = FakeServer::start().await;
+    server.mock("POST", CHAT_PATH, FakeResponse::error(400, "bad request body"));
+
+    let llm = OpenAICompatible::new(server.url("/v1"), "test-model");
+    let err = llm.generate(&[Message::user("Hi")]).await.expect_err("bad request");
+    assert!(matches!(err, LLMError::Api { status: 400, .. }), "{:?}", err);
+    assert!(!err.is_retryable());
+}
+
+#[tokio::test]
+async fn openai_generate_against_api_base() {
+    let server = FakeServer::start().await;
+    server.mock("POST", "/v1/chat/completions", FakeResponse::openai_chat("Hi from OpenAI"));
+
+    let llm = OpenAI::with_api_key("sk-test").with_api_base(server.url("/v1"));
+    let result = llm.generate(&[Message::user("Hi")]).await.expect("generate");
+    assert_eq!(result.generation, "Hi from OpenAI");
+    assert_eq!(result.model.as_deref(), Some("fake-model"));
+}
+
+#[tokio::test]
+async fn openai_sends_stop_sequences_and_seed() {
+    let server = FakeServer::start().await;
+    server.mock("POST", "/v1/chat/completions", FakeResponse::openai_chat("Thought: look it up"));
+
+    let llm = OpenAI::with_api_key("sk-test").with_api_base(server.url("/v1"));
+    let options = GenerateOptions::new().with_stop(["Observation:"]).with_seed(42);
+    llm.generate_with(&[Message::user("Hi")], &options).await.expect("generate");
+    let body = server.requests()[0].json();
+    assert_eq!(body["stop"], json!(["Observation:"]));
+    assert_eq!(body["seed"], 42);
+}
+
+#[tokio::test]
+async fn streamed_calls_take_per_call_options() {
+    let server = FakeServer::start().await;
+    server.mock("POST", "/v1/chat/completions", FakeResponse::openai_chat_stream(&["Thought: look it up"]));
+
+    let llm = OpenAI::with_api_key("sk-test").with_api_base(server.url("/v1"));
+    let options = GenerateOptions::new().with_stop(["Observation:"]).with_temperature(0.0);
+    let streamed = collect_stream(llm.stream_with_options(&[Message::user("Hi")], &[], &options))
+        .await
+        .expect("stream");
+    assert_eq!(streamed.generation, "Thought: look it up");
+    let body = server.requests()[0].json();
+    assert_eq!(body["stop"], json!(["Observation:"]));
+    assert_eq!(body["temperature"], 0.0);
+
+    let retried = RetryLLM::new(MockLLM::new().with_response("done"));
+    collect_stream(retried.stream_with_options(&[Message::user("Hi")], &[], &options))
+        .await
+        .expect("stream");
+    assert_eq!(retried.inner.requests()[0].options.stop, ["Observation:"]);
+}
+
+#[tokio::test]
+async fn stop_sequences_end_a_stream_across_chunks() {
+    let chunks = ["Thought: a", "Obs", "ervation: x", " more"]
+        .map(|chunk| Ok(StreamData::new(json!({}), None, chunk)));
+    let upstream = futures::stream::iter(chunks).chain(futures::stream::once(async {
+        Ok(StreamData::done(None, Some(FinishReason::Length)))
+    }));
+    let options = GenerateOptions::new().with_stop(["Observation:"]);
+    let items: Vec<_> = options
+        .stop_stream(Box::pin(upstream))
+        .map(|item| item.expect("ok item"))
+        .collect()
+        .await;
+    let content: String = items.iter().map(|item| item.content.as_str()).collect();
+    assert_eq!(content, "Thought: a");
+    let last = items.last().expect("items");
+    assert!(last.done);
+    assert_eq!(last.finish_reason, Some(FinishReason::Stop));
+}
+
+#[tokio::test]
+async fn openai_responses_api_maps_function_calls() {
+    let server = FakeServer::start().await;
+    server.mock(
+        "POST",
+        "/v1/responses",
+        FakeResponse::json(200, json!({
+            "id": "resp_1",
+            "object": "response",
+            "created_at": 0,
+            "model": "o4-mini",
+            "status": "completed",
+            "output": [
+                { "type": "reasoning", "id": "rs_1", "summary": [{ "type": "summary_text", "text": "Need the weather." }] },
+                {
+                    "type": "function_call",
+                    "id": "fc_1",
+                    "call_id": "call_1",
+                    "name": "get_weather",
+                    "arguments": "{\"city\":\"Paris\"}",
+                    "status": "completed"
+                }
+            ],
+            "usage": {
+                "input_tokens": 20,
+                "input_tokens_details": { "cached_tokens": 0 },
+                "output_tokens": 8,
+                "output_tokens_details": { "reasoning_tokens": 4 },
+                "total_tokens": 28
+            }
+        })),
+    );
+
+    let llm = OpenAI::with_api_key("sk-test")
+        .with_api_base(server.url("/v1"))
+        .with_api(OpenAIApi::Responses)
+        .with_reasoning_effort(ReasoningEffort::Low)
+        .with_builtin_tool(BuiltinTool::WebSearch);
+    let mut agent = Agent::new("fake", Arc::new(OpenAI::new()), Some(5));
+    agent.register_tool(None, Arc::new(GetWeatherTool)).expect("register tool");
+    let result = llm
+        .generate_with_tools(&[Message::user("Weather in Paris?")], &agent.tool_schemas())
+        .await
+        .expect("generate");
+
+    assert_eq!(result.finish_reason, Some(FinishReason::ToolCalls));
+    assert_eq!(result.tool_calls[0].id.as_deref(), Some("call_1"));
+    assert_eq!(result.tool_calls[0].args, json!({ "city": "Paris" }));
+    assert_eq!(result.reasoning.as_deref(), Some("Need the weather."));
+    assert_eq!(result.tokens.total_tokens, 28);
+
+    let body = server.requests()[0].json();
+    assert_eq!(body["input"][0]["content"], "Weather in Paris?");
+    assert_eq!(body["reasoning"]["effort"], "low");
+    assert_eq!(body["tools"][0]["type"], "function");
+    assert_eq!(body["tools"][1]["type"], "web_search_preview");
+}
+
+#[tokio::test]
+async fn anthropic_prompt_caching_marks_tools_and_system() {
+    let server = FakeServer::start().await;
+    server.mock(
+        "POST",
+        "/v1/messages",
+        FakeResponse::json(200, json!({
+            "model": "claude-sonnet-4-5",
+            "content": [{ "type": "text", "text": "Hi" }],
+            "stop_reason": "end_turn",
+            "usage": {
+                "input_tokens": 12,
+                "output_tokens": 3,
+                "cache_creation_input_tokens": 0,
+                "cache_read_input_tokens": 2048
+            }
+        })),
+    );
+
+    let llm = Anthropic::new("sk-test").with_base_url(server.url("/v1")).with_prompt_caching(true);
+    let mut agent = Agent::new("fake", Arc::new(llm.clone()), Some(5));
+    agent.register_tool(None, Arc::new(GetWeatherTool)).expect("register tool");
+    let messages = [Message::system("Be brief."), Message::user("Hi")];
+    let result = llm.generate_with_tools(&messages, &agent.tool_schemas()).await.expect("generate");
+
+    assert_eq!(result.tokens.prompt_tokens, 2060);
+    assert_eq!(result.tokens.cache_read_tokens, 2048);
+    assert_eq!(result.tokens.cache_write_tokens, 0);
+    let body = server.requests()[0].json();
+    assert_eq!(body["tools"][0]["cache_control"]["type"], "ephemeral");
+    assert_eq!(body["system"][0]["text"], "Be brief.");
+    assert_eq!(body["system"][0]["cache_control"]["type"], "ephemeral");
+}
+
+#[tokio::test]
+async fn ollama_generate() {
+    let server = FakeServer::start().await;
+    server.mock("POST", "/api/chat", FakeResponse::ollama_chat("Hi from Ollama"));
+
+    let client = OllamaClient::new("http://127.0.0.1", server.port());
+    let llm = Ollama::new(Arc::new(client)).with_model("fake-model");
+    let result = llm.generate(&[Message::user("Hi")]).await.expect("generate");
+    assert_eq!(result.generation, "Hi from Ollama");
+    assert_eq!(server.requests()[0].json()["model"], "fake-model");
+}
+
+#[tokio::test]
+async fn model_catalog_reports_capabilities() {
+    let server = FakeServer::start().await;
+    let pulled = |name: &str| json!({ "name": name, "modified_at": "2025-01-01T00:00:00Z", "size": 1 });
+    server
+        .mock("GET", "/api/tags", FakeResponse::json(200, json!({ "models": [pulled("qwen3:8b"), pulled("llava:7b")] })))
+        .mock(
+            "POST",
+            "/api/show",
+            FakeResponse::json(200, json!({
+                "capabilities": ["completion", "tools", "thinking"],
+                "model_info": { "general.architecture": "qwen3", "qwen3.context_length": 40960 },
+            })),
+        )
+        .mock("POST", "/api/show", FakeResponse::json(200, json!({ "capabilities": ["completion", "vision"] })))
+        .mock("GET", "/v1/models", FakeResponse::json(200, json!({
+            "object": "list",
+            "data": [
+                { "id": "gpt-4o", "object": "model", "created": 0, "owned_by": "openai" },
+                { "id": "text-embedding-3-small", "object": "model", "created": 0, "owned_by": "openai" },
+            ],
+        })));
+
+    let ollama = Ollama::new(Arc::new(OllamaClient::new("http://127.0.0.1", server.port())));
+    let models = ollama.list_models().await.expect("ollama models");
+    assert_eq!(models.len(), 2);
+    assert!(models[0].supports_tools && models[0].is_local);
+    assert_eq!(models[0].context_window, Some(40960));
+    assert!(!models[1].supports_tools && models[1].supports_vision);
+
+    let mut agent = Agent::new("fake", Arc::new(ollama), Some(5));
+    agent.set_model_info(models[1].clone()).expect("no tools yet");
+    let refused = agent.register_tool(None, Arc::new(GetWeatherTool));
+    assert!(matches!(refused, Err(AgentError::ToolsNotSupported { .. })), "{:?}", refused.err());
+
+    let openai = OpenAI::with_api_key("sk-test").with_api_base(server.url("/v1"));
+    let models = openai.list_models().await.expect("openai models");
+    assert!(models[0].supports_tools && models[0].supports_vision && !models[0].is_local);
+    assert_eq!(models[0].context_window, Some(128_000));
+    assert!(!models[1].supports_tools);
+}
+
+#[tokio::test]
+async fn repro_bundle_reruns_with_the_same_seed_and_prompts() {
+    let server = FakeServer::start().await;
+    server
+        .mock("POST", CHAT_PATH, FakeResponse::openai_chat("First."))
+        .mock("POST", CHAT_PATH, FakeResponse::openai_chat("First."));
+
+    let llm = OpenAICompatible::new(server.url("/v1"), "test-model");
+    let mut agent = Agent::new("fake", Arc::new(llm), Some(5));
+    agent.register_tool(None, Arc::new(GetWeatherTool)).expect("register tool");
+    agent.set_seed_per_run(true);
+    let result = agent.call_llm("Weather in Paris?").await.expect("agent run");
+
+    let repro = result.repro.expect("repro bundle");
+    let seed = repro.seed().expect("seed");
+    assert_eq!(repro.model.as_deref(), Some("test-model"));
+    assert_eq!(repro.tools[0].schema.name, "get_weather");
+
+    let saved = serde_json::to_string(&repro).expect("serialize");
+    let loaded: ReproBundle = serde_json::from_str(&saved).expect("deserialize");
+    agent.rerun(&loaded).await.expect("rerun");
+
+    let requests = server.requests();
+    assert_eq!(requests[0].json()["seed"], seed);
+    assert_eq!(requests[1].json()["seed"], seed);
+    assert_eq!(requests[0].json()["messages"], requests[1].json()["messages"]);
+}
+
+#[tokio::test]
+async fn agent_native_tool_round_trip() {
+    let server = FakeServer::start().await;
+    server
+        .mock("POST", CHAT_PATH, FakeResponse::openai_tool_call("get_weather", json!({ "city": "Paris" })))
+        .mock("POST", CHAT_PATH, FakeResponse::openai_chat("It's sunny in Paris."));
+
+    let llm = OpenAICompatible::new(server.url("/v1"), "test-model").with_native_tools(true);
+    let mut agent = Agent::new("fake", Arc::new(llm), Some(5));
+    agent.register_tool(None, Arc::new(GetWeatherTool)).expect("register tool");
+
+    let result = agent.call_llm("Weather in Paris?").await.expect("agent run");
+    assert_eq!(result.generation, "It's sunny in Paris.");
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    let messages = requests[1].json()["messages"].clone();
+    let fed_back = messages
+        .as_array()
+        .expect("messages")
+        .iter()
+        .any(|m| m["content"].as_str().unwrap_or_default().contains("It's always sunny in Paris!"));
+    assert!(fed_back, "tool output not sent back: {}", messages);
+    assert_eq!(messages[1]["tool_calls"][0]["id"], "call_fake");
+    assert_eq!(messages[2]["role"], "tool");
+    assert_eq!(messages[2]["tool_call_id"], "call_fake");
+}
+
+#[tokio::test]
+async fn mistral_sends_native_tools() {
+    let server = FakeServer::start().await;
+    server.mock("POST", CHAT_PATH, FakeResponse::openai_tool_call("get_weather", json!({ "city": "Paris" })));
+
+    let llm = Mistral::new("key").with_base_url(server.url("/v1"));
+    assert!(llm.capabilities().native_tools);
+    let mut agent = Agent::new("fake", Arc::new(MockLLM::new()), Some(5));
+    agent.register_tool(None, Arc::new(GetWeatherTool)).expect("register tool");
+    let options = GenerateOptions { temperature: Some(0.0), ..Default::default() };
+    let result = llm
+        .generate_with_options(&[Message::user("Weather in Paris?")], &agent.tool_schemas(), &options)
+        .await
+        .expect("generate");
+
+    assert_eq!(result.tool_calls[0].id.as_deref(), Some("call_fake"));
+    assert_eq!(result.tool_calls[0].name, "get_weather");
+    assert_eq!(result.tool_calls[0].args, json!({ "city": "Paris" }));
+    let body = server.requests()[0].json();
+    assert_eq!(body["tools"][0]["function"]["name"], "get_weather");
+    assert_eq!(body["temperature"], 0.0);
+}
+
+#[tokio::test]
+async fn grok_sends_native_tools() {
+    let server = FakeServer::start().await;
+    server
+        .mock("POST", CHAT_PATH, FakeResponse::openai_tool_call("get_weather", json!({ "city": "Paris" })))
+        .mock("POST", CHAT_PATH, FakeResponse::openai_chat_stream(&["Sunny."]));
+
+    let llm = Grok::new("key").with_base_url(server.url("/v1"));
+    assert!(llm.capabilities().native_tools);
+    let mut agent = Agent::new("fake", Arc::new(MockLLM::new()), Some(5));
+    agent.register_tool(None, Arc::new(GetWeatherTool)).expect("register tool");
+    let tools = agent.tool_schemas();
+    let messages = [Message::user("Weather in Paris?")];
+
+    let result = llm.generate_with_tools(&messages, &tools).await.expect("generate");
+    assert_eq!(result.tool_calls[0].name, "get_weather");
+    assert_eq!(result.tool_calls[0].args, json!({ "city": "Paris" }));
+    collect_stream(llm.stream_with_tools(&messages, &tools)).await.expect("stream");
+
+    let requests = server.requests();
+    for request in &requests {
+        assert_eq!(request.json()["tools"][0]["function"]["name"], "get_weather");
+    }
+    assert_eq!(requests[1].json()["stream"], true);
+}
+
+#[tokio::test]
+async fn cohere_sends_native_tools() {
+    let server = FakeServer::start().await;
+    server
+        .mock("POST", "/v2/chat", FakeResponse::json(200, json!({
+            "id": "c1",
+            "finish_reason": "TOOL_CALL",
+            "message": {
+                "role": "assistant",
+                "tool_plan": "Look up the weather.",
+                "tool_calls": [{
+                    "id": "call_1",
+                    "type": "function",
+                    "function": { "name": "get_weather", "arguments": "{\"city\":\"Paris\"}" }
+                }]
+            },
+            "usage": { "billed_units": { "input_tokens": 12, "output_tokens": 6 } }
+        })))
+        .mock("POST", "/v2/chat", FakeResponse::sse([
+            json!({ "type": "tool-call-start", "index": 0, "delta": { "message": { "tool_calls": {
+                "id": "call_2", "type": "function", "function": { "name": "get_weather", "arguments": "" }
+            } } } }).to_string(),
+            json!({ "type": "tool-call-delta", "index": 0, "delta": { "message": { "tool_calls": {
+                "function": { "arguments": "{\"city\":" }
+            } } } }).to_string(),
+            json!({ "type": "tool-call-delta", "index": 0, "delta": { "message": { "tool_calls": {
+                "function": { "arguments": "\"Lyon\"}" }
+            } } } }).to_string(),
+            json!({ "type": "message-end", "delta": { "finish_reason": "TOOL_CALL" } }).to_string(),
+        ]));
+
+    let llm = Cohere::new("key").with_base_url(server.url("/v2"));
+    assert!(llm.capabilities().native_tools);
+    let mut agent = Agent::new("fake", Arc::new(MockLLM::new()), Some(5));
+    agent.register_tool(None, Arc::new(GetWeatherTool)).expect("register tool");
+    let tools = agent.tool_schemas();
+    let mut messages = vec![Message::user("Weather in Paris?")];
+
+    let result = llm.generate_with_tools(&messages, &tools).await.expect("generate");
+    assert_eq!(result.tool_calls[0].id.as_deref(), Some("call_1"));
+    assert_eq!(result.tool_calls[0].args, json!({ "city": "Paris" }));
+    assert_eq!(server.requests()[0].json()["tools"][0]["function"]["name"], "get_weather");
+
+    messages.push(Message::assistant("").with_tool_calls(result.tool_calls.clone()));
+    messages.push(Message::tool_result("get_weather", "call_1", "Sunny"));
+    let streamed = collect_stream(llm.stream_with_tools(&messages, &tools)).await.expect("stream");
+    assert_eq!(streamed.tool_calls[0].id.as_deref(), Some("call_2"));
+    assert_eq!(streamed.tool_calls[0].args, json!({ "city": "Lyon" }));
+
+    let body = server.requests()[1].json();
+    assert_eq!(body["tools"][0]["function"]["name"], "get_weather");
+    assert_eq!(body["messages"][1]["tool_calls"][0]["id"], "call_1");
+    assert_eq!(body["messages"][2]["role"], "tool");
+    assert_eq!(body["messages"][2]["tool_call_id"], "call_1");
+}
+
+#[tokio::test]
+async fn standard_catalog_registers_sandboxed_and_runs() {
+    let server = FakeServer::start().await;
+    server
+        .mock("POST", CHAT_PATH, FakeResponse::openai_tool_call("calculator", json!({ "expression": "(2 + 3) * 2 ^ 3" })))
+        .mock("POST", CHAT_PATH, FakeResponse::openai_chat("It's 40."));
+
+    let llm = OpenAICompatible::new(server.url("/v1"), "test-model").with_native_tools(true);
+    let mut agent = Agent::new("fake", Arc::new(llm), Some(5));
+    agent.set_tool_policy(ToolPolicy::sandboxed()).expect("policy");
+    agent.register_tools(catalog::standard()).expect("register catalog");
+    assert!(agent.get_tool("current_datetime").is_some());
+
+    agent.call_llm("What is (2 + 3) * 2^3?").await.expect("agent run");
+    let messages = server.requests()[1].json()["messages"].clone();
+    let result = messages.as_array().expect("messages").last().expect("tool result");
+    assert!(result["content"].as_str().unwrap_or_default().contains("40"), "{}", result);
+
+    let kv = agent.get_tool("kv_store").expect("kv_store");
+    kv.run(json!({ "operation": "set", "key": "city", "value": "Paris" })).await.expect("set");
+    assert_eq!(kv.run(json!({ "operation": "get", "key": "city" })).await.expect("get"), "Paris");
+}
+
+#[tokio::test]
+async fn openai_echoes_tool_call_ids() {
+    let server = FakeServer::start().await;
+    server
+        .mock("POST", CHAT_PATH, FakeResponse::openai_tool_call("get_weather", json!({ "city": "Paris" })))
+        .mock("POST", CHAT_PATH, FakeResponse::openai_chat("It's sunny in Paris."));
+
+    let llm = OpenAI::with_api_key("sk-test").with_api_base(server.url("/v1"));
+    let mut agent = Agent::new("fake", Arc::new(llm), Some(5));
+    agent.register_tool(None, Arc::new(GetWeatherTool)).expect("register tool");
+    agent.call_llm("Weather in Paris?").await.expect("agent run");
+
+    let messages = server.requests()[1].json()["messages"].clone();
+    let assistant = messages
+        .as_array()
+        .expect("messages")
+        .iter()
+        .find(|m| m["role"] == "assistant")
+        .expect("assistant turn");
+    assert_eq!(assistant["tool_calls"][0]["id"], "call_fake");
+    assert_eq!(assistant["tool_calls"][0]["function"]["arguments"], json!({ "city": "Paris" }).to_string());
+    let result = messages.as_array().expect("messages").last().expect("tool result");
+    assert_eq!(result["role"], "tool");
+    assert_eq!(result["tool_call_id"], "call_fake");
+}
+
+#[tokio::test]
+async fn agent_reports_retried_call_as_warning() {
+    let server = FakeServer::start().await;
+    server
+        .mock("POST", CHAT_PATH, FakeResponse::error(503, "overloaded"))
+        .mock("POST", CHAT_PATH, FakeResponse::openai_chat("Recovered."));
+
+    let llm = OpenAICompatible::new(server.url("/v1"), "test-model");
+    let policy = RetryPolicy::default().with_initial_backoff(std::time::Duration::from_millis(1));
+    let agent = Agent::new("fake", Arc::new(RetryLLM::with_policy(llm, policy)), Some(5));
+
+    let result = agent.call_llm("Hi").await.expect("agent run");
+    assert_eq!(result.generation, "Recovered.");
+    assert!(
+        matches!(result.warnings.as_slice(), [RunWarning::Retried { retries: 1, .. }]),
+        "{:?}",
+        result.warnings
+    );
+}
+
+#[tokio::test]
+async fn tool_images_are_shown_to_vision_models() {
+    let server = FakeServer::start().await;
+    server
+        .mock("POST", CHAT_PATH, FakeResponse::openai_tool_call("screenshot", json!({})))
+        .mock("POST", CHAT_PATH, FakeResponse::openai_chat("The page is blank."))
+        .mock("POST", CHAT_PATH, FakeResponse::openai_tool_call("screenshot", json!({})))
+        .mock("POST", CHAT_PATH, FakeResponse::openai_chat("I can't see it."))
+        .mock("POST", CHAT_PATH, FakeResponse::openai_tool_call("screenshot", json!({})))
+        .mock("POST", CHAT_PATH, FakeResponse::openai_chat("I can't see it either."));
+
+    let llm = OpenAICompatible::new(server.url("/v1"), "test-model").with_native_tools(true);
+    let mut agent = Agent::new("fake", Arc::new(llm.clone().with_vision(true)), Some(5));
+    agent.register_tool(None, Arc::new(ScreenshotTool)).expect("register tool");
+    let result = agent.call_llm("What's on the page?").await.expect("agent run");
+    assert!(result.warnings.is_empty(), "{:?}", result.warnings);
+
+    let requests = server.requests();
+    let messages = requests[1].json()["messages"].clone();
+    let messages = messages.as_array().expect("messages");
+    let [.., tool_result, attachment] = messages.as_slice() else {
+        panic!("no attachment: {:?}", messages);
+    };
+    assert_eq!(tool_result["role"], "tool");
+    assert!(tool_result["content"].as_str().expect("text").contains("image/png"));
+    assert_eq!(attachment["role"], "user");
+    let url = attachment["content"][1]["image_url"]["url"].as_str().expect("image part");
+    assert_eq!(url, "data:image/png;base64,iVBORw0K");
+
+    agent
+        .set_model_info(ModelInfo::new("test-model").with_tools(true))
+        .expect("model info");
+    let result = agent.call_llm("What's on the page?").await.expect("agent run");
+    assert!(matches!(result.warnings.as_slice(), [RunWarning::ImageNotShown { tool }] if tool == "screenshot"));
+    let messages = server.requests()[3].json()["messages"].clone();
+    assert_eq!(messages.as_array().expect("messages").last().expect("tool result")["role"], "tool");
+
+    // backends don't claim vision unless told to
+    let mut agent = Agent::new("fake", Arc::new(llm), Some(5));
+    agent.register_tool(None, Arc::new(ScreenshotTool)).expect("register tool");
+    let result = agent.call_llm("What's on the page?").await.expect("agent run");
+    assert!(matches!(result.warnings.as_slice(), [RunWarning::ImageNotShown { .. }]));
+    let messages = server.requests()[5].json()["messages"].clone();
+    let tool_result = messages.as_array().expect("messages").last().expect("tool result").clone();
+    assert!(tool_result["content"].as_str().expect("text").contains("can't view"));
+}
+
+#[tokio::test]
+async fn health_check_pings_the_backend() {
+    let server = FakeServer::start().await;
+    server
+        .mock("POST", CHAT_PATH, FakeResponse::openai_chat("p"))
+        .mock("POST", "/api/show", FakeResponse::json(404, json!({ "error": "model 'qwen3' not found" })));
+
+    let llm = OpenAICompatible::new(server.url("/v1"), "test-model");
+    let agent = Agent::new("fake", Arc::new(llm), Some(5));
+    agent.health_check().await.expect("healthy");
+    assert_eq!(server.requests()[0].json()["max_tokens"], 1);
+
+    let ollama = Ollama::new(Arc::new(OllamaClient::new("http://127.0.0.1", server.port()))).with_model("qwen3");
+    let missing = ollama.health_check().await;
+    assert!(matches!(&missing, Err(LLMError::ModelNotFound(model)) if model == "qwen3"), "{:?}", missing);
+    assert_eq!(server.requests().len(), 2, "looked the model up without generating");
+}
+
+#[cfg(feature = "browser")]
+#[tokio::test]
+async fn browser_tool_drives_webdriver_within_its_limits() {
+    use mini_langchain::tools::builtin::browser::BrowserTool;
+
+    let server = FakeServer::start().await;
+    let value = |value: serde_json::Value| FakeResponse::json(200, json!({ "value": value }));
+    server
+        .mock("POST", "/session", value(json!({ "sessionId": "s1", "capabilities": {} })))
+        .mock("POST", "/session/s1/url", value(serde_json::Value::Null))
+        .mock("GET", "/session/s1/url", value(json!("https://www.example.com/")))
+        .mock("POST", "/session/s1/element", value(json!({ "element-6066-11e4-a52e-4f735466cecf": "e1" })))
+        .mock("GET", "/session/s1/element/e1/text", value(json!("Example Domain")))
+        .mock("GET", "/session/s1/screenshot", value(json!("iVBORw0K")))
+        .mock("DELETE", "/session/s1", value(serde_json::Value::Null));
+
+    let browser = BrowserTool::new(server.url(""), ["example.com"]).with_max_steps(4);
+    let opened = browser.run(json!({ "action": "navigate", "url": "https://www.example.com" })).await.expect("navigate");
+    assert_eq!(opened, "OK: opened https://www.example.com/");
+    let text = browser.run(json!({ "action": "extract_text", "selector": "h1" })).await.expect("extract");
+    assert_eq!(text, "Example Domain");
+    let screenshot = browser.run_output(json!({ "action": "screenshot" })).await.expect("screenshot");
+    assert_eq!(screenshot, ToolOutput::binary("image/png", b"\x89PNG\r\n".to_vec()));
+
+    let refused = browser.run(json!({ "action": "navigate", "url": "http://169.254.169.254/" })).await.expect("refused");
+    assert!(refused.starts_with("FAILED: '169.254.169.254' is not an allowed host"), "{}", refused);
+    let exhausted = browser.run(json!({ "action": "screenshot" })).await.expect("budget");
+    assert_eq!(exhausted, "FAILED: the step budget of 4 actions is used up");
+    browser.close().await.expect("close");
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 7, "the refused calls reached the driver");
+    assert_eq!(requests[3].json()["value"], "h1");
+}
+
+#[tokio::test]
+async fn pool_config_timeout_bounds_requests() {
+    let server = FakeServer::start().await;
+    server.mock("POST", CHAT_PATH, FakeResponse::openai_chat("Late.").with_delay(std::time::Duration::from_millis(500)));
+
+    let client = PoolConfig::new()
+        .with_timeout(std::time::Duration::from_millis(50))
+        .build_client()
+        .expect("client");
+    let llm = OpenAICompatible::new(server.url("/v1"), "test-model").with_http_client(client.clone());
+    let result = llm.generate(&[Message::user("Hi")]).await;
+    assert!(matches!(&result, Err(LLMError::Http(e)) if e.is_timeout()), "{:?}", result.err());
+
+    // pointing at another server keeps the caller's client
+    let openai = OpenAI::with_api_key("sk-test").with_http_client(client).with_api_base(server.url("/v1"));
+    let result = openai.generate(&[Message::user("Hi")]).await;
+    assert!(result.is_err(), "answered without the 50ms timeout: {:?}", result.ok());
+}
+
+/// Replaces every message with "[redacted]" when it mentions a secret.
+struct RedactSecrets;
+
+impl LLMMiddleware for RedactSecrets {
+    fn before<'a>(&'a self, request: &'a mut LLMRequest) -> BoxFuture<'a, LLMResult<Option<GenerateResult>>> {
+        for message in request.messages.iter_mut().filter(|m| m.content.contains("secret")) {
+            message.content = "[redacted]".to_string();
+        }
+        async { Ok(None) }.boxed()
+    }
+}
+
+/// Answers "ping" itself and counts the results it sees.
+#[derive(Default)]
+struct PingResponder {
+    seen: Arc<std::sync::Mutex<Vec<String>>>,
+}
+
+impl LLMMiddleware for PingResponder {
+    fn before<'a>(&'a self, request: &'a mut LLMRequest) -> BoxFuture<'a, LLMResult<Option<GenerateResult>>> {
+        let pong = request.messages.last().is_some_and(|m| m.content == "ping").then(|| GenerateResult {
+            generation: "pong".to_string(),
+            ..Default::default()
+        });
+        async move { Ok(pong) }.boxed()
+    }
+
+    fn after<'a>(&'a self, _request: &'a LLMRequest, result: &'a mut GenerateResult) -> BoxFuture<'a, LLMResult<()>> {
+        self.seen.lock().unwrap().push(result.generation.clone());
+        async { Ok(()) }.boxed()
+    }
+}
+
+#[tokio::test]
+async fn layered_llm_runs_middleware_around_the_backend() {
+    let server = FakeServer::start().await;
+    server
+        .mock("POST", CHAT_PATH, FakeResponse::openai_chat("Noted."))
+        .mock("POST", CHAT_PATH, FakeResponse::openai_chat_stream(&["Not", "ed."]));
+
+    let responder = PingResponder::default();
+    let seen = responder.seen.clone();
+    let llm = LayeredLLM::new(OpenAICompatible::new(server.url("/v1"), "test-model"))
+        .with_layer(responder)
+        .with_layer(RedactSecrets);
+
+    let pong = llm.generate(&[Message::user("ping")]).await.expect("answered by a layer");
+    assert_eq!(pong.generation, "pong");
+    let result = llm.generate(&[Message::user("my secret is 42")]).await.expect("generate");
+    assert_eq!(result.generation, "Noted.");
+    let streamed = collect_stream(llm.stream(&[Message::user("another secret")])).await.expect("stream");
+    assert_eq!(streamed.generation, "Noted.");
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2, "the answered request reached the backend");
+    assert_eq!(requests[0].json()["messages"][0]["content"], "[redacted]");
+    assert_eq!(requests[1].json()["messages"][0]["content"], "[redacted]");
+    assert_eq!(*seen.lock().unwrap(), ["pong", "Noted.", "Noted."]);
+}
+
+#[tokio::test]
+async fn mock_llm_scripts_an_agent_run() {
+    let llm = Arc::new(
+        MockLLM::new()
+            .with_model("scripted")
+            .with_native_tools(true)
+            .with_tool_call("get_weather", json!({ "city": "Paris" }))
+            .with_response("It's sunny in Paris."),
+    );
+    let mut agent = Agent::new("mock", llm.clone(), Some(5));
+    agent.register_tool(None, Arc::new(GetWeatherTool)).expect("register tool");
+
+    let result = agent.call_llm("Weather in Paris?").await.expect("agent run");
+    assert_eq!(result.generation, "It's sunny in Paris.");
+    assert_eq!(llm.remaining(), 0);
+    let requests = llm.requests();
+    assert_eq!(requests[0].tools[0].name, "get_weather");
+    let tool_result = requests[1].messages.last().expect("tool result");
+    assert_eq!(tool_result.tool_call_id.as_deref(), Some("call_0"));
+    assert!(tool_result.content.contains("It's always sunny in Paris!"));
+
+    let exhausted = agent.call_llm("And tomorrow?").await;
+    assert!(matches!(exhausted, Err(AgentError::LLMExecutionError(LLMError::InvalidResponse(_)))));
+}
+
+#[tokio::test]
+async fn quota_guard_stops_requests_once_the_monthly_budget_is_spent() {
+    let path = std::env::temp_dir().join(format!("mini-langchain-quota-{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let costly = || GenerateResult {
+        generation: "Done.".to_string(),
+        tokens: TokenUsage::new(1_000_000, 0),
+        ..Default::default()
+    };
+    // $1 per call against a $1.50 budget
+    let pricing = PricingTable::new().with_price("*", "metered", Pricing::new(1.0, 0.0));
+    let guard = Arc::new(QuotaGuard::open(&path).expect("open").with_budget("hobby", 1.5));
+    let llm = QuotaLLM::new(
+        MockLLM::new().with_model("metered").with_result(costly()).with_result(costly()).with_result(costly()),
+        guard.clone(),
+    )
+    .with_account("hobby")
+    .with_pricing(pricing.clone());
+
+    llm.generate(&[Message::user("one")]).await.expect("within budget");
+    let streamed = collect_stream(llm.stream(&[Message::user("two")])).await.expect("still within budget");
+    assert_eq!(streamed.generation, "Done.");
+    assert_eq!(guard.spent("hobby").expect("spent"), 2.0);
+    assert_eq!(guard.remaining("hobby").expect("remaining"), Some(0.0));
+    let refused = llm.generate(&[Message::user("three")]).await;
+    assert!(matches!(refused, Err(LLMError::QuotaExhausted { budget, spent, .. }) if budget == 1.5 && spent == 2.0));
+
+    // a restarted process reads the same ledger
+    let reopened = Arc::new(QuotaGuard::open(&path).expect("reopen").with_budget("hobby", 1.5).with_budget("other", 1.5));
+    assert_eq!(reopened.spent("hobby").expect("spent"), 2.0);
+    let other = QuotaLLM::new(MockLLM::new().with_model("metered").with_result(costly()), reopened.clone())
+        .with_account("other")
+        .with_pricing(pricing);
+    other.generate(&[Message::user("one")]).await.expect("separate budget");
+
+    // a model without a price can't slip past a budget
+    let unpriced = QuotaLLM::new(MockLLM::new().with_model("unlisted").with_result(costly()), reopened.clone())
+        .with_account("other")
+        .with_pricing(PricingTable::new().with_price("*", "metered", Pricing::new(1.0, 0.0)));
+    let refused = unpriced.generate(&[Message::user("one")]).await;
+    assert!(matches!(refused, Err(LLMError::Unpriced { account, model }) if account == "other" && model == "unlisted"));
+    assert_eq!(unpriced.inner.remaining(), 1);
+    let untracked = QuotaLLM::new(MockLLM::new().with_model("unlisted").with_result(costly()), reopened)
+        .with_account("unbudgeted")
+        .with_pricing(PricingTable::new());
+    untracked.generate(&[Message::user("one")]).await.expect("no budget to protect");
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn quota_guards_sharing_a_file_lose_no_updates() {
+    let dir = std::env::temp_dir().join(format!("mini-langchain-quota-shared-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let path = dir.join("spend.json");
+    // separate guards stand in for separate processes: only the file lock
+    // is shared between them
+    let guards: Vec<_> = (0..2).map(|_| Arc::new(QuotaGuard::open(&path).expect("open"))).collect();
+    let writers: Vec<_> = (0..4)
+        .map(|i| {
+            let guard = guards[i % 2].clone();
+            std::thread::spawn(move || {
+                for _ in 0..25 {
+                    guard.record("shared", 0.25).expect("record");
+                }
+            })
+        })
+        .collect();
+    for writer in writers {
+        writer.join().expect("writer");
+    }
+    assert_eq!(guards[0].spent("shared").expect("spent"), 25.0);
+    let leftovers: Vec<_> = std::fs::read_dir(&dir)
+        .expect("read dir")
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|name| name.ends_with(".tmp"))
+        .collect();
+    assert!(leftovers.is_empty(), "{:?}", leftovers);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn pricing_bills_prompt_cache_tokens_at_their_own_rates() {
+    // 1M prompt tokens: 600k read from the cache, 200k written to it
+    let usage = TokenUsage {
+        prompt_tokens: 1_000_000,
+        completion_tokens: 0,
+        total_tokens: 1_000_000,
+        cache_read_tokens: 600_000,
+        cache_write_tokens: 200_000,
+    };
+    let sonnet = PricingTable::builtin().lookup(Some("anthropic"), "claude-sonnet-4-20250514").expect("priced");
+    assert!((sonnet.cost(&usage) - (0.2 * 3.0 + 0.6 * 0.3 + 0.2 * 3.75)).abs() < 1e-9);
+    // without cache rates every prompt token costs the input price
+    assert!((Pricing::new(3.0, 15.0).cost(&usage) - 3.0).abs() < 1e-9);
+}
+
+#[tokio::test]
+async fn agent_prices_a_fallback_answer_as_the_backend_that_gave_it() {
+    let primary = Arc::new(MockLLM::new().with_model("shared").with_provider("primary").with_error(LLMError::Timeout(std::time::Duration::from_secs(1))));
+    let backup = Arc::new(
+        MockLLM::new().with_model("shared").with_provider("backup").with_result(GenerateResult {
+            generation: "Paris.".to_string(),
+            tokens: TokenUsage::new(1_000_000, 0),
+            ..Default::default()
+        }),
+    );
+    let llm = FallbackLLM::new(vec![primary, backup]);
+    let mut agent = Agent::new("fallback", Arc::new(llm), Some(5));
+    agent.set_pricing(
+        PricingTable::new()
+            .with_price("primary", "shared", Pricing::new(1.0, 0.0))
+            .with_price("backup", "shared", Pricing::new(3.0, 0.0)),
+    );
+    let report = agent.call_llm("Capital of France?").await.expect("agent run").cost.expect("cost report");
+    assert_eq!(report.models[0].provider.as_deref(), Some("backup"));
+    assert!((report.total - 3.0).abs() < 1e-9);
+}
+
+#[cfg(feature = "tiktoken")]
+#[test]
+fn tiktoken_merges_pieces_by_rank() {
+    use base64::Engine;
+    use mini_langchain::llm::tokens::{TokenCounter, tiktoken::{Encoding, Tiktoken}};
+
+    // every byte, then a few merges in rank order
+    let merges: [&[u8]; 7] = [b"he", b"ll", b"hell", b"hello", b" w", b"or", b" wor"];
+    let tokens: Vec<Vec<u8>> = (0..=255u8).map(|b| vec![b]).chain(merges.iter().map(|m| m.to_vec())).collect();
+    let file: String = tokens
+        .iter()
+        .enumerate()
+        .map(|(rank, token)| format!("{} {}\n", base64::engine::general_purpose::STANDARD.encode(token), rank))
+        .collect();
+    let tiktoken = Tiktoken::parse(Encoding::Cl100kBase, file.as_bytes()).expect("parse");
+
+    // "hello" is one token; " world" merges to " wor", "l", "d"
+    assert_eq!(tiktoken.count("hello world"), 4);
+    // the space before a word goes with it: "a", " ", " b"
+    assert_eq!(tiktoken.count("a  b"), 4);
+    assert!(Tiktoken::parse(Encoding::O200kBase, b"not a rank file").is_err());
+}
+
+#[tokio::test]
+async fn moderation_fails_closed_unless_told_otherwise() {
+    use mini_langchain::safety::{ModerationChecker, SafetyAction, SafetyPolicy};
+
+    // a mock with nothing scripted fails every call
+    let moderator: Arc<dyn LLM> = Arc::new(MockLLM::new());
+    let closed = SafetyPolicy::new().add_rule(Arc::new(ModerationChecker::new("moderation", moderator.clone())), SafetyAction::Block);
+    let outcome = closed.apply("Some answer.").await;
+    assert_eq!(outcome.blocked_by.as_deref(), Some("moderation"));
+    assert!(outcome.findings[0].detail.starts_with("moderation failed"));
+
+    let open = SafetyPolicy::new().add_rule(
+        Arc::new(ModerationChecker::new("moderation", moderator).with_fail_open(true)),
+        SafetyAction::Block,
+    );
+    let outcome = open.apply("Some answer.").await;
+    assert_eq!(outcome.blocked_by, None);
+    assert_eq!(outcome.text, "Some answer.");
+    assert_eq!(outcome.findings[0].action, SafetyAction::Annotate);
+    assert!(outcome.findings[0].detail.contains("not checked"));
+}
+
+#[cfg(feature = "catalog-http")]
+#[tokio::test]
+async fn http_get_reads_no_more_of_the_body_than_it_keeps() {
+    use mini_langchain::tools::builtin::http_get::HttpGetTool;
+
+    let server = FakeServer::start().await;
+    server.mock("GET", "/big", FakeResponse::json(200, json!("é".repeat(100_000))));
+    let tool = HttpGetTool::new(["127.0.0.1"]).with_max_response_chars(10);
+    let text = tool.run(json!({ "url": server.url("/big") })).await.expect("http_get");
+    assert_eq!(text, format!("HTTP 200\n\"{}\n[truncated]", "é".repeat(9)));
+}
+
+#[tokio::test]
+async fn recorded_agent_runs_replay_without_the_backend() {
+    let path = std::env::temp_dir().join(format!("mini-langchain-cassette-{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let server = FakeServer::start().await;
+    server
+        .mock("POST", CHAT_PATH, FakeResponse::openai_tool_call("get_weather", json!({ "city": "Paris" })))
+        .mock("POST", CHAT_PATH, FakeResponse::openai_chat("It's sunny in Paris."));
+    let run = |llm| async move {
+        let mut agent = Agent::new("vcr", llm, Some(5));
+        agent.register_tool(None, Arc::new(GetWeatherTool)).expect("register tool");
+        agent.call_llm("Weather in Paris?").await
+    };
+
+    let recording = record_or_replay(&path, || OpenAICompatible::new(server.url("/v1"), "test-model").with_native_tools(true))
+        .expect("record");
+    let recorded = run(recording).await.expect("recorded run");
+    assert_eq!(recorded.generation, "It's sunny in Paris.");
+    assert_eq!(server.requests().len(), 2);
+
+    let replaying = record_or_replay(&path, || -> OpenAICompatible { panic!("the cassette exists") }).expect("replay");
+    assert_eq!(replaying.model_name(), Some("test-model"));
+    let replayed = run(replaying).await.expect("replayed run");
+    assert_eq!(replayed.generation, recorded.generation);
+    assert_eq!(server.requests().len(), 2, "replay reached the backend");
+
+    let replay = ReplayLLM::open(&path).expect("open cassette");
+    assert_eq!(replay.remaining(), 2);
+    let unknown = replay.generate(&[Message::user("Weather in Rome?")]).await;
+    assert!(matches!(unknown, Err(LLMError::InvalidResponse(_))));
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn generate_n_returns_every_candidate() {
+    let server = FakeServer::start().await;
+    server.mock(
+        "POST",
+        CHAT_PATH,
+        FakeResponse::json(200, json!({
+            "model": "test-model",
+            "choices": [
+                { "index": 0, "message": { "role": "assistant", "content": "42" }, "finish_reason": "stop" },
+                { "index": 1, "message": { "role": "assistant", "content": "41" }, "finish_reason": "stop" },
+                { "index": 2, "message": { "role": "assistant", "content": "42" }, "finish_reason": "length" },
+            ],
+            "usage": { "prompt_tokens": 10, "completion_tokens": 3, "total_tokens": 13 },
+        })),
+    );
+
+    let llm = OpenAICompatible::new(server.url("/v1"), "test-model");
+    let options = GenerateOptions::new().with_temperature(1.0);
+    let result = llm.generate_n(&[Message::user("6 * 7?")], &[], &options, 3).await.expect("generate_n");
+    assert_eq!(result.generation, "42");
+    let generations: Vec<_> = result.candidates.iter().map(|c| c.generation.as_str()).collect();
+    assert_eq!(generations, ["42", "41", "42"]);
+    assert_eq!(result.candidates[2].finish_reason, Some(FinishReason::Length));
+    assert_eq!(result.tokens.total_tokens, 13);
+    let body = server.requests()[0].json();
+    assert_eq!(body["n"], 3);
+    assert_eq!(body["temperature"], 1.0);
+
+    // backends without `n` sample with one call per candidate
+    let mock = MockLLM::new()
+        .with_result(GenerateResult { generation: "a".to_string(), tokens: TokenUsage::new(5, 1), ..Default::default() })
+        .with_result(GenerateResult { generation: "b".to_string(), tokens: TokenUsage::new(5, 1), ..Default::default() });
+    let sampled = mock.generate_n(&[Message::user("pick")], &[], &GenerateOptions::default(), 2).await.expect("generate_n");
+    assert_eq!(sampled.all_candidates().iter().map(|c| c.generation.as_str()).collect::<Vec<_>>(), ["a", "b"]);
+    assert_eq!(sampled.tokens.prompt_tokens, 10);
+    assert_eq!(mock.requests().len(), 2);
+    let single = mock.generate_n(&[Message::user("pick")], &[], &GenerateOptions::default(), 1).await;
+    assert!(single.is_err(), "the script ran out");
+}
+
+#[tokio::test]
+async fn wrappers_forward_generate_n_and_openai_splits_large_n() {
+    let choices = |count: usize| {
+        let choices: Vec<_> = (0..count)
+            .map(|index| json!({ "index": index, "message": { "role": "assistant", "content": index.to_string() }, "finish_reason": "stop" }))
+            .collect();
+        FakeResponse::json(200, json!({
+            "id": "chatcmpl-fake",
+            "object": "chat.completion",
+            "created": 0,
+            "model": "gpt-4o-mini",
+            "choices": choices,
+            "usage": { "prompt_tokens": 10, "completion_tokens": count, "total_tokens": 10 + count },
+        }))
+    };
+    let server = FakeServer::start().await;
+    server.mock("POST", CHAT_PATH, choices(128)).mock("POST", CHAT_PATH, choices(2));
+
+    let openai = OpenAI::with_api_key("sk-test").with_api_base(server.url("/v1"));
+    let llm = ThrottledLLM::with_limits(LayeredLLM::new(RetryLLM::new(openai)), ThrottleConfig::default());
+    let result = llm.generate_n(&[Message::user("count")], &[], &GenerateOptions::default(), 130).await.expect("generate_n");
+    assert_eq!(result.candidates.len(), 130);
+    assert_eq!(result.tokens.prompt_tokens, 20);
+    let sent: Vec<_> = server.requests().iter().map(|request| request.json()["n"].clone()).collect();
+    assert_eq!(sent, [json!(128), json!(2)]);
+
+    let none = llm.generate_n(&[Message::user("count")], &[], &GenerateOptions::default(), 0).await;
+    assert!(matches!(none, Err(LLMError::InvalidRequest(_))));
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn agent_shows_tool_descriptions_in_its_locale() {
+    let llm = Arc::new(MockLLM::new().with_native_tools(true).with_response("Il fait beau."));
+    let mut agent = Agent::new("météo", llm.clone(), Some(5));
+    agent.register_tool(None, Arc::new(GetWeatherTool)).expect("register tool");
+    assert_eq!(agent.tool_schemas()[0].description, "Get weather for a given city");
+
+    // a regional tag falls back to the language
+    agent.set_locale("fr-CA");
+    agent.call_llm("Quel temps fait-il à Lyon ?").await.expect("agent run");
+    let sent = &llm.requests()[0].tools[0];
+    assert_eq!(sent.description, "Donne la météo d'une ville");
+    assert_eq!(sent.args[0].description, "Nom de la ville, p. ex. 'Lyon'");
+
+    // without its own locale the agent uses the time context's; untranslated
+    // arguments keep their description
+    agent.locale = None;
+    agent.set_time_context(TimeContext::new().with_locale("pt_BR"));
+    let schema = &agent.tool_schemas()[0];
+    assert_eq!(schema.description, "Obtém o clima de uma cidade");
+    assert_eq!(schema.args[0].description, "City name, e.g. 'San Francisco'");
+    agent.set_locale("de-DE");
+    assert_eq!(agent.tool_schemas()[0].description, "Get weather for a given city");
+}
+
+#[tokio::test]
+async fn strict_tools_get_well_formed_arguments() {
+    let server = FakeServer::start().await;
+    server.mock("POST", CHAT_PATH, FakeResponse::openai_chat("Done."));
+    let mut agent = Agent::new("alarms", Arc::new(MockLLM::new()), Some(5));
+    agent.register_tool(None, Arc::new(SetAlarmTool)).expect("register tool");
+    agent.register_tool(None, Arc::new(GetWeatherTool)).expect("register tool");
+
+    // OpenAI enforces the schema itself
+    let openai = OpenAI::with_api_key("sk-test").with_api_base(server.url("/v1"));
+    openai
+        .generate_with_tools(&[Message::user("Wake me at 7")], &agent.tool_schemas())
+        .await
+        .expect("generate");
+    let tools = server.requests()[0].json()["tools"].clone();
+    assert_eq!(tools[1]["function"]["name"], "set_alarm");
+    assert_eq!(tools[1]["function"]["strict"], true);
+    assert_eq!(tools[1]["function"]["parameters"]["additionalProperties"], false);
+    assert!(tools[0]["function"].get("strict").is_none_or(|strict| strict.is_null()));
+
+    // elsewhere the agent checks the arguments before running the tool
+    let llm = Arc::new(
+        MockLLM::new()
+            .with_native_tools(true)
+            .with_tool_call("set_alarm", json!({ "hour": "seven", "label": "work" }))
+            .with_tool_call("set_alarm", json!({ "hour": 7, "label": "work" }))
+            .with_response("Your alarm is set.")
+            .with_tool_call("get_weather", json!({ "city": "Paris", "units": "C" })),
+    );
+    agent.llm = llm.clone();
+    let rejected = agent.call_llm("Wake me at seven").await;
+    assert!(matches!(
+        rejected,
+        Err(AgentError::ToolExecutionError(ToolError::ParamsNotMatched(reason))) if reason.contains("`hour` must be of type integer")
+    ));
+    assert_eq!(ALARMS_SET.load(std::sync::atomic::Ordering::SeqCst), 0);
+    let result = agent.call_llm("Wake me at seven").await.expect("agent run");
+    assert_eq!(result.generation, "Your alarm is set.");
+    assert_eq!(ALARMS_SET.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+    // every tool is strict once the agent asks for it
+    agent.set_strict_tools(true);
+    let unknown = agent.call_llm("Weather in Paris?").await;
+    assert!(matches!(
+        unknown,
+        Err(AgentError::ToolExecutionError(ToolError::ParamsNotMatched(reason))) if reason.contains("unknown argument `units`")
+    ));
+}
+
+#[tokio::test]
+async fn agent_adapts_prompts_to_backend_capabilities() {
+    // no system role: instructions move into the first user message, tool
+    // results stay after the call that produced them
+    let mock = MockLLM::new()
+        .with_tool_call("get_weather", json!({ "city": "Paris" }))
+        .with_response("It's sunny in Paris.");
+    let no_system_role = Capabilities { system_role: false, ..Capabilities::default() };
+    let llm = Arc::new(WithCapabilities::new(mock, no_system_role));
+    let mut agent = Agent::new("adapt", llm.clone(), Some(5));
+    agent.register_tool(None, Arc::new(GetWeatherTool)).expect("register tool");
+    agent.set_system_prompt("You are terse.");
+    let result = agent.call_llm("Weather in Paris?").await.expect("agent run");
+    assert_eq!(result.generation, "It's sunny in Paris.");
+
+    let second = &llm.inner.received()[1];
+    assert!(second.iter().all(|m| matches!(m.role, MessageRole::User | MessageRole::Assistant)));
+    assert!(second[0].content.starts_with("You are terse."));
+    assert!(second[0].content.ends_with("Weather in Paris?"));
+    assert!(matches!(second[1].role, MessageRole::Assistant));
+    assert!(second[2].content.contains("Tool get_weather returned"));
+
+    // images for a model without vision fail before any request is sent
+    let mock = Arc::new(
+        MockLLM::new()
+            .with_model("text-only")
+            .with_response("A chart.")
+            .with_response("unused"),
+    );
+    let agent = Agent::new("vision", mock.clone(), Some(5));
+    let mut repro = agent.call_llm("What is this?").await.expect("agent run").repro.expect("repro bundle");
+    let question = repro.prompts.pop().expect("prompt");
+    repro.prompts.push(question.with_images(vec![ImageContent::from_bytes("image/png", b"png")]));
+    let rejected = agent.rerun(&repro).await;
+    assert!(matches!(rejected, Err(AgentError::VisionNotSupported { model }) if model == "text-only"));
+    assert_eq!(mock.remaining(), 1);
+}
+
+#[tokio::test]
+async fn cached_llm_skips_sampled_calls_and_reports_savings() {
+    let answer = || GenerateResult {
+        generation: "Paris.".to_string(),
+        tokens: TokenUsage::new(1_000, 100),
+        ..Default::default()
+    };
+    let mock = MockLLM::new().with_model("mock").with_result(answer()).with_result(answer()).with_result(answer());
+    let llm = Arc::new(CachedLLM::new(mock, Arc::new(MemoryCache::new(8))));
+    let mut agent = Agent::new("cache", llm.clone(), Some(5));
+    agent.set_pricing(PricingTable::new().with_price("*", "mock", Pricing::new(1.0, 10.0)));
+
+    // deterministic calls are cached, and the hit isn't billed
+    agent.set_generate_options(GenerateOptions::new().with_temperature(0.0));
+    agent.call_llm("Capital of France?").await.expect("agent run");
+    let report = agent.call_llm("Capital of France?").await.expect("agent run").cost.expect("cost report");
+    assert_eq!((report.cache.hits, report.cache.misses), (1, 0));
+    assert_eq!(report.cache.saved_tokens.total_tokens, 1_100);
+    assert_eq!(report.total, 0.0);
+    assert!((report.saved - 0.002).abs() < 1e-9);
+    assert_eq!(report.models[0].cached_calls, 1);
+
+    // sampled calls reach the backend every time
+    agent.set_generate_options(GenerateOptions::new().with_temperature(0.7));
+    agent.call_llm("Capital of France?").await.expect("agent run");
+    agent.call_llm("Capital of France?").await.expect("agent run");
+    assert_eq!(llm.inner.remaining(), 0);
+    assert_eq!((llm.stats().hits, llm.stats().misses), (1, 1));
+
+    let llm = CachedLLM::new(MockLLM::new().with_result(answer()), Arc::new(MemoryCache::new(8))).with_cache_sampled(true);
+    let options = GenerateOptions::new().with_temperature(0.7);
+    let messages = [Message::user("Capital of France?")];
+    llm.generate_with_options(&messages, &[], &options).await.expect("generate");
+    let again = llm.generate_with_options(&messages, &[], &options).await.expect("cached");
+    assert!(again.cached);
+}
+
+#[test]
+fn code_edit_keeps_crlf_and_holds_hunks_to_their_counts() {
+    let original = "fn main() {\r\n    one();\r\n}\r\n";
+    let diff = "@@ -1,3 +1,3 @@\n fn main() {\n-    one();\n+    two();\n }\n";
+    assert_eq!(apply_unified_diff(original, diff).expect("applies"), "fn main() {\r\n    two();\r\n}\r\n");
+    let crlf_diff = diff.replace('\n', "\r\n");
+    assert_eq!(apply_unified_diff(original, &crlf_diff).expect("applies"), "fn main() {\r\n    two();\r\n}\r\n");
+
+    let original = "a\nb\nc\nd\n";
+    // one line more than the second hunk declares
+    let overflow = "@@ -1,1 +1,1 @@\n-a\n+A\n@@ -3,1 +3,1 @@\n-c\n+C\n d\n";
+    let error = apply_unified_diff(original, overflow).expect_err("overflow");
+    assert!(error.contains("more lines than its header declares"), "{}", error);
+    let short = "@@ -1,2 +1,2 @@\n-a\n+A\n@@ -3,1 +3,1 @@\n-c\n+C\n";
+    let error = apply_unified_diff(original, short).expect_err("short");
+    assert!(error.contains("fewer lines than its header declares"), "{}", error);
+    let exact = "@@ -1,1 +1,1 @@\n-a\n+A\n@@ -3,2 +3,2 @@\n-c\n+C\n d\n";
+    assert_eq!(apply_unified_diff(original, exact).expect("applies"), "A\nb\nC\nd\n");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn code_edit_reads_hunks_by_their_counts_and_stays_in_the_workspace() {
+    // body lines that look like file headers
+    let original = "-- keep\n-- note\nselect 1;\n";
+    let diff = "--- a/q.sql\n+++ b/q.sql\n@@ -1,3 +1,3 @@\n -- keep\n--- note\n+++ x\n select 1;\n";
+    assert_eq!(apply_unified_diff(original, diff).expect("applies"), "-- keep\n++ x\nselect 1;\n");
+
+    let root = std::env::temp_dir().join(format!("mini-langchain-code-edit-{}", std::process::id()));
+    let outside = std::env::temp_dir().join(format!("mini-langchain-code-edit-outside-{}", std::process::id()));
+    std::fs::create_dir_all(&root).expect("root");
+    std::fs::create_dir_all(&outside).expect("outside");
+    std::os::unix::fs::symlink(&outside, root.join("link")).expect("symlink");
+    let tool = CodeEditTool::new(&root);
+    let add = "@@ -0,0 +1 @@\n+hello\n";
+
+    let escaped = tool.run(json!({ "path": "link/new.txt", "diff": add })).await.expect("tool run");
+    assert!(escaped.starts_with("FAILED") && escaped.contains("outside the workspace"), "{}", escaped);
+    assert!(!outside.join("new.txt").exists());
+    let inside = tool.run(json!({ "path": "src/new.txt", "diff": add })).await.expect("tool run");
+    assert!(inside.starts_with("OK"), "{}", inside);
+    assert_eq!(std::fs::read_to_string(root.join("src/new.txt")).expect("written"), "hello\n");
+
+    std::fs::remove_dir_all(&root).ok();
+    std::fs::remove_dir_all(&outside).ok();
+}
+
+#[test]
+fn template_placeholders_may_shadow_generated_names() {
+    let prompt = template!("{out} then {self_check}");
+    assert_eq!(prompt.render("draft", "review"), "draft then review");
+}
@@ -0,0 +1,146 @@
+//! Expansion tests for the proc-macros. Cases that must not compile are
+//! `compile_fail` doctests on the re-exports in `src/lib.rs`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use serde_json::json;
+use mini_langchain::{agent, template, tool, llm::mock::MockLLM, tools::traits::Tool};
+
+#[tool(description = "Echo the input back", params(text = "Text to echo"))]
+fn echo(text: String) -> String {
+    text
+}
+
+#[test]
+fn template_renders_placeholders_in_order_of_first_use() {
+    let prompt = template!("{greeting}, {name}! {greeting} again.");
+    assert_eq!(prompt.render("Hi", "Ada"), "Hi, Ada! Hi again.");
+}
+
+#[test]
+fn template_keeps_escaped_braces_and_trims_placeholder_names() {
+    let prompt = template!("{{\"city\": \"{ city }\"}}");
+    assert_eq!(prompt.render("Paris"), "{\"city\": \"Paris\"}");
+    assert_eq!(template!("no placeholders").render(), "no placeholders");
+}
+
+/// You are a helpful assistant.
+///
+/// Answer briefly.
+#[agent(name = "helper", llm = "MockLLM", model = "mock-1", tools(EchoTool), max_iterations = 3)]
+struct HelperAgent;
+
+// without a doc comment there is no system prompt
+#[agent]
+struct BareAgent;
+
+#[test]
+fn agent_takes_its_prompt_tools_and_llm_from_the_attribute() {
+    assert_eq!(HelperAgent::SYSTEM_PROMPT, "You are a helpful assistant.\n\nAnswer briefly.");
+    let agent = HelperAgent::build();
+    assert_eq!(agent.name, "helper");
+    assert_eq!(agent.max_iterations, 3);
+    assert_eq!(agent.llm.model_name(), Some("mock-1"));
+    assert_eq!(agent.system_prompt.as_deref(), Some(HelperAgent::SYSTEM_PROMPT));
+    assert!(agent.get_tool("echo").is_some());
+}
+
+#[test]
+fn agent_without_arguments_only_builds_on_a_given_llm() {
+    let agent = BareAgent::build_with_llm(Arc::new(MockLLM::new()));
+    assert_eq!(agent.name, "BareAgent");
+    assert_eq!(agent.system_prompt, None);
+    assert!(agent.tools.is_empty());
+}
+
+#[tool(
+    description = "Summarize a table",
+    params(
+        columns = "Column names",
+        rows = "Rows of cells",
+        totals = "Total per column",
+        flags = "Flags per column",
+        origin = "X and Y of the first cell",
+    )
+)]
+fn summarize(
+    columns: Vec<String>,
+    rows: Vec<Vec<i64>>,
+    totals: HashMap<String, f64>,
+    flags: BTreeMap<String, Vec<bool>>,
+    origin: [u32; 2],
+) -> String {
+    format!("{} {} {} {} {:?}", columns.len(), rows.len(), totals.len(), flags.len(), origin)
+}
+
+#[tokio::test]
+async fn tool_types_container_params() {
+    let args = SummarizeTool.args();
+    let schema = |name: &str| {
+        let arg = args.iter().find(|arg| arg.name == name).expect("param");
+        (arg.arg_type.as_str(), arg.schema.clone())
+    };
+    assert_eq!(schema("columns"), ("array", Some(json!({ "items": { "type": "string" } }))));
+    assert_eq!(
+        schema("rows"),
+        ("array", Some(json!({ "items": { "type": "array", "items": { "type": "integer" } } })))
+    );
+    assert_eq!(schema("totals"), ("object", Some(json!({ "additionalProperties": { "type": "number" } }))));
+    assert_eq!(
+        schema("flags"),
+        ("object", Some(json!({ "additionalProperties": { "type": "array", "items": { "type": "boolean" } } })))
+    );
+    assert_eq!(schema("origin"), ("array", Some(json!({ "items": { "type": "integer" } }))));
+
+    let input = json!({
+        "columns": ["a"],
+        "rows": [[1], [2]],
+        "totals": { "a": 3.0 },
+        "flags": {},
+        "origin": [0, 1],
+    });
+    assert_eq!(SummarizeTool.run(input).await.expect("run"), "1 2 1 0 [0, 1]");
+}
+
+#[tool(
+    description = "Search the notes",
+    params(
+        query(description = "What to look for", rename = "q"),
+        session(skip_in_schema),
+    )
+)]
+fn search_notes(query: String, session: Option<String>) -> String {
+    format!("{} in {}", query, session.unwrap_or_else(|| "default".to_string()))
+}
+
+#[tokio::test]
+async fn tool_renames_and_hides_params() {
+    let args = SearchNotesTool.args();
+    assert_eq!(args.len(), 1);
+    assert_eq!(args[0].name, "q");
+    assert_eq!(args[0].description, "What to look for");
+
+    let output = SearchNotesTool.run(json!({ "q": "rust" })).await.expect("run");
+    assert_eq!(output, "rust in default");
+    assert!(SearchNotesTool.run(json!({ "query": "rust" })).await.is_err());
+}
+
+mod weather {
+    #[mini_langchain::tool(
+        name = "forecast",
+        struct_name = "Forecast",
+        vis = "pub(crate)",
+        description = "Forecast the weather",
+        params(days = "Days ahead")
+    )]
+    fn forecast(days: u32) -> String {
+        format!("sunny for {} days", days)
+    }
+}
+
+#[tokio::test]
+async fn tool_names_and_exports_its_struct() {
+    let tool = weather::Forecast;
+    assert_eq!(tool.name(), "forecast");
+    assert_eq!(tool.run(json!({ "days": 3 })).await.expect("run"), "sunny for 3 days");
+}